@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::State;
+
+use crate::crs::parse_srs;
+use crate::settings::SettingsStore;
+use crate::{setup_gdal_runtime, Extent};
+
+// A named map view, e.g. "Study area north", to jump back to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub extent: Extent,
+    // CRS the extent is given in, as accepted by `parse_srs`
+    pub crs: String,
+    // Visibility of map layers when the bookmark was saved, keyed by the frontend's
+    // layer ids; restored on recall when present
+    #[serde(default)]
+    pub layers: Option<BTreeMap<String, bool>>,
+}
+
+#[tauri::command]
+pub fn list_bookmarks(store: State<'_, SettingsStore>) -> Vec<Bookmark> {
+    store.get().bookmarks
+}
+
+// Adds a bookmark, replacing any existing bookmark with the same name
+#[tauri::command]
+pub fn save_bookmark(
+    store: State<'_, SettingsStore>,
+    bookmark: Bookmark,
+) -> Result<Vec<Bookmark>, String> {
+    // Ensure GDAL runtime is set up
+    setup_gdal_runtime();
+
+    if bookmark.name.trim().is_empty() {
+        return Err("Bookmark name must not be empty".to_string());
+    }
+    let extent = bookmark.extent;
+    if !(extent.min_x < extent.max_x && extent.min_y < extent.max_y) {
+        return Err("Bookmark extent must not be empty".to_string());
+    }
+    parse_srs(&bookmark.crs).map_err(|e| e.to_string())?;
+
+    store
+        .update(|settings| {
+            settings
+                .bookmarks
+                .retain(|existing| existing.name != bookmark.name);
+            settings.bookmarks.push(bookmark);
+        })
+        .map(|settings| settings.bookmarks)
+        .map_err(|e| e.to_string())
+}
+
+// Looks up a bookmark, with its extent reprojected to `crs` when the map is now shown
+// in another CRS than the one it was saved in
+#[tauri::command]
+pub fn recall_bookmark(
+    store: State<'_, SettingsStore>,
+    name: String,
+    crs: Option<String>,
+) -> Result<Bookmark, String> {
+    // Ensure GDAL runtime is set up
+    setup_gdal_runtime();
+
+    let mut bookmark = store
+        .get()
+        .bookmarks
+        .into_iter()
+        .find(|bookmark| bookmark.name == name)
+        .ok_or_else(|| format!("Unknown bookmark: {}", name))?;
+
+    if let Some(crs) = crs {
+        let from = parse_srs(&bookmark.crs).map_err(|e| e.to_string())?;
+        let to = parse_srs(&crs).map_err(|e| e.to_string())?;
+        bookmark.extent = bookmark
+            .extent
+            .transform(&from, &to)
+            .map_err(|e| e.to_string())?;
+        bookmark.crs = crs;
+    }
+    Ok(bookmark)
+}
+
+#[tauri::command]
+pub fn delete_bookmark(
+    store: State<'_, SettingsStore>,
+    name: String,
+) -> Result<Vec<Bookmark>, String> {
+    store
+        .update(|settings| settings.bookmarks.retain(|bookmark| bookmark.name != name))
+        .map(|settings| settings.bookmarks)
+        .map_err(|e| e.to_string())
+}
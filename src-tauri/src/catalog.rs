@@ -0,0 +1,603 @@
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::sql::Dialect;
+use gdal::vector::{
+    Feature, FieldDefn, Geometry, Layer, LayerAccess, LayerOptions, OGRFieldType,
+    OGRwkbGeometryType,
+};
+use gdal::{Dataset, DatasetOptions, DriverManager, GdalOpenFlags};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+use crate::crs::{crs_key, dataset_files};
+use crate::jobs::unix_millis;
+use crate::paths::AppPaths;
+use crate::progress::Progress;
+use crate::settings::SettingsStore;
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+const LAYER_NAME: &str = "datasets";
+
+// How long a writer waits for another user's lock before giving up
+const LOCK_WAIT: Duration = Duration::from_secs(30);
+const LOCK_RETRY: Duration = Duration::from_millis(250);
+
+// Locks older than this are left over from a crashed or disconnected writer
+const STALE_LOCK: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CatalogSettings {
+    // GeoPackage on a network drive shared by a team; None keeps a private catalog in
+    // the application data directory
+    pub shared_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub path: String,
+    pub driver: String,
+    pub width: i64,
+    pub height: i64,
+    pub band_count: i64,
+    pub layer_count: i64,
+    pub crs: Option<String>,
+    // Modification time of the file when indexed, Unix milliseconds
+    pub modified: i64,
+    pub indexed_at: i64,
+    // `user@host` of whoever indexed it last
+    pub indexed_by: String,
+    // Incremented each time the entry is rewritten
+    pub revision: i64,
+    // Longitude/latitude bounds, None for data without georeferencing
+    pub bounds: Option<Extent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatalogConflict {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CatalogUpdate {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    // Entries left as they are because the catalog already holds something newer
+    pub conflicts: Vec<CatalogConflict>,
+    // Files GDAL could not open
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatalogInfo {
+    pub path: String,
+    pub shared: bool,
+    pub entry_count: u64,
+    // Holder of the write lock right now, if any
+    pub locked_by: Option<String>,
+}
+
+// Who is writing, as recorded in entries and lock files
+fn writer_name() -> String {
+    let user = env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    let host = env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    format!("{}@{}", user, host)
+}
+
+fn catalog_path(app: &AppHandle, settings: &CatalogSettings) -> Result<PathBuf, GdalError> {
+    if let Some(shared) = &settings.shared_path {
+        return Ok(PathBuf::from(shared));
+    }
+    let paths = app.try_state::<AppPaths>().ok_or_else(|| {
+        GdalError::OperationFailed("Application paths are not set up".to_string())
+    })?;
+    Ok(paths.data_dir.join("catalog.gpkg"))
+}
+
+fn lock_path(catalog: &Path) -> PathBuf {
+    let mut name = catalog.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+fn lock_holder(catalog: &Path) -> Option<String> {
+    fs::read_to_string(lock_path(catalog))
+        .ok()
+        .map(|text| text.trim().to_string())
+}
+
+// Exclusive write access to a catalog, held through a lock file beside it. SQLite's own
+// locking is unreliable on SMB and NFS shares, while creating a file that must not
+// exist yet is atomic on them.
+struct CatalogLock {
+    path: PathBuf,
+}
+
+impl CatalogLock {
+    fn acquire(catalog: &Path) -> Result<Self, GdalError> {
+        let path = lock_path(catalog);
+        let started = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{} (pid {})", writer_name(), std::process::id())?;
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK);
+                    if stale {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed().unwrap_or_default() > LOCK_WAIT {
+                        return Err(GdalError::OperationFailed(format!(
+                            "The catalog is being updated by {}, try again later",
+                            lock_holder(catalog).unwrap_or_else(|| "another user".to_string())
+                        )));
+                    }
+                    thread::sleep(LOCK_RETRY);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for CatalogLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn create_catalog(path: &Path) -> Result<Dataset, GdalError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let driver = DriverManager::get_driver_by_name("GPKG")?;
+    let mut dataset = driver.create_vector_only(path)?;
+    let wgs84 = SpatialRef::from_epsg(4326)?;
+    let layer = dataset.create_layer(LayerOptions {
+        name: LAYER_NAME,
+        srs: Some(&wgs84),
+        ty: OGRwkbGeometryType::wkbPolygon,
+        ..Default::default()
+    })?;
+    for (name, field_type) in [
+        ("path", OGRFieldType::OFTString),
+        ("driver", OGRFieldType::OFTString),
+        ("width", OGRFieldType::OFTInteger64),
+        ("height", OGRFieldType::OFTInteger64),
+        ("band_count", OGRFieldType::OFTInteger64),
+        ("layer_count", OGRFieldType::OFTInteger64),
+        ("crs", OGRFieldType::OFTString),
+        ("modified", OGRFieldType::OFTInteger64),
+        ("indexed_at", OGRFieldType::OFTInteger64),
+        ("indexed_by", OGRFieldType::OFTString),
+        ("revision", OGRFieldType::OFTInteger64),
+    ] {
+        FieldDefn::new(name, field_type)?.add_to_layer(&layer)?;
+    }
+    dataset.execute_sql(
+        format!("CREATE UNIQUE INDEX {0}_path ON {0} (path)", LAYER_NAME),
+        None,
+        Dialect::DEFAULT,
+    )?;
+    Ok(dataset)
+}
+
+fn open_catalog(path: &Path, update: bool) -> Result<Dataset, GdalError> {
+    let flags = if update {
+        GdalOpenFlags::GDAL_OF_UPDATE
+    } else {
+        GdalOpenFlags::GDAL_OF_READONLY
+    };
+    Ok(Dataset::open_ex(
+        path,
+        DatasetOptions {
+            open_flags: flags | GdalOpenFlags::GDAL_OF_VECTOR,
+            allowed_drivers: Some(&["GPKG"]),
+            ..Default::default()
+        },
+    )?)
+}
+
+pub(crate) fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn read_entry(feature: &Feature) -> Result<CatalogEntry, GdalError> {
+    let string = |name: &str| -> Result<Option<String>, GdalError> {
+        Ok(feature.field_as_string(feature.field_index(name)?)?)
+    };
+    let integer = |name: &str| -> Result<i64, GdalError> {
+        Ok(feature
+            .field_as_integer64(feature.field_index(name)?)?
+            .unwrap_or_default())
+    };
+    let bounds = feature.geometry().map(|geometry| {
+        let envelope = geometry.envelope();
+        Extent {
+            min_x: envelope.MinX,
+            min_y: envelope.MinY,
+            max_x: envelope.MaxX,
+            max_y: envelope.MaxY,
+        }
+    });
+    Ok(CatalogEntry {
+        path: string("path")?.unwrap_or_default(),
+        driver: string("driver")?.unwrap_or_default(),
+        width: integer("width")?,
+        height: integer("height")?,
+        band_count: integer("band_count")?,
+        layer_count: integer("layer_count")?,
+        crs: string("crs")?,
+        modified: integer("modified")?,
+        indexed_at: integer("indexed_at")?,
+        indexed_by: string("indexed_by")?.unwrap_or_default(),
+        revision: integer("revision")?,
+        bounds,
+    })
+}
+
+fn find_entry(layer: &mut Layer, path: &str) -> Result<Option<(u64, CatalogEntry)>, GdalError> {
+    layer.set_attribute_filter(&format!("path = {}", sql_string(path)))?;
+    let found = match layer.features().next() {
+        Some(feature) => Some((feature.fid().unwrap_or_default(), read_entry(&feature)?)),
+        None => None,
+    };
+    layer.clear_attribute_filter();
+    Ok(found)
+}
+
+fn modified_millis(path: &Path) -> Result<i64, GdalError> {
+    let modified = fs::metadata(path)?.modified()?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default())
+}
+
+// Native extent of a dataset, from the geotransform for rasters and the union of layer
+// extents for vector data, with the CRS it is in
+fn native_extent(dataset: &Dataset) -> Option<(Extent, SpatialRef)> {
+    if dataset.raster_count() > 0 {
+        let gt = dataset.geo_transform().ok()?;
+        let srs = dataset.spatial_ref().ok()?;
+        let (width, height) = dataset.raster_size();
+        let (width, height) = (width as f64, height as f64);
+        let xs = [0.0, width].map(|col| [0.0, height].map(|row| gt[0] + col * gt[1] + row * gt[2]));
+        let ys = [0.0, width].map(|col| [0.0, height].map(|row| gt[3] + col * gt[4] + row * gt[5]));
+        let (xs, ys) = (xs.concat(), ys.concat());
+        let extent = Extent {
+            min_x: xs.iter().cloned().fold(f64::INFINITY, f64::min),
+            min_y: ys.iter().cloned().fold(f64::INFINITY, f64::min),
+            max_x: xs.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            max_y: ys.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        };
+        return Some((extent, srs));
+    }
+    let srs = dataset.layers().find_map(|layer| layer.spatial_ref())?;
+    let extent = dataset
+        .layers()
+        .filter_map(|layer| layer.get_extent().ok())
+        .map(|envelope| Extent {
+            min_x: envelope.MinX,
+            min_y: envelope.MinY,
+            max_x: envelope.MaxX,
+            max_y: envelope.MaxY,
+        })
+        .reduce(|a, b| Extent {
+            min_x: a.min_x.min(b.min_x),
+            min_y: a.min_y.min(b.min_y),
+            max_x: a.max_x.max(b.max_x),
+            max_y: a.max_y.max(b.max_y),
+        })?;
+    Some((extent, srs))
+}
+
+// Reads what the catalog stores about one file, without touching the catalog
+fn describe(path: &Path) -> Result<CatalogEntry, GdalError> {
+    let dataset = Dataset::open(path)?;
+    let (width, height) = dataset.raster_size();
+    let crs = if dataset.raster_count() > 0 {
+        dataset.spatial_ref().ok()
+    } else {
+        dataset.layers().find_map(|layer| layer.spatial_ref())
+    };
+    // Data that cannot be placed on a map is still catalogued, just not found spatially
+    let bounds = native_extent(&dataset).and_then(|(extent, srs)| {
+        let wgs84 = SpatialRef::from_epsg(4326).ok()?;
+        extent.transform(&srs, &wgs84).ok()
+    });
+    Ok(CatalogEntry {
+        path: path.to_string_lossy().to_string(),
+        driver: dataset.driver().short_name(),
+        width: width as i64,
+        height: height as i64,
+        band_count: dataset.raster_count() as i64,
+        layer_count: dataset.layer_count() as i64,
+        crs: crs.as_ref().and_then(crs_key),
+        modified: modified_millis(path)?,
+        indexed_at: unix_millis() as i64,
+        indexed_by: writer_name(),
+        revision: 1,
+        bounds,
+    })
+}
+
+fn write_entry(layer: &Layer, fid: Option<u64>, entry: &CatalogEntry) -> Result<(), GdalError> {
+    // Updates rewrite the stored feature so it keeps its FID
+    let mut feature = match fid {
+        Some(fid) => layer.feature(fid).ok_or_else(|| {
+            GdalError::OperationFailed(format!("Catalog entry {} disappeared", fid))
+        })?,
+        None => Feature::new(layer.defn())?,
+    };
+    let set_string = |feature: &mut Feature, name: &str, value: &str| -> Result<(), GdalError> {
+        let index = feature.field_index(name)?;
+        Ok(feature.set_field_string(index, value)?)
+    };
+    let set_integer = |feature: &mut Feature, name: &str, value: i64| -> Result<(), GdalError> {
+        let index = feature.field_index(name)?;
+        Ok(feature.set_field_integer64(index, value)?)
+    };
+    set_string(&mut feature, "path", &entry.path)?;
+    set_string(&mut feature, "driver", &entry.driver)?;
+    set_integer(&mut feature, "width", entry.width)?;
+    set_integer(&mut feature, "height", entry.height)?;
+    set_integer(&mut feature, "band_count", entry.band_count)?;
+    set_integer(&mut feature, "layer_count", entry.layer_count)?;
+    match &entry.crs {
+        Some(crs) => set_string(&mut feature, "crs", crs)?,
+        None => feature.set_field_null(feature.field_index("crs")?)?,
+    }
+    set_integer(&mut feature, "modified", entry.modified)?;
+    set_integer(&mut feature, "indexed_at", entry.indexed_at)?;
+    set_string(&mut feature, "indexed_by", &entry.indexed_by)?;
+    set_integer(&mut feature, "revision", entry.revision)?;
+    if let Some(b) = entry.bounds {
+        feature.set_geometry(Geometry::from_wkt(&format!(
+            "POLYGON (({0} {1}, {2} {1}, {2} {3}, {0} {3}, {0} {1}))",
+            b.min_x, b.min_y, b.max_x, b.max_y
+        ))?)?;
+    }
+    match fid {
+        Some(_) => layer.set_feature(feature)?,
+        None => feature.create(layer)?,
+    }
+    Ok(())
+}
+
+// Writes `entries` under the catalog lock. An entry whose stored copy was indexed from
+// a newer file is a conflict: someone else catalogued a more recent version, typically
+// through a different mount of the same share, and it is kept.
+fn merge_entries(
+    catalog: &Path,
+    entries: Vec<CatalogEntry>,
+    update: &mut CatalogUpdate,
+) -> Result<(), GdalError> {
+    let _lock = CatalogLock::acquire(catalog)?;
+    let mut dataset = if catalog.exists() {
+        open_catalog(catalog, true)?
+    } else {
+        create_catalog(catalog)?
+    };
+    let transaction = dataset.start_transaction()?;
+    let mut layer = transaction.layer_by_name(LAYER_NAME)?;
+    for mut entry in entries {
+        match find_entry(&mut layer, &entry.path)? {
+            None => {
+                write_entry(&layer, None, &entry)?;
+                update.added.push(entry.path);
+            }
+            Some((_, stored)) if stored.modified == entry.modified => {
+                update.unchanged.push(entry.path);
+            }
+            Some((_, stored)) if stored.modified > entry.modified => {
+                update.conflicts.push(CatalogConflict {
+                    reason: format!(
+                        "{} indexed a newer version of this file (revision {})",
+                        stored.indexed_by, stored.revision
+                    ),
+                    path: entry.path,
+                });
+            }
+            Some((fid, stored)) => {
+                entry.revision = stored.revision + 1;
+                write_entry(&layer, Some(fid), &entry)?;
+                update.updated.push(entry.path);
+            }
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+// Adds files to the catalog, or refreshes them when they changed since they were last
+// indexed. Directories are scanned for datasets. Files are read before the catalog is
+// locked, so other users are only kept waiting while entries are written.
+#[tauri::command]
+pub async fn catalog_add(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    paths: Vec<String>,
+    recursive: Option<bool>,
+) -> Result<CatalogUpdate, String> {
+    let settings = store.get().catalog;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let catalog = catalog_path(&app, &settings).map_err(|e| e.to_string())?;
+        let mut files = Vec::new();
+        for path in &paths {
+            let path = Path::new(path);
+            if path.is_dir() {
+                files.extend(
+                    dataset_files(path, recursive.unwrap_or(true)).map_err(|e| e.to_string())?,
+                );
+            } else if path.exists() {
+                files.push(path.to_path_buf());
+            } else {
+                return Err(format!("File not found: {}", path.display()));
+            }
+        }
+        // The catalog must not index itself
+        files.retain(|file| *file != catalog);
+
+        let progress = Progress::new(&app, "catalog_add");
+        let mut update = CatalogUpdate::default();
+        let mut entries = Vec::new();
+        for (index, file) in files.iter().enumerate() {
+            progress.report(
+                index as f64 / files.len().max(1) as f64,
+                Some(file.to_string_lossy().to_string()),
+            );
+            match describe(file) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => update.skipped.push(file.to_string_lossy().to_string()),
+            }
+        }
+
+        merge_entries(&catalog, entries, &mut update).map_err(|e| e.to_string())?;
+        progress.report(1.0, None);
+        Ok(update)
+    })
+    .await
+}
+
+// Searches the catalog by longitude/latitude extent and by a substring of the path.
+// Reading needs no lock, so searches keep working while someone else writes.
+#[tauri::command]
+pub async fn catalog_search(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    extent: Option<Extent>,
+    text: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<CatalogEntry>, String> {
+    let settings = store.get().catalog;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let catalog = catalog_path(&app, &settings).map_err(|e| e.to_string())?;
+        if !catalog.exists() {
+            return Ok(Vec::new());
+        }
+        let dataset = open_catalog(&catalog, false).map_err(|e| e.to_string())?;
+        let mut layer = dataset
+            .layer_by_name(LAYER_NAME)
+            .map_err(|e| e.to_string())?;
+        if let Some(e) = extent {
+            layer.set_spatial_filter_rect(e.min_x, e.min_y, e.max_x, e.max_y);
+        }
+        if let Some(text) = text.filter(|text| !text.is_empty()) {
+            let pattern = format!("%{}%", text);
+            layer
+                .set_attribute_filter(&format!("path LIKE {}", sql_string(&pattern)))
+                .map_err(|e| e.to_string())?;
+        }
+        layer
+            .features()
+            .take(limit.unwrap_or(1000))
+            .map(|feature| read_entry(&feature).map_err(|e| e.to_string()))
+            .collect()
+    })
+    .await
+}
+
+// Removes entries by path; returns how many were removed
+#[tauri::command]
+pub async fn catalog_remove(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    paths: Vec<String>,
+) -> Result<usize, String> {
+    let settings = store.get().catalog;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let catalog = catalog_path(&app, &settings).map_err(|e| e.to_string())?;
+        if !catalog.exists() {
+            return Ok(0);
+        }
+        let _lock = CatalogLock::acquire(&catalog).map_err(|e| e.to_string())?;
+        let dataset = open_catalog(&catalog, true).map_err(|e| e.to_string())?;
+        let mut layer = dataset
+            .layer_by_name(LAYER_NAME)
+            .map_err(|e| e.to_string())?;
+        let mut removed = 0;
+        for path in &paths {
+            if find_entry(&mut layer, path)
+                .map_err(|e| e.to_string())?
+                .is_some()
+            {
+                dataset
+                    .execute_sql(
+                        format!(
+                            "DELETE FROM {} WHERE path = {}",
+                            LAYER_NAME,
+                            sql_string(path)
+                        ),
+                        None,
+                        Dialect::DEFAULT,
+                    )
+                    .map_err(|e| e.to_string())?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_catalog_info(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+) -> Result<CatalogInfo, String> {
+    let settings = store.get().catalog;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let catalog = catalog_path(&app, &settings).map_err(|e| e.to_string())?;
+        let entry_count = if catalog.exists() {
+            let dataset = open_catalog(&catalog, false).map_err(|e| e.to_string())?;
+            let layer = dataset
+                .layer_by_name(LAYER_NAME)
+                .map_err(|e| e.to_string())?;
+            layer.feature_count()
+        } else {
+            0
+        };
+        Ok(CatalogInfo {
+            path: catalog.to_string_lossy().to_string(),
+            shared: settings.shared_path.is_some(),
+            entry_count,
+            locked_by: lock_holder(&catalog),
+        })
+    })
+    .await
+}
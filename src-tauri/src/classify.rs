@@ -0,0 +1,457 @@
+use gdal::vector::LayerAccess;
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::vector::{field_value_json, layer_by_name};
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+// Rasters are read through overviews at no more than this many pixels
+const RASTER_SAMPLE_PIXELS: usize = 1 << 20;
+
+// Distinct values tracked while counting categories
+const MAX_TRACKED_CATEGORIES: usize = 100_000;
+
+const DEFAULT_MAX_CATEGORIES: usize = 50;
+
+// Tableau 10 and ColorBrewer Set3, qualitative palettes for categories
+const TABLEAU10: &[&str] = &[
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+const SET3: &[&str] = &[
+    "#8dd3c7", "#ffffb3", "#bebada", "#fb8072", "#80b1d3", "#fdb462", "#b3de69", "#fccde5",
+    "#d9d9d9", "#bc80bd", "#ccebc5", "#ffed6f",
+];
+
+// Jenks is quadratic in the number of values, so it runs on an evenly spaced sample of
+// the sorted values
+const JENKS_SAMPLE: usize = 2000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClassSource {
+    Raster {
+        path: String,
+        #[serde(default = "first_band")]
+        band: usize,
+    },
+    Vector {
+        path: String,
+        #[serde(default)]
+        layer: Option<String>,
+        field: String,
+    },
+}
+
+impl ClassSource {
+    fn path(&self) -> &str {
+        match self {
+            ClassSource::Raster { path, .. } | ClassSource::Vector { path, .. } => path,
+        }
+    }
+}
+
+fn first_band() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassMethod {
+    EqualInterval,
+    Quantile,
+    // Classes one standard deviation wide, centred on the mean
+    StandardDeviation,
+    // Jenks natural breaks, minimizing the variance within classes
+    Jenks,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassBreaks {
+    pub method: ClassMethod,
+    // Class boundaries from the minimum to the maximum, one more than the class count.
+    // Quantiles over repeated values can yield fewer classes than requested.
+    pub breaks: Vec<f64>,
+    // Values per class; a value on a boundary belongs to the lower class
+    pub counts: Vec<u64>,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub stddev: f64,
+    // Values the breaks were computed from, after dropping nodata
+    pub sample_size: usize,
+}
+
+fn raster_values(path: &str, band: usize) -> Result<Vec<f64>, GdalError> {
+    let dataset = Dataset::open(path)?;
+    let raster_band = dataset.rasterband(band)?;
+    let (width, height) = dataset.raster_size();
+    let scale = ((width * height) as f64 / RASTER_SAMPLE_PIXELS as f64)
+        .sqrt()
+        .max(1.0);
+    let size = (
+        ((width as f64 / scale) as usize).max(1),
+        ((height as f64 / scale) as usize).max(1),
+    );
+    let nodata = raster_band.no_data_value();
+    let values = raster_band
+        .read_as::<f64>((0, 0), (width, height), size, None)?
+        .into_shape_and_vec()
+        .1;
+    Ok(values
+        .into_iter()
+        .filter(|value| value.is_finite() && nodata != Some(*value))
+        .collect())
+}
+
+fn vector_values(path: &str, layer: Option<&str>, field: &str) -> Result<Vec<f64>, GdalError> {
+    let dataset = Dataset::open(path)?;
+    let mut layer = layer_by_name(&dataset, layer)?;
+    let index = layer.defn().field_index(field)?;
+    let mut values = Vec::new();
+    for feature in layer.features() {
+        if let Some(value) = feature.field_as_double(index)? {
+            if value.is_finite() {
+                values.push(value);
+            }
+        }
+    }
+    Ok(values)
+}
+
+fn equal_interval(min: f64, max: f64, classes: usize) -> Vec<f64> {
+    (0..=classes)
+        .map(|i| min + (max - min) * i as f64 / classes as f64)
+        .collect()
+}
+
+fn quantile(sorted: &[f64], classes: usize) -> Vec<f64> {
+    (0..=classes)
+        .map(|i| sorted[(i * (sorted.len() - 1)) / classes])
+        .collect()
+}
+
+fn standard_deviation(min: f64, max: f64, mean: f64, stddev: f64, classes: usize) -> Vec<f64> {
+    let mut breaks = vec![min];
+    for i in 1..classes {
+        let offset = i as f64 - classes as f64 / 2.0;
+        breaks.push((mean + offset * stddev).clamp(min, max));
+    }
+    breaks.push(max);
+    breaks
+}
+
+// Fisher-Jenks dynamic programming over sorted values
+fn jenks(sorted: &[f64], classes: usize) -> Vec<f64> {
+    let sample: Vec<f64> = if sorted.len() > JENKS_SAMPLE {
+        (0..JENKS_SAMPLE)
+            .map(|i| sorted[i * (sorted.len() - 1) / (JENKS_SAMPLE - 1)])
+            .collect()
+    } else {
+        sorted.to_vec()
+    };
+    let n = sample.len();
+    let classes = classes.min(n);
+
+    // lower[i][j]: index (1-based) of the first value in the last class when the first
+    // i values are split into j classes; variance[i][j]: the total within-class variance
+    let mut lower = vec![vec![0usize; classes + 1]; n + 1];
+    let mut variance = vec![vec![f64::INFINITY; classes + 1]; n + 1];
+    for j in 1..=classes {
+        lower[1][j] = 1;
+        variance[1][j] = 0.0;
+    }
+
+    for i in 2..=n {
+        let (mut sum, mut sum_squares, mut count) = (0.0, 0.0, 0.0);
+        let mut class_variance = 0.0;
+        for m in 1..=i {
+            let start = i - m + 1;
+            let value = sample[start - 1];
+            sum += value;
+            sum_squares += value * value;
+            count += 1.0;
+            class_variance = sum_squares - sum * sum / count;
+            if start > 1 {
+                for j in 2..=classes {
+                    let candidate = class_variance + variance[start - 1][j - 1];
+                    if variance[i][j] >= candidate {
+                        lower[i][j] = start;
+                        variance[i][j] = candidate;
+                    }
+                }
+            }
+        }
+        lower[i][1] = 1;
+        variance[i][1] = class_variance;
+    }
+
+    let mut breaks = vec![0.0; classes + 1];
+    breaks[0] = sample[0];
+    breaks[classes] = sample[n - 1];
+    let mut k = n;
+    for j in (2..=classes).rev() {
+        let start = lower[k][j];
+        breaks[j - 1] = sample[start - 2];
+        k = start - 1;
+    }
+    breaks
+}
+
+fn count_classes(values: &[f64], breaks: &[f64]) -> Vec<u64> {
+    let mut counts = vec![0; breaks.len().saturating_sub(1)];
+    if counts.is_empty() {
+        return counts;
+    }
+    for value in values {
+        let class = breaks[1..]
+            .iter()
+            .position(|upper| value <= upper)
+            .unwrap_or(counts.len() - 1);
+        counts[class] += 1;
+    }
+    counts
+}
+
+pub(crate) fn compute_breaks(
+    mut values: Vec<f64>,
+    method: ClassMethod,
+    classes: usize,
+) -> Result<ClassBreaks, String> {
+    if classes == 0 {
+        return Err("At least one class is required".to_string());
+    }
+    if values.is_empty() {
+        return Err("No valid values to classify".to_string());
+    }
+    values.sort_by(f64::total_cmp);
+
+    let n = values.len() as f64;
+    let (min, max) = (values[0], values[values.len() - 1]);
+    let mean = values.iter().sum::<f64>() / n;
+    let stddev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+
+    let mut breaks = match method {
+        ClassMethod::EqualInterval => equal_interval(min, max, classes),
+        ClassMethod::Quantile => quantile(&values, classes),
+        ClassMethod::StandardDeviation => standard_deviation(min, max, mean, stddev, classes),
+        ClassMethod::Jenks => jenks(&values, classes),
+    };
+    breaks.dedup();
+    if breaks.len() == 1 {
+        // A constant field is one class of zero width
+        breaks.push(max);
+    }
+
+    Ok(ClassBreaks {
+        method,
+        counts: count_classes(&values, &breaks),
+        breaks,
+        min,
+        max,
+        mean,
+        stddev,
+        sample_size: values.len(),
+    })
+}
+
+// Class breaks for a graduated style and its legend. Large rasters are classified from
+// a decimated read, so counts are proportional rather than exact.
+#[tauri::command]
+pub async fn compute_class_breaks(
+    source: ClassSource,
+    method: ClassMethod,
+    classes: usize,
+) -> Result<ClassBreaks, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(source.path()).exists() {
+            return Err(format!("File not found: {}", source.path()));
+        }
+        let values = match &source {
+            ClassSource::Raster { path, band } => raster_values(path, *band),
+            ClassSource::Vector { path, layer, field } => {
+                vector_values(path, layer.as_deref(), field)
+            }
+        }
+        .map_err(|e| e.to_string())?;
+
+        compute_breaks(values, method, classes)
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryPalette {
+    #[default]
+    Tableau10,
+    Set3,
+}
+
+impl CategoryPalette {
+    // Palette colours first, then hues spaced by the golden angle so that any number of
+    // categories stays distinguishable
+    fn color(self, index: usize) -> String {
+        let palette = match self {
+            CategoryPalette::Tableau10 => TABLEAU10,
+            CategoryPalette::Set3 => SET3,
+        };
+        if let Some(color) = palette.get(index) {
+            return color.to_string();
+        }
+        let hue = ((index - palette.len()) as f64 * 137.508) % 360.0;
+        let (r, g, b) = hsl_to_rgb(hue, 0.55, 0.6);
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Category {
+    pub value: Value,
+    pub count: u64,
+    // "#rrggbb"
+    pub color: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Categories {
+    // Sorted by value
+    pub categories: Vec<Category>,
+    // Values not among the returned categories
+    pub other_count: u64,
+    // More distinct values exist than were returned
+    pub truncated: bool,
+    pub sample_size: u64,
+}
+
+// Counts distinct values, keyed by their JSON text so numbers and strings mix
+#[derive(Default)]
+struct CategoryCounter {
+    counts: HashMap<String, (Value, u64)>,
+    total: u64,
+    untracked: u64,
+}
+
+impl CategoryCounter {
+    fn add(&mut self, value: Value) {
+        self.total += 1;
+        let key = value.to_string();
+        if let Some((_, count)) = self.counts.get_mut(&key) {
+            *count += 1;
+        } else if self.counts.len() < MAX_TRACKED_CATEGORIES {
+            self.counts.insert(key, (value, 1));
+        } else {
+            self.untracked += 1;
+        }
+    }
+
+    fn finish(self, max_categories: usize, palette: CategoryPalette) -> Categories {
+        let distinct = self.counts.len();
+        let mut categories: Vec<(Value, u64)> = self.counts.into_values().collect();
+        categories.sort_by_key(|(_, count)| Reverse(*count));
+        categories.truncate(max_categories);
+        categories.sort_by(|(a, _), (b, _)| match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            _ => a.to_string().cmp(&b.to_string()),
+        });
+
+        let listed: u64 = categories.iter().map(|(_, count)| count).sum();
+        Categories {
+            truncated: distinct > categories.len() || self.untracked > 0,
+            other_count: self.total - listed,
+            sample_size: self.total,
+            categories: categories
+                .into_iter()
+                .enumerate()
+                .map(|(index, (value, count))| Category {
+                    value,
+                    count,
+                    color: palette.color(index),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn vector_categories(
+    path: &str,
+    layer: Option<&str>,
+    field: &str,
+    counter: &mut CategoryCounter,
+) -> Result<(), GdalError> {
+    let dataset = Dataset::open(path)?;
+    let mut layer = layer_by_name(&dataset, layer)?;
+    let index = layer.defn().field_index(field)?;
+    for feature in layer.features() {
+        if let Some(value) = feature.field(index)? {
+            counter.add(field_value_json(value));
+        }
+    }
+    Ok(())
+}
+
+// Distinct values of a band or field with a colour each, for categorized styling. The
+// most frequent `max_categories` values are kept; rasters are counted from a decimated
+// read like class breaks.
+#[tauri::command]
+pub async fn compute_categories(
+    source: ClassSource,
+    max_categories: Option<usize>,
+    palette: Option<CategoryPalette>,
+) -> Result<Categories, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(source.path()).exists() {
+            return Err(format!("File not found: {}", source.path()));
+        }
+        let mut counter = CategoryCounter::default();
+        match &source {
+            ClassSource::Raster { path, band } => raster_values(path, *band).map(|values| {
+                for value in values {
+                    // Class codes read as whole numbers
+                    if value.fract() == 0.0 && value.abs() < 9e15 {
+                        counter.add(json!(value as i64));
+                    } else {
+                        counter.add(json!(value));
+                    }
+                }
+            }),
+            ClassSource::Vector { path, layer, field } => {
+                vector_categories(path, layer.as_deref(), field, &mut counter)
+            }
+        }
+        .map_err(|e| e.to_string())?;
+
+        Ok(counter.finish(
+            max_categories.unwrap_or(DEFAULT_MAX_CATEGORIES),
+            palette.unwrap_or_default(),
+        ))
+    })
+    .await
+}
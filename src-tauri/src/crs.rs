@@ -0,0 +1,968 @@
+use gdal::cpl::CslStringList;
+use gdal::spatial_ref::{AxisMappingStrategy, CoordTransform, SpatialRef};
+use gdal::vector::LayerAccess;
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags, Metadata};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager, State};
+
+use crate::datasets::{DatasetRegistry, OpenDataset};
+use crate::ffi::{last_error, string_from_ptr};
+use crate::jobs::{record_command_line, run_job};
+use crate::progress::Progress;
+use crate::qa;
+use crate::raster::pansharpen::xml_escape;
+use crate::raster::translate::translate;
+use crate::raster::warp::warp;
+use crate::raster::Resampling;
+use crate::render::cache::RenderCache;
+use crate::vector::translate::vector_translate;
+use crate::{dataset_info, run_blocking, setup_gdal_runtime, DatasetInfo, Extent, GdalError};
+
+// Sidecar and component files that are never datasets on their own
+const SIDECAR_EXTENSIONS: &[&str] = &[
+    "aux", "xml", "ovr", "shx", "dbf", "prj", "cpg", "qix", "sbn", "sbx", "tfw", "tifw", "wld",
+    "jgw", "pgw", "msk", "lock",
+];
+
+// Accepts anything OSRSetFromUserInput understands: "EPSG:4326", WKT, PROJ strings, ...
+pub(crate) fn parse_srs(definition: &str) -> Result<SpatialRef, GdalError> {
+    SpatialRef::from_definition(definition).map_err(|e| {
+        GdalError::InvalidArgument(format!("Unrecognised CRS '{}': {}", definition, e))
+    })
+}
+
+// Transformation that keeps x/y as easting/northing or lon/lat whatever the CRS axis order
+pub(crate) fn transformer(from: &SpatialRef, to: &SpatialRef) -> Result<CoordTransform, GdalError> {
+    let mut from = from.clone();
+    from.set_axis_mapping_strategy(AxisMappingStrategy::TraditionalGisOrder);
+    let mut to = to.clone();
+    to.set_axis_mapping_strategy(AxisMappingStrategy::TraditionalGisOrder);
+    Ok(CoordTransform::new(&from, &to)?)
+}
+
+// Where an assigned CRS ended up
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrsStorage {
+    // Set on an in-memory dataset
+    Memory,
+    // Held by a VRT wrapper for this session only, the file is untouched
+    Virtual,
+    // Written into the file itself
+    InPlace,
+    // Written to a .aux.xml sidecar (GDAL PAM) for formats that cannot be updated
+    Sidecar,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssignedCrs {
+    pub storage: CrsStorage,
+    pub info: DatasetInfo,
+}
+
+pub(crate) fn write_crs(path: &str, srs: &SpatialRef) -> Result<CrsStorage, GdalError> {
+    let definition = match (srs.auth_name(), srs.auth_code()) {
+        (Some(name), Ok(code)) => format!("{}:{}", name, code),
+        _ => srs.to_wkt()?,
+    };
+    record_command_line(
+        "gdal_edit.py",
+        &["-a_srs".to_string(), definition, path.to_string()],
+    );
+
+    let update = Dataset::open_ex(
+        path,
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+            ..Default::default()
+        },
+    );
+    if let Ok(mut update) = update {
+        if update.set_spatial_ref(srs).is_ok() {
+            update.close()?;
+            return Ok(CrsStorage::InPlace);
+        }
+    }
+
+    // Read-only datasets store the CRS through PAM, which is flushed on close
+    let mut dataset = Dataset::open(path)?;
+    dataset.set_spatial_ref(srs)?;
+    dataset.close()?;
+    Ok(CrsStorage::Sidecar)
+}
+
+// An open dataset, or a file that is not open
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DatasetTarget {
+    Handle(u64),
+    Path(String),
+}
+
+// OGR VRT exposing every layer of `path` with `srs`, the vector counterpart of the VRT
+// wrapper used for rasters. The XML is opened directly, nothing is written.
+fn vector_crs_vrt(path: &str, srs: &SpatialRef) -> Result<Dataset, GdalError> {
+    let source = Dataset::open(path)?;
+    let wkt = xml_escape(&srs.to_wkt()?);
+    let mut xml = String::from("<OGRVRTDataSource>");
+    for layer in source.layers() {
+        let name = xml_escape(&layer.name());
+        xml.push_str(&format!(
+            "<OGRVRTLayer name=\"{0}\"><SrcDataSource relativeToVRT=\"0\">{1}</SrcDataSource>\
+             <SrcLayer>{0}</SrcLayer><LayerSRS>{2}</LayerSRS></OGRVRTLayer>",
+            name,
+            xml_escape(path),
+            wkt
+        ));
+    }
+    xml.push_str("</OGRVRTDataSource>");
+    Ok(Dataset::open(&xml)?)
+}
+
+// Sets the CRS of an open dataset. In-memory rasters are changed directly; otherwise
+// `persist` writes it to the file, or the dataset is wrapped in a VRT for the session.
+fn assign_open_crs(
+    app: &AppHandle,
+    open: &mut OpenDataset,
+    srs: &SpatialRef,
+    persist: bool,
+) -> Result<CrsStorage, GdalError> {
+    let is_raster = open.dataset.raster_count() > 0;
+    if open.in_memory {
+        open.dataset.set_spatial_ref(srs)?;
+        open.reset_mercator();
+        return Ok(CrsStorage::Memory);
+    }
+    if persist {
+        let storage = assign_file_crs(Path::new(&open.path), srs)?;
+        // Reopen so the registered handle picks up the new CRS
+        open.replace_dataset(Dataset::open(&open.path)?);
+        return Ok(storage);
+    }
+
+    // Wrap the file itself rather than the registered dataset, which may already be a
+    // wrapper from an earlier assignment
+    let wrapped = if is_raster {
+        // An unnamed VRT lives in memory and reads pixels from the original file
+        let args = vec![
+            "-of".to_string(),
+            "VRT".to_string(),
+            "-a_srs".to_string(),
+            srs.to_wkt()?,
+        ];
+        let source = Dataset::open(&open.path)?;
+        let progress = Progress::new(app, "assign_crs");
+        translate(&source, "", &args, &progress)?
+    } else {
+        vector_crs_vrt(&open.path, srs)?
+    };
+    open.replace_dataset(wrapped);
+    Ok(CrsStorage::Virtual)
+}
+
+// Sets or overrides the CRS without touching coordinates, for data with a missing or
+// wrong .prj. Nothing is reprojected. A path is always changed on disk, like
+// `persist` does for an open dataset: rasters in place or in a .aux.xml sidecar,
+// shapefiles through their .prj.
+#[tauri::command]
+pub async fn assign_crs(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+    dataset: DatasetTarget,
+    srs: String,
+    persist: Option<bool>,
+) -> Result<AssignedCrs, String> {
+    let persist = persist.unwrap_or(false);
+    match dataset {
+        DatasetTarget::Handle(handle) => {
+            let entry = registry.get(handle).map_err(|e| e.to_string())?;
+            run_blocking(move || {
+                // Ensure GDAL runtime is set up
+                setup_gdal_runtime();
+
+                let spatial_ref = parse_srs(&srs).map_err(|e| e.to_string())?;
+                let mut open = entry.lock().unwrap();
+                let storage = assign_open_crs(&app, &mut open, &spatial_ref, persist)
+                    .map_err(|e| e.to_string())?;
+                // Tiles rendered with the old CRS are in the wrong place now
+                app.state::<RenderCache>().invalidate(handle);
+
+                Ok(AssignedCrs {
+                    storage,
+                    info: dataset_info(&open.dataset),
+                })
+            })
+            .await
+        }
+        DatasetTarget::Path(path) => {
+            run_blocking(move || {
+                // Ensure GDAL runtime is set up
+                setup_gdal_runtime();
+
+                let spatial_ref = parse_srs(&srs).map_err(|e| e.to_string())?;
+                if !Path::new(&path).exists() {
+                    return Err(format!("File not found: {}", path));
+                }
+                let storage =
+                    assign_file_crs(Path::new(&path), &spatial_ref).map_err(|e| e.to_string())?;
+
+                // Datasets opened from the file read the new CRS from now on
+                for (handle, entry) in app.state::<DatasetRegistry>().entries() {
+                    let mut open = entry.lock().unwrap();
+                    if !open.in_memory && open.path == path {
+                        let reopened = Dataset::open(&path).map_err(|e| e.to_string())?;
+                        open.replace_dataset(reopened);
+                        app.state::<RenderCache>().invalidate(handle);
+                    }
+                }
+
+                let dataset = Dataset::open(&path).map_err(|e| e.to_string())?;
+                Ok(AssignedCrs {
+                    storage,
+                    info: dataset_info(&dataset),
+                })
+            })
+            .await
+        }
+    }
+}
+
+// Files under `dir` that may be datasets, skipping known sidecars
+pub(crate) fn dataset_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, GdalError> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if recursive {
+                    pending.push(path);
+                }
+                continue;
+            }
+
+            let sidecar = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| SIDECAR_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+            if !sidecar {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+// Grouping key such as "EPSG:32633", falling back to the CRS name when no code is known
+pub(crate) fn crs_key(srs: &SpatialRef) -> Option<String> {
+    let mut srs = srs.clone();
+    if srs.auth_code().is_err() {
+        let _ = srs.auto_identify_epsg();
+    }
+    match (srs.auth_name(), srs.auth_code()) {
+        (Some(name), Ok(code)) => Some(format!("{}:{}", name, code)),
+        _ => srs.name(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetKind {
+    Raster,
+    Vector,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrsAuditEntry {
+    pub path: String,
+    pub kind: DatasetKind,
+    pub crs: Option<String>,
+    pub crs_name: Option<String>,
+    // Vector layers in the same file disagree on their CRS
+    pub mixed: bool,
+    // The coordinates do not fit the declared CRS, see `check_crs_placement`
+    pub likely_misassigned: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrsGroup {
+    // `None` groups the files without any CRS
+    pub crs: Option<String>,
+    pub crs_name: Option<String>,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrsAudit {
+    pub entries: Vec<CrsAuditEntry>,
+    pub groups: Vec<CrsGroup>,
+    // Files GDAL could not open
+    pub skipped: Vec<String>,
+}
+
+fn audit_dataset(path: &Path, dataset: &Dataset) -> CrsAuditEntry {
+    let path = path.to_string_lossy().to_string();
+    let likely_misassigned = qa::check_dataset(dataset)
+        .iter()
+        .any(|check| check.likely_misassigned);
+
+    if dataset.raster_count() > 0 {
+        let srs = dataset.spatial_ref().ok();
+        return CrsAuditEntry {
+            path,
+            kind: DatasetKind::Raster,
+            crs: srs.as_ref().and_then(crs_key),
+            crs_name: srs.as_ref().and_then(|srs| srs.name()),
+            mixed: false,
+            likely_misassigned,
+        };
+    }
+
+    let layer_crs: Vec<Option<SpatialRef>> =
+        dataset.layers().map(|layer| layer.spatial_ref()).collect();
+    let keys: Vec<Option<String>> = layer_crs
+        .iter()
+        .map(|srs| srs.as_ref().and_then(crs_key))
+        .collect();
+    let first = layer_crs.iter().flatten().next();
+
+    CrsAuditEntry {
+        path,
+        kind: DatasetKind::Vector,
+        crs: first.and_then(crs_key),
+        crs_name: first.and_then(|srs| srs.name()),
+        mixed: keys.windows(2).any(|pair| pair[0] != pair[1]),
+        likely_misassigned,
+    }
+}
+
+// Reports the CRS of every dataset in a folder and groups files sharing one
+#[tauri::command]
+pub async fn audit_crs(
+    app: AppHandle,
+    directory: String,
+    recursive: Option<bool>,
+) -> Result<CrsAudit, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let dir = Path::new(&directory);
+        if !dir.is_dir() {
+            return Err(format!("Directory not found: {}", directory));
+        }
+
+        let files = dataset_files(dir, recursive.unwrap_or(true)).map_err(|e| e.to_string())?;
+        let progress = Progress::new(&app, "audit_crs");
+        let mut entries = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (index, file) in files.iter().enumerate() {
+            progress.report(
+                index as f64 / files.len() as f64,
+                Some(file.to_string_lossy().to_string()),
+            );
+            match Dataset::open(file) {
+                Ok(dataset) => entries.push(audit_dataset(file, &dataset)),
+                Err(_) => skipped.push(file.to_string_lossy().to_string()),
+            }
+        }
+        progress.report(1.0, None);
+
+        let mut groups: BTreeMap<Option<String>, CrsGroup> = BTreeMap::new();
+        for entry in &entries {
+            groups
+                .entry(entry.crs.clone())
+                .or_insert_with(|| CrsGroup {
+                    crs: entry.crs.clone(),
+                    crs_name: entry.crs_name.clone(),
+                    files: Vec::new(),
+                })
+                .files
+                .push(entry.path.clone());
+        }
+
+        Ok(CrsAudit {
+            entries,
+            groups: groups.into_values().collect(),
+            skipped,
+        })
+    })
+    .await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchFileResult {
+    pub path: String,
+    // Written file, for batch reprojection
+    pub output: Option<String>,
+    // Where the CRS was stored, for batch assignment
+    pub storage: Option<CrsStorage>,
+    pub error: Option<String>,
+}
+
+fn assign_file_crs(path: &Path, srs: &SpatialRef) -> Result<CrsStorage, GdalError> {
+    let dataset = Dataset::open(path)?;
+    if dataset.raster_count() > 0 {
+        drop(dataset);
+        return write_crs(&path.to_string_lossy(), srs);
+    }
+
+    let driver = dataset.driver().short_name();
+    if driver == "ESRI Shapefile" {
+        // Shapefiles read their CRS from the .prj sidecar, which uses ESRI flavoured WKT
+        let esri = srs.clone();
+        esri.morph_to_esri()?;
+        fs::write(path.with_extension("prj"), esri.to_wkt()?)?;
+        return Ok(CrsStorage::Sidecar);
+    }
+
+    Err(GdalError::InvalidArgument(format!(
+        "Cannot assign a CRS to {} datasets",
+        driver
+    )))
+}
+
+// Writes a reprojected copy into `output_dir`, keeping the format when GDAL can write it
+fn reproject_file(
+    path: &Path,
+    target_epsg: u32,
+    output_dir: &Path,
+    resampling: Resampling,
+    progress: &Progress,
+) -> Result<PathBuf, GdalError> {
+    let dataset = Dataset::open(path)?;
+    let driver = dataset.driver();
+    let writable = driver.metadata_item("DCAP_CREATE", "").is_some();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let source_extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string());
+    let is_raster = dataset.raster_count() > 0;
+
+    let (format, extension) = match (writable, source_extension) {
+        (true, Some(extension)) => (driver.short_name(), extension),
+        _ if is_raster => ("GTiff".to_string(), "tif".to_string()),
+        _ => ("GPKG".to_string(), "gpkg".to_string()),
+    };
+
+    let dst = output_dir.join(format!("{}.{}", stem, extension));
+    if dst.exists() {
+        return Err(GdalError::InvalidArgument(format!(
+            "{} already exists",
+            dst.display()
+        )));
+    }
+    let dst_str = dst.to_string_lossy();
+    let t_srs = format!("EPSG:{}", target_epsg);
+
+    if is_raster {
+        let args = vec![
+            "-of".to_string(),
+            format,
+            "-t_srs".to_string(),
+            t_srs,
+            "-r".to_string(),
+            resampling.as_gdal_arg().to_string(),
+            "-multi".to_string(),
+            "-wo".to_string(),
+            "NUM_THREADS=ALL_CPUS".to_string(),
+        ];
+        warp(&[&dataset], &dst_str, &args, progress)?.close()?;
+    } else {
+        let args = vec!["-f".to_string(), format, "-t_srs".to_string(), t_srs];
+        vector_translate(&[&dataset], &dst_str, &args, progress)?.close()?;
+    }
+    Ok(dst)
+}
+
+#[tauri::command]
+pub async fn batch_assign_crs(
+    app: AppHandle,
+    paths: Vec<String>,
+    srs: String,
+) -> Result<Vec<BatchFileResult>, String> {
+    let params = json!({ "paths": paths, "srs": srs });
+    run_job(app.clone(), "batch_assign_crs", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let spatial_ref = parse_srs(&srs).map_err(|e| e.to_string())?;
+
+        Ok(paths
+            .into_iter()
+            .map(|path| {
+                let result = assign_file_crs(Path::new(&path), &spatial_ref);
+                BatchFileResult {
+                    path,
+                    output: None,
+                    storage: result.as_ref().ok().copied(),
+                    error: result.err().map(|e| e.to_string()),
+                }
+            })
+            .collect())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn batch_reproject(
+    app: AppHandle,
+    paths: Vec<String>,
+    target_epsg: u32,
+    output_dir: String,
+    resampling: Option<Resampling>,
+) -> Result<Vec<BatchFileResult>, String> {
+    let params = json!({
+        "paths": paths,
+        "target_epsg": target_epsg,
+        "output_dir": output_dir,
+        "resampling": resampling,
+    });
+    run_job(app.clone(), "batch_reproject", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let output_dir = PathBuf::from(output_dir);
+        fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
+
+        let resampling = resampling.unwrap_or_default();
+        let progress = Progress::new(&app, "batch_reproject");
+        let count = paths.len().max(1) as f64;
+
+        Ok(paths
+            .into_iter()
+            .enumerate()
+            .map(|(index, path)| {
+                progress.set_range(index as f64 / count, (index + 1) as f64 / count);
+                let result = reproject_file(
+                    Path::new(&path),
+                    target_epsg,
+                    &output_dir,
+                    resampling,
+                    &progress,
+                );
+                BatchFileResult {
+                    path,
+                    output: result
+                        .as_ref()
+                        .ok()
+                        .map(|dst| dst.to_string_lossy().to_string()),
+                    storage: None,
+                    error: result.err().map(|e| e.to_string()),
+                }
+            })
+            .collect())
+    })
+    .await
+}
+
+// Order of the first two values of each coordinate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisOrder {
+    // Longitude/latitude and easting/northing, whatever the CRS definition says
+    #[default]
+    Traditional,
+    // As defined by the CRS authority, e.g. latitude first for EPSG:4326
+    Authority,
+}
+
+fn coordinate_transform(
+    src_crs: &str,
+    dst_crs: &str,
+    axis_order: AxisOrder,
+) -> Result<CoordTransform, GdalError> {
+    let from = parse_srs(src_crs)?;
+    let to = parse_srs(dst_crs)?;
+    match axis_order {
+        AxisOrder::Traditional => transformer(&from, &to),
+        AxisOrder::Authority => Ok(CoordTransform::new(&from, &to)?),
+    }
+}
+
+// Transforms all points in one PROJ call. Points are `[x, y]` or `[x, y, z]`; a point
+// that cannot be transformed, e.g. outside the area a projection is defined for,
+// comes back as null without failing the others.
+fn transform_points(
+    transform: &CoordTransform,
+    points: &[Vec<f64>],
+) -> Result<Vec<Option<Vec<f64>>>, GdalError> {
+    if let Some(point) = points.iter().find(|point| !(2..=3).contains(&point.len())) {
+        return Err(GdalError::InvalidArgument(format!(
+            "Coordinates need 2 or 3 values, got {:?}",
+            point
+        )));
+    }
+    let mut x: Vec<f64> = points.iter().map(|point| point[0]).collect();
+    let mut y: Vec<f64> = points.iter().map(|point| point[1]).collect();
+    let mut z: Vec<f64> = points
+        .iter()
+        .map(|point| point.get(2).copied().unwrap_or(0.0))
+        .collect();
+    let mut success = vec![0; points.len()];
+    unsafe {
+        gdal_sys::OCTTransformEx(
+            transform.to_c_hct(),
+            points.len() as i32,
+            x.as_mut_ptr(),
+            y.as_mut_ptr(),
+            z.as_mut_ptr(),
+            success.as_mut_ptr(),
+        );
+    }
+
+    Ok(points
+        .iter()
+        .enumerate()
+        .map(|(index, point)| {
+            let ok = success[index] != 0 && x[index].is_finite() && y[index].is_finite();
+            ok.then(|| match point.len() {
+                3 => vec![x[index], y[index], z[index]],
+                _ => vec![x[index], y[index]],
+            })
+        })
+        .collect())
+}
+
+// Reprojects coordinates between two CRSs in one call, e.g. for "go to coordinate" or
+// the cursor position readout. CRSs are anything `parse_srs` accepts.
+#[tauri::command]
+pub async fn transform_coordinates(
+    points: Vec<Vec<f64>>,
+    src_crs: String,
+    dst_crs: String,
+    axis_order: Option<AxisOrder>,
+) -> Result<Vec<Option<Vec<f64>>>, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let transform = coordinate_transform(&src_crs, &dst_crs, axis_order.unwrap_or_default())
+            .map_err(|e| e.to_string())?;
+        transform_points(&transform, &points).map_err(|e| e.to_string())
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrsKind {
+    Geographic2d,
+    Geographic3d,
+    Geocentric,
+    Projected,
+    Vertical,
+    Compound,
+    Other,
+}
+
+impl CrsKind {
+    fn from_osr(kind: gdal_sys::OSRCRSType::Type) -> Self {
+        match kind {
+            gdal_sys::OSRCRSType::OSR_CRS_TYPE_GEOGRAPHIC_2D => CrsKind::Geographic2d,
+            gdal_sys::OSRCRSType::OSR_CRS_TYPE_GEOGRAPHIC_3D => CrsKind::Geographic3d,
+            gdal_sys::OSRCRSType::OSR_CRS_TYPE_GEOCENTRIC => CrsKind::Geocentric,
+            gdal_sys::OSRCRSType::OSR_CRS_TYPE_PROJECTED => CrsKind::Projected,
+            gdal_sys::OSRCRSType::OSR_CRS_TYPE_VERTICAL => CrsKind::Vertical,
+            gdal_sys::OSRCRSType::OSR_CRS_TYPE_COMPOUND => CrsKind::Compound,
+            _ => CrsKind::Other,
+        }
+    }
+
+    fn of(srs: &SpatialRef) -> Self {
+        if srs.is_compound() {
+            CrsKind::Compound
+        } else if srs.is_projected() {
+            CrsKind::Projected
+        } else if srs.is_geocentric() {
+            CrsKind::Geocentric
+        } else if srs.is_vertical() {
+            CrsKind::Vertical
+        } else if srs.is_geographic() && srs.axes_count() == 3 {
+            CrsKind::Geographic3d
+        } else if srs.is_geographic() {
+            CrsKind::Geographic2d
+        } else {
+            CrsKind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrsRecord {
+    // "EPSG:32633", usable wherever a CRS definition is accepted
+    pub code: String,
+    pub name: String,
+    pub kind: CrsKind,
+    pub deprecated: bool,
+    pub area_name: Option<String>,
+    // Area of use in degrees; west is greater than east across the antimeridian
+    pub area_of_use: Option<Extent>,
+    pub projection_method: Option<String>,
+}
+
+// Every CRS in the PROJ database, read once per session
+static CRS_RECORDS: OnceLock<Vec<CrsRecord>> = OnceLock::new();
+
+fn crs_records() -> &'static [CrsRecord] {
+    CRS_RECORDS.get_or_init(|| unsafe {
+        let mut count = 0;
+        let list =
+            gdal_sys::OSRGetCRSInfoListFromDatabase(std::ptr::null(), std::ptr::null(), &mut count);
+        if list.is_null() {
+            return Vec::new();
+        }
+        let records = std::slice::from_raw_parts(list, count.max(0) as usize)
+            .iter()
+            .filter(|info| !info.is_null())
+            .map(|&info| {
+                let info = &*info;
+                CrsRecord {
+                    code: format!(
+                        "{}:{}",
+                        string_from_ptr(info.pszAuthName).unwrap_or_default(),
+                        string_from_ptr(info.pszCode).unwrap_or_default()
+                    ),
+                    name: string_from_ptr(info.pszName).unwrap_or_default(),
+                    kind: CrsKind::from_osr(info.eType),
+                    deprecated: info.bDeprecated != 0,
+                    area_name: string_from_ptr(info.pszAreaName),
+                    area_of_use: (info.bBboxValid != 0).then_some(Extent {
+                        min_x: info.dfWestLongitudeDeg,
+                        min_y: info.dfSouthLatitudeDeg,
+                        max_x: info.dfEastLongitudeDeg,
+                        max_y: info.dfNorthLatitudeDeg,
+                    }),
+                    projection_method: string_from_ptr(info.pszProjectionMethod),
+                }
+            })
+            .collect();
+        gdal_sys::OSRDestroyCRSInfoList(list);
+        records
+    })
+}
+
+// How well a record matches the query, None when it does not. Codes match exactly,
+// with or without the authority; names must contain every word of the query.
+fn match_rank(record: &CrsRecord, query: &str, words: &[String]) -> Option<u8> {
+    let code = record.code.to_lowercase();
+    let bare_code = code.split_once(':').map_or(code.as_str(), |(_, code)| code);
+    if code == query || bare_code == query {
+        return Some(0);
+    }
+    let name = record.name.to_lowercase();
+    if !words.iter().all(|word| name.contains(word.as_str())) {
+        return None;
+    }
+    if name == query {
+        Some(1)
+    } else if name.starts_with(query) {
+        Some(2)
+    } else if bare_code.starts_with(query) {
+        Some(3)
+    } else {
+        Some(4)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrsSearchOptions {
+    // Only this authority, e.g. "EPSG" or "ESRI"
+    pub authority: Option<String>,
+    // Only these kinds of CRS, any kind when empty
+    pub kinds: Vec<CrsKind>,
+    pub include_deprecated: bool,
+    // At most this many matches, 50 when unset
+    pub limit: Option<usize>,
+}
+
+// Searches the PROJ database by code ("32633", "EPSG:32633") or name ("utm 33n"),
+// best matches first, for populating a CRS picker
+#[tauri::command]
+pub async fn search_crs(
+    query: String,
+    options: Option<CrsSearchOptions>,
+) -> Result<Vec<CrsRecord>, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let options = options.unwrap_or_default();
+        let query = query.trim().to_lowercase();
+        let words: Vec<String> = query.split_whitespace().map(str::to_string).collect();
+        let authority = options
+            .authority
+            .as_ref()
+            .map(|authority| format!("{}:", authority.to_lowercase()));
+
+        let mut matches: Vec<(u8, &CrsRecord)> = crs_records()
+            .iter()
+            .filter(|record| options.include_deprecated || !record.deprecated)
+            .filter(|record| options.kinds.is_empty() || options.kinds.contains(&record.kind))
+            .filter(|record| {
+                authority
+                    .as_ref()
+                    .is_none_or(|prefix| record.code.to_lowercase().starts_with(prefix))
+            })
+            .filter_map(|record| match_rank(record, &query, &words).map(|rank| (rank, record)))
+            .collect();
+        // Stable, so records keep the database order (by authority and code) within a rank
+        matches.sort_by_key(|(rank, _)| *rank);
+
+        Ok(matches
+            .into_iter()
+            .take(options.limit.unwrap_or(50))
+            .map(|(_, record)| record.clone())
+            .collect())
+    })
+    .await
+}
+
+// Single-line WKT in one of the formats OSRExportToWktEx knows, e.g. "WKT2_2019"
+fn export_wkt(srs: &SpatialRef, format: &str) -> Result<String, GdalError> {
+    let mut options = CslStringList::new();
+    options.add_string(&format!("FORMAT={}", format))?;
+    options.add_string("MULTILINE=NO")?;
+    let mut wkt = std::ptr::null_mut();
+    unsafe {
+        let err = gdal_sys::OSRExportToWktEx(srs.to_c_hsrs(), &mut wkt, options.as_ptr() as _);
+        let text = string_from_ptr(wkt);
+        gdal_sys::VSIFree(wkt as _);
+        match text {
+            Some(text) if err == gdal_sys::OGRErr::OGRERR_NONE => Ok(text),
+            _ => Err(last_error("OSRExportToWktEx")),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CrsDescription {
+    pub input: String,
+    pub valid: bool,
+    // Why the input could not be read; everything below is unset then
+    pub error: Option<String>,
+    // Problems with an input that was read, e.g. no known code or a deprecated one
+    pub warnings: Vec<String>,
+    // Authority code such as "EPSG:32633", when the CRS has or matches one
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub kind: Option<CrsKind>,
+    pub deprecated: bool,
+    pub units: Option<String>,
+    pub area_name: Option<String>,
+    pub area_of_use: Option<Extent>,
+    pub wkt1: Option<String>,
+    pub wkt2: Option<String>,
+    pub esri_wkt: Option<String>,
+    pub proj4: Option<String>,
+    pub projjson: Option<Value>,
+}
+
+fn describe(input: &str) -> CrsDescription {
+    let mut description = CrsDescription {
+        input: input.to_string(),
+        ..Default::default()
+    };
+    let srs = match parse_srs(input.trim()) {
+        Ok(srs) => srs,
+        Err(e) => {
+            description.error = Some(e.to_string());
+            return description;
+        }
+    };
+    description.valid = true;
+
+    let mut identified = srs.clone();
+    if identified.auth_code().is_err() {
+        let _ = identified.auto_identify_epsg();
+    }
+    description.code = match (identified.auth_name(), identified.auth_code()) {
+        (Some(name), Ok(code)) => Some(format!("{}:{}", name, code)),
+        _ => {
+            description
+                .warnings
+                .push("No authority code matches this CRS".to_string());
+            None
+        }
+    };
+    if let Some(code) = &description.code {
+        description.deprecated = crs_records()
+            .iter()
+            .any(|record| record.deprecated && record.code.eq_ignore_ascii_case(code));
+        if description.deprecated {
+            description
+                .warnings
+                .push(format!("{} is deprecated in the EPSG database", code));
+        }
+    }
+    if unsafe { gdal_sys::OSRValidate(srs.to_c_hsrs()) } != gdal_sys::OGRErr::OGRERR_NONE {
+        description
+            .warnings
+            .push("The definition does not fully conform to the WKT specification".to_string());
+    }
+
+    let kind = CrsKind::of(&srs);
+    description.units = match kind {
+        CrsKind::Geographic2d | CrsKind::Geographic3d => srs.angular_units_name(),
+        _ => srs.linear_units_name(),
+    };
+    description.kind = Some(kind);
+    description.name = srs.name();
+    if let Some(area) = srs.area_of_use() {
+        description.area_of_use = Some(Extent {
+            min_x: area.west_lon_degree,
+            min_y: area.south_lat_degree,
+            max_x: area.east_lon_degree,
+            max_y: area.north_lat_degree,
+        });
+        description.area_name = Some(area.name).filter(|name| !name.is_empty());
+    }
+
+    description.wkt1 = export_wkt(&srs, "WKT1_GDAL").ok();
+    description.wkt2 = export_wkt(&srs, "WKT2_2019").ok();
+    description.esri_wkt = export_wkt(&srs, "WKT1_ESRI").ok();
+    description.proj4 = srs.to_proj4().ok().filter(|proj4| !proj4.is_empty());
+    description.projjson = srs
+        .to_projjson()
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok());
+    for (form, missing) in [
+        ("WKT1", description.wkt1.is_none()),
+        ("ESRI WKT", description.esri_wkt.is_none()),
+        ("a PROJ string", description.proj4.is_none()),
+    ] {
+        if missing {
+            description
+                .warnings
+                .push(format!("Cannot be expressed as {}", form));
+        }
+    }
+    description
+}
+
+// Reads a CRS in any form users paste (EPSG code, WKT1/WKT2, ESRI WKT, PROJJSON,
+// PROJ string, URN) and returns it in all the others. An unreadable input is reported
+// in the description rather than as an error.
+#[tauri::command]
+pub async fn describe_crs(input: String) -> Result<CrsDescription, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        Ok(describe(&input))
+    })
+    .await
+}
@@ -0,0 +1,377 @@
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags, Metadata};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
+
+use crate::ingest;
+use crate::network::{check_remote, is_remote};
+use crate::progress::Progress;
+use crate::raster::translate::translate;
+use crate::render::cache::RenderCache;
+use crate::render::mercator::mercator_vrt;
+use crate::settings::SettingsStore;
+use crate::{dataset_info, run_blocking, setup_gdal_runtime, DatasetInfo, GdalError};
+
+pub struct OpenDataset {
+    // For in-memory copies this is the file the copy was made from
+    pub path: String,
+    // Web Mercator view of `dataset` for the map, built on first use. Declared first so
+    // it is dropped before the dataset it reads from.
+    mercator: Option<Dataset>,
+    pub dataset: Dataset,
+    pub in_memory: bool,
+}
+
+impl OpenDataset {
+    pub fn new(path: String, dataset: Dataset, in_memory: bool) -> Self {
+        Self {
+            path,
+            mercator: None,
+            dataset,
+            in_memory,
+        }
+    }
+
+    // Swaps in a reopened or rewrapped dataset
+    pub fn replace_dataset(&mut self, dataset: Dataset) {
+        self.mercator = None;
+        self.dataset = dataset;
+    }
+
+    // Drops the Web Mercator view after the dataset's georeferencing changed in place
+    pub fn reset_mercator(&mut self) {
+        self.mercator = None;
+    }
+
+    pub fn mercator(&mut self) -> Result<&Dataset, GdalError> {
+        if self.mercator.is_none() {
+            self.mercator = Some(mercator_vrt(&self.dataset)?);
+        }
+        Ok(self.mercator.as_ref().unwrap())
+    }
+}
+
+pub type DatasetEntry = Arc<Mutex<OpenDataset>>;
+
+// Datasets kept open between commands, addressed by handle from the frontend.
+// Each entry has its own lock since GDAL datasets must not be used from two threads at once.
+#[derive(Default)]
+pub struct DatasetRegistry {
+    next_handle: Mutex<u64>,
+    datasets: Mutex<HashMap<u64, DatasetEntry>>,
+}
+
+impl DatasetRegistry {
+    pub fn insert(&self, open: OpenDataset) -> u64 {
+        let handle = {
+            let mut next_handle = self.next_handle.lock().unwrap();
+            *next_handle += 1;
+            *next_handle
+        };
+
+        self.datasets
+            .lock()
+            .unwrap()
+            .insert(handle, Arc::new(Mutex::new(open)));
+        handle
+    }
+
+    pub fn get(&self, handle: u64) -> Result<DatasetEntry, GdalError> {
+        self.datasets
+            .lock()
+            .unwrap()
+            .get(&handle)
+            .cloned()
+            .ok_or(GdalError::UnknownHandle(handle))
+    }
+
+    pub fn entries(&self) -> Vec<(u64, DatasetEntry)> {
+        self.datasets
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(handle, entry)| (*handle, entry.clone()))
+            .collect()
+    }
+
+    pub fn remove(&self, handle: u64) -> bool {
+        self.datasets.lock().unwrap().remove(&handle).is_some()
+    }
+}
+
+// In-memory clones above this size are refused unless the caller raises the limit
+const DEFAULT_MEMORY_LIMIT: u64 = 2 * 1024 * 1024 * 1024;
+
+// Drivers worth probing for a given file extension, `None` when any driver may apply
+fn drivers_for_extension(path: &Path) -> Option<&'static [&'static str]> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let drivers: &'static [&'static str] = match extension.as_str() {
+        "tif" | "tiff" | "gtiff" => &["GTiff", "COG"],
+        "jp2" | "j2k" => &["JP2OpenJPEG", "JP2ECW", "JP2KAK", "JP2MrSID"],
+        "png" => &["PNG"],
+        "jpg" | "jpeg" => &["JPEG"],
+        "gif" => &["GIF"],
+        "bmp" => &["BMP"],
+        "img" => &["HFA"],
+        "vrt" => &["VRT"],
+        "asc" => &["AAIGrid"],
+        "nc" => &["netCDF"],
+        "hdf" | "h4" => &["HDF4"],
+        "h5" | "he5" => &["HDF5"],
+        "gpkg" => &["GPKG"],
+        "shp" => &["ESRI Shapefile"],
+        "geojson" | "json" => &["GeoJSON", "GeoJSONSeq"],
+        "fgb" => &["FlatGeobuf"],
+        "pmtiles" => &["PMTiles"],
+        "mbtiles" => &["MBTiles"],
+        "pbf" | "osm" => &["OSM"],
+        "kml" => &["KML", "LIBKML"],
+        "gml" => &["GML"],
+        _ => return None,
+    };
+    Some(drivers)
+}
+
+// Fast read-only open for scanners and thumbnails: only probes the drivers matching the
+// extension and tells GDAL there are no sibling files, so it skips reading the directory
+// to look for .aux.xml, .ovr or world files. Georeferencing from sidecars may be missing.
+pub(crate) fn open_quick(path: &Path) -> Result<Dataset, GdalError> {
+    // Shapefiles are split over .shp/.shx/.dbf, so they still need the directory listing
+    let is_shapefile = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("shp"));
+    let sibling_files: Option<&[&str]> = if is_shapefile { None } else { Some(&[]) };
+
+    Ok(Dataset::open_ex(
+        path,
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_READONLY,
+            allowed_drivers: drivers_for_extension(path),
+            sibling_files,
+            ..Default::default()
+        },
+    )?)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Subdataset {
+    // Connection string such as `NETCDF:"file.nc":sst`, passed back to `open_dataset`
+    pub name: String,
+    pub description: String,
+}
+
+// Subdatasets in the order the driver lists them, empty for plain datasets
+pub(crate) fn subdatasets(dataset: &Dataset) -> Vec<Subdataset> {
+    let mut names = Vec::new();
+    let mut descriptions = HashMap::new();
+    for item in dataset.metadata_domain("SUBDATASETS").unwrap_or_default() {
+        let Some((key, value)) = item.split_once('=') else {
+            continue;
+        };
+        let Some(key) = key.strip_prefix("SUBDATASET_") else {
+            continue;
+        };
+        if let Some(index) = key.strip_suffix("_NAME") {
+            names.push((index.to_string(), value.to_string()));
+        } else if let Some(index) = key.strip_suffix("_DESC") {
+            descriptions.insert(index.to_string(), value.to_string());
+        }
+    }
+    names.sort_by_key(|(index, _)| index.parse::<usize>().unwrap_or(usize::MAX));
+    names
+        .into_iter()
+        .map(|(index, name)| Subdataset {
+            description: descriptions.remove(&index).unwrap_or_else(|| name.clone()),
+            name,
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn list_subdatasets(file_path: String) -> Result<Vec<Subdataset>, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let remote = file_path.starts_with("/vsi") || is_remote(&file_path);
+        if !remote && !Path::new(&file_path).exists() {
+            return Err(format!("File not found: {}", file_path));
+        }
+        check_remote(&file_path).map_err(|e| e.to_string())?;
+        let dataset = Dataset::open(&file_path).map_err(|e| e.to_string())?;
+        Ok(subdatasets(&dataset))
+    })
+    .await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenedDataset {
+    pub handle: u64,
+    pub info: DatasetInfo,
+    // Set when an ingest recipe replaced the file with a faster cached copy
+    pub cached_path: Option<String>,
+}
+
+#[tauri::command]
+pub async fn open_dataset(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+    settings: State<'_, SettingsStore>,
+    file_path: String,
+    quick: Option<bool>,
+    subdataset: Option<String>,
+) -> Result<OpenedDataset, String> {
+    let recipes = settings.get().ingest_recipes;
+
+    let (path, dataset, cached_path) = run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        // URLs and /vsi paths such as /vsicurl/ or /vsis3/ have no local file to look for
+        let remote = file_path.starts_with("/vsi") || is_remote(&file_path);
+        if !remote && !Path::new(&file_path).exists() {
+            return Err(format!("File not found: {}", file_path));
+        }
+        check_remote(&file_path).map_err(|e| e.to_string())?;
+
+        // A subdataset of the container at `file_path`, registered under its own name so
+        // it can be reopened. Ingest recipes work on whole files, so they are skipped.
+        if let Some(name) = subdataset {
+            let container = Dataset::open(&file_path).map_err(|e| e.to_string())?;
+            if !subdatasets(&container).iter().any(|item| item.name == name) {
+                return Err(format!("{} has no subdataset named {}", file_path, name));
+            }
+            let dataset = Dataset::open(&name).map_err(|e| e.to_string())?;
+            return Ok((name, dataset, None));
+        }
+
+        // Quick looks skip ingest recipes, they exist to avoid expensive work
+        if quick.unwrap_or(false) {
+            let dataset = open_quick(Path::new(&file_path)).map_err(|e| e.to_string())?;
+            return Ok((file_path, dataset, None));
+        }
+
+        let dataset = Dataset::open(&file_path).map_err(|e| e.to_string())?;
+
+        // A failing recipe only costs speed, so fall back to the original file
+        match ingest::apply_recipes(&app, &recipes, Path::new(&file_path), &dataset) {
+            Ok(Some(cached)) => {
+                let cached_dataset = Dataset::open(&cached).map_err(|e| e.to_string())?;
+                let cached = cached.to_string_lossy().to_string();
+                Ok((cached.clone(), cached_dataset, Some(cached)))
+            }
+            _ => Ok((file_path, dataset, None)),
+        }
+    })
+    .await?;
+
+    let info = dataset_info(&dataset);
+    let handle = registry.insert(OpenDataset::new(path, dataset, false));
+
+    Ok(OpenedDataset {
+        handle,
+        info,
+        cached_path,
+    })
+}
+
+#[tauri::command]
+pub fn close_dataset(
+    registry: State<'_, DatasetRegistry>,
+    cache: State<'_, RenderCache>,
+    handle: u64,
+) -> Result<(), String> {
+    if registry.remove(handle) {
+        cache.invalidate(handle);
+        Ok(())
+    } else {
+        Err(GdalError::UnknownHandle(handle).to_string())
+    }
+}
+
+fn raster_bytes(dataset: &Dataset) -> u64 {
+    let (size_x, size_y) = dataset.raster_size();
+    dataset
+        .rasterbands()
+        .flatten()
+        .map(|band| size_x as u64 * size_y as u64 * band.band_type().bytes() as u64)
+        .sum()
+}
+
+// Copies a raster into the MEM driver so destructive edits never touch the original file
+#[tauri::command]
+pub async fn clone_to_memory(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    max_bytes: Option<u64>,
+) -> Result<OpenedDataset, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    let (path, copy) = run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        if open.dataset.raster_count() == 0 {
+            return Err("Only raster datasets can be cloned to memory".to_string());
+        }
+
+        let bytes = raster_bytes(&open.dataset);
+        let limit = max_bytes.unwrap_or(DEFAULT_MEMORY_LIMIT);
+        if bytes > limit {
+            return Err(format!(
+                "Dataset needs {} MB in memory, more than the {} MB limit",
+                bytes / (1024 * 1024),
+                limit / (1024 * 1024)
+            ));
+        }
+
+        let args = vec!["-of".to_string(), "MEM".to_string()];
+        let progress = Progress::new(&app, "clone_to_memory");
+        let copy = translate(&open.dataset, "", &args, &progress).map_err(|e| e.to_string())?;
+        Ok((open.path.clone(), copy))
+    })
+    .await?;
+
+    let info = dataset_info(&copy);
+    let handle = registry.insert(OpenDataset::new(path, copy, true));
+
+    Ok(OpenedDataset {
+        handle,
+        info,
+        cached_path: None,
+    })
+}
+
+// Writes any open dataset (typically an edited in-memory clone) to a new file
+#[tauri::command]
+pub async fn save_dataset_as(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    dst: String,
+    format: Option<String>,
+) -> Result<DatasetInfo, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        // Without an explicit format GDAL picks the driver from the file extension
+        let mut args = Vec::new();
+        if let Some(format) = format {
+            args.push("-of".to_string());
+            args.push(format);
+        }
+
+        let open = entry.lock().unwrap();
+        let progress = Progress::new(&app, "save_dataset_as");
+        let output = translate(&open.dataset, &dst, &args, &progress).map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
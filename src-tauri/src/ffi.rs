@@ -0,0 +1,106 @@
+use gdal::cpl::CslStringList;
+use std::ffi::{CStr, CString};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::network::ensure_online;
+use crate::GdalError;
+
+static NEXT_VSIMEM_ID: AtomicU64 = AtomicU64::new(1);
+
+// Builds the error for a failed GDAL call from the last CPL error message
+pub(crate) fn last_error(function: &str) -> GdalError {
+    let message = unsafe {
+        let ptr = gdal_sys::CPLGetLastErrorMsg();
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        }
+    };
+    unsafe { gdal_sys::CPLErrorReset() };
+
+    if message.is_empty() {
+        GdalError::OperationFailed(format!("{} failed", function))
+    } else {
+        GdalError::OperationFailed(format!("{}: {}", function, message))
+    }
+}
+
+pub(crate) fn c_string(value: &str) -> Result<CString, GdalError> {
+    CString::new(value)
+        .map_err(|_| GdalError::InvalidArgument(format!("Embedded NUL in '{}'", value)))
+}
+
+// Copies a string owned by GDAL, None for a null pointer
+pub(crate) unsafe fn string_from_ptr(ptr: *const std::ffi::c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+// Converts command-line style arguments into the argv list the GDAL utility API expects
+pub(crate) fn arg_list(args: &[String]) -> Result<CslStringList, GdalError> {
+    let mut list = CslStringList::new();
+    for arg in args {
+        list.add_string(arg)?;
+    }
+    Ok(list)
+}
+
+// Unique /vsimem/ path for scratch datasets that never need to touch the disk
+pub(crate) fn vsimem_path(file_name: &str) -> String {
+    let id = NEXT_VSIMEM_ID.fetch_add(1, Ordering::Relaxed);
+    format!("/vsimem/tauri_gdal_{}_{}", id, file_name)
+}
+
+// gdal-sys does not bind cpl_http.h. Only the leading fields of CPLHTTPResult, which
+// have not changed across GDAL 3.x, are declared; the struct is never allocated here.
+#[repr(C)]
+struct CPLHTTPResult {
+    status: std::ffi::c_int,
+    content_type: *mut std::ffi::c_char,
+    error: *mut std::ffi::c_char,
+}
+
+extern "C" {
+    fn CPLHTTPFetch(
+        url: *const std::ffi::c_char,
+        options: gdal_sys::CSLConstList,
+    ) -> *mut CPLHTTPResult;
+    fn CPLHTTPDestroyResult(result: *mut CPLHTTPResult);
+}
+
+// POSTs `body` through GDAL's libcurl support, so HTTPS works without another TLS stack
+pub(crate) fn http_post(url: &str, body: &str, content_type: &str) -> Result<(), GdalError> {
+    ensure_online(url)?;
+    let c_url = c_string(url)?;
+    let mut options = CslStringList::new();
+    options.set_name_value("POSTFIELDS", body)?;
+    options.set_name_value("HEADERS", &format!("Content-Type: {}", content_type))?;
+    options.set_name_value("TIMEOUT", "30")?;
+
+    unsafe {
+        let result = CPLHTTPFetch(c_url.as_ptr(), options.as_ptr());
+        if result.is_null() {
+            return Err(last_error("CPLHTTPFetch"));
+        }
+        let error = (*result).error;
+        let outcome = if (*result).status != 0 || !error.is_null() {
+            let message = if error.is_null() {
+                format!("request failed with status {}", (*result).status)
+            } else {
+                CStr::from_ptr(error).to_string_lossy().into_owned()
+            };
+            Err(GdalError::OperationFailed(format!(
+                "POST {}: {}",
+                url, message
+            )))
+        } else {
+            Ok(())
+        };
+        CPLHTTPDestroyResult(result);
+        outcome
+    }
+}
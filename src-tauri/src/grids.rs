@@ -0,0 +1,435 @@
+use gdal::cpl::CslStringList;
+use gdal::vector::sql::Dialect;
+use gdal::vector::LayerAccess;
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::CStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+
+use crate::catalog::sql_string;
+use crate::crs::{crs_key, parse_srs};
+use crate::ffi::{c_string, last_error};
+use crate::network::ensure_online;
+use crate::paths::AppPaths;
+use crate::progress::{gdal_progress, Progress};
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+// Directory under the data directory that downloaded grids go to
+const GRID_DIR: &str = "proj-grids";
+
+// PROJ's content delivery network, which hosts every openly licensed grid as GeoTIFF
+const CDN_URL: &str = "https://cdn.proj.org";
+
+// Where the managed grids are kept and what PROJ currently searches
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjGridStatus {
+    pub grid_dir: String,
+    pub search_paths: Vec<String>,
+    // PROJ fetches missing grids from the CDN on demand, see `NetworkSettings::proj_network`
+    pub network_enabled: bool,
+    // Grid files in `grid_dir`
+    pub installed: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GridOperation {
+    // e.g. "EPSG:7709"
+    pub code: String,
+    pub name: String,
+    // Metres, when the EPSG registry gives one
+    pub accuracy: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransformationGrid {
+    // File PROJ looks for, e.g. uk_os_OSTN15_NTv2_OSGBtoETRS.tif
+    pub name: String,
+    // Name in the EPSG registry, e.g. OSTN15_NTv2_OSGBtoETRS.gsb
+    pub original_name: String,
+    pub operations: Vec<GridOperation>,
+    // Where PROJ finds the grid, None when it is missing
+    pub installed: Option<String>,
+    // Openly licensed grids are on the CDN; others have to be obtained from their producer
+    pub open_license: bool,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransformationGrids {
+    pub src_crs: String,
+    pub dst_crs: String,
+    pub grids: Vec<TransformationGrid>,
+    pub missing: usize,
+    pub network_enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DownloadedGrid {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+}
+
+pub(crate) fn grid_dir(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join(GRID_DIR)
+}
+
+fn search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    unsafe {
+        let list = gdal_sys::OSRGetPROJSearchPaths();
+        if list.is_null() {
+            return paths;
+        }
+        let mut index = 0;
+        while !(*list.add(index)).is_null() {
+            let path = CStr::from_ptr(*list.add(index)).to_string_lossy();
+            paths.push(PathBuf::from(path.as_ref()));
+            index += 1;
+        }
+        gdal_sys::CSLDestroy(list);
+    }
+    paths
+}
+
+// Adds the managed grid directory to PROJ's search paths, after the ones already in
+// use so proj.db is still found first. PROJ_DATA (PROJ_LIB before PROJ 9.1) is set to
+// the same list for contexts PROJ creates from the environment.
+pub(crate) fn use_grid_dir(dir: &Path) -> Result<(), GdalError> {
+    fs::create_dir_all(dir)?;
+    let mut paths = search_paths();
+    if !paths.iter().any(|path| path == dir) {
+        paths.push(dir.to_path_buf());
+    }
+    let mut list = CslStringList::new();
+    for path in &paths {
+        list.add_string(&path.to_string_lossy())?;
+    }
+    unsafe { gdal_sys::OSRSetPROJSearchPaths(list.as_ptr() as *const *const _) };
+    if let Ok(joined) = env::join_paths(&paths) {
+        env::set_var("PROJ_DATA", &joined);
+        env::set_var("PROJ_LIB", &joined);
+    }
+    Ok(())
+}
+
+fn network_enabled() -> bool {
+    unsafe { gdal_sys::OSRGetPROJEnableNetwork() != 0 }
+}
+
+fn find_grid(name: &str) -> Option<PathBuf> {
+    search_paths()
+        .into_iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+// The PROJ database, read through GDAL's SQLite driver
+fn open_proj_db() -> Result<Dataset, GdalError> {
+    let path = find_grid("proj.db").ok_or_else(|| {
+        GdalError::InvalidArgument("proj.db was not found in the PROJ search paths".to_string())
+    })?;
+    Ok(Dataset::open_ex(
+        &path,
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_READONLY | GdalOpenFlags::GDAL_OF_VECTOR,
+            allowed_drivers: Some(&["SQLite"]),
+            ..Default::default()
+        },
+    )?)
+}
+
+fn query(db: &Dataset, sql: String) -> Result<Vec<Vec<Option<String>>>, GdalError> {
+    let mut rows = Vec::new();
+    if let Some(mut result) = db.execute_sql(sql, None, Dialect::DEFAULT)? {
+        let columns = result.defn().fields().count();
+        for feature in result.features() {
+            rows.push(
+                (0..columns)
+                    .map(|index| feature.field_as_string(index))
+                    .collect::<Result<Vec<_>, _>>()?,
+            );
+        }
+    }
+    Ok(rows)
+}
+
+// The CRS with the ones PROJ looks up transformations between in its place: the
+// geodetic base of a projected CRS and both parts of a compound one
+fn crs_components(db: &Dataset, crs: &str) -> Result<Vec<(String, String)>, GdalError> {
+    let key = parse_srs(crs).ok().as_ref().and_then(crs_key);
+    let Some((auth, code)) = key.as_deref().and_then(|key| key.split_once(':')) else {
+        return Err(GdalError::InvalidArgument(format!(
+            "{} has no authority code to look transformations up by",
+            crs
+        )));
+    };
+    let mut components = vec![(auth.to_string(), code.to_string())];
+    let mut index = 0;
+    while index < components.len() {
+        let (auth, code) = components[index].clone();
+        let filter = format!(
+            "auth_name = {} AND code = {}",
+            sql_string(&auth),
+            sql_string(&code)
+        );
+        let mut found = query(
+            db,
+            format!(
+                "SELECT geodetic_crs_auth_name, geodetic_crs_code FROM projected_crs WHERE {}",
+                filter
+            ),
+        )?;
+        for row in query(
+            db,
+            format!(
+                "SELECT horiz_crs_auth_name, horiz_crs_code, vertical_crs_auth_name, \
+                 vertical_crs_code FROM compound_crs WHERE {}",
+                filter
+            ),
+        )? {
+            found.push(row[..2].to_vec());
+            found.push(row[2..].to_vec());
+        }
+        for row in found {
+            if let [Some(auth), Some(code)] = row.as_slice() {
+                let component = (auth.clone(), code.clone());
+                if !components.contains(&component) {
+                    components.push(component);
+                }
+            }
+        }
+        index += 1;
+    }
+    Ok(components)
+}
+
+// SQL condition matching `column_crs_auth_name`/`column_crs_code` against any of `crs`
+fn crs_condition(column: &str, crs: &[(String, String)]) -> String {
+    let terms: Vec<String> = crs
+        .iter()
+        .map(|(auth, code)| {
+            format!(
+                "({0}_crs_auth_name = {1} AND {0}_crs_code = {2})",
+                column,
+                sql_string(auth),
+                sql_string(code)
+            )
+        })
+        .collect();
+    format!("({})", terms.join(" OR "))
+}
+
+// Grids of the grid-based operations between two CRSs, in either direction, directly or
+// as a step of a concatenated operation. Operations through an intermediate CRS that
+// PROJ may chain at runtime are not covered.
+fn transformation_grids(src_crs: &str, dst_crs: &str) -> Result<TransformationGrids, GdalError> {
+    let db = open_proj_db()?;
+    let source = crs_components(&db, src_crs)?;
+    let target = crs_components(&db, dst_crs)?;
+    let between = |alias: &str| {
+        format!(
+            "(({} AND {}) OR ({} AND {}))",
+            crs_condition(&format!("{}.source", alias), &source),
+            crs_condition(&format!("{}.target", alias), &target),
+            crs_condition(&format!("{}.source", alias), &target),
+            crs_condition(&format!("{}.target", alias), &source),
+        )
+    };
+    let mut rows = query(
+        &db,
+        format!(
+            "SELECT g.auth_name, g.code, g.name, g.accuracy, g.grid_name, g.grid2_name \
+             FROM grid_transformation g WHERE g.deprecated = 0 AND {}",
+            between("g")
+        ),
+    )?;
+    rows.extend(query(
+        &db,
+        format!(
+            "SELECT c.auth_name, c.code, c.name, c.accuracy, g.grid_name, g.grid2_name \
+             FROM concatenated_operation c \
+             JOIN concatenated_operation_step s \
+             ON s.operation_auth_name = c.auth_name AND s.operation_code = c.code \
+             JOIN grid_transformation g \
+             ON g.auth_name = s.step_auth_name AND g.code = s.step_code \
+             WHERE c.deprecated = 0 AND {}",
+            between("c")
+        ),
+    )?);
+
+    let mut grids: BTreeMap<String, TransformationGrid> = BTreeMap::new();
+    for row in rows {
+        let [auth, code, name, accuracy, grid, grid2] = row.as_slice() else {
+            continue;
+        };
+        for original_name in [grid, grid2].into_iter().flatten() {
+            if original_name.is_empty() {
+                continue;
+            }
+            let alternative = query(
+                &db,
+                format!(
+                    "SELECT proj_grid_name, url, open_license FROM grid_alternatives \
+                     WHERE original_grid_name = {}",
+                    sql_string(original_name)
+                ),
+            )?;
+            let (name_in_proj, url, open_license) = match alternative.first().map(Vec::as_slice) {
+                Some([Some(proj_name), url, open_license]) => (
+                    proj_name.clone(),
+                    url.clone().filter(|url| !url.is_empty()),
+                    open_license.as_deref() == Some("1"),
+                ),
+                _ => (original_name.clone(), None, false),
+            };
+            let entry = grids
+                .entry(name_in_proj.clone())
+                .or_insert_with(|| TransformationGrid {
+                    installed: find_grid(&name_in_proj).map(|path| path.display().to_string()),
+                    name: name_in_proj,
+                    original_name: original_name.clone(),
+                    operations: Vec::new(),
+                    open_license,
+                    url,
+                });
+            let code = format!(
+                "{}:{}",
+                auth.as_deref().unwrap_or_default(),
+                code.as_deref().unwrap_or_default()
+            );
+            if !entry
+                .operations
+                .iter()
+                .any(|operation| operation.code == code)
+            {
+                entry.operations.push(GridOperation {
+                    code,
+                    name: name.clone().unwrap_or_default(),
+                    accuracy: accuracy.as_deref().and_then(|value| value.parse().ok()),
+                });
+            }
+        }
+    }
+
+    let grids: Vec<TransformationGrid> = grids.into_values().collect();
+    Ok(TransformationGrids {
+        src_crs: src_crs.to_string(),
+        dst_crs: dst_crs.to_string(),
+        missing: grids.iter().filter(|grid| grid.installed.is_none()).count(),
+        grids,
+        network_enabled: network_enabled(),
+    })
+}
+
+// Copies one grid from the CDN into `dir`, through a temporary file so an interrupted
+// download never leaves a truncated grid for PROJ to pick up
+fn download_grid(dir: &Path, name: &str, progress: &Progress) -> Result<DownloadedGrid, GdalError> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(GdalError::InvalidArgument(format!(
+            "Invalid grid name: {}",
+            name
+        )));
+    }
+    let path = dir.join(name);
+    let partial = dir.join(format!("{}.part", name));
+    let source = c_string(&format!("/vsicurl/{}/{}", CDN_URL, name))?;
+    let target = c_string(&partial.to_string_lossy())?;
+    let failed = unsafe {
+        gdal_sys::VSICopyFile(
+            source.as_ptr(),
+            target.as_ptr(),
+            std::ptr::null_mut(),
+            gdal_sys::vsi_l_offset::MAX,
+            std::ptr::null(),
+            Some(gdal_progress),
+            progress.as_arg(),
+        ) != 0
+    };
+    if failed {
+        let _ = fs::remove_file(&partial);
+        return Err(last_error(&format!("Downloading {}", name)));
+    }
+    fs::rename(&partial, &path)?;
+    Ok(DownloadedGrid {
+        name: name.to_string(),
+        size: fs::metadata(&path)?.len(),
+        path: path.display().to_string(),
+    })
+}
+
+#[tauri::command]
+pub fn get_proj_grid_status(paths: State<'_, AppPaths>) -> Result<ProjGridStatus, String> {
+    // Ensure GDAL runtime is set up
+    setup_gdal_runtime();
+
+    let dir = grid_dir(&paths);
+    let mut installed: Vec<String> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| !name.ends_with(".part"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    installed.sort();
+    Ok(ProjGridStatus {
+        grid_dir: dir.display().to_string(),
+        search_paths: search_paths()
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+        network_enabled: network_enabled(),
+        installed,
+    })
+}
+
+// Datum-shift and geoid grids the transformations between two CRSs use, and which of
+// them PROJ cannot find, so they can be downloaded before working offline
+#[tauri::command]
+pub async fn list_transformation_grids(
+    src_crs: String,
+    dst_crs: String,
+) -> Result<TransformationGrids, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        transformation_grids(&src_crs, &dst_crs).map_err(|e| e.to_string())
+    })
+    .await
+}
+
+// Downloads grids by their PROJ file name from the PROJ CDN into the managed grid
+// directory, which PROJ searches from then on. Grids without an open license are not on
+// the CDN and fail; their `url` in `list_transformation_grids` says where to get them.
+#[tauri::command]
+pub async fn download_proj_grids(
+    app: AppHandle,
+    paths: State<'_, AppPaths>,
+    names: Vec<String>,
+) -> Result<Vec<DownloadedGrid>, String> {
+    let dir = grid_dir(&paths);
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        ensure_online(CDN_URL).map_err(|e| e.to_string())?;
+        use_grid_dir(&dir).map_err(|e| e.to_string())?;
+        let progress = Progress::new(&app, "download_proj_grids");
+        let total = names.len().max(1) as f64;
+        let mut downloaded = Vec::new();
+        for (index, name) in names.iter().enumerate() {
+            progress.set_range(index as f64 / total, (index + 1) as f64 / total);
+            downloaded.push(download_grid(&dir, name, &progress).map_err(|e| e.to_string())?);
+        }
+        Ok(downloaded)
+    })
+    .await
+}
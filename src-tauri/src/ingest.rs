@@ -0,0 +1,227 @@
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::paths::AppPaths;
+use crate::progress::Progress;
+use crate::raster::overviews::{self, default_levels};
+use crate::raster::translate::translate;
+use crate::raster::{Compression, Resampling};
+use crate::GdalError;
+
+// Open-time recipe that swaps a slow format for a fast cached derivative
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestRecipe {
+    // GDAL short driver name the recipe applies to, e.g. "AAIGrid"
+    pub driver: String,
+    pub action: IngestAction,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IngestAction {
+    // Converts to a tiled, compressed GeoTIFF with internal overviews
+    TiledGeotiff {
+        #[serde(default)]
+        compression: Compression,
+    },
+    // Builds overviews for a VRT wrapper, so the source file is never modified
+    Overviews {
+        #[serde(default)]
+        resampling: Resampling,
+    },
+}
+
+pub fn default_recipes() -> Vec<IngestRecipe> {
+    let tiled = |driver: &str| IngestRecipe {
+        driver: driver.to_string(),
+        action: IngestAction::TiledGeotiff {
+            compression: Compression::Deflate,
+        },
+        enabled: true,
+    };
+    let overviews = |driver: &str| IngestRecipe {
+        driver: driver.to_string(),
+        action: IngestAction::Overviews {
+            resampling: Resampling::Average,
+        },
+        enabled: true,
+    };
+
+    vec![
+        tiled("AAIGrid"),
+        tiled("XYZ"),
+        overviews("JP2OpenJPEG"),
+        overviews("JPEG"),
+        overviews("PNG"),
+    ]
+}
+
+pub(crate) fn cache_dir(app: &AppHandle) -> Result<PathBuf, GdalError> {
+    let paths = app.try_state::<AppPaths>().ok_or_else(|| {
+        GdalError::OperationFailed("Application paths are not set up".to_string())
+    })?;
+    Ok(paths.cache_dir.join("ingest"))
+}
+
+// Cache entries are keyed by path, size and modification time so edits invalidate them
+fn cache_key(path: &Path) -> Result<String, GdalError> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let mut hasher = DefaultHasher::new();
+    fs::canonicalize(path)?.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    modified.hash(&mut hasher);
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+fn build_cached(
+    dataset: &Dataset,
+    action: &IngestAction,
+    cached: &Path,
+    progress: &Progress,
+) -> Result<(), GdalError> {
+    let cached_str = cached.to_string_lossy();
+    let (size_x, size_y) = dataset.raster_size();
+    let levels = default_levels(size_x, size_y);
+
+    match action {
+        IngestAction::TiledGeotiff { compression } => {
+            let args: Vec<String> = [
+                "-of",
+                "GTiff",
+                "-co",
+                "TILED=YES",
+                "-co",
+                &format!("COMPRESS={}", compression.as_gdal_option()),
+                "-co",
+                "BIGTIFF=IF_SAFER",
+            ]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+
+            let output = translate(dataset, &cached_str, &args, progress)?;
+            if !levels.is_empty() {
+                overviews::build(&output, &levels, Resampling::Average, progress)?;
+            }
+            output.close()?;
+        }
+        IngestAction::Overviews { resampling } => {
+            let args = vec!["-of".to_string(), "VRT".to_string()];
+            translate(dataset, &cached_str, &args, progress)?.close()?;
+
+            // Read-only VRTs get their overviews in a sidecar <name>.vrt.ovr
+            if !levels.is_empty() {
+                let wrapper = Dataset::open(cached)?;
+                overviews::build(&wrapper, &levels, *resampling, progress)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Applies the first enabled recipe matching the dataset's driver and returns the path
+// of the cached derivative to open instead, reusing a previous run when still valid
+pub(crate) fn apply_recipes(
+    app: &AppHandle,
+    recipes: &[IngestRecipe],
+    path: &Path,
+    dataset: &Dataset,
+) -> Result<Option<PathBuf>, GdalError> {
+    let driver = dataset.driver().short_name();
+    let recipe = match recipes
+        .iter()
+        .find(|recipe| recipe.enabled && recipe.driver.eq_ignore_ascii_case(&driver))
+    {
+        Some(recipe) => recipe,
+        None => return Ok(None),
+    };
+    if dataset.raster_count() == 0 {
+        return Ok(None);
+    }
+
+    let dir = cache_dir(app)?;
+    fs::create_dir_all(&dir)?;
+
+    let extension = match recipe.action {
+        IngestAction::TiledGeotiff { .. } => "tif",
+        IngestAction::Overviews { .. } => "vrt",
+    };
+    let cached = dir.join(format!("{}.{}", cache_key(path)?, extension));
+    if cached.exists() {
+        return Ok(Some(cached));
+    }
+
+    let progress = Progress::new(app, "ingest");
+    if let Err(e) = build_cached(dataset, &recipe.action, &cached, &progress) {
+        // Don't leave a half-written entry that would be picked up next time
+        let _ = fs::remove_file(&cached);
+        let _ = fs::remove_file(cached.with_extension(format!("{}.ovr", extension)));
+        return Err(e);
+    }
+    Ok(Some(cached))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IngestCacheInfo {
+    pub path: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+}
+
+pub(crate) fn cache_info(dir: &Path) -> IngestCacheInfo {
+    let mut info = IngestCacheInfo {
+        path: dir.to_string_lossy().to_string(),
+        file_count: 0,
+        total_bytes: 0,
+    };
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    info.file_count += 1;
+                    info.total_bytes += metadata.len();
+                }
+            }
+        }
+    }
+    info
+}
+
+#[tauri::command]
+pub fn get_ingest_cache(app: AppHandle) -> Result<IngestCacheInfo, String> {
+    let dir = cache_dir(&app).map_err(|e| e.to_string())?;
+    Ok(cache_info(&dir))
+}
+
+#[tauri::command]
+pub fn clear_ingest_cache(app: AppHandle) -> Result<IngestCacheInfo, String> {
+    let dir = cache_dir(&app).map_err(|e| e.to_string())?;
+
+    // Files still held open by a dataset handle (on Windows) are left for the next clear
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+    Ok(cache_info(&dir))
+}
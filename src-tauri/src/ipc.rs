@@ -0,0 +1,44 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::ipc::Response;
+
+// Binary command result, returned to `invoke` as an ArrayBuffer instead of JSON so
+// pixels and PNGs skip base64 and JSON encoding. Layout: the byte length of the header
+// as a little-endian u32, the header as UTF-8 JSON, then the parts back to back. The
+// header is `{"meta": ..., "parts": [byte length, ...]}`, `meta` being what the command
+// would otherwise have returned without its binary fields.
+pub(crate) struct Frame {
+    meta: Value,
+    lengths: Vec<usize>,
+    payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new<T: Serialize>(meta: &T) -> Self {
+        Self {
+            meta: serde_json::to_value(meta).unwrap_or_default(),
+            lengths: Vec::new(),
+            payload: Vec::new(),
+        }
+    }
+
+    // A frame without content, e.g. for a superseded render
+    pub fn empty() -> Self {
+        Self::new(&Value::Null)
+    }
+
+    pub fn part(mut self, bytes: &[u8]) -> Self {
+        self.lengths.push(bytes.len());
+        self.payload.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn into_response(self) -> Response {
+        let header = json!({ "meta": self.meta, "parts": self.lengths }).to_string();
+        let mut body = Vec::with_capacity(4 + header.len() + self.payload.len());
+        body.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        body.extend_from_slice(header.as_bytes());
+        body.extend_from_slice(&self.payload);
+        Response::new(body)
+    }
+}
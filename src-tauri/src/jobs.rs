@@ -0,0 +1,533 @@
+use gdal::{Dataset, Metadata};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+use crate::network::check_remote_params;
+use crate::notifications::job_finished;
+use crate::policy::CommandPolicy;
+use crate::provenance::write_sidecar;
+use crate::settings::SettingsStore;
+use crate::{run_blocking, GdalError};
+
+// Recipe files carry a version so the format can change without breaking old exports
+const RECIPE_VERSION: u32 = 1;
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // GDAL command lines of the job running on this thread, while inside `run_job`
+    static COMMAND_LINES: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    // GDAL warnings raised on this thread, such as HTTP retries, while inside `run_job`
+    static WARNINGS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Succeeded,
+    Failed,
+}
+
+// One finished processing command with everything needed to run it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub id: String,
+    pub command: String,
+    // Arguments by their Rust parameter names, as passed to the command
+    pub params: Value,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    // Unix time in milliseconds
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub gdal_version: String,
+    // Equivalent gdal_translate/gdalwarp/ogr2ogr/... invocations, one per step
+    #[serde(default)]
+    pub command_lines: Vec<String>,
+    // GDAL warnings raised while the job ran, e.g. retried HTTP requests
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+// Completed jobs, appended to a JSON Lines file so a crash loses at most one entry
+pub struct JobHistory {
+    path: PathBuf,
+    jobs: Mutex<Vec<JobRecord>>,
+}
+
+impl JobHistory {
+    pub fn load(path: PathBuf) -> Self {
+        let jobs = fs::read_to_string(&path)
+            .map(|text| {
+                text.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            jobs: Mutex::new(jobs),
+        }
+    }
+
+    pub fn record(&self, job: JobRecord) -> Result<(), GdalError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let line =
+            serde_json::to_string(&job).map_err(|e| GdalError::OperationFailed(e.to_string()))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        jobs.push(job);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<JobRecord> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|job| job.id == id)
+            .cloned()
+    }
+
+    // Most recent first
+    pub fn list(&self, limit: usize) -> Vec<JobRecord> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) -> Result<(), GdalError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        jobs.clear();
+        Ok(())
+    }
+}
+
+// Quotes an argument for a POSIX shell when it contains anything unsafe
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+// Notes the command line equivalent to one step of the running job. Outside `run_job`
+// this does nothing.
+pub(crate) fn record_command_line(program: &str, args: &[String]) {
+    COMMAND_LINES.with(|lines| {
+        if let Some(lines) = lines.borrow_mut().as_mut() {
+            let line = std::iter::once(program.to_string())
+                .chain(args.iter().map(|arg| shell_quote(arg)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.push(line);
+        }
+    });
+}
+
+// Adds a GDAL warning to the job running on this thread, if any
+pub(crate) fn record_warning(message: &str) {
+    WARNINGS.with(|warnings| {
+        if let Some(warnings) = warnings.borrow_mut().as_mut() {
+            warnings.push(message.to_string());
+        }
+    });
+}
+
+// Runs `f` without recording its command lines, for steps that are part of a
+// higher-level command already recorded
+pub(crate) fn without_command_lines<T>(f: impl FnOnce() -> T) -> T {
+    let lines = COMMAND_LINES.with(|lines| lines.borrow_mut().take());
+    let result = f();
+    COMMAND_LINES.with(|current| *current.borrow_mut() = lines);
+    result
+}
+
+// How a dataset is named on the command line, empty for in-memory datasets
+pub(crate) fn dataset_name(dataset: &Dataset) -> String {
+    dataset.description().unwrap_or_default()
+}
+
+pub(crate) fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Like `run_blocking`, but records the command and its parameters in the job history
+pub(crate) async fn run_job<T, F>(
+    app: AppHandle,
+    command: &str,
+    params: Value,
+    f: F,
+) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    let started_at = unix_millis();
+    let timer = Instant::now();
+    // Remote inputs and outputs fail up front in offline mode, recorded like any failure
+    let offline_check = check_remote_params(&params).map_err(|e| e.to_string());
+    let (result, command_lines, warnings) = match run_blocking(move || {
+        COMMAND_LINES.with(|lines| *lines.borrow_mut() = Some(Vec::new()));
+        WARNINGS.with(|warnings| *warnings.borrow_mut() = Some(Vec::new()));
+        let result = offline_check.and_then(|_| f());
+        let lines = COMMAND_LINES.with(|lines| lines.borrow_mut().take());
+        let warnings = WARNINGS.with(|warnings| warnings.borrow_mut().take());
+        Ok((result, lines.unwrap_or_default(), warnings.unwrap_or_default()))
+    })
+    .await
+    {
+        Ok(outcome) => outcome,
+        Err(e) => (Err(e), Vec::new(), Vec::new()),
+    };
+
+    let job = JobRecord {
+        id: format!(
+            "{:x}-{}",
+            started_at,
+            NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed)
+        ),
+        command: command.to_string(),
+        params,
+        status: if result.is_ok() {
+            JobStatus::Succeeded
+        } else {
+            JobStatus::Failed
+        },
+        error: result.as_ref().err().cloned(),
+        started_at,
+        duration_ms: timer.elapsed().as_millis() as u64,
+        gdal_version: gdal::version_info("RELEASE_NAME"),
+        command_lines,
+        warnings,
+    };
+    // Failing to write history or provenance must not fail the job itself
+    let provenance = app
+        .try_state::<SettingsStore>()
+        .is_some_and(|store| store.get().provenance_sidecars);
+    if provenance && result.is_ok() {
+        let job = job.clone();
+        let _ = run_blocking(move || write_sidecar(&job).map_err(|e| e.to_string())).await;
+    }
+    job_finished(&app, &job);
+    if let Some(history) = app.try_state::<JobHistory>() {
+        let _ = history.record(job);
+    }
+
+    result
+}
+
+// Calls a replayable command with recorded parameters. Each arm names the command's
+// parameters in order; `app` is supplied by the caller.
+macro_rules! dispatch {
+    ($app:expr, $command:expr, $params:expr, { $($name:literal => $func:path [$($arg:ident: $ty:ty),*]),* $(,)? }) => {
+        match $command {
+            $(
+                $name => {
+                    #[derive(Deserialize)]
+                    struct Args {
+                        $($arg: $ty),*
+                    }
+                    let Args { $($arg),* } = serde_json::from_value($params)
+                        .map_err(|e| format!("Invalid parameters for {}: {}", $name, e))?;
+                    let output = $func($app, $($arg),*).await?;
+                    serde_json::to_value(output).map_err(|e| e.to_string())
+                }
+            )*
+            other => Err(format!("Command {} cannot be replayed", other)),
+        }
+    };
+}
+
+pub(crate) async fn replay(app: AppHandle, command: &str, params: Value) -> Result<Value, String> {
+    use crate::crs::*;
+    use crate::raster::ascii::*;
+    use crate::raster::calc::indices::*;
+    use crate::raster::calc::*;
+    use crate::raster::clip::*;
+    use crate::raster::compare::*;
+    use crate::raster::cog::*;
+    use crate::raster::contours::*;
+    use crate::raster::dem::*;
+    use crate::raster::density::*;
+    use crate::raster::fill::*;
+    use crate::raster::grid::*;
+    use crate::raster::merge::*;
+    use crate::raster::pansharpen::*;
+    use crate::raster::polygonize::*;
+    use crate::raster::proximity::*;
+    use crate::raster::rasterize::*;
+    use crate::raster::reclassify::*;
+    use crate::raster::resample::*;
+    use crate::raster::retile::*;
+    use crate::raster::sieve::*;
+    use crate::raster::tiles::*;
+    use crate::raster::vrt::*;
+    use crate::raster::warp::*;
+    use crate::raster::zonal::*;
+    use crate::raster::Resampling;
+    use crate::render::raster::RasterStyle;
+    use crate::render::sheets::*;
+    use crate::vector::bins::*;
+    use crate::vector::cluster::*;
+    use crate::vector::dedupe::*;
+    use crate::vector::dxf::*;
+    use crate::vector::flatgeobuf::*;
+    use crate::vector::geojson::*;
+    use crate::vector::hulls::*;
+    use crate::vector::lines::*;
+    use crate::vector::merge::*;
+    use crate::vector::mvt::*;
+    use crate::vector::osm::*;
+    use crate::vector::pmtiles::*;
+    use crate::vector::snap::*;
+    use crate::vector::split::*;
+    use crate::Extent;
+
+    // Presets, recipes and watch folders reach the commands from here, not through the
+    // command handler
+    app.state::<CommandPolicy>()
+        .check(command, &params)
+        .map_err(|e| e.to_string())?;
+
+    dispatch!(app, command, params, {
+        "warp_raster" => warp_raster [src: String, dst: String, target_epsg: u32, resampling: Option<Resampling>, resolution: Option<f64>, extent: Option<Extent>],
+        "clip_raster_by_geometry" => clip_raster_by_geometry [src: String, dst: String, cutline: Cutline, crop_to_cutline: Option<bool>, blend_distance: Option<f64>],
+        "clip_raster" => clip_raster [src: String, dst: String, bbox: Extent, bbox_crs: Option<String>],
+        "resample_raster" => resample_raster [src: String, dst: String, target: ResampleTarget, method: Option<Resampling>],
+        "export_ascii" => export_ascii [src: String, dst: String, options: Option<AsciiExportOptions>],
+        "retile_raster" => retile_raster [src: String, out_dir: String, tile_size: (usize, usize), overlap: Option<usize>, format: Option<String>, index_format: Option<TileIndexFormat>],
+        "dem_process" => dem_process [src: String, dst: String, mode: DemMode, params: Option<DemParams>],
+        "generate_contours" => generate_contours [src: String, dst: String, interval: f64, base: Option<f64>, attribute_name: Option<String>, polygons: Option<bool>],
+        "fill_nodata" => fill_nodata [src: String, dst: String, max_search_distance: Option<f64>, smoothing_iterations: Option<u32>],
+        "sieve_raster" => sieve_raster [src: String, dst: String, threshold: u32, connectedness: Option<u8>, band: Option<usize>],
+        "polygonize" => polygonize [src: String, band: Option<usize>, dst: String, field_name: Option<String>, eight_connected: Option<bool>],
+        "compute_proximity" => compute_proximity [src: String, dst: String, target_values: Option<Vec<f64>>, distance_units: Option<DistanceUnits>, max_distance: Option<f64>],
+        "grid_points" => grid_points [src_vector: String, field: Option<String>, algorithm: GridAlgorithm, params: Option<GridParams>, dst: String],
+        "kernel_density" => kernel_density [src_vector: String, radius: f64, cell_size: f64, weight_field: Option<String>, dst: String, options: Option<KernelDensityOptions>],
+        "rasterize" => rasterize [src_vector: String, layer: Option<String>, dst: String, resolution_or_template: RasterTarget, burn_value_or_attribute: BurnValue, all_touched: Option<bool>],
+        "zonal_statistics" => zonal_statistics [raster: String, band: Option<usize>, zones_vector: String, layer: Option<String>, options: Option<ZonalOptions>, dst: Option<String>],
+        "raster_calc" => raster_calc [inputs: Vec<CalcInput>, expression: String, dst: String, options: Option<CalcOptions>],
+        "compute_index" => compute_index [src: String, index_name: String, band_mapping: BTreeMap<String, usize>, dst: String],
+        "reclassify_raster" => reclassify_raster [src: String, dst: String, rules: Vec<ReclassRule>, options: Option<ReclassOptions>],
+        "compare_rasters" => compare_rasters [a: String, b: String, tolerance: Option<f64>, dst: Option<String>],
+        "pansharpen" => pansharpen [pan_raster: String, multispectral_raster: String, dst: String, weights: Option<Vec<f64>>, resampling: Option<Resampling>],
+        "export_cog" => export_cog [src: String, dst: String, options: Option<CogOptions>],
+        "export_tiles" => export_tiles [src: String, dst: String, format: Option<TileFormat>, zoom_range: Option<(u8, u8)>, tiling_scheme: Option<TilingScheme>, style: Option<RasterStyle>],
+        "build_vrt" => build_vrt [inputs: Vec<String>, dst: String, options: Option<VrtOptions>],
+        "merge_rasters" => merge_rasters [inputs: Vec<String>, dst: String, options: Option<MergeOptions>],
+        "batch_assign_crs" => batch_assign_crs [paths: Vec<String>, srs: String],
+        "batch_reproject" => batch_reproject [paths: Vec<String>, target_epsg: u32, output_dir: String, resampling: Option<Resampling>],
+        "export_flatgeobuf" => export_flatgeobuf [src: String, dst: String, layer: Option<String>, spatial_index: Option<bool>, selected_only: Option<bool>, filter: Option<String>],
+        "import_dxf" => import_dxf [src: String, dst: String, options: Option<DxfImportOptions>],
+        "export_dxf" => export_dxf [src: String, dst: String, layers: Option<Vec<String>>, crs: Option<String>, layer_field: Option<String>],
+        "export_geojson" => export_geojson [src: String, dst: String, options: Option<GeoJsonOptions>],
+        "merge_vectors" => merge_vectors [inputs: Vec<String>, dst: String, options: Option<MergeVectorOptions>],
+        "split_by_attribute" => split_by_attribute [src: String, layer: Option<String>, field: String, target: SplitTarget],
+        "deduplicate_features" => deduplicate_features [src: String, layer: Option<String>, dst: String, key: DuplicateKey, options: Option<DedupeOptions>],
+        "snap_geometries" => snap_geometries [src: String, layer: Option<String>, dst: String, target: SnapTarget],
+        "merge_lines" => merge_lines [src: String, layer: Option<String>, dst: String, group_by: Option<Vec<String>>],
+        "split_lines_at_intersections" => split_lines_at_intersections [src: String, layer: Option<String>, dst: String],
+        "planarize_lines" => planarize_lines [src: String, layer: Option<String>, dst: String],
+        "bin_points" => bin_points [src: String, size: f64, dst: String, options: Option<BinOptions>],
+        "cluster_points" => cluster_points [src: String, layer: Option<String>, dst: String, method: ClusterMethod],
+        "bounding_geometries" => bounding_geometries [src: String, layer: Option<String>, dst: String, kind: BoundingKind, group_by: Option<String>],
+        "export_pmtiles" => export_pmtiles [src: String, dst: String, layers: Option<Vec<String>>, min_zoom: Option<u8>, max_zoom: Option<u8>, geometry: Option<TileGeometry>],
+        "export_mvt" => export_mvt [src_vector: String, dst: String, zoom_range: Option<(u8, u8)>, layer_config: Option<BTreeMap<String, MvtLayerConfig>>, geometry: Option<TileGeometry>],
+        "generate_sheet_index" => generate_sheet_index [aoi: Extent, crs: String, grid: SheetGrid, dst: String],
+        "extract_osm" => extract_osm [src: String, dst: String, layers: Option<Vec<String>>, filters: Option<Vec<OsmTagFilter>>],
+    })
+}
+
+// Parameters of each replayable command that name input or output files, left out when
+// a job is saved as a preset. Keep in step with the arms of `replay`.
+pub(crate) fn path_params(command: &str) -> &'static [&'static str] {
+    match command {
+        "warp_raster" => &["src", "dst"],
+        "clip_raster_by_geometry" => &["src", "dst"],
+        "clip_raster" => &["src", "dst"],
+        "resample_raster" => &["src", "dst"],
+        "export_ascii" => &["src", "dst"],
+        "retile_raster" => &["src", "out_dir"],
+        "dem_process" => &["src", "dst"],
+        "generate_contours" => &["src", "dst"],
+        "fill_nodata" => &["src", "dst"],
+        "sieve_raster" => &["src", "dst"],
+        "polygonize" => &["src", "dst"],
+        "compute_proximity" => &["src", "dst"],
+        "grid_points" => &["src_vector", "dst"],
+        "kernel_density" => &["src_vector", "dst"],
+        "rasterize" => &["src_vector", "dst"],
+        "zonal_statistics" => &["raster", "zones_vector", "dst"],
+        "raster_calc" => &["inputs", "dst"],
+        "compute_index" => &["src", "dst"],
+        "reclassify_raster" => &["src", "dst"],
+        "compare_rasters" => &["a", "b", "dst"],
+        "pansharpen" => &["pan_raster", "multispectral_raster", "dst"],
+        "export_cog" => &["src", "dst"],
+        "export_tiles" => &["src", "dst"],
+        "build_vrt" => &["inputs", "dst"],
+        "merge_rasters" => &["inputs", "dst"],
+        "batch_assign_crs" => &["paths"],
+        "batch_reproject" => &["paths", "output_dir"],
+        "export_flatgeobuf" => &["src", "dst"],
+        "import_dxf" => &["src", "dst"],
+        "export_dxf" => &["src", "dst"],
+        "export_geojson" => &["src", "dst"],
+        "merge_vectors" => &["inputs", "dst"],
+        "split_by_attribute" => &["src", "target"],
+        "deduplicate_features" => &["src", "dst"],
+        "snap_geometries" => &["src", "dst"],
+        "merge_lines" => &["src", "dst"],
+        "split_lines_at_intersections" => &["src", "dst"],
+        "planarize_lines" => &["src", "dst"],
+        "bin_points" => &["src", "dst"],
+        "cluster_points" => &["src", "dst"],
+        "bounding_geometries" => &["src", "dst"],
+        "export_pmtiles" => &["src", "dst"],
+        "export_mvt" => &["src_vector", "dst"],
+        "generate_sheet_index" => &["dst"],
+        "extract_osm" => &["src", "dst"],
+        _ => &[],
+    }
+}
+
+// Replaces recorded parameters by name, e.g. {"src": "other.tif", "dst": "out.tif"}
+pub(crate) fn apply_overrides(mut params: Value, overrides: Option<Map<String, Value>>) -> Value {
+    if let (Value::Object(params), Some(overrides)) = (&mut params, overrides) {
+        params.extend(overrides);
+    }
+    params
+}
+
+#[tauri::command]
+pub fn get_job_history(history: State<'_, JobHistory>, limit: Option<usize>) -> Vec<JobRecord> {
+    history.list(limit.unwrap_or(100))
+}
+
+#[tauri::command]
+pub fn clear_job_history(history: State<'_, JobHistory>) -> Result<(), String> {
+    history.clear().map_err(|e| e.to_string())
+}
+
+// Runs a recorded job again, optionally on other inputs; the rerun is recorded as a new job
+#[tauri::command]
+pub async fn replay_job(
+    app: AppHandle,
+    history: State<'_, JobHistory>,
+    job_id: String,
+    new_inputs: Option<Map<String, Value>>,
+) -> Result<Value, String> {
+    let job = history
+        .get(&job_id)
+        .ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    replay(app, &job.command, apply_overrides(job.params, new_inputs)).await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecipeStep {
+    pub command: String,
+    pub params: Value,
+}
+
+// Portable workflow: the steps of one or more jobs, runnable with `run_recipe`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Recipe {
+    pub version: u32,
+    pub gdal_version: String,
+    pub steps: Vec<RecipeStep>,
+}
+
+#[tauri::command]
+pub fn export_recipe(
+    history: State<'_, JobHistory>,
+    job_ids: Vec<String>,
+    path: String,
+) -> Result<Recipe, String> {
+    let steps = job_ids
+        .iter()
+        .map(|id| {
+            history
+                .get(id)
+                .map(|job| RecipeStep {
+                    command: job.command,
+                    params: job.params,
+                })
+                .ok_or_else(|| format!("Unknown job: {}", id))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let recipe = Recipe {
+        version: RECIPE_VERSION,
+        gdal_version: gdal::version_info("RELEASE_NAME"),
+        steps,
+    };
+    let text = serde_json::to_string_pretty(&recipe).map_err(|e| e.to_string())?;
+    fs::write(&path, text).map_err(|e| e.to_string())?;
+    Ok(recipe)
+}
+
+// Runs every step of a recipe file in order, stopping at the first failure. Overrides
+// apply to all steps that have a parameter of that name.
+#[tauri::command]
+pub async fn run_recipe(
+    app: AppHandle,
+    path: String,
+    overrides: Option<Map<String, Value>>,
+) -> Result<Vec<Value>, String> {
+    let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let recipe: Recipe =
+        serde_json::from_str(&text).map_err(|e| format!("Invalid recipe: {}", e))?;
+    if recipe.version > RECIPE_VERSION {
+        return Err(format!(
+            "Recipe version {} is newer than supported version {}",
+            recipe.version, RECIPE_VERSION
+        ));
+    }
+
+    let mut outputs = Vec::new();
+    for step in recipe.steps {
+        let overrides = overrides.as_ref().map(|overrides| {
+            overrides
+                .iter()
+                .filter(|(key, _)| step.params.get(key.as_str()).is_some())
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        });
+        let params = apply_overrides(step.params, overrides);
+        outputs.push(replay(app.clone(), &step.command, params).await?);
+    }
+    Ok(outputs)
+}
@@ -1,15 +1,52 @@
+use gdal::spatial_ref::SpatialRef;
 use gdal::{Dataset, DriverManager};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::env;
+use tauri::Manager;
 use thiserror::Error;
 
+pub mod bookmarks;
+pub mod catalog;
+pub mod classify;
+pub mod crs;
+pub mod datasets;
+mod ffi;
+pub mod grids;
+pub mod ingest;
+mod ipc;
+pub mod jobs;
+pub mod network;
+pub mod presets;
+pub mod notifications;
+pub mod paths;
+pub mod policy;
+mod progress;
+pub mod provenance;
+pub mod qa;
+pub mod raster;
+pub mod render;
+pub mod settings;
+pub mod summary;
+pub mod vector;
+pub mod watch;
+
 #[derive(Error, Debug)]
 pub enum GdalError {
     #[error("GDAL error: {0}")]
     Gdal(#[from] gdal::errors::GdalError),
     #[error("File not found: {0}")]
     FileNotFound(String),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("{0}")]
+    OperationFailed(String),
+    #[error("Offline mode: {0} needs network access")]
+    Offline(String),
+    #[error("Unknown dataset handle: {0}")]
+    UnknownHandle(u64),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,6 +56,38 @@ pub struct DatasetInfo {
     pub projection: String,
     pub band_count: usize,
     pub driver_name: String,
+    // Datasets inside a container such as NetCDF, HDF or a Sentinel SAFE, opened by name
+    // from `list_subdatasets`
+    #[serde(default)]
+    pub subdataset_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Extent {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Extent {
+    // Formats the extent as `xmin ymin xmax ymax` for switches such as `-te`
+    pub fn to_args(&self) -> Vec<String> {
+        vec![
+            self.min_x.to_string(),
+            self.min_y.to_string(),
+            self.max_x.to_string(),
+            self.max_y.to_string(),
+        ]
+    }
+
+    // Reprojects the extent, densifying the edges so curved boundaries are covered
+    pub fn transform(&self, from: &SpatialRef, to: &SpatialRef) -> Result<Extent, GdalError> {
+        let transform = crs::transformer(from, to)?;
+        let [min_x, min_y, max_x, max_y] = transform
+            .transform_bounds(&[self.min_x, self.min_y, self.max_x, self.max_y], 21)?;
+        Ok(Extent { min_x, min_y, max_x, max_y })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -57,6 +126,34 @@ fn setup_gdal_runtime() {
     }
 }
 
+// Runs blocking GDAL work off the main thread so the UI stays responsive
+pub(crate) async fn run_blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+pub(crate) fn dataset_info(dataset: &Dataset) -> DatasetInfo {
+    let size = dataset.raster_size();
+    let projection = dataset.projection();
+    let band_count = dataset.raster_count();
+    let driver = dataset.driver();
+    let driver_name = driver.long_name();
+
+    DatasetInfo {
+        size_x: size.0,
+        size_y: size.1,
+        projection,
+        band_count,
+        driver_name,
+        subdataset_count: datasets::subdatasets(dataset).len(),
+    }
+}
+
 #[tauri::command]
 fn get_gdal_info() -> Result<GdalInfo, String> {
     // Ensure GDAL runtime is set up
@@ -91,34 +188,201 @@ fn get_dataset_info(file_path: String) -> Result<DatasetInfo, String> {
     }
 
     let dataset = Dataset::open(path).map_err(|e| e.to_string())?;
-    
-    let size = dataset.raster_size();
-    let projection = dataset.projection();
-    let band_count = dataset.raster_count();
-    let driver = dataset.driver();
-    let driver_name = driver.long_name();
 
-    Ok(DatasetInfo {
-        size_x: size.0,
-        size_y: size.1,
-        projection,
-        band_count,
-        driver_name,
-    })
+    Ok(dataset_info(&dataset))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Set up GDAL runtime environment before starting the app
     setup_gdal_runtime();
+    network::install_error_handler();
+    // Missing support files only degrade CRS lookups, the app still starts
+    let _ = paths::use_portable_support_files();
     
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![
+        .manage(datasets::DatasetRegistry::default())
+        .manage(render::RenderQueue::default())
+        .manage(render::cache::RenderCache::default())
+        .manage(vector::selection::SelectionStore::default())
+        .manage(vector::routing::RouteGraphStore::default())
+        .manage(raster::timeseries::TimeSeriesStore::default())
+        .register_asynchronous_uri_scheme_protocol(
+            render::tiles::TILE_SCHEME,
+            |ctx, request, responder| {
+                let app = ctx.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(render::tiles::tile_response(&app, &request))
+                });
+            },
+        )
+        .setup(|app| {
+            let paths = paths::AppPaths::resolve(app.handle())?;
+            let settings = settings::SettingsStore::load(paths.config_dir.join("settings.json"));
+            network::apply_network_settings(&settings.get().network)?;
+            app.state::<render::cache::RenderCache>()
+                .set_budget_mb(settings.get().render_cache_mb);
+            app.manage(policy::CommandPolicy::load(
+                settings.get().disabled_command_groups,
+            ));
+            app.manage(settings);
+            app.manage(jobs::JobHistory::load(paths.data_dir.join("jobs.jsonl")));
+            app.manage(watch::WatchManager::load(paths.data_dir.join("watch_activity.jsonl")));
+            // Without the managed grids PROJ falls back to less accurate transformations
+            let _ = grids::use_grid_dir(&grids::grid_dir(&paths));
+            app.manage(paths);
+            watch::start_all(app.handle());
+            Ok(())
+        })
+        .invoke_handler(policy::enforce(tauri::generate_handler![
             get_gdal_info,
-            get_dataset_info
-        ])
+            get_dataset_info,
+            datasets::open_dataset,
+            datasets::list_subdatasets,
+            datasets::close_dataset,
+            datasets::clone_to_memory,
+            datasets::save_dataset_as,
+            summary::get_layer_summary,
+            crs::assign_crs,
+            crs::audit_crs,
+            crs::batch_assign_crs,
+            crs::batch_reproject,
+            crs::transform_coordinates,
+            crs::search_crs,
+            crs::describe_crs,
+            grids::get_proj_grid_status,
+            grids::list_transformation_grids,
+            grids::download_proj_grids,
+            settings::get_settings,
+            settings::update_settings,
+            paths::get_app_paths,
+            policy::get_command_policy,
+            network::get_offline_status,
+            network::set_offline_mode,
+            bookmarks::list_bookmarks,
+            bookmarks::save_bookmark,
+            bookmarks::recall_bookmark,
+            bookmarks::delete_bookmark,
+            catalog::catalog_add,
+            catalog::catalog_search,
+            catalog::catalog_remove,
+            catalog::get_catalog_info,
+            presets::list_presets,
+            presets::save_preset,
+            presets::save_preset_from_job,
+            presets::delete_preset,
+            presets::set_preset_favorite,
+            presets::run_preset,
+            watch::list_watch_folders,
+            watch::save_watch_folder,
+            watch::delete_watch_folder,
+            watch::get_watch_activity,
+            watch::clear_watch_activity,
+            ingest::get_ingest_cache,
+            ingest::clear_ingest_cache,
+            jobs::get_job_history,
+            jobs::clear_job_history,
+            jobs::replay_job,
+            jobs::export_recipe,
+            jobs::run_recipe,
+            notifications::test_notification,
+            provenance::read_provenance,
+            provenance::verify_provenance,
+            raster::warp::warp_raster,
+            raster::warp::clip_raster_by_geometry,
+            raster::clip::clip_raster,
+            raster::resample::resample_raster,
+            raster::ascii::export_ascii,
+            raster::dem::dem_process,
+            raster::contours::generate_contours,
+            raster::fill::fill_nodata,
+            raster::sieve::sieve_raster,
+            raster::polygonize::polygonize,
+            raster::proximity::compute_proximity,
+            raster::grid::grid_points,
+            raster::density::kernel_density,
+            raster::rasterize::rasterize,
+            raster::zonal::zonal_statistics,
+            raster::rat::get_raster_attribute_table,
+            raster::read::read_raster_window,
+            raster::multidim::open_multidim,
+            raster::multidim::open_zarr,
+            raster::multidim::list_arrays,
+            raster::multidim::read_array_slice,
+            raster::multidim::read_array_chunk,
+            raster::timeseries::create_timeseries_stack,
+            raster::timeseries::get_pixel_timeseries,
+            raster::timeseries::get_scene_at,
+            raster::timeseries::close_timeseries_stack,
+            raster::coords::pixel_to_world,
+            raster::coords::world_to_pixel,
+            raster::compare::compare_rasters,
+            raster::calc::raster_calc,
+            raster::calc::validate_calc_expression,
+            raster::calc::indices::list_index_presets,
+            raster::calc::indices::compute_index,
+            raster::reclassify::reclassify_raster,
+            raster::pansharpen::pansharpen,
+            raster::overviews::build_overviews,
+            raster::overviews::inspect_overviews,
+            raster::cog::export_cog,
+            raster::cog::validate_cog,
+            raster::retile::retile_raster,
+            raster::retile::retile_dataset,
+            raster::tiles::export_tiles,
+            raster::vrt::build_vrt,
+            raster::merge::merge_rasters,
+            vector::dxf::import_dxf,
+            vector::dxf::export_dxf,
+            qa::check_crs_placement,
+            vector::features::read_features,
+            vector::features::stream_features,
+            vector::stats::get_field_statistics,
+            vector::selection::select_features,
+            vector::selection::get_selection,
+            vector::selection::clear_selection,
+            classify::compute_class_breaks,
+            classify::compute_categories,
+            vector::flatgeobuf::export_flatgeobuf,
+            vector::geojson::export_geojson,
+            vector::merge::merge_vectors,
+            vector::split::split_by_attribute,
+            vector::dedupe::deduplicate_features,
+            vector::snap::snap_geometries,
+            vector::lines::merge_lines,
+            vector::lines::split_lines_at_intersections,
+            vector::lines::planarize_lines,
+            vector::bins::bin_points,
+            vector::cluster::cluster_points,
+            vector::hulls::bounding_geometries,
+            vector::routing::build_route_graph,
+            vector::routing::find_route,
+            vector::routing::service_area,
+            vector::routing::close_route_graph,
+            vector::measure::measure,
+            vector::pmtiles::get_pmtiles_info,
+            vector::pmtiles::export_pmtiles,
+            vector::mvt::export_mvt,
+            vector::osm::get_osm_layers,
+            vector::osm::extract_osm,
+            render::cancel_render,
+            render::cache::get_render_cache,
+            render::cache::clear_render_cache,
+            render::composite::render_composite,
+            render::export::export_view,
+            render::animation::export_animation_frames,
+            render::sheets::generate_sheet_index,
+            render::sheets::export_sheets,
+            render::swipe::render_swipe,
+            render::tiles::render_vector_tile,
+            render::query::query_rendered_features,
+            render::preview::render_preview,
+            render::styled::render_styled,
+            render::rgb::render_rgb,
+            render::palette::get_color_table
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,248 @@
+use gdal::config::{clear_config_option, set_config_option, set_error_handler};
+use gdal::errors::CplErrType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, State};
+
+use crate::datasets::{DatasetEntry, DatasetRegistry};
+use crate::ingest::{cache_dir, cache_info, IngestCacheInfo};
+use crate::jobs::record_warning;
+use crate::settings::SettingsStore;
+use crate::{run_blocking, GdalError};
+
+// Prefixes of paths GDAL reads over the network, compared case-insensitively. The /vsi
+// ones also match when nested, e.g. /vsizip//vsicurl/https://...
+const REMOTE_VSI: &[&str] = &[
+    "/vsicurl",
+    "/vsis3",
+    "/vsigs",
+    "/vsiaz",
+    "/vsiadls",
+    "/vsioss",
+    "/vsiswift",
+    "/vsiwebhdfs",
+    "/vsihdfs",
+];
+const REMOTE_PREFIXES: &[&str] = &[
+    "http://",
+    "https://",
+    "ftp://",
+    "wms:",
+    "wmts:",
+    "<gdal_wms",
+    "stacit:",
+    "stacta:",
+    "eedai:",
+];
+
+// Mirrors `NetworkSettings::offline` so guards need no access to the settings store
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+// Retry policy for remote datasets read through /vsicurl, /vsis3, /vsigs, /vsiaz and
+// friends. GDAL does the retrying itself, doubling the delay after each attempt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    // Attempts after the first failure; 0 disables retrying
+    pub max_retries: u32,
+    // Delay before the first retry
+    pub retry_delay_secs: f64,
+    // Per-request timeout; None keeps GDAL's default
+    pub timeout_secs: Option<u32>,
+    // HTTP statuses worth retrying. Timeouts and dropped connections are always retried.
+    pub retry_codes: Vec<u16>,
+    // Blocks everything that would touch the network, for machines without connectivity
+    pub offline: bool,
+    // Lets PROJ fetch missing transformation grids from its CDN while transforming
+    pub proj_network: bool,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            retry_delay_secs: 1.0,
+            timeout_secs: None,
+            retry_codes: vec![429, 500, 502, 503, 504],
+            offline: false,
+            proj_network: false,
+        }
+    }
+}
+
+// Sets the GDAL_HTTP_* options for `settings`. Config options are process-wide, so
+// this applies to every dataset opened afterwards.
+pub(crate) fn apply_network_settings(settings: &NetworkSettings) -> Result<(), GdalError> {
+    OFFLINE.store(settings.offline, Ordering::Relaxed);
+    // Requests that slip past the guards, e.g. remote sources inside a local VRT, should
+    // fail fast rather than retry against a network that is not there
+    let max_retries = if settings.offline {
+        0
+    } else {
+        settings.max_retries
+    };
+    set_config_option("GDAL_HTTP_MAX_RETRY", &max_retries.to_string())?;
+    set_config_option(
+        "GDAL_HTTP_RETRY_DELAY",
+        &settings.retry_delay_secs.to_string(),
+    )?;
+    let codes = settings
+        .retry_codes
+        .iter()
+        .map(|code| code.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    set_config_option("GDAL_HTTP_RETRY_CODES", &codes)?;
+    match settings.timeout_secs {
+        Some(timeout) => set_config_option("GDAL_HTTP_TIMEOUT", &timeout.to_string())?,
+        None => clear_config_option("GDAL_HTTP_TIMEOUT")?,
+    }
+    if settings.offline {
+        set_config_option("GDAL_HTTP_CONNECTTIMEOUT", "1")?;
+    } else {
+        clear_config_option("GDAL_HTTP_CONNECTTIMEOUT")?;
+    }
+    let proj_network = settings.proj_network && !settings.offline;
+    unsafe { gdal_sys::OSRSetPROJEnableNetwork(proj_network as i32) };
+    Ok(())
+}
+
+pub(crate) fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+// Whether GDAL would go over the network to read `path`
+pub(crate) fn is_remote(path: &str) -> bool {
+    let path = path.trim_start().to_ascii_lowercase();
+    REMOTE_VSI.iter().any(|prefix| path.contains(prefix))
+        || REMOTE_PREFIXES
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+}
+
+// Fails with `GdalError::Offline` in offline mode; `what` names the resource needed
+pub(crate) fn ensure_online(what: &str) -> Result<(), GdalError> {
+    if is_offline() {
+        return Err(GdalError::Offline(what.to_string()));
+    }
+    Ok(())
+}
+
+// Fails in offline mode when `path` is remote, local paths always pass
+pub(crate) fn check_remote(path: &str) -> Result<(), GdalError> {
+    if is_remote(path) {
+        ensure_online(path)?;
+    }
+    Ok(())
+}
+
+// Checks every string in recorded job parameters, so processing commands fail before
+// opening a remote input or writing to a remote output
+pub(crate) fn check_remote_params(params: &Value) -> Result<(), GdalError> {
+    match params {
+        Value::String(text) => check_remote(text),
+        Value::Array(values) => values.iter().try_for_each(check_remote_params),
+        Value::Object(map) => map.values().try_for_each(check_remote_params),
+        _ => Ok(()),
+    }
+}
+
+// Replaces GDAL's default error handler with one that still prints to stderr but also
+// records warnings, which include "Retrying again in ..." notices, in the current job.
+pub(crate) fn install_error_handler() {
+    set_error_handler(|class, code, message| match class {
+        CplErrType::None => {}
+        CplErrType::Debug => eprintln!("{}", message),
+        CplErrType::Warning => {
+            eprintln!("Warning {}: {}", code, message);
+            record_warning(message);
+        }
+        CplErrType::Failure | CplErrType::Fatal => eprintln!("ERROR {}: {}", code, message),
+    });
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatasetAvailability {
+    pub handle: u64,
+    pub path: String,
+    // False for remote datasets while offline, as their reads would fail
+    pub usable: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OfflineStatus {
+    pub offline: bool,
+    pub open_datasets: Vec<DatasetAvailability>,
+    // Local copies made by ingest recipes, usable either way
+    pub ingest_cache: IngestCacheInfo,
+    pub cached_files: Vec<String>,
+}
+
+fn offline_status(
+    app: &AppHandle,
+    entries: Vec<(u64, DatasetEntry)>,
+) -> Result<OfflineStatus, GdalError> {
+    let offline = is_offline();
+    let open_datasets = entries
+        .into_iter()
+        .map(|(handle, entry)| {
+            let open = entry.lock().unwrap();
+            DatasetAvailability {
+                handle,
+                path: open.path.clone(),
+                usable: !offline || open.in_memory || !is_remote(&open.path),
+            }
+        })
+        .collect();
+
+    let dir = cache_dir(app)?;
+    let mut cached_files: Vec<String> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_file())
+                .map(|entry| entry.path().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    cached_files.sort();
+
+    Ok(OfflineStatus {
+        offline,
+        open_datasets,
+        ingest_cache: cache_info(&dir),
+        cached_files,
+    })
+}
+
+// Reports whether offline mode is on and which open datasets and cached files can
+// still be used
+#[tauri::command]
+pub async fn get_offline_status(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+) -> Result<OfflineStatus, String> {
+    let entries = registry.entries();
+    run_blocking(move || offline_status(&app, entries).map_err(|e| e.to_string())).await
+}
+
+// Turns offline mode on or off and persists it. While on, remote paths, processing jobs
+// with remote inputs or outputs and webhook notifications fail at once with an
+// "Offline mode" error instead of waiting on network timeouts.
+#[tauri::command]
+pub async fn set_offline_mode(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+    store: State<'_, SettingsStore>,
+    offline: bool,
+) -> Result<OfflineStatus, String> {
+    let settings = store
+        .update(|settings| settings.network.offline = offline)
+        .map_err(|e| e.to_string())?;
+    apply_network_settings(&settings.network).map_err(|e| e.to_string())?;
+
+    let entries = registry.entries();
+    run_blocking(move || offline_status(&app, entries).map_err(|e| e.to_string())).await
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::jobs::{unix_millis, JobRecord, JobStatus};
+use crate::settings::SettingsStore;
+use crate::{ffi, run_blocking, setup_gdal_runtime};
+
+// The frontend turns these into system notifications with the Web Notification API
+pub const NOTIFICATION_EVENT: &str = "job-notification";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyWhen {
+    Never,
+    Failure,
+    Always,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    // Receives a JSON POST per notification, with a Slack-compatible `text` field
+    pub webhook_url: Option<String>,
+    pub desktop: bool,
+    // Jobs finishing sooner are left alone, the user is likely still watching
+    pub min_duration_secs: u64,
+    pub notify: NotifyWhen,
+    // Per-command overrides of `notify`, e.g. {"batch_reproject": "always"}
+    pub commands: BTreeMap<String, NotifyWhen>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            desktop: true,
+            min_duration_secs: 60,
+            notify: NotifyWhen::Always,
+            commands: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobNotification {
+    pub title: String,
+    pub body: String,
+    pub job: JobRecord,
+}
+
+impl JobNotification {
+    fn new(job: &JobRecord) -> Self {
+        let seconds = job.duration_ms / 1000;
+        let duration = format!(
+            "{}h {:02}m {:02}s",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        );
+        let (title, body) = match job.status {
+            JobStatus::Succeeded => (
+                format!("{} finished", job.command),
+                format!("Completed in {}", duration),
+            ),
+            JobStatus::Failed => (
+                format!("{} failed", job.command),
+                format!(
+                    "Failed after {}: {}",
+                    duration,
+                    job.error.as_deref().unwrap_or("unknown error")
+                ),
+            ),
+        };
+        Self {
+            title,
+            body,
+            job: job.clone(),
+        }
+    }
+}
+
+fn should_notify(settings: &NotificationSettings, job: &JobRecord) -> bool {
+    if job.duration_ms < settings.min_duration_secs * 1000 {
+        return false;
+    }
+    let when = settings
+        .commands
+        .get(&job.command)
+        .copied()
+        .unwrap_or(settings.notify);
+    match when {
+        NotifyWhen::Never => false,
+        NotifyWhen::Failure => matches!(job.status, JobStatus::Failed),
+        NotifyWhen::Always => true,
+    }
+}
+
+fn send(app: &AppHandle, settings: &NotificationSettings, notification: JobNotification) {
+    if settings.desktop {
+        let _ = app.emit(NOTIFICATION_EVENT, notification.clone());
+    }
+    if let Some(url) = settings.webhook_url.clone().filter(|url| !url.is_empty()) {
+        // Webhooks can be slow to answer, so they never hold up the job's result
+        thread::spawn(move || {
+            let _ = post_webhook(&url, &notification);
+        });
+    }
+}
+
+fn post_webhook(url: &str, notification: &JobNotification) -> Result<(), String> {
+    // Ensure GDAL runtime is set up
+    setup_gdal_runtime();
+
+    let body = json!({
+        "text": format!("{}: {}", notification.title, notification.body),
+        "title": notification.title,
+        "job": notification.job,
+    });
+    ffi::http_post(url, &body.to_string(), "application/json").map_err(|e| e.to_string())
+}
+
+// Called by `run_job` for every finished job
+pub(crate) fn job_finished(app: &AppHandle, job: &JobRecord) {
+    let Some(store) = app.try_state::<SettingsStore>() else {
+        return;
+    };
+    let settings = store.get().notifications;
+    if should_notify(&settings, job) {
+        send(app, &settings, JobNotification::new(job));
+    }
+}
+
+// Sends a sample notification through the configured channels, so a webhook can be
+// checked before relying on it. Unlike job notifications, webhook errors are returned.
+#[tauri::command]
+pub async fn test_notification(
+    app: AppHandle,
+    settings: Option<NotificationSettings>,
+) -> Result<(), String> {
+    let settings = match settings {
+        Some(settings) => settings,
+        None => app.state::<SettingsStore>().get().notifications,
+    };
+    let notification = JobNotification {
+        title: "Test notification".to_string(),
+        body: "Notifications are set up".to_string(),
+        job: JobRecord {
+            id: "test".to_string(),
+            command: "test_notification".to_string(),
+            params: json!({}),
+            status: JobStatus::Succeeded,
+            error: None,
+            started_at: unix_millis(),
+            duration_ms: 0,
+            gdal_version: gdal::version_info("RELEASE_NAME"),
+            command_lines: Vec::new(),
+            warnings: Vec::new(),
+        },
+    };
+
+    if settings.desktop {
+        let _ = app.emit(NOTIFICATION_EVENT, notification.clone());
+    }
+    match settings.webhook_url.filter(|url| !url.is_empty()) {
+        Some(url) => run_blocking(move || post_webhook(&url, &notification)).await,
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,92 @@
+use gdal::config::set_config_option;
+use gdal::cpl::CslStringList;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::GdalError;
+
+// An empty file with this name next to the executable turns on portable mode
+pub const PORTABLE_MARKER: &str = "portable";
+
+// Command line switch with the same effect as the marker file
+pub const PORTABLE_FLAG: &str = "--portable";
+
+// Directory next to the executable holding everything a portable install writes
+const PORTABLE_DIR: &str = "data";
+
+// Directories next to the executable that, in portable mode, replace the GDAL and PROJ
+// support files of the system
+const GDAL_DATA_DIR: &str = "gdal-data";
+const PROJ_DATA_DIR: &str = "proj";
+
+// Where the application keeps its files. Installed copies use the per-user directories
+// of the platform; portable copies keep everything beside the executable, e.g. on a USB
+// stick.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppPaths {
+    pub portable: bool,
+    // Settings
+    pub config_dir: PathBuf,
+    // Job history, watch activity and catalogs
+    pub data_dir: PathBuf,
+    // Ingest copies and other files that can be rebuilt
+    pub cache_dir: PathBuf,
+}
+
+pub(crate) fn executable_dir() -> Option<PathBuf> {
+    env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+// Directory portable installs resolve their paths against, None when not portable
+pub(crate) fn portable_root() -> Option<PathBuf> {
+    let dir = executable_dir()?;
+    let flagged = env::args().any(|arg| arg == PORTABLE_FLAG);
+    (flagged || dir.join(PORTABLE_MARKER).is_file()).then_some(dir)
+}
+
+impl AppPaths {
+    pub(crate) fn resolve(app: &AppHandle) -> Result<Self, tauri::Error> {
+        if let Some(root) = portable_root() {
+            let base = root.join(PORTABLE_DIR);
+            return Ok(Self {
+                portable: true,
+                config_dir: base.join("config"),
+                data_dir: base.join("data"),
+                cache_dir: base.join("cache"),
+            });
+        }
+        Ok(Self {
+            portable: false,
+            config_dir: app.path().app_config_dir()?,
+            data_dir: app.path().app_data_dir()?,
+            cache_dir: app.path().app_cache_dir()?,
+        })
+    }
+}
+
+// Points GDAL and PROJ at the support files shipped beside a portable executable, so
+// CRS lookups and drivers needing GDAL_DATA work without an installation. Runs before
+// anything creates a PROJ context.
+pub(crate) fn use_portable_support_files() -> Result<(), GdalError> {
+    let Some(root) = portable_root() else {
+        return Ok(());
+    };
+    let gdal_data = root.join(GDAL_DATA_DIR);
+    if gdal_data.is_dir() {
+        set_config_option("GDAL_DATA", &gdal_data.to_string_lossy())?;
+    }
+    let proj_data = root.join(PROJ_DATA_DIR);
+    if proj_data.is_dir() {
+        let mut paths = CslStringList::new();
+        paths.add_string(&proj_data.to_string_lossy())?;
+        unsafe { gdal_sys::OSRSetPROJSearchPaths(paths.as_ptr() as *const *const _) };
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_app_paths(paths: State<'_, AppPaths>) -> AppPaths {
+    paths.inner().clone()
+}
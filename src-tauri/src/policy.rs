@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::ipc::{Invoke, InvokeBody};
+use tauri::{Manager, Runtime, State};
+
+use crate::paths::executable_dir;
+use crate::GdalError;
+
+// Installation-wide policy file next to the executable, e.g. written by an installer or
+// device management for kiosk and viewer deployments. Users cannot loosen it from the
+// app.
+pub const POLICY_FILE: &str = "policy.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandGroup {
+    // Removes saved data: bookmarks, presets, watch folders, catalog entries, history
+    Delete,
+    // Changes datasets in place or saved configuration
+    Edit,
+    // Writes output files, which may replace existing ones
+    Overwrite,
+}
+
+impl CommandGroup {
+    pub const ALL: [CommandGroup; 3] = [
+        CommandGroup::Delete,
+        CommandGroup::Edit,
+        CommandGroup::Overwrite,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            CommandGroup::Delete => "delete",
+            CommandGroup::Edit => "edit",
+            CommandGroup::Overwrite => "overwrite",
+        }
+    }
+
+    fn commands(&self) -> &'static [&'static str] {
+        match self {
+            CommandGroup::Delete => &[
+                "delete_bookmark",
+                "delete_preset",
+                "delete_watch_folder",
+                "catalog_remove",
+                "clear_job_history",
+                "clear_watch_activity",
+                "clear_ingest_cache",
+            ],
+            CommandGroup::Edit => &[
+                "assign_crs",
+                "batch_assign_crs",
+                "build_overviews",
+                "update_settings",
+                "set_offline_mode",
+                "save_bookmark",
+                "save_preset",
+                "save_preset_from_job",
+                "set_preset_favorite",
+                "save_watch_folder",
+                "catalog_add",
+            ],
+            CommandGroup::Overwrite => &[
+                "save_dataset_as",
+                "export_view",
+                "export_animation_frames",
+                "generate_sheet_index",
+                "export_sheets",
+                "export_recipe",
+                "warp_raster",
+                "clip_raster_by_geometry",
+                "clip_raster",
+                "resample_raster",
+                "export_ascii",
+                "retile_raster",
+                "retile_dataset",
+                "dem_process",
+                "generate_contours",
+                "fill_nodata",
+                "sieve_raster",
+                "polygonize",
+                "compute_proximity",
+                "grid_points",
+                "kernel_density",
+                "rasterize",
+                "zonal_statistics",
+                "raster_calc",
+                "compute_index",
+                "reclassify_raster",
+                "compare_rasters",
+                "pansharpen",
+                "export_cog",
+                "export_tiles",
+                "build_vrt",
+                "merge_rasters",
+                "batch_reproject",
+                "export_flatgeobuf",
+                "import_dxf",
+                "export_dxf",
+                "export_geojson",
+                "merge_vectors",
+                "split_by_attribute",
+                "deduplicate_features",
+                "snap_geometries",
+                "merge_lines",
+                "split_lines_at_intersections",
+                "planarize_lines",
+                "bin_points",
+                "cluster_points",
+                "bounding_geometries",
+                "export_pmtiles",
+                "export_mvt",
+                "extract_osm",
+                "download_proj_grids",
+            ],
+        }
+    }
+
+    pub fn of(command: &str) -> Option<CommandGroup> {
+        Self::ALL
+            .into_iter()
+            .find(|group| group.commands().contains(&command))
+    }
+}
+
+// Commands that only write a file when given a `dst`, and are otherwise read-only
+const OPTIONAL_OUTPUT: &[&str] = &["zonal_statistics", "compare_rasters"];
+
+// Commands only the policy file can disable, so groups turned off in the settings can
+// always be turned back on
+const MANAGED_ONLY: &[&str] = &["update_settings"];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PolicyFile {
+    disabled_groups: BTreeSet<CommandGroup>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PolicyInfo {
+    // Everything disabled, from the policy file and the settings
+    pub disabled_groups: BTreeSet<CommandGroup>,
+    // The part set by the policy file, which settings cannot re-enable
+    pub managed_groups: BTreeSet<CommandGroup>,
+    pub policy_file: Option<String>,
+    // Commands the frontend should hide or grey out
+    pub disabled_commands: Vec<String>,
+}
+
+// Command groups switched off for this installation, checked for every command before
+// it runs
+pub struct CommandPolicy {
+    managed: BTreeSet<CommandGroup>,
+    policy_file: Option<PathBuf>,
+    configured: Mutex<BTreeSet<CommandGroup>>,
+}
+
+impl CommandPolicy {
+    // Reads the policy file beside the executable. A policy file that cannot be read
+    // disables every group, a locked-down install must not open up by accident.
+    pub(crate) fn load(configured: BTreeSet<CommandGroup>) -> Self {
+        let path = executable_dir()
+            .map(|dir| dir.join(POLICY_FILE))
+            .filter(|path| path.is_file());
+        let managed = match &path {
+            Some(path) => fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| {
+                    serde_json::from_str::<PolicyFile>(&text).map_err(|e| e.to_string())
+                })
+                .map(|file| file.disabled_groups)
+                .unwrap_or_else(|e| {
+                    eprintln!("Invalid policy file {}: {}", path.display(), e);
+                    CommandGroup::ALL.into_iter().collect()
+                }),
+            None => BTreeSet::new(),
+        };
+        Self {
+            managed,
+            policy_file: path,
+            configured: Mutex::new(configured),
+        }
+    }
+
+    pub fn set_configured(&self, groups: BTreeSet<CommandGroup>) {
+        *self.configured.lock().unwrap() = groups;
+    }
+
+    pub fn disabled_groups(&self) -> BTreeSet<CommandGroup> {
+        let configured = self.configured.lock().unwrap();
+        self.managed.union(&configured).copied().collect()
+    }
+
+    fn disables(&self, group: CommandGroup, command: &str) -> bool {
+        if MANAGED_ONLY.contains(&command) {
+            self.managed.contains(&group)
+        } else {
+            self.disabled_groups().contains(&group)
+        }
+    }
+
+    // Fails when `command` belongs to a disabled group. `params` are the command's
+    // arguments, deciding whether commands with an optional output write anything.
+    pub fn check(&self, command: &str, params: &Value) -> Result<(), GdalError> {
+        let Some(group) = CommandGroup::of(command) else {
+            return Ok(());
+        };
+        if OPTIONAL_OUTPUT.contains(&command) && params.get("dst").is_none_or(Value::is_null) {
+            return Ok(());
+        }
+        if self.disables(group, command) {
+            return Err(GdalError::OperationFailed(format!(
+                "{} is disabled on this installation ({} commands are turned off)",
+                command,
+                group.name()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn info(&self) -> PolicyInfo {
+        let disabled_groups = self.disabled_groups();
+        let disabled_commands = disabled_groups
+            .iter()
+            .flat_map(|group| {
+                group
+                    .commands()
+                    .iter()
+                    .map(move |command| (*group, *command))
+            })
+            .filter(|(group, command)| self.disables(*group, command))
+            .map(|(_, command)| command.to_string())
+            .collect();
+        PolicyInfo {
+            disabled_groups,
+            managed_groups: self.managed.clone(),
+            policy_file: self
+                .policy_file
+                .as_ref()
+                .map(|path| path.to_string_lossy().to_string()),
+            disabled_commands,
+        }
+    }
+}
+
+// Wraps the command handler so disabled commands are rejected before they run
+pub(crate) fn enforce<R, F>(handler: F) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static
+where
+    R: Runtime,
+    F: Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+{
+    move |invoke| {
+        let app = invoke.message.webview().app_handle().clone();
+        if let Some(policy) = app.try_state::<CommandPolicy>() {
+            let params = match invoke.message.payload() {
+                InvokeBody::Json(params) => params.clone(),
+                InvokeBody::Raw(_) => Value::Null,
+            };
+            if let Err(e) = policy.check(invoke.message.command(), &params) {
+                invoke.resolver.reject(e.to_string());
+                return true;
+            }
+        }
+        handler(invoke)
+    }
+}
+
+#[tauri::command]
+pub fn get_command_policy(policy: State<'_, CommandPolicy>) -> PolicyInfo {
+    policy.info()
+}
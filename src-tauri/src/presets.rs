@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, State};
+
+use crate::jobs::{apply_overrides, path_params, replay, JobHistory};
+use crate::settings::SettingsStore;
+
+// A saved parameter set for one processing command, e.g. "Export COG for web". Inputs
+// such as `src` and `dst` are usually left out and given when the preset is run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub command: String,
+    pub params: Map<String, Value>,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+pub(crate) fn find_preset(store: &SettingsStore, name: &str) -> Result<Preset, String> {
+    store
+        .get()
+        .presets
+        .into_iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| format!("Unknown preset: {}", name))
+}
+
+#[tauri::command]
+pub fn list_presets(store: State<'_, SettingsStore>) -> Vec<Preset> {
+    store.get().presets
+}
+
+// Adds a preset, replacing any existing preset with the same name
+#[tauri::command]
+pub fn save_preset(store: State<'_, SettingsStore>, preset: Preset) -> Result<Vec<Preset>, String> {
+    if preset.name.trim().is_empty() {
+        return Err("Preset name must not be empty".to_string());
+    }
+    store
+        .update(|settings| {
+            settings
+                .presets
+                .retain(|existing| existing.name != preset.name);
+            settings.presets.push(preset);
+        })
+        .map(|settings| settings.presets)
+        .map_err(|e| e.to_string())
+}
+
+// Saves the parameters of a recorded job as a preset, without its input and output paths
+// (see `path_params`)
+#[tauri::command]
+pub fn save_preset_from_job(
+    store: State<'_, SettingsStore>,
+    history: State<'_, JobHistory>,
+    job_id: String,
+    name: String,
+) -> Result<Vec<Preset>, String> {
+    let job = history
+        .get(&job_id)
+        .ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    let mut params = match job.params {
+        Value::Object(params) => params,
+        _ => Map::new(),
+    };
+    for key in path_params(&job.command) {
+        params.remove(*key);
+    }
+
+    save_preset(
+        store,
+        Preset {
+            name,
+            command: job.command,
+            params,
+            favorite: false,
+        },
+    )
+}
+
+#[tauri::command]
+pub fn delete_preset(store: State<'_, SettingsStore>, name: String) -> Result<Vec<Preset>, String> {
+    store
+        .update(|settings| settings.presets.retain(|preset| preset.name != name))
+        .map(|settings| settings.presets)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_preset_favorite(
+    store: State<'_, SettingsStore>,
+    name: String,
+    favorite: bool,
+) -> Result<Vec<Preset>, String> {
+    store
+        .update(|settings| {
+            for preset in settings
+                .presets
+                .iter_mut()
+                .filter(|preset| preset.name == name)
+            {
+                preset.favorite = favorite;
+            }
+        })
+        .map(|settings| settings.presets)
+        .map_err(|e| e.to_string())
+}
+
+// Runs the preset's command with `inputs` merged over the saved parameters
+#[tauri::command]
+pub async fn run_preset(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    name: String,
+    inputs: Option<Map<String, Value>>,
+) -> Result<Value, String> {
+    let preset = find_preset(&store, &name)?;
+    let params = apply_overrides(Value::Object(preset.params), inputs);
+    replay(app, &preset.command, params).await
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+use std::cell::Cell;
+use std::ffi::{c_char, c_int, c_void, CStr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter};
+
+pub const PROGRESS_EVENT: &str = "gdal-progress";
+
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    pub id: u64,
+    pub operation: String,
+    pub fraction: f64,
+    pub message: Option<String>,
+}
+
+// Emits progress events for one long-running operation
+pub struct Progress {
+    app: AppHandle,
+    id: u64,
+    operation: String,
+    last_fraction: Cell<f64>,
+    // Slice of the overall operation covered by the current step
+    range: Cell<(f64, f64)>,
+}
+
+impl Progress {
+    pub fn new(app: &AppHandle, operation: &str) -> Self {
+        Self {
+            app: app.clone(),
+            id: NEXT_OPERATION_ID.fetch_add(1, Ordering::Relaxed),
+            operation: operation.to_string(),
+            last_fraction: Cell::new(-1.0),
+            range: Cell::new((0.0, 1.0)),
+        }
+    }
+
+    // Maps subsequent step fractions into `start..end` of the overall operation, for
+    // commands that run several GDAL calls in sequence
+    pub fn set_range(&self, start: f64, end: f64) {
+        self.range.set((start, end));
+    }
+
+    pub fn report(&self, fraction: f64, message: Option<String>) {
+        // GDAL reports very frequently, only forward whole-percent changes
+        let (start, end) = self.range.get();
+        let fraction = start + fraction.clamp(0.0, 1.0) * (end - start);
+        if fraction < 1.0 && fraction - self.last_fraction.get() < 0.01 {
+            return;
+        }
+        self.last_fraction.set(fraction);
+
+        let _ = self.app.emit(
+            PROGRESS_EVENT,
+            ProgressEvent {
+                id: self.id,
+                operation: self.operation.clone(),
+                fraction,
+                message,
+            },
+        );
+    }
+
+    // Pointer handed to GDAL as pProgressArg for `gdal_progress`
+    pub fn as_arg(&self) -> *mut c_void {
+        self as *const Progress as *mut c_void
+    }
+}
+
+pub unsafe extern "C" fn gdal_progress(
+    complete: f64,
+    message: *const c_char,
+    arg: *mut c_void,
+) -> c_int {
+    if arg.is_null() {
+        return 1;
+    }
+
+    let progress = &*(arg as *const Progress);
+    let message = if message.is_null() {
+        None
+    } else {
+        let text = CStr::from_ptr(message).to_string_lossy().into_owned();
+        (!text.is_empty()).then_some(text)
+    };
+    progress.report(complete, message);
+    1
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::jobs::JobRecord;
+use crate::raster::vrt::expand_inputs;
+use crate::{run_blocking, GdalError};
+
+const PROVENANCE_VERSION: u32 = 1;
+const SIDECAR_SUFFIX: &str = ".provenance.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHash {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+// What produced an output: the job that wrote it and checksums of the files involved
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Provenance {
+    pub version: u32,
+    pub job_id: String,
+    pub command: String,
+    pub params: Value,
+    pub inputs: Vec<FileHash>,
+    pub outputs: Vec<FileHash>,
+    pub command_lines: Vec<String>,
+    pub gdal_version: String,
+    // Unix time in milliseconds
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Unchanged,
+    Modified,
+    Missing,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileCheck {
+    pub path: String,
+    pub output: bool,
+    pub status: FileStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProvenanceCheck {
+    pub provenance: Provenance,
+    pub files: Vec<FileCheck>,
+    // True when every input and output still matches its recorded checksum
+    pub valid: bool,
+}
+
+pub(crate) fn sidecar_path(output: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", output, SIDECAR_SUFFIX))
+}
+
+pub(crate) fn hash_file(path: &str) -> Result<FileHash, GdalError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher)?;
+    Ok(FileHash {
+        path: path.to_string(),
+        size,
+        sha256: format!("{:x}", hasher.finalize()),
+    })
+}
+
+fn param_paths(params: &Value, keys: &[&str]) -> Vec<String> {
+    let mut paths = Vec::new();
+    for key in keys {
+        match params.get(*key) {
+            Some(Value::String(path)) => paths.push(path.clone()),
+            // Plain paths, or objects with a `path` such as raster_calc inputs
+            Some(Value::Array(items)) => paths.extend(items.iter().filter_map(|item| {
+                item.as_str()
+                    .or_else(|| item.get("path").and_then(Value::as_str))
+                    .map(str::to_string)
+            })),
+            _ => {}
+        }
+    }
+    paths
+}
+
+// Writes `<dst>.provenance.json` for a successful job. Only jobs with a single `dst`
+// output get a sidecar; batch commands write many files and are covered by the job
+// history instead.
+pub(crate) fn write_sidecar(job: &JobRecord) -> Result<Option<PathBuf>, GdalError> {
+    let Some(dst) = job.params.get("dst").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    if !Path::new(dst).is_file() {
+        return Ok(None);
+    }
+
+    // Glob patterns are recorded as given and hashed as the files they matched
+    let inputs = expand_inputs(&param_paths(&job.params, &["src", "src_vector", "inputs"]))?;
+    let provenance = Provenance {
+        version: PROVENANCE_VERSION,
+        job_id: job.id.clone(),
+        command: job.command.clone(),
+        params: job.params.clone(),
+        inputs: inputs
+            .iter()
+            .filter(|path| Path::new(path).is_file())
+            .map(|path| hash_file(path))
+            .collect::<Result<_, _>>()?,
+        outputs: vec![hash_file(dst)?],
+        command_lines: job.command_lines.clone(),
+        gdal_version: job.gdal_version.clone(),
+        started_at: job.started_at,
+        finished_at: job.started_at + job.duration_ms,
+    };
+
+    let path = sidecar_path(dst);
+    let text = serde_json::to_string_pretty(&provenance)
+        .map_err(|e| GdalError::OperationFailed(e.to_string()))?;
+    fs::write(&path, text)?;
+    Ok(Some(path))
+}
+
+// Accepts either an output file or its sidecar
+fn read(path: &str) -> Result<Provenance, GdalError> {
+    let sidecar = if path.ends_with(SIDECAR_SUFFIX) {
+        PathBuf::from(path)
+    } else {
+        sidecar_path(path)
+    };
+    if !sidecar.exists() {
+        return Err(GdalError::FileNotFound(
+            sidecar.to_string_lossy().to_string(),
+        ));
+    }
+    let text = fs::read_to_string(&sidecar)?;
+    serde_json::from_str(&text)
+        .map_err(|e| GdalError::OperationFailed(format!("Invalid provenance file: {}", e)))
+}
+
+fn check(recorded: &FileHash, output: bool) -> FileCheck {
+    let status = match hash_file(&recorded.path) {
+        Ok(current) if current.sha256 == recorded.sha256 => FileStatus::Unchanged,
+        Ok(_) => FileStatus::Modified,
+        Err(_) => FileStatus::Missing,
+    };
+    FileCheck {
+        path: recorded.path.clone(),
+        output,
+        status,
+    }
+}
+
+#[tauri::command]
+pub async fn read_provenance(path: String) -> Result<Provenance, String> {
+    run_blocking(move || read(&path).map_err(|e| e.to_string())).await
+}
+
+// Re-hashes the recorded inputs and outputs to show whether anything changed since
+// the output was written
+#[tauri::command]
+pub async fn verify_provenance(path: String) -> Result<ProvenanceCheck, String> {
+    run_blocking(move || {
+        let provenance = read(&path).map_err(|e| e.to_string())?;
+        let files: Vec<_> = provenance
+            .inputs
+            .iter()
+            .map(|input| check(input, false))
+            .chain(provenance.outputs.iter().map(|output| check(output, true)))
+            .collect();
+        let valid = files
+            .iter()
+            .all(|file| file.status == FileStatus::Unchanged);
+
+        Ok(ProvenanceCheck {
+            provenance,
+            files,
+            valid,
+        })
+    })
+    .await
+}
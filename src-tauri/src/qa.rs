@@ -0,0 +1,262 @@
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::LayerAccess;
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::network::check_remote;
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+// Share of the data extent that may fall outside the CRS area of use before it is reported
+const AREA_OF_USE_TOLERANCE: f64 = 0.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrsIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrsCheck {
+    // Layer name for vector data, `None` for the raster itself
+    pub layer: Option<String>,
+    pub crs_name: Option<String>,
+    pub epsg: Option<i32>,
+    pub bounds: Option<Extent>,
+    pub wgs84_bounds: Option<Extent>,
+    pub area_of_use: Option<Extent>,
+    pub area_of_use_name: Option<String>,
+    pub issues: Vec<CrsIssue>,
+    // True when the declared CRS is most likely wrong for the coordinates
+    pub likely_misassigned: bool,
+}
+
+impl CrsCheck {
+    fn warn(&mut self, message: String) {
+        self.issues.push(CrsIssue {
+            severity: Severity::Warning,
+            message,
+        });
+    }
+
+    fn error(&mut self, message: String) {
+        self.likely_misassigned = true;
+        self.issues.push(CrsIssue {
+            severity: Severity::Error,
+            message,
+        });
+    }
+}
+
+fn looks_geographic(bounds: &Extent) -> bool {
+    bounds.min_x >= -180.0 && bounds.max_x <= 360.0 && bounds.min_y >= -90.0 && bounds.max_y <= 90.0
+}
+
+fn intersection_area(a: &Extent, b: &Extent) -> f64 {
+    let width = a.max_x.min(b.max_x) - a.min_x.max(b.min_x);
+    let height = a.max_y.min(b.max_y) - a.min_y.max(b.min_y);
+    width.max(0.0) * height.max(0.0)
+}
+
+fn area(extent: &Extent) -> f64 {
+    (extent.max_x - extent.min_x) * (extent.max_y - extent.min_y)
+}
+
+// Bounds of the raster from its four corners, so rotated geotransforms are covered
+pub(crate) fn raster_bounds(dataset: &Dataset) -> Option<Extent> {
+    let gt = dataset.geo_transform().ok()?;
+    if gt == [0.0, 1.0, 0.0, 0.0, 0.0, 1.0] {
+        return None;
+    }
+
+    let (size_x, size_y) = dataset.raster_size();
+    let (size_x, size_y) = (size_x as f64, size_y as f64);
+    let corners = [(0.0, 0.0), (size_x, 0.0), (0.0, size_y), (size_x, size_y)];
+    let xs = corners.map(|(col, row)| gt[0] + col * gt[1] + row * gt[2]);
+    let ys = corners.map(|(col, row)| gt[3] + col * gt[4] + row * gt[5]);
+
+    Some(Extent {
+        min_x: xs.iter().cloned().fold(f64::INFINITY, f64::min),
+        min_y: ys.iter().cloned().fold(f64::INFINITY, f64::min),
+        max_x: xs.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        max_y: ys.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+    })
+}
+
+pub(crate) fn to_wgs84(srs: &SpatialRef, bounds: &Extent) -> Result<Extent, GdalError> {
+    bounds.transform(srs, &SpatialRef::from_epsg(4326)?)
+}
+
+// Cross-checks declared CRS against where the coordinates actually fall
+pub(crate) fn check_placement(
+    layer: Option<String>,
+    srs: Option<SpatialRef>,
+    bounds: Option<Extent>,
+) -> CrsCheck {
+    let mut check = CrsCheck {
+        layer,
+        crs_name: srs.as_ref().and_then(|srs| srs.name()),
+        epsg: srs.as_ref().and_then(|srs| srs.auth_code().ok()),
+        bounds,
+        wgs84_bounds: None,
+        area_of_use: None,
+        area_of_use_name: None,
+        issues: Vec::new(),
+        likely_misassigned: false,
+    };
+
+    let bounds = match bounds {
+        Some(bounds) => bounds,
+        None => {
+            check.warn("Dataset has no georeferencing, placement cannot be checked".to_string());
+            return check;
+        }
+    };
+
+    let srs = match srs {
+        Some(srs) => srs,
+        None => {
+            if looks_geographic(&bounds) {
+                check.warn(
+                    "No CRS is defined; coordinates look like longitude/latitude degrees (EPSG:4326?)"
+                        .to_string(),
+                );
+            } else {
+                check.warn("No CRS is defined".to_string());
+            }
+            return check;
+        }
+    };
+
+    if srs.is_geographic() && !looks_geographic(&bounds) {
+        if bounds.min_x >= -90.0
+            && bounds.max_x <= 90.0
+            && bounds.min_y >= -180.0
+            && bounds.max_y <= 180.0
+        {
+            check.error(
+                "Coordinates fit the CRS only with X and Y swapped; axis order is likely reversed"
+                    .to_string(),
+            );
+        } else {
+            check.error(
+                "CRS is geographic but coordinates are outside the valid degree range; data is likely projected"
+                    .to_string(),
+            );
+        }
+        return check;
+    }
+
+    if srs.is_projected() && looks_geographic(&bounds) {
+        check.error(
+            "CRS is projected but coordinates look like longitude/latitude degrees".to_string(),
+        );
+    }
+
+    match to_wgs84(&srs, &bounds) {
+        Ok(wgs84) => check.wgs84_bounds = Some(wgs84),
+        Err(e) => {
+            check.error(format!(
+                "Coordinates cannot be transformed to WGS84 with the declared CRS: {}",
+                e
+            ));
+            return check;
+        }
+    }
+
+    if let Some(area_of_use) = srs.area_of_use() {
+        let area_extent = Extent {
+            min_x: area_of_use.west_lon_degree,
+            min_y: area_of_use.south_lat_degree,
+            // Areas crossing the antimeridian have east < west
+            max_x: if area_of_use.east_lon_degree < area_of_use.west_lon_degree {
+                area_of_use.east_lon_degree + 360.0
+            } else {
+                area_of_use.east_lon_degree
+            },
+            max_y: area_of_use.north_lat_degree,
+        };
+        check.area_of_use = Some(area_extent);
+        check.area_of_use_name = Some(area_of_use.name);
+
+        if let Some(wgs84) = check.wgs84_bounds {
+            let data_area = area(&wgs84);
+            let overlap = intersection_area(&wgs84, &area_extent);
+            let outside = wgs84.min_x > area_extent.max_x
+                || wgs84.max_x < area_extent.min_x
+                || wgs84.min_y > area_extent.max_y
+                || wgs84.max_y < area_extent.min_y;
+
+            if outside {
+                check.error(format!(
+                    "Data lies entirely outside the CRS area of use ({})",
+                    check.area_of_use_name.clone().unwrap_or_default()
+                ));
+            } else if data_area > 0.0 && overlap / data_area < 1.0 - AREA_OF_USE_TOLERANCE {
+                check.warn(format!(
+                    "Only {:.0}% of the data falls within the CRS area of use",
+                    overlap / data_area * 100.0
+                ));
+            }
+        }
+    }
+
+    check
+}
+
+pub(crate) fn check_dataset(dataset: &Dataset) -> Vec<CrsCheck> {
+    let mut checks = Vec::new();
+
+    if dataset.raster_count() > 0 {
+        checks.push(check_placement(
+            None,
+            dataset.spatial_ref().ok(),
+            raster_bounds(dataset),
+        ));
+    }
+
+    for layer in dataset.layers() {
+        let bounds = layer
+            .try_get_extent()
+            .ok()
+            .flatten()
+            .or_else(|| layer.get_extent().ok());
+        let bounds = bounds.map(|envelope| Extent {
+            min_x: envelope.MinX,
+            min_y: envelope.MinY,
+            max_x: envelope.MaxX,
+            max_y: envelope.MaxY,
+        });
+        checks.push(check_placement(
+            Some(layer.name()),
+            layer.spatial_ref(),
+            bounds,
+        ));
+    }
+
+    checks
+}
+
+#[tauri::command]
+pub async fn check_crs_placement(path: String) -> Result<Vec<CrsCheck>, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !path.starts_with("/vsi") && !Path::new(&path).exists() {
+            return Err(format!("File not found: {}", path));
+        }
+        check_remote(&path).map_err(|e| e.to_string())?;
+
+        let dataset = Dataset::open(&path).map_err(|e| e.to_string())?;
+        Ok(check_dataset(&dataset))
+    })
+    .await
+}
@@ -0,0 +1,131 @@
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{dataset_info, setup_gdal_runtime, DatasetInfo};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AsciiFormat {
+    // ESRI ASCII Grid (.asc), as read by HEC-RAS and most GIS packages
+    #[default]
+    AsciiGrid,
+    // One "x y z" line per pixel
+    Xyz,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsciiExportOptions {
+    pub format: AsciiFormat,
+    // Both formats hold a single band
+    pub band: usize,
+    pub decimal_precision: Option<u8>,
+    pub significant_digits: Option<u8>,
+    // Column separator for XYZ
+    pub delimiter: String,
+    // Writes an "X Y Z" header line for XYZ
+    pub header: bool,
+    // Resamples non-square pixels to a single cell size, which some ASCII Grid readers require
+    pub force_square: bool,
+}
+
+impl Default for AsciiExportOptions {
+    fn default() -> Self {
+        Self {
+            format: AsciiFormat::AsciiGrid,
+            band: 1,
+            decimal_precision: None,
+            significant_digits: None,
+            delimiter: " ".to_string(),
+            header: false,
+            force_square: false,
+        }
+    }
+}
+
+impl AsciiExportOptions {
+    fn to_args(&self) -> Result<Vec<String>, String> {
+        if self.decimal_precision.is_some() && self.significant_digits.is_some() {
+            return Err("Use either decimal precision or significant digits, not both".to_string());
+        }
+
+        let mut creation_options = Vec::new();
+        if let Some(precision) = self.decimal_precision {
+            creation_options.push(format!("DECIMAL_PRECISION={}", precision));
+        }
+        if let Some(digits) = self.significant_digits {
+            creation_options.push(format!("SIGNIFICANT_DIGITS={}", digits));
+        }
+
+        let driver = match self.format {
+            AsciiFormat::AsciiGrid => {
+                if self.force_square {
+                    creation_options.push("FORCE_CELLSIZE=TRUE".to_string());
+                }
+                "AAIGrid"
+            }
+            AsciiFormat::Xyz => {
+                if self.delimiter.is_empty() {
+                    return Err("Delimiter must not be empty".to_string());
+                }
+                creation_options.push(format!("COLUMN_SEPARATOR={}", self.delimiter));
+                if self.header {
+                    creation_options.push("ADD_HEADER_LINE=YES".to_string());
+                }
+                "XYZ"
+            }
+        };
+
+        let mut args = vec![
+            "-of".to_string(),
+            driver.to_string(),
+            "-b".to_string(),
+            self.band.to_string(),
+        ];
+        for option in creation_options {
+            args.push("-co".to_string());
+            args.push(option);
+        }
+        Ok(args)
+    }
+}
+
+#[tauri::command]
+pub async fn export_ascii(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    options: Option<AsciiExportOptions>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({ "src": src, "dst": dst, "options": options });
+    run_job(app.clone(), "export_ascii", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+
+        let options = options.unwrap_or_default();
+        let args = options.to_args()?;
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if options.band == 0 || options.band > source.raster_count() {
+            return Err(format!(
+                "Band {} does not exist, the raster has {} bands",
+                options.band,
+                source.raster_count()
+            ));
+        }
+
+        let progress = Progress::new(&app, "export_ascii");
+        let output = translate(&source, &dst, &args, &progress).map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
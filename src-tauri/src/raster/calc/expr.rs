@@ -0,0 +1,575 @@
+use std::fmt;
+
+use crate::GdalError;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "'{}'", value),
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+// Longest operators first so `<=` is not read as `<` followed by `=`
+const OPERATORS: &[&str] = &[
+    "**", "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "^", "<", ">", "!",
+];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, GdalError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent, as in 1e-3
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| GdalError::InvalidArgument(format!("Invalid number '{}'", text)))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| {
+                    GdalError::InvalidArgument(format!("Unexpected character '{}'", c))
+                })?;
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Sin,
+    Cos,
+    Tan,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+    Where,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        let function = match name.to_ascii_lowercase().as_str() {
+            "abs" => Function::Abs,
+            "sqrt" => Function::Sqrt,
+            "exp" => Function::Exp,
+            "ln" | "log" => Function::Ln,
+            "log10" => Function::Log10,
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "round" => Function::Round,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "where" | "if" => Function::Where,
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    // Allowed argument counts as (min, max)
+    fn arity(&self) -> (usize, usize) {
+        match self {
+            Function::Min | Function::Max => (2, usize::MAX),
+            Function::Where => (3, 3),
+            _ => (1, 1),
+        }
+    }
+
+    fn numpy_name(&self) -> &'static str {
+        match self {
+            Function::Abs => "numpy.abs",
+            Function::Sqrt => "numpy.sqrt",
+            Function::Exp => "numpy.exp",
+            Function::Ln => "numpy.log",
+            Function::Log10 => "numpy.log10",
+            Function::Sin => "numpy.sin",
+            Function::Cos => "numpy.cos",
+            Function::Tan => "numpy.tan",
+            Function::Floor => "numpy.floor",
+            Function::Ceil => "numpy.ceil",
+            Function::Round => "numpy.round",
+            Function::Min => "numpy.minimum",
+            Function::Max => "numpy.maximum",
+            Function::Where => "numpy.where",
+        }
+    }
+}
+
+// Parsed raster calculator expression. Variables refer to inputs by index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(usize),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    names: &'a [&'a str],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat_op(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.position += 1;
+                Some(op)
+            }
+            // `and`, `or` and `not` as words
+            Some(Token::Ident(word)) => {
+                let op = match word.to_ascii_lowercase().as_str() {
+                    "and" => "&&",
+                    "or" => "||",
+                    "not" => "!",
+                    _ => return None,
+                };
+                if !ops.contains(&op) {
+                    return None;
+                }
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), GdalError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(GdalError::InvalidArgument(format!(
+                "Expected {}, found {}",
+                expected, token
+            ))),
+            None => Err(GdalError::InvalidArgument(format!(
+                "Expected {} at end of expression",
+                expected
+            ))),
+        }
+    }
+
+    // Precedence, lowest first: or, and, not, comparison, + -, * / %, unary minus, power.
+    // As in Python, `not a < b` negates the comparison.
+    fn or(&mut self) -> Result<Expr, GdalError> {
+        let mut left = self.and()?;
+        while self.eat_op(&["||"]).is_some() {
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, GdalError> {
+        let mut left = self.not()?;
+        while self.eat_op(&["&&"]).is_some() {
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, GdalError> {
+        if self.eat_op(&["!"]).is_some() {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, GdalError> {
+        let mut left = self.additive()?;
+        while let Some(op) = self.eat_op(&["<", "<=", ">", ">=", "==", "!="]) {
+            let op = match op {
+                "<" => BinaryOp::Lt,
+                "<=" => BinaryOp::Le,
+                ">" => BinaryOp::Gt,
+                ">=" => BinaryOp::Ge,
+                "==" => BinaryOp::Eq,
+                _ => BinaryOp::Ne,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.additive()?));
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, GdalError> {
+        let mut left = self.multiplicative()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            let op = if op == "+" {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+        Ok(left)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, GdalError> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.eat_op(&["*", "/", "%"]) {
+            let op = match op {
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                _ => BinaryOp::Rem,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, GdalError> {
+        match self.eat_op(&["-", "+"]) {
+            Some("-") => Ok(Expr::Unary(UnaryOp::Neg, Box::new(self.unary()?))),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    // Right associative, and binds tighter than unary minus: -2^2 is -4
+    fn power(&mut self) -> Result<Expr, GdalError> {
+        let base = self.primary()?;
+        if self.eat_op(&["^", "**"]).is_some() {
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, GdalError> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                let function = Function::from_name(&name).ok_or_else(|| {
+                    GdalError::InvalidArgument(format!("Unknown function '{}'", name))
+                })?;
+                self.position += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    args.push(self.or()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.position += 1;
+                        args.push(self.or()?);
+                    }
+                }
+                self.expect(Token::RParen)?;
+
+                let (min, max) = function.arity();
+                if args.len() < min || args.len() > max {
+                    return Err(GdalError::InvalidArgument(format!(
+                        "Wrong number of arguments for '{}'",
+                        name
+                    )));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Some(Token::Ident(name)) => {
+                if let Some(index) = self.names.iter().position(|n| *n == name) {
+                    return Ok(Expr::Variable(index));
+                }
+                match name.to_ascii_lowercase().as_str() {
+                    "pi" => Ok(Expr::Number(std::f64::consts::PI)),
+                    "e" => Ok(Expr::Number(std::f64::consts::E)),
+                    _ => Err(GdalError::InvalidArgument(format!(
+                        "Unknown input '{}', expected one of {}",
+                        name,
+                        self.names.join(", ")
+                    ))),
+                }
+            }
+            Some(token) => Err(GdalError::InvalidArgument(format!("Unexpected {}", token))),
+            None => Err(GdalError::InvalidArgument(
+                "Unexpected end of expression".to_string(),
+            )),
+        }
+    }
+}
+
+fn truth(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl Expr {
+    // Parses `input`, resolving identifiers against the input `names`
+    pub fn parse(input: &str, names: &[&str]) -> Result<Expr, GdalError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            names,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(GdalError::InvalidArgument(format!(
+                "Unexpected {} after expression",
+                token
+            )));
+        }
+        Ok(expr)
+    }
+
+    // Indices of the inputs the expression reads
+    pub fn variables(&self, used: &mut Vec<usize>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Variable(index) => {
+                if !used.contains(index) {
+                    used.push(*index);
+                }
+            }
+            Expr::Unary(_, operand) => operand.variables(used),
+            Expr::Binary(_, left, right) => {
+                left.variables(used);
+                right.variables(used);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|arg| arg.variables(used)),
+        }
+    }
+
+    // Evaluates over a block of `len` pixels, one slice per input
+    pub fn evaluate(&self, inputs: &[Vec<f64>], len: usize) -> Vec<f64> {
+        match self {
+            Expr::Number(value) => vec![*value; len],
+            Expr::Variable(index) => inputs[*index].clone(),
+            Expr::Unary(op, operand) => {
+                let mut values = operand.evaluate(inputs, len);
+                for value in &mut values {
+                    *value = match op {
+                        UnaryOp::Neg => -*value,
+                        UnaryOp::Not => truth(*value == 0.0),
+                    };
+                }
+                values
+            }
+            Expr::Binary(op, left, right) => {
+                let mut values = left.evaluate(inputs, len);
+                let right = right.evaluate(inputs, len);
+                for (a, b) in values.iter_mut().zip(right) {
+                    *a = match op {
+                        BinaryOp::Add => *a + b,
+                        BinaryOp::Sub => *a - b,
+                        BinaryOp::Mul => *a * b,
+                        BinaryOp::Div => *a / b,
+                        BinaryOp::Rem => *a % b,
+                        BinaryOp::Pow => a.powf(b),
+                        BinaryOp::Lt => truth(*a < b),
+                        BinaryOp::Le => truth(*a <= b),
+                        BinaryOp::Gt => truth(*a > b),
+                        BinaryOp::Ge => truth(*a >= b),
+                        BinaryOp::Eq => truth(*a == b),
+                        BinaryOp::Ne => truth(*a != b),
+                        BinaryOp::And => truth(*a != 0.0 && b != 0.0),
+                        BinaryOp::Or => truth(*a != 0.0 || b != 0.0),
+                    };
+                }
+                values
+            }
+            Expr::Call(Function::Where, args) => {
+                let condition = args[0].evaluate(inputs, len);
+                let mut values = args[1].evaluate(inputs, len);
+                let otherwise = args[2].evaluate(inputs, len);
+                for ((value, condition), otherwise) in
+                    values.iter_mut().zip(condition).zip(otherwise)
+                {
+                    if condition == 0.0 {
+                        *value = otherwise;
+                    }
+                }
+                values
+            }
+            Expr::Call(function @ (Function::Min | Function::Max), args) => {
+                let mut values = args[0].evaluate(inputs, len);
+                for arg in &args[1..] {
+                    let other = arg.evaluate(inputs, len);
+                    for (value, other) in values.iter_mut().zip(other) {
+                        *value = if *function == Function::Min {
+                            value.min(other)
+                        } else {
+                            value.max(other)
+                        };
+                    }
+                }
+                values
+            }
+            Expr::Call(function, args) => {
+                let mut values = args[0].evaluate(inputs, len);
+                for value in &mut values {
+                    *value = match function {
+                        Function::Abs => value.abs(),
+                        Function::Sqrt => value.sqrt(),
+                        Function::Exp => value.exp(),
+                        Function::Ln => value.ln(),
+                        Function::Log10 => value.log10(),
+                        Function::Sin => value.sin(),
+                        Function::Cos => value.cos(),
+                        Function::Tan => value.tan(),
+                        Function::Floor => value.floor(),
+                        Function::Ceil => value.ceil(),
+                        Function::Round => value.round(),
+                        Function::Min | Function::Max | Function::Where => unreachable!(),
+                    };
+                }
+                values
+            }
+        }
+    }
+
+    // The expression in gdal_calc.py (numpy) syntax, with inputs named by `names`
+    pub fn to_numpy(&self, names: &[String]) -> String {
+        match self {
+            Expr::Number(value) => value.to_string(),
+            Expr::Variable(index) => names[*index].clone(),
+            Expr::Unary(UnaryOp::Neg, operand) => format!("(-{})", operand.to_numpy(names)),
+            Expr::Unary(UnaryOp::Not, operand) => {
+                format!("numpy.logical_not({})", operand.to_numpy(names))
+            }
+            Expr::Binary(op, left, right) => {
+                let (left, right) = (left.to_numpy(names), right.to_numpy(names));
+                let symbol = match op {
+                    BinaryOp::And => return format!("numpy.logical_and({}, {})", left, right),
+                    BinaryOp::Or => return format!("numpy.logical_or({}, {})", left, right),
+                    // Rust's % keeps the sign of the dividend, like fmod
+                    BinaryOp::Rem => return format!("numpy.fmod({}, {})", left, right),
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Mul => "*",
+                    BinaryOp::Div => "/",
+                    BinaryOp::Pow => "**",
+                    BinaryOp::Lt => "<",
+                    BinaryOp::Le => "<=",
+                    BinaryOp::Gt => ">",
+                    BinaryOp::Ge => ">=",
+                    BinaryOp::Eq => "==",
+                    BinaryOp::Ne => "!=",
+                };
+                format!("({} {} {})", left, symbol, right)
+            }
+            Expr::Call(function, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_numpy(names)).collect();
+                match function {
+                    // numpy.minimum and numpy.maximum take exactly two arrays
+                    Function::Min | Function::Max => {
+                        args[1..].iter().fold(args[0].clone(), |acc, arg| {
+                            format!("{}({}, {})", function.numpy_name(), acc, arg)
+                        })
+                    }
+                    _ => format!("{}({})", function.numpy_name(), args.join(", ")),
+                }
+            }
+        }
+    }
+}
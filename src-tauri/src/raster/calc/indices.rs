@@ -0,0 +1,138 @@
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use super::{calculate, CalcInput, CalcOptions};
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, DatasetInfo};
+
+// A spectral index written as a raster_calc expression over named bands. The
+// expressions assume surface reflectance scaled to 0..1, which matters for EVI and SAVI.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexPreset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub expression: &'static str,
+    // Band names the expression uses, to be mapped to band numbers
+    pub bands: &'static [&'static str],
+}
+
+pub const INDEX_PRESETS: &[IndexPreset] = &[
+    IndexPreset {
+        name: "NDVI",
+        description: "Normalized Difference Vegetation Index",
+        expression: "(nir - red) / (nir + red)",
+        bands: &["nir", "red"],
+    },
+    IndexPreset {
+        name: "NDWI",
+        description: "Normalized Difference Water Index (McFeeters)",
+        expression: "(green - nir) / (green + nir)",
+        bands: &["green", "nir"],
+    },
+    IndexPreset {
+        name: "NDMI",
+        description: "Normalized Difference Moisture Index",
+        expression: "(nir - swir1) / (nir + swir1)",
+        bands: &["nir", "swir1"],
+    },
+    IndexPreset {
+        name: "NBR",
+        description: "Normalized Burn Ratio",
+        expression: "(nir - swir2) / (nir + swir2)",
+        bands: &["nir", "swir2"],
+    },
+    IndexPreset {
+        name: "NDBI",
+        description: "Normalized Difference Built-up Index",
+        expression: "(swir1 - nir) / (swir1 + nir)",
+        bands: &["swir1", "nir"],
+    },
+    IndexPreset {
+        name: "EVI",
+        description: "Enhanced Vegetation Index",
+        expression: "2.5 * (nir - red) / (nir + 6 * red - 7.5 * blue + 1)",
+        bands: &["nir", "red", "blue"],
+    },
+    IndexPreset {
+        name: "SAVI",
+        description: "Soil Adjusted Vegetation Index (L = 0.5)",
+        expression: "1.5 * (nir - red) / (nir + red + 0.5)",
+        bands: &["nir", "red"],
+    },
+];
+
+fn preset(name: &str) -> Result<&'static IndexPreset, String> {
+    INDEX_PRESETS
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            let names: Vec<&str> = INDEX_PRESETS.iter().map(|preset| preset.name).collect();
+            format!(
+                "Unknown index '{}', expected one of {}",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+#[tauri::command]
+pub fn list_index_presets() -> Vec<IndexPreset> {
+    INDEX_PRESETS.to_vec()
+}
+
+// Computes a preset index from the bands of one raster, e.g. NDVI with
+// {"nir": 8, "red": 4} for Sentinel-2
+#[tauri::command]
+pub async fn compute_index(
+    app: AppHandle,
+    src: String,
+    index_name: String,
+    band_mapping: BTreeMap<String, usize>,
+    dst: String,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src": src,
+        "index_name": index_name,
+        "band_mapping": band_mapping,
+        "dst": dst,
+    });
+    run_job(app.clone(), "compute_index", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let preset = preset(&index_name)?;
+        let inputs = preset
+            .bands
+            .iter()
+            .map(|name| {
+                let band = band_mapping
+                    .get(*name)
+                    .or_else(|| {
+                        band_mapping
+                            .iter()
+                            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                            .map(|(_, band)| band)
+                    })
+                    .ok_or_else(|| format!("{} needs a band number for '{}'", preset.name, name))?;
+                Ok(CalcInput {
+                    name: name.to_string(),
+                    path: src.clone(),
+                    band: *band,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let progress = Progress::new(&app, "compute_index");
+        calculate(
+            &inputs,
+            preset.expression,
+            &dst,
+            &CalcOptions::default(),
+            &progress,
+        )
+    })
+    .await
+}
@@ -0,0 +1,361 @@
+use gdal::raster::{Buffer, GdalDataType};
+use gdal::{Dataset, DriverManager, DriverType, Metadata};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use crate::jobs::{record_command_line, run_job};
+use crate::progress::Progress;
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, GdalError};
+
+mod expr;
+pub mod indices;
+
+use expr::Expr;
+
+// Pixels evaluated per block, bounding memory use independently of raster size
+const BLOCK_PIXELS: usize = 1 << 20;
+
+fn default_band() -> usize {
+    1
+}
+
+// A raster band referenced by name in the expression, e.g. {"name": "B4", "path": "..."}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalcInput {
+    pub name: String,
+    pub path: String,
+    #[serde(default = "default_band")]
+    pub band: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalcOptions {
+    // GDAL data type name, e.g. "Float32", "Int16" or "Byte"
+    pub output_type: String,
+    // Written where any input is nodata or the result is not a finite number;
+    // defaults to a value suited to the output type
+    pub nodata: Option<f64>,
+    pub creation_options: Vec<String>,
+}
+
+impl Default for CalcOptions {
+    fn default() -> Self {
+        Self {
+            output_type: "Float32".to_string(),
+            nodata: None,
+            creation_options: Vec::new(),
+        }
+    }
+}
+
+// Same defaults as gdal_calc.py
+pub(super) fn default_nodata(data_type: GdalDataType) -> f64 {
+    match data_type {
+        GdalDataType::UInt8 => 255.0,
+        GdalDataType::Int8 => -128.0,
+        GdalDataType::UInt16 => 65535.0,
+        GdalDataType::Int16 => -32768.0,
+        GdalDataType::UInt32 => 4294967295.0,
+        GdalDataType::Int32 => -2147483648.0,
+        GdalDataType::Float32 => -3.402823466e38,
+        _ => -1e20,
+    }
+}
+
+pub(super) fn parse_data_type(name: &str) -> Result<GdalDataType, GdalError> {
+    let c_name = ffi::c_string(name)?;
+    let ordinal = unsafe { gdal_sys::GDALGetDataTypeByName(c_name.as_ptr()) };
+    GdalDataType::try_from(ordinal as u32)
+        .ok()
+        .filter(|data_type| *data_type != GdalDataType::Unknown)
+        .ok_or_else(|| GdalError::InvalidArgument(format!("Unknown data type '{}'", name)))
+}
+
+// Creates the output, going through a temporary GeoTIFF for formats GDAL can only write
+// by copying (PNG, JPEG, ...)
+fn create_output(
+    dst: &str,
+    reference: &Dataset,
+    band_count: usize,
+    data_type: GdalDataType,
+    creation_options: &[String],
+) -> Result<(Dataset, Option<String>), GdalError> {
+    let driver = DriverManager::get_output_driver_for_dataset_name(dst, DriverType::Raster)
+        .map_or_else(|| DriverManager::get_driver_by_name("GTiff"), Ok)?;
+    let direct = driver.metadata_item("DCAP_CREATE", "").is_some();
+    let (driver, path, temporary) = if direct {
+        (driver, dst.to_string(), None)
+    } else {
+        let path = ffi::vsimem_path("calc.tif");
+        (
+            DriverManager::get_driver_by_name("GTiff")?,
+            path.clone(),
+            Some(path),
+        )
+    };
+
+    let c_path = ffi::c_string(&path)?;
+    let options = ffi::arg_list(if direct { creation_options } else { &[] })?;
+    let (width, height) = reference.raster_size();
+    let handle = unsafe {
+        gdal_sys::GDALCreate(
+            driver.c_driver(),
+            c_path.as_ptr(),
+            width as i32,
+            height as i32,
+            band_count as i32,
+            data_type as u32,
+            options.as_ptr(),
+        )
+    };
+    if handle.is_null() {
+        return Err(ffi::last_error("GDALCreate"));
+    }
+
+    let mut output = unsafe { Dataset::from_c_dataset(handle) };
+    if let Ok(gt) = reference.geo_transform() {
+        output.set_geo_transform(&gt)?;
+    }
+    if let Ok(srs) = reference.spatial_ref() {
+        output.set_spatial_ref(&srs)?;
+    }
+    Ok((output, temporary))
+}
+
+// Creates a raster with `band_count` bands at `dst` on the grid of `reference` and has
+// `fill` write its pixels, converting afterwards for formats that cannot be created directly
+pub(super) fn write_raster<F>(
+    dst: &str,
+    reference: &Dataset,
+    band_count: usize,
+    data_type: GdalDataType,
+    creation_options: &[String],
+    progress: &Progress,
+    fill: F,
+) -> Result<Dataset, GdalError>
+where
+    F: FnOnce(&Dataset, &Progress) -> Result<(), GdalError>,
+{
+    let (output, temporary) =
+        create_output(dst, reference, band_count, data_type, creation_options)?;
+    let Some(path) = temporary else {
+        fill(&output, progress)?;
+        return Ok(output);
+    };
+
+    progress.set_range(0.0, 0.8);
+    let result = fill(&output, progress).and_then(|_| {
+        progress.set_range(0.8, 1.0);
+        let mut args = Vec::new();
+        for option in creation_options {
+            args.extend(["-co".to_string(), option.clone()]);
+        }
+        translate(&output, dst, &args, progress)
+    });
+    drop(output);
+    let _ = gdal::vsi::unlink_mem_file(&path);
+    result
+}
+
+fn evaluate(
+    sources: &[(Dataset, usize)],
+    used: &[usize],
+    expr: &Expr,
+    output: &Dataset,
+    nodata: f64,
+    progress: &Progress,
+) -> Result<(), GdalError> {
+    let (width, height) = output.raster_size();
+    let rows = (BLOCK_PIXELS / width.max(1)).clamp(1, height.max(1));
+    let bands = sources
+        .iter()
+        .map(|(dataset, band)| dataset.rasterband(*band))
+        .collect::<Result<Vec<_>, _>>()?;
+    let band_nodata: Vec<Option<f64>> = bands.iter().map(|band| band.no_data_value()).collect();
+    let mut output_band = output.rasterband(1)?;
+    output_band.set_no_data_value(Some(nodata))?;
+
+    let mut y = 0;
+    while y < height {
+        let block_rows = rows.min(height - y);
+        let len = width * block_rows;
+
+        // Inputs the expression does not read stay empty
+        let mut values = vec![Vec::new(); sources.len()];
+        let mut valid = vec![true; len];
+        for &index in used {
+            let data = bands[index]
+                .read_as::<f64>(
+                    (0, y as isize),
+                    (width, block_rows),
+                    (width, block_rows),
+                    None,
+                )?
+                .into_shape_and_vec()
+                .1;
+            for (valid, value) in valid.iter_mut().zip(&data) {
+                if value.is_nan() || band_nodata[index] == Some(*value) {
+                    *valid = false;
+                }
+            }
+            values[index] = data;
+        }
+
+        let mut result = expr.evaluate(&values, len);
+        for (value, valid) in result.iter_mut().zip(valid) {
+            if !valid || !value.is_finite() {
+                *value = nodata;
+            }
+        }
+        let mut buffer = Buffer::new((width, block_rows), result);
+        output_band.write((0, y as isize), (width, block_rows), &mut buffer)?;
+
+        y += block_rows;
+        progress.report(y as f64 / height as f64, None);
+    }
+    Ok(())
+}
+
+// Records the gdal_calc.py equivalent, which names its inputs A to Z
+fn record_gdal_calc(inputs: &[CalcInput], expr: &Expr, dst: &str, output_type: &str, nodata: f64) {
+    if inputs.len() > 26 {
+        return;
+    }
+    let letters: Vec<String> = (b'A'..=b'Z')
+        .take(inputs.len())
+        .map(|letter| (letter as char).to_string())
+        .collect();
+    let mut args = Vec::new();
+    for (letter, input) in letters.iter().zip(inputs) {
+        args.extend([
+            format!("-{}", letter),
+            input.path.clone(),
+            format!("--{}_band", letter),
+            input.band.to_string(),
+        ]);
+    }
+    args.extend([
+        "--calc".to_string(),
+        expr.to_numpy(&letters),
+        "--outfile".to_string(),
+        dst.to_string(),
+        "--type".to_string(),
+        output_type.to_string(),
+        "--NoDataValue".to_string(),
+        nodata.to_string(),
+    ]);
+    record_command_line("gdal_calc.py", &args);
+}
+
+// Validates the inputs and writes `expression` evaluated over them to `dst`
+pub(crate) fn calculate(
+    inputs: &[CalcInput],
+    expression: &str,
+    dst: &str,
+    options: &CalcOptions,
+    progress: &Progress,
+) -> Result<DatasetInfo, String> {
+    if inputs.is_empty() {
+        return Err("At least one input is required".to_string());
+    }
+    for (i, input) in inputs.iter().enumerate() {
+        if !Path::new(&input.path).exists() {
+            return Err(format!("File not found: {}", input.path));
+        }
+        if inputs[..i].iter().any(|other| other.name == input.name) {
+            return Err(format!("Input name '{}' is used twice", input.name));
+        }
+    }
+
+    let names: Vec<&str> = inputs.iter().map(|input| input.name.as_str()).collect();
+    let expr = Expr::parse(expression, &names).map_err(|e| e.to_string())?;
+    let mut used = Vec::new();
+    expr.variables(&mut used);
+
+    let data_type = parse_data_type(&options.output_type).map_err(|e| e.to_string())?;
+    let nodata = options.nodata.unwrap_or_else(|| default_nodata(data_type));
+
+    let sources = inputs
+        .iter()
+        .map(|input| Dataset::open(&input.path).map(|dataset| (dataset, input.band)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let size = sources[0].0.raster_size();
+    for ((dataset, band), input) in sources.iter().zip(inputs) {
+        if *band == 0 || *band > dataset.raster_count() {
+            return Err(format!("{} has no band {}", input.name, band));
+        }
+        if dataset.raster_size() != size {
+            return Err(format!(
+                "{} is {}x{} pixels but {} is {}x{}; align the inputs with resample_raster first",
+                input.name,
+                dataset.raster_size().0,
+                dataset.raster_size().1,
+                inputs[0].name,
+                size.0,
+                size.1
+            ));
+        }
+    }
+
+    record_gdal_calc(inputs, &expr, dst, &options.output_type, nodata);
+    let output = write_raster(
+        dst,
+        &sources[0].0,
+        1,
+        data_type,
+        &options.creation_options,
+        progress,
+        |output, progress| evaluate(&sources, &used, &expr, output, nodata, progress),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let info = dataset_info(&output);
+    output.close().map_err(|e| e.to_string())?;
+    Ok(info)
+}
+
+// Evaluates `expression` pixel by pixel over inputs that share one grid, like
+// gdal_calc.py. Pixels where any input is nodata are nodata in the output.
+#[tauri::command]
+pub async fn raster_calc(
+    app: AppHandle,
+    inputs: Vec<CalcInput>,
+    expression: String,
+    dst: String,
+    options: Option<CalcOptions>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "inputs": inputs,
+        "expression": expression,
+        "dst": dst,
+        "options": options,
+    });
+    run_job(app.clone(), "raster_calc", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let progress = Progress::new(&app, "raster_calc");
+        calculate(
+            &inputs,
+            &expression,
+            &dst,
+            &options.unwrap_or_default(),
+            &progress,
+        )
+    })
+    .await
+}
+
+// Checks an expression against input names without running it, for live validation
+#[tauri::command]
+pub fn validate_calc_expression(expression: String, names: Vec<String>) -> Result<(), String> {
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    Expr::parse(&expression, &names)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,56 @@
+use gdal::Dataset;
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use crate::crs::parse_srs;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{dataset_info, setup_gdal_runtime, DatasetInfo, Extent};
+
+// Crops to a rectangle with gdal_translate -projwin, so no resampling happens. Use
+// `clip_raster_by_geometry` for polygons or when the output should be reprojected.
+#[tauri::command]
+pub async fn clip_raster(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    bbox: Extent,
+    bbox_crs: Option<String>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({ "src": src, "dst": dst, "bbox": bbox, "bbox_crs": bbox_crs });
+    run_job(app.clone(), "clip_raster", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        if bbox.min_x >= bbox.max_x || bbox.min_y >= bbox.max_y {
+            return Err("Bounding box is empty".to_string());
+        }
+
+        // -projwin takes the upper-left then lower-right corner
+        let mut args = vec![
+            "-projwin".to_string(),
+            bbox.min_x.to_string(),
+            bbox.max_y.to_string(),
+            bbox.max_x.to_string(),
+            bbox.min_y.to_string(),
+        ];
+
+        // Without a CRS the box is in the raster's own coordinates
+        if let Some(bbox_crs) = bbox_crs {
+            let srs = parse_srs(&bbox_crs).map_err(|e| e.to_string())?;
+            args.push("-projwin_srs".to_string());
+            args.push(srs.to_wkt().map_err(|e| e.to_string())?);
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        let progress = Progress::new(&app, "clip_raster");
+        let output = translate(&source, &dst, &args, &progress).map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
@@ -0,0 +1,229 @@
+use gdal::raster::RasterBand;
+use gdal::{Dataset, Metadata};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use super::{Compression, Resampling};
+use crate::jobs::run_job;
+use crate::network::{check_remote, is_remote};
+use crate::progress::Progress;
+use crate::{dataset_info, run_blocking, setup_gdal_runtime, DatasetInfo};
+
+// Rasters at or below this size are valid COGs without tiling or overviews
+const UNTILED_LIMIT: usize = 512;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CogOptions {
+    pub compression: Compression,
+    pub level: Option<u8>,
+    pub quality: Option<u8>,
+    pub block_size: Option<usize>,
+    pub predictor: bool,
+    pub overview_resampling: Option<Resampling>,
+}
+
+impl CogOptions {
+    fn to_args(&self) -> Result<Vec<String>, String> {
+        let mut creation_options = vec![
+            format!("COMPRESS={}", self.compression.as_gdal_option()),
+            "NUM_THREADS=ALL_CPUS".to_string(),
+            "BIGTIFF=IF_SAFER".to_string(),
+        ];
+
+        if let Some(block_size) = self.block_size {
+            if !(16..=4096).contains(&block_size) || block_size % 16 != 0 {
+                return Err("Block size must be a multiple of 16 between 16 and 4096".to_string());
+            }
+            creation_options.push(format!("BLOCKSIZE={}", block_size));
+        }
+        if let Some(level) = self.level {
+            creation_options.push(format!("LEVEL={}", level));
+        }
+        if let Some(quality) = self.quality {
+            creation_options.push(format!("QUALITY={}", quality));
+        }
+        if self.predictor {
+            creation_options.push("PREDICTOR=YES".to_string());
+        }
+        if let Some(resampling) = self.overview_resampling {
+            creation_options.push(format!(
+                "OVERVIEW_RESAMPLING={}",
+                resampling.as_overview_method()
+            ));
+        }
+
+        let mut args = vec!["-of".to_string(), "COG".to_string()];
+        for option in creation_options {
+            args.push("-co".to_string());
+            args.push(option);
+        }
+        Ok(args)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CogValidation {
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    pub driver: String,
+    pub layout: Option<String>,
+    pub compression: Option<String>,
+    pub block_size: (usize, usize),
+    pub overview_count: usize,
+}
+
+fn tiff_offset(band: &RasterBand, key: &str) -> Option<u64> {
+    band.metadata_item(key, "TIFF")?.parse().ok()
+}
+
+// Same checks as GDAL's validate_cloud_optimized_geotiff.py: tiling, overviews,
+// IFDs ordered from full resolution to smallest overview, and image data stored
+// in the reverse order so a reader can fetch all headers in one request.
+pub(crate) fn validate(dataset: &Dataset) -> CogValidation {
+    let mut report = CogValidation {
+        driver: dataset.driver().short_name(),
+        layout: dataset.metadata_item("LAYOUT", "IMAGE_STRUCTURE"),
+        compression: dataset.metadata_item("COMPRESSION", "IMAGE_STRUCTURE"),
+        ..Default::default()
+    };
+
+    if report.driver != "GTiff" {
+        report
+            .errors
+            .push(format!("File is not a GeoTIFF (driver {})", report.driver));
+        return report;
+    }
+
+    let main_band = match dataset.rasterband(1) {
+        Ok(band) => band,
+        Err(_) => {
+            report.errors.push("File has no raster bands".to_string());
+            return report;
+        }
+    };
+
+    let (size_x, size_y) = main_band.size();
+    report.block_size = main_band.block_size();
+    report.overview_count = main_band.overview_count().unwrap_or(0).max(0) as usize;
+
+    if report.layout.as_deref() != Some("COG") {
+        report
+            .warnings
+            .push("File lacks the COG ghost header (LAYOUT=COG)".to_string());
+    }
+
+    let large = size_x > UNTILED_LIMIT || size_y > UNTILED_LIMIT;
+    if large && report.block_size.0 == size_x {
+        report
+            .errors
+            .push("Full resolution image is not tiled".to_string());
+    }
+    if large && report.overview_count == 0 {
+        report
+            .warnings
+            .push("Image is larger than 512 pixels but has no overviews".to_string());
+    }
+
+    let mut ifd_offsets = vec![tiff_offset(&main_band, "IFD_OFFSET")];
+    let mut data_offsets = vec![tiff_offset(&main_band, "BLOCK_OFFSET_0_0")];
+
+    for index in 0..report.overview_count {
+        let overview = match main_band.overview(index) {
+            Ok(overview) => overview,
+            Err(_) => continue,
+        };
+
+        let (ovr_x, ovr_y) = overview.size();
+        let (block_x, _) = overview.block_size();
+        if (ovr_x > UNTILED_LIMIT || ovr_y > UNTILED_LIMIT) && block_x == ovr_x {
+            report
+                .errors
+                .push(format!("Overview of index {} is not tiled", index));
+        }
+
+        ifd_offsets.push(tiff_offset(&overview, "IFD_OFFSET"));
+        data_offsets.push(tiff_offset(&overview, "BLOCK_OFFSET_0_0"));
+    }
+
+    for i in 1..ifd_offsets.len() {
+        if let (Some(previous), Some(current)) = (ifd_offsets[i - 1], ifd_offsets[i]) {
+            if current < previous {
+                report.errors.push(format!(
+                    "IFD of overview {} is at offset {}, before the previous IFD at {}",
+                    i - 1,
+                    current,
+                    previous
+                ));
+            }
+        }
+    }
+
+    for i in 1..data_offsets.len() {
+        if let (Some(larger), Some(smaller)) = (data_offsets[i - 1], data_offsets[i]) {
+            if smaller > larger {
+                let name = if i == 1 {
+                    "full resolution image".to_string()
+                } else {
+                    format!("overview {}", i - 2)
+                };
+                report.errors.push(format!(
+                    "Data of overview {} is stored after the data of the {}",
+                    i - 1,
+                    name
+                ));
+            }
+        }
+    }
+
+    report.valid = report.errors.is_empty();
+    report
+}
+
+#[tauri::command]
+pub async fn export_cog(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    options: Option<CogOptions>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({ "src": src, "dst": dst, "options": options });
+    run_job(app.clone(), "export_cog", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+
+        let args = options.unwrap_or_default().to_args()?;
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+
+        let progress = Progress::new(&app, "export_cog");
+        let output = translate(&source, &dst, &args, &progress).map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn validate_cog(path: String) -> Result<CogValidation, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let remote = path.starts_with("/vsi") || is_remote(&path);
+        if !remote && !Path::new(&path).exists() {
+            return Err(format!("File not found: {}", path));
+        }
+        check_remote(&path).map_err(|e| e.to_string())?;
+
+        let dataset = Dataset::open(&path).map_err(|e| e.to_string())?;
+        Ok(validate(&dataset))
+    })
+    .await
+}
@@ -0,0 +1,341 @@
+use gdal::raster::{Buffer, GdalDataType};
+use gdal::{Dataset, GeoTransform};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::calc::{default_nodata, write_raster};
+use crate::jobs::{record_command_line, run_job};
+use crate::progress::Progress;
+use crate::qa::raster_bounds;
+use crate::{dataset_info, setup_gdal_runtime, DatasetInfo, Extent, GdalError};
+
+// Pixels compared per block
+const BLOCK_PIXELS: usize = 1 << 20;
+
+// Geotransform terms may differ by this share of a pixel and still count as equal
+const GEO_TRANSFORM_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BandDifference {
+    pub band: usize,
+    // Pixels valid in both rasters
+    pub compared_pixels: u64,
+    // Valid pixels whose values differ by more than the tolerance
+    pub differing_pixels: u64,
+    // Pixels that are nodata in one raster only
+    pub nodata_mismatches: u64,
+    // Statistics of b - a over the compared pixels; unset when none were compared
+    pub min_difference: Option<f64>,
+    pub max_difference: Option<f64>,
+    pub mean_difference: Option<f64>,
+    pub mean_absolute_difference: Option<f64>,
+    pub rmse: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RasterComparison {
+    // True when nothing below reports a difference
+    pub identical: bool,
+    // Human readable list of the structural differences (size, CRS, geotransform, ...)
+    pub differences: Vec<String>,
+    pub same_size: bool,
+    pub same_band_count: bool,
+    pub same_crs: bool,
+    pub same_geo_transform: bool,
+    pub extent_a: Option<Extent>,
+    pub extent_b: Option<Extent>,
+    // Empty when the sizes differ and pixels cannot be compared
+    pub bands: Vec<BandDifference>,
+    // The difference raster, when one was written
+    pub output: Option<DatasetInfo>,
+}
+
+// Running totals of b - a for one band
+#[derive(Default)]
+struct Accumulator {
+    compared: u64,
+    differing: u64,
+    nodata_mismatches: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    sum_absolute: f64,
+    sum_squares: f64,
+}
+
+impl Accumulator {
+    fn add(&mut self, difference: f64, tolerance: f64) {
+        if self.compared == 0 {
+            self.min = difference;
+            self.max = difference;
+        } else {
+            self.min = self.min.min(difference);
+            self.max = self.max.max(difference);
+        }
+        self.compared += 1;
+        if difference.abs() > tolerance {
+            self.differing += 1;
+        }
+        self.sum += difference;
+        self.sum_absolute += difference.abs();
+        self.sum_squares += difference * difference;
+    }
+
+    fn finish(self, band: usize) -> BandDifference {
+        let n = self.compared as f64;
+        let stat = |value: f64| (self.compared > 0).then_some(value);
+        BandDifference {
+            band,
+            compared_pixels: self.compared,
+            differing_pixels: self.differing,
+            nodata_mismatches: self.nodata_mismatches,
+            min_difference: stat(self.min),
+            max_difference: stat(self.max),
+            mean_difference: stat(self.sum / n),
+            mean_absolute_difference: stat(self.sum_absolute / n),
+            rmse: stat((self.sum_squares / n).sqrt()),
+        }
+    }
+}
+
+fn same_geo_transform(a: &GeoTransform, b: &GeoTransform) -> bool {
+    let pixel = a[1].abs().max(a[5].abs()).max(f64::EPSILON);
+    a.iter()
+        .zip(b)
+        .all(|(a, b)| (a - b).abs() <= pixel * GEO_TRANSFORM_TOLERANCE)
+}
+
+// Size, band count, CRS and geotransform checks
+fn compare_structure(a: &Dataset, b: &Dataset) -> RasterComparison {
+    let mut differences = Vec::new();
+
+    let same_size = a.raster_size() == b.raster_size();
+    if !same_size {
+        differences.push(format!(
+            "Size differs: {}x{} and {}x{}",
+            a.raster_size().0,
+            a.raster_size().1,
+            b.raster_size().0,
+            b.raster_size().1
+        ));
+    }
+    let same_band_count = a.raster_count() == b.raster_count();
+    if !same_band_count {
+        differences.push(format!(
+            "Band count differs: {} and {}",
+            a.raster_count(),
+            b.raster_count()
+        ));
+    }
+    let same_crs = match (a.spatial_ref(), b.spatial_ref()) {
+        (Ok(srs_a), Ok(srs_b)) => srs_a == srs_b,
+        (Err(_), Err(_)) => true,
+        _ => false,
+    };
+    if !same_crs {
+        differences.push("CRS differs".to_string());
+    }
+    let same_geo_transform = match (a.geo_transform(), b.geo_transform()) {
+        (Ok(gt_a), Ok(gt_b)) => same_geo_transform(&gt_a, &gt_b),
+        (Err(_), Err(_)) => true,
+        _ => false,
+    };
+    if !same_geo_transform {
+        differences.push("Geotransform differs".to_string());
+    }
+
+    for band in 1..=a.raster_count().min(b.raster_count()) {
+        let (Ok(band_a), Ok(band_b)) = (a.rasterband(band), b.rasterband(band)) else {
+            continue;
+        };
+        if band_a.band_type() != band_b.band_type() {
+            differences.push(format!(
+                "Band {} data type differs: {} and {}",
+                band,
+                band_a.band_type().name(),
+                band_b.band_type().name()
+            ));
+        }
+        if band_a.no_data_value() != band_b.no_data_value() {
+            differences.push(format!("Band {} nodata value differs", band));
+        }
+    }
+
+    RasterComparison {
+        identical: false,
+        differences,
+        same_size,
+        same_band_count,
+        same_crs,
+        same_geo_transform,
+        extent_a: raster_bounds(a),
+        extent_b: raster_bounds(b),
+        bands: Vec::new(),
+        output: None,
+    }
+}
+
+// Compares one band block by block, writing b - a to `output` band `band` when given
+fn compare_band(
+    a: &Dataset,
+    b: &Dataset,
+    band: usize,
+    tolerance: f64,
+    output: Option<(&Dataset, f64)>,
+) -> Result<BandDifference, GdalError> {
+    let (width, height) = a.raster_size();
+    let rows = (BLOCK_PIXELS / width.max(1)).clamp(1, height.max(1));
+    let band_a = a.rasterband(band)?;
+    let band_b = b.rasterband(band)?;
+    let (nodata_a, nodata_b) = (band_a.no_data_value(), band_b.no_data_value());
+    let is_nodata = |value: f64, nodata: Option<f64>| value.is_nan() || nodata == Some(value);
+    let mut output_band = match output {
+        Some((output, nodata)) => {
+            let mut output_band = output.rasterband(band)?;
+            output_band.set_no_data_value(Some(nodata))?;
+            Some((output_band, nodata))
+        }
+        None => None,
+    };
+
+    let mut accumulator = Accumulator::default();
+    let mut y = 0;
+    while y < height {
+        let block_rows = rows.min(height - y);
+        let read = |band: &gdal::raster::RasterBand| {
+            band.read_as::<f64>(
+                (0, y as isize),
+                (width, block_rows),
+                (width, block_rows),
+                None,
+            )
+            .map(|buffer| buffer.into_shape_and_vec().1)
+        };
+        let values_a = read(&band_a)?;
+        let values_b = read(&band_b)?;
+
+        let mut differences = Vec::with_capacity(values_a.len());
+        for (value_a, value_b) in values_a.into_iter().zip(values_b) {
+            match (is_nodata(value_a, nodata_a), is_nodata(value_b, nodata_b)) {
+                (false, false) => {
+                    let difference = value_b - value_a;
+                    accumulator.add(difference, tolerance);
+                    differences.push(Some(difference));
+                }
+                (true, true) => differences.push(None),
+                _ => {
+                    accumulator.nodata_mismatches += 1;
+                    differences.push(None);
+                }
+            }
+        }
+
+        if let Some((output_band, nodata)) = output_band.as_mut() {
+            let data = differences
+                .into_iter()
+                .map(|difference| difference.map_or(*nodata as f32, |d| d as f32))
+                .collect();
+            let mut buffer = Buffer::new((width, block_rows), data);
+            output_band.write((0, y as isize), (width, block_rows), &mut buffer)?;
+        }
+        y += block_rows;
+    }
+    Ok(accumulator.finish(band))
+}
+
+// Compares two rasters for QA of processing outputs: structure (size, bands, CRS,
+// geotransform, data types, nodata) and, when the grids match in size, per-band
+// statistics of b - a. Differences within `tolerance` do not count as differing. With
+// `dst` the Float32 difference b - a is written there, one band per compared band.
+#[tauri::command]
+pub async fn compare_rasters(
+    app: AppHandle,
+    a: String,
+    b: String,
+    tolerance: Option<f64>,
+    dst: Option<String>,
+) -> Result<RasterComparison, String> {
+    let params = json!({
+        "a": a,
+        "b": b,
+        "tolerance": tolerance,
+        "dst": dst,
+    });
+    run_job(app.clone(), "compare_rasters", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        for path in [&a, &b] {
+            if !Path::new(path).exists() {
+                return Err(format!("File not found: {}", path));
+            }
+        }
+        let tolerance = tolerance.unwrap_or(0.0);
+        if tolerance < 0.0 {
+            return Err("Tolerance must not be negative".to_string());
+        }
+
+        let dataset_a = Dataset::open(&a).map_err(|e| e.to_string())?;
+        let dataset_b = Dataset::open(&b).map_err(|e| e.to_string())?;
+        record_command_line("gdalcompare.py", &[a.clone(), b.clone()]);
+
+        let mut comparison = compare_structure(&dataset_a, &dataset_b);
+        if !comparison.same_size {
+            if dst.is_some() {
+                return Err("Rasters of different sizes cannot be differenced".to_string());
+            }
+            return Ok(comparison);
+        }
+
+        let band_count = dataset_a.raster_count().min(dataset_b.raster_count());
+        let progress = Progress::new(&app, "compare_rasters");
+        let compare_all = |output: Option<&Dataset>, progress: &Progress| {
+            let nodata = default_nodata(GdalDataType::Float32);
+            let mut bands = Vec::new();
+            for band in 1..=band_count {
+                bands.push(compare_band(
+                    &dataset_a,
+                    &dataset_b,
+                    band,
+                    tolerance,
+                    output.map(|output| (output, nodata)),
+                )?);
+                progress.report(band as f64 / band_count as f64, None);
+            }
+            Ok::<_, GdalError>(bands)
+        };
+
+        match &dst {
+            Some(dst) => {
+                let mut bands = Vec::new();
+                let output = write_raster(
+                    dst,
+                    &dataset_a,
+                    band_count,
+                    GdalDataType::Float32,
+                    &[],
+                    &progress,
+                    |output, progress| {
+                        bands = compare_all(Some(output), progress)?;
+                        Ok(())
+                    },
+                )
+                .map_err(|e| e.to_string())?;
+                comparison.output = Some(dataset_info(&output));
+                output.close().map_err(|e| e.to_string())?;
+                comparison.bands = bands;
+            }
+            None => comparison.bands = compare_all(None, &progress).map_err(|e| e.to_string())?,
+        }
+
+        comparison.identical = comparison.differences.is_empty()
+            && comparison
+                .bands
+                .iter()
+                .all(|band| band.differing_pixels == 0 && band.nodata_mismatches == 0);
+        Ok(comparison)
+    })
+    .await
+}
@@ -0,0 +1,159 @@
+use gdal::cpl::CslStringList;
+use gdal::vector::{FieldDefn, LayerAccess, LayerOptions, OGRFieldType, OGRwkbGeometryType};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::vector::create_output;
+use crate::{ffi, setup_gdal_runtime, GdalError};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContourResult {
+    pub path: String,
+    pub layer: String,
+    pub feature_count: u64,
+}
+
+fn contours(
+    source: &Dataset,
+    dst: &str,
+    interval: f64,
+    base: f64,
+    attribute: &str,
+    polygons: bool,
+    progress: &Progress,
+) -> Result<u64, GdalError> {
+    let band = source.rasterband(1)?;
+    let srs = source.spatial_ref().ok();
+
+    let mut output = create_output(dst)?;
+    let layer = output.create_layer(LayerOptions {
+        name: "contours",
+        srs: srs.as_ref(),
+        ty: if polygons {
+            OGRwkbGeometryType::wkbMultiPolygon
+        } else {
+            OGRwkbGeometryType::wkbLineString
+        },
+        ..Default::default()
+    })?;
+
+    // GDALContourGenerateEx addresses fields by index, in creation order
+    let mut command_args = vec![
+        "-i".to_string(),
+        interval.to_string(),
+        "-off".to_string(),
+        base.to_string(),
+        "-nln".to_string(),
+        "contours".to_string(),
+    ];
+    if polygons {
+        command_args.extend([
+            "-p".to_string(),
+            "-amin".to_string(),
+            format!("{}_min", attribute),
+            "-amax".to_string(),
+            format!("{}_max", attribute),
+        ]);
+    } else {
+        command_args.extend(["-a".to_string(), attribute.to_string()]);
+    }
+    command_args.extend([dataset_name(source), dst.to_string()]);
+    record_command_line("gdal_contour", &command_args);
+
+    let mut options = CslStringList::new();
+    FieldDefn::new("ID", OGRFieldType::OFTInteger)?.add_to_layer(&layer)?;
+    options.set_name_value("ID_FIELD", "0")?;
+    if polygons {
+        FieldDefn::new(&format!("{}_min", attribute), OGRFieldType::OFTReal)?
+            .add_to_layer(&layer)?;
+        FieldDefn::new(&format!("{}_max", attribute), OGRFieldType::OFTReal)?
+            .add_to_layer(&layer)?;
+        options.set_name_value("ELEV_FIELD_MIN", "1")?;
+        options.set_name_value("ELEV_FIELD_MAX", "2")?;
+        options.set_name_value("POLYGONIZE", "YES")?;
+    } else {
+        FieldDefn::new(attribute, OGRFieldType::OFTReal)?.add_to_layer(&layer)?;
+        options.set_name_value("ELEV_FIELD", "1")?;
+    }
+
+    options.set_name_value("LEVEL_INTERVAL", &interval.to_string())?;
+    options.set_name_value("LEVEL_BASE", &base.to_string())?;
+    if let Some(nodata) = band.no_data_value() {
+        options.set_name_value("NODATA", &nodata.to_string())?;
+    }
+
+    let rv = unsafe {
+        gdal_sys::GDALContourGenerateEx(
+            band.c_rasterband(),
+            layer.c_layer() as *mut _,
+            options.as_ptr() as _,
+            Some(gdal_progress),
+            progress.as_arg(),
+        )
+    };
+    if rv != gdal_sys::CPLErr::CE_None {
+        return Err(ffi::last_error("GDALContourGenerateEx"));
+    }
+
+    let count = layer.feature_count();
+    output.close()?;
+    Ok(count)
+}
+
+#[tauri::command]
+pub async fn generate_contours(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    interval: f64,
+    base: Option<f64>,
+    attribute_name: Option<String>,
+    polygons: Option<bool>,
+) -> Result<ContourResult, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "interval": interval,
+        "base": base,
+        "attribute_name": attribute_name,
+        "polygons": polygons,
+    });
+    run_job(app.clone(), "generate_contours", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        if interval <= 0.0 {
+            return Err("Contour interval must be positive".to_string());
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        let attribute = attribute_name.unwrap_or_else(|| "elev".to_string());
+        let progress = Progress::new(&app, "generate_contours");
+
+        let feature_count = contours(
+            &source,
+            &dst,
+            interval,
+            base.unwrap_or(0.0),
+            &attribute,
+            polygons.unwrap_or(false),
+            &progress,
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(ContourResult {
+            path: dst,
+            layer: "contours".to_string(),
+            feature_count,
+        })
+    })
+    .await
+}
@@ -0,0 +1,222 @@
+use gdal::{Dataset, GeoTransform, GeoTransformEx};
+use serde::{Deserialize, Serialize};
+use std::ffi::c_void;
+use tauri::State;
+
+use crate::crs::crs_key;
+use crate::datasets::DatasetRegistry;
+use crate::ffi::last_error;
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+// Position in the raster grid, in fractional pixels from the top left corner of the
+// top left pixel; the centre of that pixel is (0.5, 0.5)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PixelPoint {
+    pub column: f64,
+    pub row: f64,
+}
+
+// Position in the dataset's CRS, or the CRS of its GCPs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WorldPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Georeferencing {
+    // Affine geotransform, possibly rotated
+    GeoTransform,
+    // Polynomial fitted through ground control points
+    Gcps,
+}
+
+// Converted points, in input order. A point is null where the GCP polynomial could not
+// be applied to it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConvertedPoints<T> {
+    pub points: Vec<Option<T>>,
+    pub georeferencing: Georeferencing,
+    // CRS of the world coordinates, e.g. "EPSG:32633"
+    pub crs: Option<String>,
+}
+
+// GDAL's GCP transformer, destroyed on drop
+struct GcpTransformer(*mut c_void);
+
+impl GcpTransformer {
+    // Picks the polynomial order from the number of GCPs; fails with fewer than three
+    fn new(dataset: &Dataset) -> Result<Self, GdalError> {
+        let gcps = dataset.gcps();
+        let transformer = unsafe {
+            gdal_sys::GDALCreateGCPTransformer(
+                gcps.len() as i32,
+                gcps.as_ptr() as *const gdal_sys::GDAL_GCP,
+                0,
+                0,
+            )
+        };
+        if transformer.is_null() {
+            return Err(last_error("GDALCreateGCPTransformer"));
+        }
+        Ok(Self(transformer))
+    }
+
+    fn transform(&self, points: &mut [(f64, f64)], inverse: bool) -> Vec<bool> {
+        let mut x: Vec<f64> = points.iter().map(|point| point.0).collect();
+        let mut y: Vec<f64> = points.iter().map(|point| point.1).collect();
+        let mut z = vec![0.0; points.len()];
+        let mut success = vec![0; points.len()];
+        unsafe {
+            gdal_sys::GDALGCPTransform(
+                self.0,
+                inverse as i32,
+                points.len() as i32,
+                x.as_mut_ptr(),
+                y.as_mut_ptr(),
+                z.as_mut_ptr(),
+                success.as_mut_ptr(),
+            );
+        }
+        for (point, (x, y)) in points.iter_mut().zip(x.into_iter().zip(y)) {
+            *point = (x, y);
+        }
+        success.into_iter().map(|ok| ok != 0).collect()
+    }
+}
+
+impl Drop for GcpTransformer {
+    fn drop(&mut self) {
+        unsafe { gdal_sys::GDALDestroyGCPTransformer(self.0) };
+    }
+}
+
+enum PixelTransform {
+    Affine(GeoTransform),
+    Gcps(GcpTransformer),
+}
+
+impl PixelTransform {
+    // The geotransform when the dataset has one, otherwise its GCPs
+    fn of(dataset: &Dataset) -> Result<Self, GdalError> {
+        if let Ok(gt) = dataset.geo_transform() {
+            return Ok(PixelTransform::Affine(gt));
+        }
+        if !dataset.gcps().is_empty() {
+            return Ok(PixelTransform::Gcps(GcpTransformer::new(dataset)?));
+        }
+        Err(GdalError::InvalidArgument(
+            "The dataset has neither a geotransform nor ground control points".to_string(),
+        ))
+    }
+
+    fn georeferencing(&self) -> Georeferencing {
+        match self {
+            PixelTransform::Affine(_) => Georeferencing::GeoTransform,
+            PixelTransform::Gcps(_) => Georeferencing::Gcps,
+        }
+    }
+
+    // Pixel to world, or world to pixel with `inverse`. Returns None for points that
+    // could not be converted.
+    fn apply(
+        &self,
+        mut points: Vec<(f64, f64)>,
+        inverse: bool,
+    ) -> Result<Vec<Option<(f64, f64)>>, GdalError> {
+        match self {
+            PixelTransform::Affine(gt) => {
+                let gt = if inverse { gt.invert()? } else { *gt };
+                Ok(points
+                    .into_iter()
+                    .map(|(x, y)| Some(gt.apply(x, y)))
+                    .collect())
+            }
+            PixelTransform::Gcps(transformer) => {
+                let success = transformer.transform(&mut points, inverse);
+                Ok(points
+                    .into_iter()
+                    .zip(success)
+                    .map(|(point, ok)| ok.then_some(point))
+                    .collect())
+            }
+        }
+    }
+}
+
+fn world_crs(dataset: &Dataset, transform: &PixelTransform) -> Option<String> {
+    let srs = match transform {
+        PixelTransform::Affine(_) => dataset.spatial_ref().ok(),
+        PixelTransform::Gcps(_) => dataset.gcp_spatial_ref(),
+    };
+    srs.as_ref().and_then(crs_key)
+}
+
+fn convert<T>(
+    dataset: &Dataset,
+    points: Vec<(f64, f64)>,
+    inverse: bool,
+    into: impl Fn((f64, f64)) -> T,
+) -> Result<ConvertedPoints<T>, GdalError> {
+    let transform = PixelTransform::of(dataset)?;
+    let converted = transform.apply(points, inverse)?;
+    Ok(ConvertedPoints {
+        points: converted
+            .into_iter()
+            .map(|point| point.map(&into))
+            .collect(),
+        georeferencing: transform.georeferencing(),
+        crs: world_crs(dataset, &transform),
+    })
+}
+
+// Georeferenced coordinates of pixel positions, through the geotransform (rotation
+// terms included) or, for datasets georeferenced only by GCPs, their polynomial
+#[tauri::command]
+pub async fn pixel_to_world(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    points: Vec<PixelPoint>,
+) -> Result<ConvertedPoints<WorldPoint>, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let points = points
+            .iter()
+            .map(|point| (point.column, point.row))
+            .collect();
+        convert(&open.dataset, points, false, |(x, y)| WorldPoint { x, y })
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
+
+// Pixel positions of georeferenced coordinates, the inverse of `pixel_to_world`.
+// Positions outside the raster are returned as they are, e.g. negative columns.
+#[tauri::command]
+pub async fn world_to_pixel(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    points: Vec<WorldPoint>,
+) -> Result<ConvertedPoints<PixelPoint>, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let points = points.iter().map(|point| (point.x, point.y)).collect();
+        convert(&open.dataset, points, true, |(column, row)| PixelPoint {
+            column,
+            row,
+        })
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
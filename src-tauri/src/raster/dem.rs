@@ -0,0 +1,216 @@
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use std::ptr;
+use tauri::AppHandle;
+
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, GdalError};
+
+// Metres per degree, the usual gdaldem -s value for DEMs in geographic coordinates
+const METRES_PER_DEGREE: f64 = 111_120.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DemMode {
+    Hillshade,
+    Slope,
+    Aspect,
+    Tri,
+    Tpi,
+    Roughness,
+}
+
+impl DemMode {
+    fn as_processing(&self) -> &'static str {
+        match self {
+            DemMode::Hillshade => "hillshade",
+            DemMode::Slope => "slope",
+            DemMode::Aspect => "aspect",
+            DemMode::Tri => "TRI",
+            DemMode::Tpi => "TPI",
+            DemMode::Roughness => "roughness",
+        }
+    }
+}
+
+// Parameters for all modes; those that do not apply to the chosen mode are ignored
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DemParams {
+    pub band: Option<usize>,
+    // Ratio of vertical to horizontal units, defaults to 111120 for geographic DEMs
+    pub scale: Option<f64>,
+    // Computes values at the raster edges instead of leaving them nodata
+    pub compute_edges: bool,
+    // Hillshade
+    pub azimuth: Option<f64>,
+    pub altitude: Option<f64>,
+    pub z_factor: Option<f64>,
+    pub multidirectional: bool,
+    pub combined: bool,
+    // Slope in percent rather than degrees
+    pub percent: bool,
+    // Aspect as a trigonometric angle (0 = east, counter-clockwise) instead of azimuth
+    pub trigonometric: bool,
+    // Aspect of flat areas as 0 instead of nodata
+    pub zero_for_flat: bool,
+    // "Horn" or "ZevenbergenThorne" for hillshade, slope and aspect, "Wilson" or "Riley" for TRI
+    pub algorithm: Option<String>,
+}
+
+impl DemParams {
+    fn to_args(&self, mode: DemMode, geographic: bool) -> Result<Vec<String>, String> {
+        let mut args = Vec::new();
+        let mut push = |flag: &str, value: Option<String>| {
+            args.push(flag.to_string());
+            args.extend(value);
+        };
+
+        if let Some(band) = self.band {
+            push("-b", Some(band.to_string()));
+        }
+        if self.compute_edges {
+            push("-compute_edges", None);
+        }
+        if let Some(algorithm) = &self.algorithm {
+            push("-alg", Some(algorithm.clone()));
+        }
+
+        let uses_scale = matches!(mode, DemMode::Hillshade | DemMode::Slope);
+        match self.scale {
+            Some(scale) if scale <= 0.0 => return Err("Scale must be positive".to_string()),
+            Some(scale) => push("-s", Some(scale.to_string())),
+            None if uses_scale && geographic => push("-s", Some(METRES_PER_DEGREE.to_string())),
+            None => {}
+        }
+
+        match mode {
+            DemMode::Hillshade => {
+                if self.multidirectional && self.combined {
+                    return Err(
+                        "Multidirectional and combined hillshades are exclusive".to_string()
+                    );
+                }
+                if let Some(azimuth) = self.azimuth {
+                    push("-az", Some(azimuth.to_string()));
+                }
+                if let Some(altitude) = self.altitude {
+                    push("-alt", Some(altitude.to_string()));
+                }
+                if let Some(z_factor) = self.z_factor {
+                    push("-z", Some(z_factor.to_string()));
+                }
+                if self.multidirectional {
+                    push("-multidirectional", None);
+                }
+                if self.combined {
+                    push("-combined", None);
+                }
+            }
+            DemMode::Slope => {
+                if self.percent {
+                    push("-p", None);
+                }
+            }
+            DemMode::Aspect => {
+                if self.trigonometric {
+                    push("-trigonometric", None);
+                }
+                if self.zero_for_flat {
+                    push("-zero_for_flat", None);
+                }
+            }
+            DemMode::Tri | DemMode::Tpi | DemMode::Roughness => {}
+        }
+        Ok(args)
+    }
+}
+
+// Runs GDALDEMProcessing (the library form of gdaldem); `color_file` is only used by
+// color-relief
+pub(crate) fn dem_processing(
+    source: &Dataset,
+    dst: &str,
+    processing: &str,
+    color_file: Option<&str>,
+    args: &[String],
+    progress: &Progress,
+) -> Result<Dataset, GdalError> {
+    let mut command_args = vec![
+        processing.to_string(),
+        dataset_name(source),
+        dst.to_string(),
+    ];
+    command_args.extend(color_file.map(str::to_string));
+    command_args.extend_from_slice(args);
+    record_command_line("gdaldem", &command_args);
+    let argv = ffi::arg_list(args)?;
+    let c_dst = ffi::c_string(dst)?;
+    let c_processing = ffi::c_string(processing)?;
+    let c_color_file = color_file.map(ffi::c_string).transpose()?;
+
+    unsafe {
+        let options = gdal_sys::GDALDEMProcessingOptionsNew(argv.as_ptr(), ptr::null_mut());
+        if options.is_null() {
+            return Err(ffi::last_error("GDALDEMProcessingOptionsNew"));
+        }
+        gdal_sys::GDALDEMProcessingOptionsSetProgress(
+            options,
+            Some(gdal_progress),
+            progress.as_arg(),
+        );
+
+        let mut usage_error = 0;
+        let result = gdal_sys::GDALDEMProcessing(
+            c_dst.as_ptr(),
+            source.c_dataset(),
+            c_processing.as_ptr(),
+            c_color_file
+                .as_ref()
+                .map_or(ptr::null(), |file| file.as_ptr()),
+            options,
+            &mut usage_error,
+        );
+        gdal_sys::GDALDEMProcessingOptionsFree(options);
+
+        if result.is_null() {
+            return Err(ffi::last_error("GDALDEMProcessing"));
+        }
+        Ok(Dataset::from_c_dataset(result))
+    }
+}
+
+#[tauri::command]
+pub async fn dem_process(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    mode: DemMode,
+    params: Option<DemParams>,
+) -> Result<DatasetInfo, String> {
+    let job_params = json!({ "src": src, "dst": dst, "mode": mode, "params": params });
+    run_job(app.clone(), "dem_process", job_params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        let geographic = source
+            .spatial_ref()
+            .map(|srs| srs.is_geographic())
+            .unwrap_or(false);
+        let args = params.unwrap_or_default().to_args(mode, geographic)?;
+
+        let progress = Progress::new(&app, "dem_process");
+        let output = dem_processing(&source, &dst, mode.as_processing(), None, &args, &progress)
+            .map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
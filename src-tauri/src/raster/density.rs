@@ -0,0 +1,224 @@
+use gdal::raster::Buffer;
+use gdal::vector::LayerAccess;
+use gdal::DriverManager;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::f64::consts::PI;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::grid::open_points;
+use super::translate::translate;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::vector::{layer_by_name, point_parts};
+use crate::{dataset_info, setup_gdal_runtime, DatasetInfo, Extent};
+
+// Cells held in memory while points are accumulated, 200 MB of f64
+const MAX_CELLS: usize = 25_000_000;
+
+// Kernel shapes, all falling to zero at the radius
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kernel {
+    // Biweight, the usual choice for heatmaps
+    #[default]
+    Quartic,
+    Triangular,
+    Uniform,
+    Triweight,
+    Epanechnikov,
+}
+
+impl Kernel {
+    // Weight at `u` = distance / radius, scaled so the kernel integrates to 1 over the
+    // disk of radius 1
+    fn weight(&self, u: f64) -> f64 {
+        if u > 1.0 {
+            return 0.0;
+        }
+        let v = 1.0 - u * u;
+        match self {
+            Kernel::Quartic => 3.0 / PI * v * v,
+            Kernel::Triangular => 3.0 / PI * (1.0 - u),
+            Kernel::Uniform => 1.0 / PI,
+            Kernel::Triweight => 4.0 / PI * v * v * v,
+            Kernel::Epanechnikov => 2.0 / PI * v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DensityValues {
+    // Weight per square CRS unit, so the raster sums to the total weight times the cell
+    // area
+    #[default]
+    Density,
+    // Weighted kernel values summed without dividing by the radius squared, so the values
+    // do not change with the radius's units
+    Raw,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KernelDensityOptions {
+    // Point layer, the first one when unset
+    pub layer: Option<String>,
+    pub kernel: Kernel,
+    pub values: DensityValues,
+    // Output bounds, the extent of the points grown by the radius when unset
+    pub extent: Option<Extent>,
+}
+
+#[tauri::command]
+pub async fn kernel_density(
+    app: AppHandle,
+    src_vector: String,
+    radius: f64,
+    cell_size: f64,
+    weight_field: Option<String>,
+    dst: String,
+    options: Option<KernelDensityOptions>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src_vector": src_vector,
+        "radius": radius,
+        "cell_size": cell_size,
+        "weight_field": weight_field,
+        "dst": dst,
+        "options": options,
+    });
+    run_job(app.clone(), "kernel_density", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src_vector).exists() {
+            return Err(format!("File not found: {}", src_vector));
+        }
+        if !(radius > 0.0 && radius.is_finite()) {
+            return Err("Radius must be positive".to_string());
+        }
+        if !(cell_size > 0.0 && cell_size.is_finite()) {
+            return Err("Cell size must be positive".to_string());
+        }
+        let options = options.unwrap_or_default();
+        let source = open_points(Path::new(&src_vector)).map_err(|e| e.to_string())?;
+        let mut layer =
+            layer_by_name(&source, options.layer.as_deref()).map_err(|e| e.to_string())?;
+        let weight_index = weight_field
+            .as_deref()
+            .map(|name| {
+                layer
+                    .defn()
+                    .field_index(name)
+                    .map_err(|_| format!("Layer {} has no field named {}", layer.name(), name))
+            })
+            .transpose()?;
+
+        // Points with their weights; features without a weight add nothing
+        let progress = Progress::new(&app, "kernel_density");
+        let mut weighted = Vec::new();
+        for feature in layer.features() {
+            let weight = match weight_index {
+                Some(index) => match feature.field_as_double(index).map_err(|e| e.to_string())? {
+                    Some(weight) => weight,
+                    None => continue,
+                },
+                None => 1.0,
+            };
+            let mut points = Vec::new();
+            if let Some(geometry) = feature.geometry() {
+                if !point_parts(geometry, &mut points) {
+                    return Err("Kernel density needs a point layer".to_string());
+                }
+            }
+            weighted.extend(points.into_iter().map(|point| (point, weight)));
+        }
+        if weighted.is_empty() && options.extent.is_none() {
+            return Err("The layer has no points".to_string());
+        }
+
+        let extent = options.extent.unwrap_or_else(|| {
+            let mut extent = Extent {
+                min_x: f64::INFINITY,
+                min_y: f64::INFINITY,
+                max_x: f64::NEG_INFINITY,
+                max_y: f64::NEG_INFINITY,
+            };
+            for ((x, y), _) in &weighted {
+                extent.min_x = extent.min_x.min(x - radius);
+                extent.min_y = extent.min_y.min(y - radius);
+                extent.max_x = extent.max_x.max(x + radius);
+                extent.max_y = extent.max_y.max(y + radius);
+            }
+            extent
+        });
+        let width = ((extent.max_x - extent.min_x) / cell_size).ceil().max(1.0) as usize;
+        let height = ((extent.max_y - extent.min_y) / cell_size).ceil().max(1.0) as usize;
+        if width.saturating_mul(height) > MAX_CELLS {
+            return Err(format!(
+                "A {} by {} raster is too large, use a bigger cell size",
+                width, height
+            ));
+        }
+
+        // Each point adds its kernel to the cells whose centres are within the radius
+        progress.set_range(0.0, 0.8);
+        let scale = match options.values {
+            DensityValues::Density => 1.0 / (radius * radius),
+            DensityValues::Raw => 1.0,
+        };
+        let mut density = vec![0.0f64; width * height];
+        let total = weighted.len().max(1) as f64;
+        for (done, ((x, y), weight)) in weighted.iter().enumerate() {
+            if done % 1000 == 0 {
+                progress.report(done as f64 / total, None);
+            }
+            let column = |x: f64| (x - extent.min_x) / cell_size - 0.5;
+            let row = |y: f64| (extent.max_y - y) / cell_size - 0.5;
+            let first_column = column(x - radius).ceil().max(0.0) as usize;
+            let last_column = column(x + radius).floor().min(width as f64 - 1.0);
+            let first_row = row(y + radius).ceil().max(0.0) as usize;
+            let last_row = row(y - radius).floor().min(height as f64 - 1.0);
+            if last_column < 0.0 || last_row < 0.0 {
+                continue;
+            }
+            for r in first_row..=last_row as usize {
+                let cell_y = extent.max_y - (r as f64 + 0.5) * cell_size;
+                for c in first_column..=last_column as usize {
+                    let cell_x = extent.min_x + (c as f64 + 0.5) * cell_size;
+                    let u = (cell_x - x).hypot(cell_y - y) / radius;
+                    density[r * width + c] += weight * scale * options.kernel.weight(u);
+                }
+            }
+        }
+
+        let driver = DriverManager::get_driver_by_name("MEM").map_err(|e| e.to_string())?;
+        let mut output = driver
+            .create_with_band_type::<f64, _>("", width, height, 1)
+            .map_err(|e| e.to_string())?;
+        output
+            .set_geo_transform(&[extent.min_x, cell_size, 0.0, extent.max_y, 0.0, -cell_size])
+            .map_err(|e| e.to_string())?;
+        if let Some(srs) = layer.spatial_ref() {
+            output.set_spatial_ref(&srs).map_err(|e| e.to_string())?;
+        }
+        output
+            .rasterband(1)
+            .and_then(|mut band| {
+                band.write(
+                    (0, 0),
+                    (width, height),
+                    &mut Buffer::new((width, height), density),
+                )
+            })
+            .map_err(|e| e.to_string())?;
+
+        progress.set_range(0.8, 1.0);
+        let args = vec!["-ot".to_string(), "Float32".to_string()];
+        let output = translate(&output, &dst, &args, &progress).map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
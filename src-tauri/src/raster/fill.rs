@@ -0,0 +1,121 @@
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use serde_json::json;
+use std::path::Path;
+use std::ptr;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, GdalError};
+
+// gdal_fillnodata's default search radius, in pixels
+const DEFAULT_SEARCH_DISTANCE: f64 = 100.0;
+
+// Interpolates every band of `dataset` into its nodata areas, in place
+fn fill_bands(
+    dataset: &Dataset,
+    max_search_distance: f64,
+    smoothing_iterations: u32,
+    progress: &Progress,
+) -> Result<(), GdalError> {
+    let band_count = dataset.raster_count();
+    for index in 1..=band_count {
+        progress.set_range(
+            0.2 + 0.8 * (index - 1) as f64 / band_count as f64,
+            0.2 + 0.8 * index as f64 / band_count as f64,
+        );
+
+        record_command_line(
+            "gdal_fillnodata.py",
+            &[
+                "-md".to_string(),
+                max_search_distance.to_string(),
+                "-si".to_string(),
+                smoothing_iterations.to_string(),
+                "-b".to_string(),
+                index.to_string(),
+                dataset_name(dataset),
+            ],
+        );
+        let band = dataset.rasterband(index)?;
+        // A null mask band makes GDAL use the band's own nodata mask
+        let rv = unsafe {
+            gdal_sys::GDALFillNodata(
+                band.c_rasterband(),
+                ptr::null_mut(),
+                max_search_distance,
+                0,
+                smoothing_iterations as i32,
+                ptr::null_mut(),
+                Some(gdal_progress),
+                progress.as_arg(),
+            )
+        };
+        if rv != gdal_sys::CPLErr::CE_None {
+            return Err(ffi::last_error("GDALFillNodata"));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn fill_nodata(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    max_search_distance: Option<f64>,
+    smoothing_iterations: Option<u32>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "max_search_distance": max_search_distance,
+        "smoothing_iterations": smoothing_iterations,
+    });
+    run_job(app.clone(), "fill_nodata", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let max_search_distance = max_search_distance.unwrap_or(DEFAULT_SEARCH_DISTANCE);
+        if max_search_distance <= 0.0 {
+            return Err("Search distance must be positive".to_string());
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if source.raster_count() == 0 {
+            return Err("Dataset has no raster bands".to_string());
+        }
+
+        // Like gdal_fillnodata, copy first and fill the copy so the source stays untouched
+        let progress = Progress::new(&app, "fill_nodata");
+        progress.set_range(0.0, 0.2);
+        let copy = translate(&source, &dst, &[], &progress).map_err(|e| e.to_string())?;
+        copy.close().map_err(|e| e.to_string())?;
+
+        let output = Dataset::open_ex(
+            &dst,
+            DatasetOptions {
+                open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| format!("Output format cannot be updated in place: {}", e))?;
+
+        fill_bands(
+            &output,
+            max_search_distance,
+            smoothing_iterations.unwrap_or(0),
+            &progress,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let info = dataset_info(&output);
+        output.close().map_err(|e| e.to_string())?;
+        Ok(info)
+    })
+    .await
+}
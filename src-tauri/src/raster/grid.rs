@@ -0,0 +1,250 @@
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use std::ptr;
+use tauri::AppHandle;
+
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, Extent, GdalError};
+
+// Column names the CSV driver checks for point coordinates
+const CSV_OPEN_OPTIONS: &[&str] = &[
+    "X_POSSIBLE_NAMES=x,lon,long,longitude,easting",
+    "Y_POSSIBLE_NAMES=y,lat,latitude,northing",
+    "Z_POSSIBLE_NAMES=z,elevation,value",
+    "KEEP_GEOM_COLUMNS=NO",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridAlgorithm {
+    InverseDistance,
+    Nearest,
+    MovingAverage,
+}
+
+// Parameters for all algorithms; those that do not apply to the chosen one are ignored
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridParams {
+    // Point layer, the first one when unset
+    pub layer: Option<String>,
+    // Search radius in CRS units; 0 uses every point
+    pub radius: f64,
+    // Inverse distance weighting power
+    pub power: f64,
+    pub smoothing: f64,
+    // Nearest points used per cell by inverse distance, GDAL's default when unset
+    pub max_points: Option<u32>,
+    // Cells with fewer points in the search radius are set to `nodata`
+    pub min_points: u32,
+    pub nodata: f64,
+    // Cell size in CRS units, takes precedence over `width`/`height`
+    pub resolution: Option<f64>,
+    pub width: usize,
+    pub height: usize,
+    // Output bounds, the extent of the points when unset
+    pub extent: Option<Extent>,
+    pub output_type: String,
+}
+
+impl Default for GridParams {
+    fn default() -> Self {
+        Self {
+            layer: None,
+            radius: 0.0,
+            power: 2.0,
+            smoothing: 0.0,
+            max_points: None,
+            min_points: 0,
+            nodata: -9999.0,
+            resolution: None,
+            width: 256,
+            height: 256,
+            extent: None,
+            output_type: "Float32".to_string(),
+        }
+    }
+}
+
+impl GridParams {
+    fn algorithm_arg(&self, algorithm: GridAlgorithm) -> String {
+        let max_points = self
+            .max_points
+            .map(|points| format!(":max_points={}", points))
+            .unwrap_or_default();
+        match algorithm {
+            // invdistnn searches a radius around each cell, which is much faster on
+            // large point sets than weighting every point
+            GridAlgorithm::InverseDistance if self.radius > 0.0 => format!(
+                "invdistnn:power={}:smoothing={}:radius={}:min_points={}:nodata={}{}",
+                self.power, self.smoothing, self.radius, self.min_points, self.nodata, max_points
+            ),
+            GridAlgorithm::InverseDistance => format!(
+                "invdist:power={}:smoothing={}:min_points={}:nodata={}{}",
+                self.power, self.smoothing, self.min_points, self.nodata, max_points
+            ),
+            GridAlgorithm::Nearest => format!(
+                "nearest:radius1={}:radius2={}:nodata={}",
+                self.radius, self.radius, self.nodata
+            ),
+            GridAlgorithm::MovingAverage => format!(
+                "average:radius1={}:radius2={}:min_points={}:nodata={}",
+                self.radius, self.radius, self.min_points, self.nodata
+            ),
+        }
+    }
+
+    fn to_args(
+        &self,
+        field: Option<&str>,
+        algorithm: GridAlgorithm,
+    ) -> Result<Vec<String>, String> {
+        if self.radius < 0.0 {
+            return Err("Search radius must not be negative".to_string());
+        }
+        if self.power <= 0.0 {
+            return Err("Power must be positive".to_string());
+        }
+
+        let mut args = vec![
+            "-a".to_string(),
+            self.algorithm_arg(algorithm),
+            "-ot".to_string(),
+            self.output_type.clone(),
+            "-a_nodata".to_string(),
+            self.nodata.to_string(),
+        ];
+        if let Some(field) = field {
+            args.extend(["-zfield".to_string(), field.to_string()]);
+        }
+        if let Some(layer) = &self.layer {
+            args.extend(["-l".to_string(), layer.clone()]);
+        }
+
+        match self.resolution {
+            Some(resolution) if resolution <= 0.0 => {
+                return Err("Resolution must be positive".to_string())
+            }
+            Some(resolution) => args.extend([
+                "-tr".to_string(),
+                resolution.to_string(),
+                resolution.to_string(),
+            ]),
+            None if self.width == 0 || self.height == 0 => {
+                return Err("Output width and height must be positive".to_string())
+            }
+            None => args.extend([
+                "-outsize".to_string(),
+                self.width.to_string(),
+                self.height.to_string(),
+            ]),
+        }
+
+        if let Some(extent) = &self.extent {
+            args.extend([
+                "-txe".to_string(),
+                extent.min_x.to_string(),
+                extent.max_x.to_string(),
+                "-tye".to_string(),
+                extent.max_y.to_string(),
+                extent.min_y.to_string(),
+            ]);
+        }
+        Ok(args)
+    }
+}
+
+// Runs GDALGrid (the library form of gdal_grid) over the points in `source`
+fn grid(
+    source: &Dataset,
+    dst: &str,
+    args: &[String],
+    progress: &Progress,
+) -> Result<Dataset, GdalError> {
+    record_command_line(
+        "gdal_grid",
+        &[args, &[dataset_name(source), dst.to_string()]].concat(),
+    );
+    let argv = ffi::arg_list(args)?;
+    let c_dst = ffi::c_string(dst)?;
+
+    unsafe {
+        let options = gdal_sys::GDALGridOptionsNew(argv.as_ptr(), ptr::null_mut());
+        if options.is_null() {
+            return Err(ffi::last_error("GDALGridOptionsNew"));
+        }
+        gdal_sys::GDALGridOptionsSetProgress(options, Some(gdal_progress), progress.as_arg());
+
+        let mut usage_error = 0;
+        let result = gdal_sys::GDALGrid(
+            c_dst.as_ptr(),
+            source.c_dataset(),
+            options,
+            &mut usage_error,
+        );
+        gdal_sys::GDALGridOptionsFree(options);
+
+        if result.is_null() {
+            return Err(ffi::last_error("GDALGrid"));
+        }
+        Ok(Dataset::from_c_dataset(result))
+    }
+}
+
+// Opens the point source; CSV files get their coordinate columns detected by name
+pub(super) fn open_points(path: &Path) -> Result<Dataset, GdalError> {
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+
+    Ok(Dataset::open_ex(
+        path,
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_VECTOR,
+            open_options: is_csv.then_some(CSV_OPEN_OPTIONS),
+            ..Default::default()
+        },
+    )?)
+}
+
+#[tauri::command]
+pub async fn grid_points(
+    app: AppHandle,
+    src_vector: String,
+    field: Option<String>,
+    algorithm: GridAlgorithm,
+    params: Option<GridParams>,
+    dst: String,
+) -> Result<DatasetInfo, String> {
+    let job_params = json!({
+        "src_vector": src_vector,
+        "field": field,
+        "algorithm": algorithm,
+        "params": params,
+        "dst": dst,
+    });
+    run_job(app.clone(), "grid_points", job_params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src_vector).exists() {
+            return Err(format!("File not found: {}", src_vector));
+        }
+
+        let args = params
+            .unwrap_or_default()
+            .to_args(field.as_deref(), algorithm)?;
+        let source = open_points(Path::new(&src_vector)).map_err(|e| e.to_string())?;
+        if source.layer_count() == 0 {
+            return Err("Dataset has no point layers".to_string());
+        }
+
+        let progress = Progress::new(&app, "grid_points");
+        let output = grid(&source, &dst, &args, &progress).map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
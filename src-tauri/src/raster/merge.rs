@@ -0,0 +1,367 @@
+use gdal::raster::Buffer;
+use gdal::spatial_ref::SpatialRef;
+use gdal::{Dataset, DriverManager};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::f64::consts::SQRT_2;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use super::vrt::expand_inputs;
+use super::warp::warp;
+use super::Resampling;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::qa::raster_bounds;
+use crate::{dataset_info, setup_gdal_runtime, DatasetInfo, Extent, GdalError};
+
+// How pixels covered by more than one input are resolved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapMode {
+    // Later inputs overwrite earlier ones, like gdalwarp with several sources
+    #[default]
+    LastOnTop,
+    Average,
+    // Weighted average that fades each input out towards its edges
+    Feather,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MergeOptions {
+    pub mode: OverlapMode,
+    // Width of the blend zone in output pixels, used by `feather`
+    pub feather_distance: f64,
+    // Output CRS, defaults to the CRS of the first input
+    pub target_epsg: Option<u32>,
+    // Output pixel size, defaults to the finest input resolution
+    pub resolution: Option<f64>,
+    pub resampling: Resampling,
+    pub nodata: Option<f64>,
+    pub format: String,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            mode: OverlapMode::LastOnTop,
+            feather_distance: 32.0,
+            target_epsg: None,
+            resolution: None,
+            resampling: Resampling::Nearest,
+            nodata: None,
+            format: "GTiff".to_string(),
+        }
+    }
+}
+
+// Common output grid with a top-left origin
+struct Grid {
+    srs: SpatialRef,
+    extent: Extent,
+    resolution: f64,
+    size: (usize, usize),
+}
+
+fn output_grid(
+    inputs: &[String],
+    sources: &[Dataset],
+    options: &MergeOptions,
+) -> Result<Grid, GdalError> {
+    let srs = match options.target_epsg {
+        Some(epsg) => SpatialRef::from_epsg(epsg)?,
+        None => sources[0].spatial_ref()?,
+    };
+
+    let mut union: Option<Extent> = None;
+    let mut finest = f64::INFINITY;
+    for (input, source) in inputs.iter().zip(sources) {
+        let bounds = raster_bounds(source)
+            .ok_or_else(|| GdalError::InvalidArgument(format!("{} is not georeferenced", input)))?;
+        let bounds = bounds.transform(&source.spatial_ref()?, &srs)?;
+
+        let (size_x, _) = source.raster_size();
+        finest = finest.min((bounds.max_x - bounds.min_x) / size_x as f64);
+        union = Some(match union {
+            Some(extent) => Extent {
+                min_x: extent.min_x.min(bounds.min_x),
+                min_y: extent.min_y.min(bounds.min_y),
+                max_x: extent.max_x.max(bounds.max_x),
+                max_y: extent.max_y.max(bounds.max_y),
+            },
+            None => bounds,
+        });
+    }
+
+    let resolution = options.resolution.unwrap_or(finest);
+    let mut extent = union.ok_or_else(|| GdalError::InvalidArgument("No inputs".to_string()))?;
+    let size_x = ((extent.max_x - extent.min_x) / resolution).ceil().max(1.0) as usize;
+    let size_y = ((extent.max_y - extent.min_y) / resolution).ceil().max(1.0) as usize;
+    extent.max_x = extent.min_x + size_x as f64 * resolution;
+    extent.min_y = extent.max_y - size_y as f64 * resolution;
+
+    Ok(Grid {
+        srs,
+        extent,
+        resolution,
+        size: (size_x, size_y),
+    })
+}
+
+// Distance in pixels from each valid pixel to the nearest invalid pixel or window edge,
+// from a two-pass chamfer transform
+fn edge_distance(valid: &[bool], width: usize, height: usize) -> Vec<f64> {
+    let mut dist: Vec<f64> = valid
+        .iter()
+        .map(|&v| if v { f64::INFINITY } else { 0.0 })
+        .collect();
+    let at = |dist: &[f64], x: isize, y: isize| -> f64 {
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+            0.0
+        } else {
+            dist[y as usize * width + x as usize]
+        }
+    };
+
+    let forward = [
+        (-1, 0, 1.0),
+        (0, -1, 1.0),
+        (-1, -1, SQRT_2),
+        (1, -1, SQRT_2),
+    ];
+    let backward = [(1, 0, 1.0), (0, 1, 1.0), (1, 1, SQRT_2), (-1, 1, SQRT_2)];
+
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let i = y as usize * width + x as usize;
+            for (dx, dy, step) in forward {
+                dist[i] = dist[i].min(at(&dist, x + dx, y + dy) + step);
+            }
+        }
+    }
+    for y in (0..height as isize).rev() {
+        for x in (0..width as isize).rev() {
+            let i = y as usize * width + x as usize;
+            for (dx, dy, step) in backward {
+                dist[i] = dist[i].min(at(&dist, x + dx, y + dy) + step);
+            }
+        }
+    }
+    dist
+}
+
+fn grid_args(
+    grid: &Grid,
+    window: &Extent,
+    size: (usize, usize),
+    options: &MergeOptions,
+) -> Result<Vec<String>, GdalError> {
+    let mut args = vec!["-t_srs".to_string(), grid.srs.to_wkt()?, "-te".to_string()];
+    args.extend(window.to_args());
+    args.extend([
+        "-ts".to_string(),
+        size.0.to_string(),
+        size.1.to_string(),
+        "-r".to_string(),
+        options.resampling.as_gdal_arg().to_string(),
+        "-multi".to_string(),
+        "-wo".to_string(),
+        "NUM_THREADS=ALL_CPUS".to_string(),
+    ]);
+    Ok(args)
+}
+
+// Average and feather modes warp each input onto its own window of the output grid
+// and accumulate weighted sums, then write the normalised result
+fn blend(
+    sources: &[Dataset],
+    grid: &Grid,
+    options: &MergeOptions,
+    nodata: f64,
+    progress: &Progress,
+) -> Result<Dataset, GdalError> {
+    let (size_x, size_y) = grid.size;
+    let band_count = sources[0].raster_count();
+    let mut sums = vec![vec![0.0; size_x * size_y]; band_count];
+    let mut weights = vec![0.0; size_x * size_y];
+    let res = grid.resolution;
+
+    for (index, source) in sources.iter().enumerate() {
+        // Warping takes the first 90%, the final write the rest
+        let step = 0.9 / sources.len() as f64;
+        progress.set_range(index as f64 * step, (index + 1) as f64 * step);
+
+        let bounds = raster_bounds(source)
+            .ok_or_else(|| GdalError::InvalidArgument("Input is not georeferenced".to_string()))?
+            .transform(&source.spatial_ref()?, &grid.srs)?;
+        let col0 = ((bounds.min_x - grid.extent.min_x) / res).floor().max(0.0) as usize;
+        let col1 = (((bounds.max_x - grid.extent.min_x) / res).ceil() as usize).min(size_x);
+        let row0 = ((grid.extent.max_y - bounds.max_y) / res).floor().max(0.0) as usize;
+        let row1 = (((grid.extent.max_y - bounds.min_y) / res).ceil() as usize).min(size_y);
+        if col1 <= col0 || row1 <= row0 {
+            continue;
+        }
+
+        let (width, height) = (col1 - col0, row1 - row0);
+        let window = Extent {
+            min_x: grid.extent.min_x + col0 as f64 * res,
+            min_y: grid.extent.max_y - row1 as f64 * res,
+            max_x: grid.extent.min_x + col1 as f64 * res,
+            max_y: grid.extent.max_y - row0 as f64 * res,
+        };
+
+        let mut args = vec![
+            "-of".to_string(),
+            "MEM".to_string(),
+            "-ot".to_string(),
+            "Float64".to_string(),
+            // The alpha band marks which output pixels the input actually covers
+            "-dstalpha".to_string(),
+        ];
+        args.extend(grid_args(grid, &window, (width, height), options)?);
+        let warped = warp(&[source], "", &args, progress)?;
+
+        let alpha = warped.rasterband(band_count + 1)?.read_as::<f64>(
+            (0, 0),
+            (width, height),
+            (width, height),
+            None,
+        )?;
+        let valid: Vec<bool> = alpha.data().iter().map(|&a| a > 0.0).collect();
+        let pixel_weights: Vec<f64> = match options.mode {
+            OverlapMode::Feather => {
+                let feather = options.feather_distance.max(1.0);
+                edge_distance(&valid, width, height)
+                    .into_iter()
+                    .map(|d| d.min(feather) / feather)
+                    .collect()
+            }
+            _ => valid.iter().map(|&v| if v { 1.0 } else { 0.0 }).collect(),
+        };
+
+        for (band_index, sum) in sums.iter_mut().enumerate() {
+            let values = warped.rasterband(band_index + 1)?.read_as::<f64>(
+                (0, 0),
+                (width, height),
+                (width, height),
+                None,
+            )?;
+            for row in 0..height {
+                for col in 0..width {
+                    let weight = pixel_weights[row * width + col];
+                    if weight > 0.0 {
+                        sum[(row0 + row) * size_x + col0 + col] +=
+                            values.data()[row * width + col] * weight;
+                    }
+                }
+            }
+        }
+        for row in 0..height {
+            for col in 0..width {
+                weights[(row0 + row) * size_x + col0 + col] += pixel_weights[row * width + col];
+            }
+        }
+    }
+
+    let driver = DriverManager::get_driver_by_name("MEM")?;
+    let mut output = driver.create_with_band_type::<f64, _>("", size_x, size_y, band_count)?;
+    output.set_geo_transform(&[grid.extent.min_x, res, 0.0, grid.extent.max_y, 0.0, -res])?;
+    output.set_spatial_ref(&grid.srs)?;
+
+    for (band_index, sum) in sums.into_iter().enumerate() {
+        let data = sum
+            .into_iter()
+            .zip(&weights)
+            .map(|(sum, &weight)| if weight > 0.0 { sum / weight } else { nodata })
+            .collect();
+        let mut band = output.rasterband(band_index + 1)?;
+        band.write(
+            (0, 0),
+            (size_x, size_y),
+            &mut Buffer::new((size_x, size_y), data),
+        )?;
+        band.set_no_data_value(Some(nodata))?;
+    }
+    Ok(output)
+}
+
+#[tauri::command]
+pub async fn merge_rasters(
+    app: AppHandle,
+    inputs: Vec<String>,
+    dst: String,
+    options: Option<MergeOptions>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({ "inputs": inputs, "dst": dst, "options": options });
+    run_job(app.clone(), "merge_rasters", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let inputs = expand_inputs(&inputs).map_err(|e| e.to_string())?;
+        if inputs.is_empty() {
+            return Err("No input rasters matched".to_string());
+        }
+        let options = options.unwrap_or_default();
+        if options.resolution.is_some_and(|r| r <= 0.0) {
+            return Err("Resolution must be positive".to_string());
+        }
+
+        let sources = inputs
+            .iter()
+            .map(Dataset::open)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let band_count = sources[0].raster_count();
+        if let Some((input, _)) = inputs
+            .iter()
+            .zip(&sources)
+            .find(|(_, source)| source.raster_count() != band_count)
+        {
+            return Err(format!(
+                "{} has a different band count than {}",
+                input, inputs[0]
+            ));
+        }
+
+        let first_band = sources[0].rasterband(1).map_err(|e| e.to_string())?;
+        let data_type = first_band.band_type().name();
+        let nodata = options.nodata.or(first_band.no_data_value());
+
+        let grid = output_grid(&inputs, &sources, &options).map_err(|e| e.to_string())?;
+        let progress = Progress::new(&app, "merge_rasters");
+
+        let output = if options.mode == OverlapMode::LastOnTop {
+            let mut args = vec![
+                "-overwrite".to_string(),
+                "-of".to_string(),
+                options.format.clone(),
+            ];
+            if let Some(nodata) = nodata {
+                args.push("-dstnodata".to_string());
+                args.push(nodata.to_string());
+            }
+            args.extend(
+                grid_args(&grid, &grid.extent, grid.size, &options).map_err(|e| e.to_string())?,
+            );
+            let refs: Vec<&Dataset> = sources.iter().collect();
+            warp(&refs, &dst, &args, &progress).map_err(|e| e.to_string())?
+        } else {
+            let blended = blend(&sources, &grid, &options, nodata.unwrap_or(0.0), &progress)
+                .map_err(|e| e.to_string())?;
+
+            // Write out in the input data type, GDAL rounds blended values for integer types
+            progress.set_range(0.9, 1.0);
+            let args = vec![
+                "-of".to_string(),
+                options.format.clone(),
+                "-ot".to_string(),
+                data_type,
+            ];
+            translate(&blended, &dst, &args, &progress).map_err(|e| e.to_string())?
+        };
+
+        Ok(dataset_info(&output))
+    })
+    .await
+}
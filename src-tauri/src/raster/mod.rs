@@ -0,0 +1,111 @@
+use gdal::raster::ResampleAlg;
+use serde::{Deserialize, Serialize};
+
+pub mod ascii;
+pub mod calc;
+pub mod clip;
+pub mod cog;
+pub mod compare;
+pub mod contours;
+pub mod coords;
+pub mod dem;
+pub mod density;
+pub mod fill;
+pub mod grid;
+pub mod merge;
+pub mod multidim;
+pub mod overviews;
+pub mod pansharpen;
+pub mod polygonize;
+pub mod proximity;
+pub mod rasterize;
+pub mod rat;
+pub mod read;
+pub mod reclassify;
+pub mod resample;
+pub mod retile;
+pub mod sieve;
+pub mod tiles;
+pub mod timeseries;
+pub(crate) mod translate;
+pub mod vrt;
+pub mod warp;
+pub mod zonal;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resampling {
+    #[default]
+    Nearest,
+    Bilinear,
+    Cubic,
+    Lanczos,
+    Average,
+    Mode,
+}
+
+impl Resampling {
+    // Name understood by the `-r` switch of the GDAL utilities
+    pub fn as_gdal_arg(&self) -> &'static str {
+        match self {
+            Resampling::Nearest => "near",
+            Resampling::Bilinear => "bilinear",
+            Resampling::Cubic => "cubic",
+            Resampling::Lanczos => "lanczos",
+            Resampling::Average => "average",
+            Resampling::Mode => "mode",
+        }
+    }
+
+    // Resampling of GDALRasterIO reads with a buffer smaller than the window
+    pub fn as_resample_alg(&self) -> ResampleAlg {
+        match self {
+            Resampling::Nearest => ResampleAlg::NearestNeighbour,
+            Resampling::Bilinear => ResampleAlg::Bilinear,
+            Resampling::Cubic => ResampleAlg::Cubic,
+            Resampling::Lanczos => ResampleAlg::Lanczos,
+            Resampling::Average => ResampleAlg::Average,
+            Resampling::Mode => ResampleAlg::Mode,
+        }
+    }
+
+    // Name understood by GDALBuildOverviews and the -r switch of gdaladdo
+    pub fn as_overview_method(&self) -> &'static str {
+        match self {
+            Resampling::Nearest => "NEAREST",
+            Resampling::Bilinear => "BILINEAR",
+            Resampling::Cubic => "CUBIC",
+            Resampling::Lanczos => "LANCZOS",
+            Resampling::Average => "AVERAGE",
+            Resampling::Mode => "MODE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Lzw,
+    #[default]
+    Deflate,
+    Zstd,
+    Jpeg,
+    Webp,
+    Lerc,
+}
+
+impl Compression {
+    // Value of the COMPRESS creation option of the GTiff and COG drivers
+    pub fn as_gdal_option(&self) -> &'static str {
+        match self {
+            Compression::None => "NONE",
+            Compression::Lzw => "LZW",
+            Compression::Deflate => "DEFLATE",
+            Compression::Zstd => "ZSTD",
+            Compression::Jpeg => "JPEG",
+            Compression::Webp => "WEBP",
+            Compression::Lerc => "LERC",
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use gdal::cpl::CslStringList;
+use gdal::raster::{Attribute, GdalDataType, Group, MDArray};
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use std::ptr;
+use tauri::ipc::Response;
+use tauri::State;
+
+use crate::datasets::{DatasetRegistry, OpenDataset};
+use crate::ffi::{c_string, last_error, string_from_ptr};
+use crate::ipc::Frame;
+use crate::network::{check_remote, is_remote};
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+// Coordinate values listed per dimension; longer dimensions only report their size
+const MAX_COORDINATES: usize = 100_000;
+
+// Values returned by one `read_array_slice`, 32 MB of f64
+const MAX_SLICE_VALUES: usize = 4_000_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MultidimDataset {
+    pub handle: u64,
+    pub driver_name: String,
+    // Global attributes of the root group, e.g. CF conventions and history
+    pub attributes: Map<String, Value>,
+    // Full names of every array, such as `/sst` or `/forecast/t2m`
+    pub arrays: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DimensionInfo {
+    pub name: String,
+    pub size: u64,
+    // GDAL's guess at what the dimension is: HORIZONTAL_X, HORIZONTAL_Y, VERTICAL,
+    // TEMPORAL or PARAMETRIC, empty when unknown
+    pub kind: String,
+    // Values of the indexing variable, e.g. times or pressure levels; None when the
+    // dimension has none or is longer than MAX_COORDINATES
+    pub values: Option<Vec<Value>>,
+    // Unit of those values, such as `days since 1970-01-01`
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArrayInfo {
+    pub name: String,
+    pub data_type: String,
+    // Slowest varying first, the order `read_array_slice` takes start and count in
+    pub dimensions: Vec<DimensionInfo>,
+    pub attributes: Map<String, Value>,
+    pub unit: String,
+    pub nodata: Option<f64>,
+    pub crs: Option<String>,
+    // Chunk shape in the order of `dimensions`, e.g. Zarr chunks or NetCDF/HDF5 chunking;
+    // 0 along dimensions that are not chunked. Reads aligned to it touch the fewest
+    // chunks, which matters most for remote stores.
+    pub chunks: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArraySlice {
+    pub name: String,
+    pub dimensions: Vec<String>,
+    pub start: Vec<u64>,
+    pub shape: Vec<usize>,
+    pub data_type: String,
+    pub nodata: Option<f64>,
+}
+
+// JSON value of an attribute: a string or number, or an array of them
+fn attribute_value(attribute: &Attribute) -> Value {
+    let class = attribute.datatype().class();
+    let scalar = attribute.dimension_sizes().is_empty();
+    if class.is_string() {
+        if scalar {
+            json!(attribute.read_as_string())
+        } else {
+            json!(attribute.read_as_string_array())
+        }
+    } else if class.is_numeric() {
+        let values = attribute.read_as_f64_array();
+        match values.as_slice() {
+            [value] if scalar => json!(value),
+            _ => json!(values),
+        }
+    } else {
+        Value::Null
+    }
+}
+
+// Takes ownership of an attribute list returned by GDALGroupGetAttributes or
+// GDALMDArrayGetAttributes
+unsafe fn attribute_map(list: *mut gdal_sys::GDALAttributeH, count: usize) -> Map<String, Value> {
+    let mut attributes = Map::new();
+    if list.is_null() {
+        return attributes;
+    }
+    for &handle in std::slice::from_raw_parts(list, count) {
+        let name = string_from_ptr(gdal_sys::GDALAttributeGetName(handle)).unwrap_or_default();
+        // The wrapper releases the attribute when dropped
+        let attribute = Attribute::from_c_attribute(handle);
+        attributes.insert(name, attribute_value(&attribute));
+    }
+    gdal_sys::VSIFree(list as *mut _);
+    attributes
+}
+
+fn open_multidim_dataset(
+    path: &str,
+    allowed_drivers: Option<&[&str]>,
+    open_options: Option<&[&str]>,
+) -> Result<Dataset, GdalError> {
+    Ok(Dataset::open_ex(
+        path,
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_MULTIDIM_RASTER | GdalOpenFlags::GDAL_OF_READONLY,
+            allowed_drivers,
+            open_options,
+            ..Default::default()
+        },
+    )?)
+}
+
+// Path GDAL reads a Zarr store from: local directories as they are, cloud URLs through
+// the matching virtual file system
+fn zarr_path(uri: &str) -> String {
+    const SCHEMES: &[(&str, &str)] = &[
+        ("s3://", "/vsis3/"),
+        ("gs://", "/vsigs/"),
+        ("az://", "/vsiaz/"),
+        ("http://", "/vsicurl/http://"),
+        ("https://", "/vsicurl/https://"),
+    ];
+    for (scheme, prefix) in SCHEMES {
+        if let Some(rest) = uri.strip_prefix(scheme) {
+            return format!("{}{}", prefix, rest);
+        }
+    }
+    uri.to_string()
+}
+
+// Full names of the arrays in `group` and its subgroups, depth first
+fn array_names(group: &Group, prefix: &str, names: &mut Vec<String>) -> Result<(), GdalError> {
+    for name in group.array_names(CslStringList::new()) {
+        names.push(format!("{}/{}", prefix, name));
+    }
+    for name in group.group_names(CslStringList::new()) {
+        let child = group.open_group(&name, CslStringList::new())?;
+        array_names(&child, &format!("{}/{}", prefix, name), names)?;
+    }
+    Ok(())
+}
+
+fn root_attributes(dataset: &Dataset) -> Map<String, Value> {
+    unsafe {
+        let root = gdal_sys::GDALDatasetGetRootGroup(dataset.c_dataset());
+        if root.is_null() {
+            return Map::new();
+        }
+        let mut count = 0;
+        let list = gdal_sys::GDALGroupGetAttributes(root, &mut count, ptr::null_mut());
+        let attributes = attribute_map(list, count);
+        gdal_sys::GDALGroupRelease(root);
+        attributes
+    }
+}
+
+// Raw handle of the array with full name `name`, to be released by the caller
+fn array_handle(dataset: &Dataset, name: &str) -> Result<gdal_sys::GDALMDArrayH, GdalError> {
+    let c_name = c_string(name)?;
+    unsafe {
+        let root = gdal_sys::GDALDatasetGetRootGroup(dataset.c_dataset());
+        if root.is_null() {
+            return Err(last_error("GDALDatasetGetRootGroup"));
+        }
+        let handle =
+            gdal_sys::GDALGroupOpenMDArrayFromFullname(root, c_name.as_ptr(), ptr::null_mut());
+        gdal_sys::GDALGroupRelease(root);
+        if handle.is_null() {
+            return Err(GdalError::InvalidArgument(format!(
+                "No array named {}",
+                name
+            )));
+        }
+        Ok(handle)
+    }
+}
+
+fn block_size(array: gdal_sys::GDALMDArrayH) -> Vec<u64> {
+    unsafe {
+        let mut count = 0;
+        let sizes = gdal_sys::GDALMDArrayGetBlockSize(array, &mut count);
+        if sizes.is_null() {
+            return Vec::new();
+        }
+        let block = std::slice::from_raw_parts(sizes, count).to_vec();
+        gdal_sys::VSIFree(sizes as *mut _);
+        block
+    }
+}
+
+fn data_type_name(array: &MDArray) -> String {
+    let data_type = array.datatype();
+    let class = data_type.class();
+    if class.is_numeric() {
+        GdalDataType::try_from(data_type.numeric_datatype())
+            .map(|data_type| data_type.name())
+            .unwrap_or_default()
+    } else {
+        class.to_string()
+    }
+}
+
+// Coordinates along a dimension, read from its indexing variable
+fn coordinates(array: &MDArray, size: u64) -> Option<Vec<Value>> {
+    if size as usize > MAX_COORDINATES || array.num_dimensions() != 1 {
+        return None;
+    }
+    if array.datatype().class().is_string() {
+        let values = array.read_as_string_array().ok()?;
+        return Some(values.into_iter().map(Value::from).collect());
+    }
+    let values = array.read_as::<f64>(vec![0], vec![size as usize]).ok()?;
+    Some(values.into_iter().map(|value| json!(value)).collect())
+}
+
+fn dimension_info(root: &Group, array: gdal_sys::GDALMDArrayH) -> Vec<DimensionInfo> {
+    let mut dimensions = Vec::new();
+    unsafe {
+        let mut count = 0;
+        let list = gdal_sys::GDALMDArrayGetDimensions(array, &mut count);
+        if list.is_null() {
+            return dimensions;
+        }
+        for &handle in std::slice::from_raw_parts(list, count) {
+            let size = gdal_sys::GDALDimensionGetSize(handle);
+            let (mut values, mut unit) = (None, None);
+            let variable = gdal_sys::GDALDimensionGetIndexingVariable(handle);
+            if !variable.is_null() {
+                // The wrapper releases the indexing variable when dropped
+                let variable = MDArray::from_c_mdarray_and_group(root, variable);
+                values = coordinates(&variable, size);
+                unit = Some(variable.unit()).filter(|unit| !unit.is_empty());
+            }
+            dimensions.push(DimensionInfo {
+                name: string_from_ptr(gdal_sys::GDALDimensionGetName(handle)).unwrap_or_default(),
+                size,
+                kind: string_from_ptr(gdal_sys::GDALDimensionGetType(handle)).unwrap_or_default(),
+                values,
+                unit,
+            });
+        }
+        gdal_sys::GDALReleaseDimensions(list, count);
+    }
+    dimensions
+}
+
+fn array_info(dataset: &Dataset, name: &str) -> Result<ArrayInfo, GdalError> {
+    let root = dataset.root_group()?;
+    let handle = array_handle(dataset, name)?;
+    let (dimensions, attributes) = unsafe {
+        let mut count = 0;
+        let list = gdal_sys::GDALMDArrayGetAttributes(handle, &mut count, ptr::null_mut());
+        (dimension_info(&root, handle), attribute_map(list, count))
+    };
+    let chunks = block_size(handle);
+    // The wrapper releases the array when dropped
+    let array = unsafe { MDArray::from_c_mdarray_and_group(&root, handle) };
+    Ok(ArrayInfo {
+        name: name.to_string(),
+        data_type: data_type_name(&array),
+        dimensions,
+        attributes,
+        unit: array.unit(),
+        nodata: array.no_data_value_as_double(),
+        crs: array
+            .spatial_reference()
+            .ok()
+            .and_then(|srs| srs.to_wkt().ok()),
+        chunks,
+    })
+}
+
+// Handle 0 until the caller registers the dataset
+fn describe(dataset: &Dataset) -> Result<MultidimDataset, GdalError> {
+    let mut arrays = Vec::new();
+    array_names(&dataset.root_group()?, "", &mut arrays)?;
+    Ok(MultidimDataset {
+        handle: 0,
+        driver_name: dataset.driver().short_name(),
+        attributes: root_attributes(dataset),
+        arrays,
+    })
+}
+
+// Which part of an array to read
+enum ReadWindow {
+    // `count` values from `start` along each dimension; the whole array, or the rest of
+    // each dimension, when unset
+    Slice {
+        start: Option<Vec<u64>>,
+        count: Option<Vec<usize>>,
+    },
+    // The chunk at this index along each dimension, smaller at the far edges
+    Chunk(Vec<u64>),
+}
+
+fn read_window(dataset: &Dataset, name: String, window: ReadWindow) -> Result<Response, String> {
+    let root = dataset.root_group().map_err(|e| e.to_string())?;
+    let array_handle = array_handle(dataset, &name).map_err(|e| e.to_string())?;
+    let dimensions = dimension_info(&root, array_handle);
+    let chunks = block_size(array_handle);
+    let array = unsafe { MDArray::from_c_mdarray_and_group(&root, array_handle) };
+    if !array.datatype().class().is_numeric() {
+        return Err(format!("Array {} is not numeric", name));
+    }
+
+    let (start, count) = match window {
+        ReadWindow::Slice { start, count } => {
+            let start = start.unwrap_or_else(|| vec![0; dimensions.len()]);
+            let count = count.unwrap_or_else(|| {
+                dimensions
+                    .iter()
+                    .zip(&start)
+                    .map(|(dimension, &start)| dimension.size.saturating_sub(start) as usize)
+                    .collect()
+            });
+            (start, count)
+        }
+        ReadWindow::Chunk(index) => {
+            if index.len() != dimensions.len() {
+                return Err(format!(
+                    "Array {} has {} dimensions, the chunk index needs one value each",
+                    name,
+                    dimensions.len()
+                ));
+            }
+            // Unchunked dimensions are one chunk spanning the whole dimension
+            dimensions
+                .iter()
+                .zip(&index)
+                .enumerate()
+                .map(|(axis, (dimension, &index))| {
+                    let chunk = chunks
+                        .get(axis)
+                        .copied()
+                        .filter(|&chunk| chunk > 0)
+                        .unwrap_or(dimension.size);
+                    let start = index.saturating_mul(chunk);
+                    (
+                        start,
+                        chunk.min(dimension.size.saturating_sub(start)) as usize,
+                    )
+                })
+                .unzip()
+        }
+    };
+    if start.len() != dimensions.len() || count.len() != dimensions.len() {
+        return Err(format!(
+            "Array {} has {} dimensions, start and count need one value each",
+            name,
+            dimensions.len()
+        ));
+    }
+    for ((dimension, &start), &count) in dimensions.iter().zip(&start).zip(&count) {
+        if count == 0 || start + count as u64 > dimension.size {
+            return Err(format!(
+                "{} values from {} are outside dimension {} of size {}",
+                count, start, dimension.name, dimension.size
+            ));
+        }
+    }
+    let total = count.iter().product::<usize>();
+    if total > MAX_SLICE_VALUES {
+        return Err(format!(
+            "The slice has {} values, more than the {} that can be read at once",
+            total, MAX_SLICE_VALUES
+        ));
+    }
+
+    let values = array
+        .read_as::<f64>(start.clone(), count.clone())
+        .map_err(|e| e.to_string())?;
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    let meta = ArraySlice {
+        name,
+        dimensions: dimensions
+            .into_iter()
+            .map(|dimension| dimension.name)
+            .collect(),
+        start,
+        shape: count,
+        data_type: GdalDataType::Float64.name(),
+        nodata: array.no_data_value_as_double(),
+    };
+    Ok(Frame::new(&meta).part(&bytes).into_response())
+}
+
+// Opens a NetCDF, HDF5, Zarr or GRIB file through GDAL's multidimensional API, which keeps
+// arrays such as temperature(time, level, lat, lon) whole instead of flattening all but
+// two dimensions into bands. The handle is closed with `close_dataset`.
+#[tauri::command]
+pub async fn open_multidim(
+    registry: State<'_, DatasetRegistry>,
+    file_path: String,
+) -> Result<MultidimDataset, String> {
+    let (path, dataset, mut described) = run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&file_path).exists() {
+            return Err(format!("File not found: {}", file_path));
+        }
+        let dataset = open_multidim_dataset(&file_path, None, None).map_err(|e| e.to_string())?;
+        let described = describe(&dataset).map_err(|e| e.to_string())?;
+        Ok((file_path, dataset, described))
+    })
+    .await?;
+
+    described.handle = registry.insert(OpenDataset::new(path, dataset, false));
+    Ok(described)
+}
+
+// Opens a Zarr v2 or v3 store from a local directory or an s3://, gs://, az:// or
+// http(s):// URL, like `open_multidim`. `consolidated` (default true) reads the v2
+// .zmetadata file, so listing a remote store takes one request instead of one per
+// array; stores without it need `consolidated: false`.
+#[tauri::command]
+pub async fn open_zarr(
+    registry: State<'_, DatasetRegistry>,
+    uri: String,
+    consolidated: Option<bool>,
+) -> Result<MultidimDataset, String> {
+    let (path, dataset, mut described) = run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let path = zarr_path(&uri);
+        if is_remote(&path) {
+            check_remote(&path).map_err(|e| e.to_string())?;
+        } else if !Path::new(&path).exists() {
+            return Err(format!("File not found: {}", uri));
+        }
+        let metadata = if consolidated.unwrap_or(true) {
+            "USE_ZMETADATA=YES"
+        } else {
+            "USE_ZMETADATA=NO"
+        };
+        let dataset = open_multidim_dataset(&path, Some(&["Zarr"]), Some(&[metadata]))
+            .map_err(|e| e.to_string())?;
+        let described = describe(&dataset).map_err(|e| e.to_string())?;
+        Ok((path, dataset, described))
+    })
+    .await?;
+
+    described.handle = registry.insert(OpenDataset::new(path, dataset, false));
+    Ok(described)
+}
+
+// Dimensions, coordinates and attributes of every array of a dataset opened with
+// `open_multidim`, or only of the arrays in `names`
+#[tauri::command]
+pub async fn list_arrays(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    names: Option<Vec<String>>,
+) -> Result<Vec<ArrayInfo>, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let names = match names {
+            Some(names) => names,
+            None => {
+                let root = open.dataset.root_group().map_err(|e| e.to_string())?;
+                let mut names = Vec::new();
+                array_names(&root, "", &mut names).map_err(|e| e.to_string())?;
+                names
+            }
+        };
+        names
+            .iter()
+            .map(|name| array_info(&open.dataset, name).map_err(|e| e.to_string()))
+            .collect()
+    })
+    .await
+}
+
+// Reads `count` values along each dimension from `start` (the whole array when unset),
+// in the dimension order of `list_arrays`, e.g. one time step and level of a
+// temperature(time, level, lat, lon) array with start [t, l, 0, 0] and count
+// [1, 1, lat, lon]. Returns a binary frame whose only part holds the values as
+// little-endian f64, last dimension varying fastest.
+#[tauri::command]
+pub async fn read_array_slice(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    name: String,
+    start: Option<Vec<u64>>,
+    count: Option<Vec<usize>>,
+) -> Result<Response, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        read_window(&open.dataset, name, ReadWindow::Slice { start, count })
+    })
+    .await
+}
+
+// Reads one chunk of an array, `chunk` being its index along each dimension (chunk
+// [0, 2, 1] of a 1x256x256 chunked array starts at [0, 512, 256]). Each call decodes a
+// single stored chunk, so tiling a view by chunks never fetches one twice. Returns the
+// same frame as `read_array_slice`.
+#[tauri::command]
+pub async fn read_array_chunk(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    name: String,
+    chunk: Vec<u64>,
+) -> Result<Response, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        read_window(&open.dataset, name, ReadWindow::Chunk(chunk))
+    })
+    .await
+}
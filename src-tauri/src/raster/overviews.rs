@@ -0,0 +1,266 @@
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags, Metadata};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::ptr;
+use tauri::{AppHandle, State};
+
+use super::Resampling;
+use crate::datasets::DatasetRegistry;
+use crate::progress::{gdal_progress, Progress};
+use crate::{ffi, run_blocking, setup_gdal_runtime, GdalError};
+
+// Overviews stop once the smallest level fits in a single display tile
+const MIN_OVERVIEW_SIZE: usize = 256;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverviewResult {
+    pub levels: Vec<i32>,
+    pub resampling: Resampling,
+    pub external: bool,
+    pub overview_count: usize,
+}
+
+// Power-of-two decimation factors down to roughly one tile, like `gdaladdo` without levels
+pub(crate) fn default_levels(size_x: usize, size_y: usize) -> Vec<i32> {
+    levels_for_tile_size(size_x, size_y, MIN_OVERVIEW_SIZE)
+}
+
+pub(crate) fn levels_for_tile_size(size_x: usize, size_y: usize, tile_size: usize) -> Vec<i32> {
+    let mut levels = Vec::new();
+    let mut factor = 2;
+    while size_x.max(size_y) / factor as usize >= tile_size {
+        levels.push(factor);
+        factor *= 2;
+    }
+    levels
+}
+
+pub(crate) fn build(
+    dataset: &Dataset,
+    levels: &[i32],
+    resampling: Resampling,
+    progress: &Progress,
+) -> Result<(), GdalError> {
+    let method = ffi::c_string(resampling.as_overview_method())?;
+
+    // Passing no band list builds overviews for every band
+    let rv = unsafe {
+        gdal_sys::GDALBuildOverviews(
+            dataset.c_dataset(),
+            method.as_ptr(),
+            levels.len() as i32,
+            levels.as_ptr(),
+            0,
+            ptr::null(),
+            Some(gdal_progress),
+            progress.as_arg(),
+        )
+    };
+    if rv != gdal_sys::CPLErr::CE_None {
+        return Err(ffi::last_error("GDALBuildOverviews"));
+    }
+    Ok(())
+}
+
+pub(crate) fn overview_count(dataset: &Dataset) -> usize {
+    dataset
+        .rasterband(1)
+        .and_then(|band| band.overview_count())
+        .map(|count| count.max(0) as usize)
+        .unwrap_or(0)
+}
+
+#[tauri::command]
+pub async fn build_overviews(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    levels: Option<Vec<i32>>,
+    resampling: Option<Resampling>,
+    external: Option<bool>,
+) -> Result<OverviewResult, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let mut open = entry.lock().unwrap();
+        if open.dataset.raster_count() == 0 {
+            return Err("Dataset has no raster bands".to_string());
+        }
+
+        let (size_x, size_y) = open.dataset.raster_size();
+        let levels = levels.unwrap_or_else(|| default_levels(size_x, size_y));
+        if levels.is_empty() {
+            return Err(format!(
+                "Dataset is smaller than {} pixels, no overviews needed",
+                MIN_OVERVIEW_SIZE
+            ));
+        }
+        if levels.iter().any(|&level| level < 2) {
+            return Err("Overview levels must be 2 or greater".to_string());
+        }
+
+        let resampling = resampling.unwrap_or(Resampling::Average);
+        let external = external.unwrap_or(false);
+        let progress = Progress::new(&app, "build_overviews");
+
+        if open.in_memory {
+            // MEM datasets keep their overviews in memory alongside the data
+            if external {
+                return Err("In-memory datasets cannot have external overviews".to_string());
+            }
+            build(&open.dataset, &levels, resampling, &progress).map_err(|e| e.to_string())?;
+        } else if external {
+            // Registered datasets are read-only, so GDAL writes a sidecar .ovr file
+            build(&open.dataset, &levels, resampling, &progress).map_err(|e| e.to_string())?;
+        } else {
+            let update = Dataset::open_ex(
+                &open.path,
+                DatasetOptions {
+                    open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| {
+                format!(
+                    "Cannot open dataset for update, try external overviews: {}",
+                    e
+                )
+            })?;
+            build(&update, &levels, resampling, &progress).map_err(|e| e.to_string())?;
+            update.close().map_err(|e| e.to_string())?;
+
+            // Reopen so the registered handle sees the new internal overviews
+            let reopened = Dataset::open(&open.path).map_err(|e| e.to_string())?;
+            open.replace_dataset(reopened);
+        }
+        // The Web Mercator view picks overview levels when it is created
+        open.reset_mercator();
+
+        Ok(OverviewResult {
+            levels,
+            resampling,
+            external,
+            overview_count: overview_count(&open.dataset),
+        })
+    })
+    .await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverviewLevel {
+    pub index: usize,
+    pub size_x: usize,
+    pub size_y: usize,
+    // Decimation factor relative to full resolution, rounded
+    pub factor: i32,
+    pub block_size: (usize, usize),
+    // Only known when the format records it, e.g. GeoTIFF overviews built by GDAL
+    pub resampling: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverviewRecommendation {
+    pub levels: Vec<i32>,
+    pub resampling: Resampling,
+    pub external: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverviewDiagnostics {
+    pub size_x: usize,
+    pub size_y: usize,
+    pub block_size: (usize, usize),
+    pub tile_size: usize,
+    pub levels: Vec<OverviewLevel>,
+    // Factors the tile scheme expects that have no matching overview
+    pub missing_levels: Vec<i32>,
+    pub has_external_overviews: bool,
+    // `None` when the pyramid already covers the tile scheme
+    pub recommendation: Option<OverviewRecommendation>,
+}
+
+fn diagnose(
+    dataset: &Dataset,
+    path: &str,
+    in_memory: bool,
+    tile_size: usize,
+) -> Result<OverviewDiagnostics, GdalError> {
+    let band = dataset.rasterband(1)?;
+    let (size_x, size_y) = band.size();
+
+    let mut levels = Vec::new();
+    for index in 0..overview_count(dataset) {
+        let overview = band.overview(index)?;
+        let (ovr_x, ovr_y) = overview.size();
+        levels.push(OverviewLevel {
+            index,
+            size_x: ovr_x,
+            size_y: ovr_y,
+            factor: (size_x as f64 / ovr_x.max(1) as f64).round() as i32,
+            block_size: overview.block_size(),
+            resampling: overview.metadata_item("RESAMPLING", ""),
+        });
+    }
+
+    let missing_levels: Vec<i32> = levels_for_tile_size(size_x, size_y, tile_size)
+        .into_iter()
+        .filter(|factor| !levels.iter().any(|level| level.factor == *factor))
+        .collect();
+
+    // Palette and thematic rasters must not have their classes averaged together
+    let categorical = band.color_table().is_some();
+    let has_external_overviews = !in_memory && Path::new(&format!("{}.ovr", path)).exists();
+
+    let recommendation = (!missing_levels.is_empty()).then(|| OverviewRecommendation {
+        levels: missing_levels.clone(),
+        resampling: if categorical {
+            Resampling::Nearest
+        } else {
+            Resampling::Average
+        },
+        // New levels go where the existing ones are, or into the file when there are none
+        external: has_external_overviews,
+    });
+
+    Ok(OverviewDiagnostics {
+        size_x,
+        size_y,
+        block_size: band.block_size(),
+        tile_size,
+        levels,
+        missing_levels,
+        has_external_overviews,
+        recommendation,
+    })
+}
+
+// Reports the overview pyramid and the `build_overviews` parameters that would complete it
+// for a display tile size
+#[tauri::command]
+pub async fn inspect_overviews(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    tile_size: Option<usize>,
+) -> Result<OverviewDiagnostics, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let tile_size = tile_size.unwrap_or(MIN_OVERVIEW_SIZE);
+        if tile_size == 0 {
+            return Err("Tile size must be positive".to_string());
+        }
+
+        let open = entry.lock().unwrap();
+        if open.dataset.raster_count() == 0 {
+            return Err("Dataset has no raster bands".to_string());
+        }
+        diagnose(&open.dataset, &open.path, open.in_memory, tile_size).map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,148 @@
+use gdal::Dataset;
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use super::Resampling;
+use crate::jobs::{record_command_line, run_job, without_command_lines};
+use crate::progress::Progress;
+use crate::{dataset_info, setup_gdal_runtime, DatasetInfo};
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// VRTPansharpenedDataset definition, the same one gdal_pansharpen.py builds. Every
+// multispectral band becomes an output band.
+fn pansharpened_vrt(
+    pan: &str,
+    multispectral: &str,
+    band_count: usize,
+    weights: Option<&[f64]>,
+    resampling: Resampling,
+    nodata: Option<f64>,
+) -> String {
+    let mut xml = String::from("<VRTDataset subClass=\"VRTPansharpenedDataset\">\n");
+    xml.push_str("  <PansharpeningOptions>\n");
+    if let Some(weights) = weights {
+        let weights: Vec<String> = weights.iter().map(|w| w.to_string()).collect();
+        xml.push_str(&format!(
+            "    <AlgorithmOptions><Weights>{}</Weights></AlgorithmOptions>\n",
+            weights.join(",")
+        ));
+    }
+    xml.push_str(&format!(
+        "    <Resampling>{}</Resampling>\n",
+        resampling.as_overview_method()
+    ));
+    if let Some(nodata) = nodata {
+        xml.push_str(&format!("    <NoData>{}</NoData>\n", nodata));
+    }
+    xml.push_str(&format!(
+        "    <PanchroBand><SourceFilename relativeToVRT=\"0\">{}</SourceFilename><SourceBand>1</SourceBand></PanchroBand>\n",
+        xml_escape(pan)
+    ));
+    for band in 1..=band_count {
+        xml.push_str(&format!(
+            "    <SpectralBand dstBand=\"{}\"><SourceFilename relativeToVRT=\"0\">{}</SourceFilename><SourceBand>{}</SourceBand></SpectralBand>\n",
+            band,
+            xml_escape(multispectral),
+            band
+        ));
+    }
+    xml.push_str("  </PansharpeningOptions>\n</VRTDataset>\n");
+    xml
+}
+
+// Sharpens a multispectral raster with a higher resolution panchromatic band using the
+// weighted Brovey method. `weights` gives each multispectral band's contribution to the
+// simulated panchromatic band, equal weights when unset.
+#[tauri::command]
+pub async fn pansharpen(
+    app: AppHandle,
+    pan_raster: String,
+    multispectral_raster: String,
+    dst: String,
+    weights: Option<Vec<f64>>,
+    resampling: Option<Resampling>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "pan_raster": pan_raster,
+        "multispectral_raster": multispectral_raster,
+        "dst": dst,
+        "weights": weights,
+        "resampling": resampling,
+    });
+    run_job(app.clone(), "pansharpen", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        for path in [&pan_raster, &multispectral_raster] {
+            if !Path::new(path).exists() {
+                return Err(format!("File not found: {}", path));
+            }
+        }
+
+        let pan = Dataset::open(&pan_raster).map_err(|e| e.to_string())?;
+        let multispectral = Dataset::open(&multispectral_raster).map_err(|e| e.to_string())?;
+        if pan.raster_count() == 0 || multispectral.raster_count() == 0 {
+            return Err("Both inputs need at least one raster band".to_string());
+        }
+        if pan.raster_size().0 <= multispectral.raster_size().0 {
+            return Err(
+                "The panchromatic raster must have a higher resolution than the multispectral one"
+                    .to_string(),
+            );
+        }
+        let band_count = multispectral.raster_count();
+        if let Some(weights) = &weights {
+            if weights.len() != band_count {
+                return Err(format!(
+                    "Expected {} weights, one per multispectral band, got {}",
+                    band_count,
+                    weights.len()
+                ));
+            }
+            if weights.iter().any(|weight| *weight < 0.0) {
+                return Err("Weights must not be negative".to_string());
+            }
+        }
+        let nodata = multispectral
+            .rasterband(1)
+            .ok()
+            .and_then(|band| band.no_data_value());
+        let resampling = resampling.unwrap_or(Resampling::Cubic);
+
+        let mut args = vec![
+            pan_raster.clone(),
+            multispectral_raster.clone(),
+            dst.clone(),
+            "-r".to_string(),
+            resampling.as_gdal_arg().to_string(),
+        ];
+        for weight in weights.iter().flatten() {
+            args.extend(["-w".to_string(), weight.to_string()]);
+        }
+        record_command_line("gdal_pansharpen.py", &args);
+
+        let xml = pansharpened_vrt(
+            &pan_raster,
+            &multispectral_raster,
+            band_count,
+            weights.as_deref(),
+            resampling,
+            nodata,
+        );
+        let vrt = Dataset::open(&xml).map_err(|e| e.to_string())?;
+
+        let progress = Progress::new(&app, "pansharpen");
+        let output = without_command_lines(|| translate(&vrt, &dst, &[], &progress))
+            .map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
@@ -0,0 +1,141 @@
+use gdal::cpl::CslStringList;
+use gdal::raster::GdalDataType;
+use gdal::vector::{FieldDefn, LayerAccess, LayerOptions, OGRFieldType, OGRwkbGeometryType};
+use gdal::Dataset;
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::vector::create_output;
+use crate::vector::flatgeobuf::ExportedLayer;
+use crate::{ffi, setup_gdal_runtime, GdalError};
+
+const LAYER_NAME: &str = "polygons";
+
+// Traces connected regions of equal value in `band` into polygons, storing the value
+// in `field_name`. Nodata pixels produce no polygons.
+fn polygonize_band(
+    source: &Dataset,
+    band: usize,
+    dst: &str,
+    field_name: &str,
+    eight_connected: bool,
+    progress: &Progress,
+) -> Result<u64, GdalError> {
+    let mut command_args = vec![dataset_name(source), "-b".to_string(), band.to_string()];
+    if eight_connected {
+        command_args.push("-8".to_string());
+    }
+    command_args.extend([
+        dst.to_string(),
+        LAYER_NAME.to_string(),
+        field_name.to_string(),
+    ]);
+    record_command_line("gdal_polygonize.py", &command_args);
+
+    let raster_band = source.rasterband(band)?;
+    let float = matches!(
+        raster_band.band_type(),
+        GdalDataType::Float32 | GdalDataType::Float64
+    );
+    let srs = source.spatial_ref().ok();
+
+    let mut output = create_output(dst)?;
+    let layer = output.create_layer(LayerOptions {
+        name: LAYER_NAME,
+        srs: srs.as_ref(),
+        ty: OGRwkbGeometryType::wkbPolygon,
+        ..Default::default()
+    })?;
+    let field_type = if float {
+        OGRFieldType::OFTReal
+    } else {
+        OGRFieldType::OFTInteger64
+    };
+    FieldDefn::new(field_name, field_type)?.add_to_layer(&layer)?;
+
+    let mut options = CslStringList::new();
+    if eight_connected {
+        options.set_name_value("8CONNECTED", "8")?;
+    }
+
+    let rv = unsafe {
+        let handle = raster_band.c_rasterband();
+        let mask = gdal_sys::GDALGetMaskBand(handle);
+        // Integer bands go through GDALPolygonize, which compares values exactly
+        let polygonize = if float {
+            gdal_sys::GDALFPolygonize
+        } else {
+            gdal_sys::GDALPolygonize
+        };
+        polygonize(
+            handle,
+            mask,
+            layer.c_layer() as *mut _,
+            0,
+            options.as_ptr(),
+            Some(gdal_progress),
+            progress.as_arg(),
+        )
+    };
+    if rv != gdal_sys::CPLErr::CE_None {
+        return Err(ffi::last_error("GDALPolygonize"));
+    }
+
+    let count = layer.feature_count();
+    output.close()?;
+    Ok(count)
+}
+
+#[tauri::command]
+pub async fn polygonize(
+    app: AppHandle,
+    src: String,
+    band: Option<usize>,
+    dst: String,
+    field_name: Option<String>,
+    eight_connected: Option<bool>,
+) -> Result<ExportedLayer, String> {
+    let params = json!({
+        "src": src,
+        "band": band,
+        "dst": dst,
+        "field_name": field_name,
+        "eight_connected": eight_connected,
+    });
+    run_job(app.clone(), "polygonize", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        let band = band.unwrap_or(1);
+        if band == 0 || band > source.raster_count() {
+            return Err(format!("Band {} does not exist", band));
+        }
+        let field_name = field_name.unwrap_or_else(|| "value".to_string());
+
+        let progress = Progress::new(&app, "polygonize");
+        let feature_count = polygonize_band(
+            &source,
+            band,
+            &dst,
+            &field_name,
+            eight_connected.unwrap_or(false),
+            &progress,
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(ExportedLayer {
+            path: dst,
+            layer: LAYER_NAME.to_string(),
+            feature_count,
+        })
+    })
+    .await
+}
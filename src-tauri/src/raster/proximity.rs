@@ -0,0 +1,171 @@
+use gdal::cpl::CslStringList;
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, GdalError};
+
+// Written beyond `max_distance`, where no target is within reach
+const OUT_OF_RANGE: f64 = -1.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceUnits {
+    // Distances in pixels, the gdal_proximity default
+    #[default]
+    Pixel,
+    // Distances in CRS units, e.g. metres for a projected raster
+    Geo,
+}
+
+// Writes the distance from each pixel of `source` band 1 to the nearest target pixel
+// into band 1 of `output`
+fn proximity(
+    source: &Dataset,
+    output: &Dataset,
+    target_values: Option<&[f64]>,
+    distance_units: DistanceUnits,
+    max_distance: Option<f64>,
+    progress: &Progress,
+) -> Result<(), GdalError> {
+    let units = match distance_units {
+        DistanceUnits::Pixel => "PIXEL",
+        DistanceUnits::Geo => "GEO",
+    };
+    // gdal_proximity.py writes into band 1 of an existing output, as done here
+    let mut command_args = vec![
+        dataset_name(source),
+        dataset_name(output),
+        "-distunits".to_string(),
+        units.to_string(),
+    ];
+
+    let mut options = CslStringList::new();
+    // Without VALUES every non-zero pixel is a target
+    if let Some(values) = target_values {
+        let values = values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        options.set_name_value("VALUES", &values)?;
+        command_args.extend(["-values".to_string(), values]);
+    }
+    options.set_name_value("DISTUNITS", units)?;
+    if let Some(max_distance) = max_distance {
+        options.set_name_value("MAXDIST", &max_distance.to_string())?;
+        options.set_name_value("NODATA", &OUT_OF_RANGE.to_string())?;
+        command_args.extend([
+            "-maxdist".to_string(),
+            max_distance.to_string(),
+            "-nodata".to_string(),
+            OUT_OF_RANGE.to_string(),
+        ]);
+    }
+    record_command_line("gdal_proximity.py", &command_args);
+
+    let source_band = source.rasterband(1)?;
+    let output_band = output.rasterband(1)?;
+    let rv = unsafe {
+        gdal_sys::GDALComputeProximity(
+            source_band.c_rasterband(),
+            output_band.c_rasterband(),
+            options.as_ptr(),
+            Some(gdal_progress),
+            progress.as_arg(),
+        )
+    };
+    if rv != gdal_sys::CPLErr::CE_None {
+        return Err(ffi::last_error("GDALComputeProximity"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn compute_proximity(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    target_values: Option<Vec<f64>>,
+    distance_units: Option<DistanceUnits>,
+    max_distance: Option<f64>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "target_values": target_values,
+        "distance_units": distance_units,
+        "max_distance": max_distance,
+    });
+    run_job(app.clone(), "compute_proximity", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        if max_distance.is_some_and(|distance| distance <= 0.0) {
+            return Err("Maximum distance must be positive".to_string());
+        }
+        if target_values
+            .as_ref()
+            .is_some_and(|values| values.is_empty())
+        {
+            return Err("At least one target value is required".to_string());
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if source.raster_count() == 0 {
+            return Err("Dataset has no raster bands".to_string());
+        }
+
+        // Copy band 1 as Float32 to get an output on the same grid, then overwrite it
+        // with distances
+        let progress = Progress::new(&app, "compute_proximity");
+        progress.set_range(0.0, 0.2);
+        let nodata = match max_distance {
+            Some(_) => OUT_OF_RANGE.to_string(),
+            None => "none".to_string(),
+        };
+        let args = [
+            "-b".to_string(),
+            "1".to_string(),
+            "-ot".to_string(),
+            "Float32".to_string(),
+            "-a_nodata".to_string(),
+            nodata,
+        ];
+        let copy = translate(&source, &dst, &args, &progress).map_err(|e| e.to_string())?;
+        copy.close().map_err(|e| e.to_string())?;
+
+        let output = Dataset::open_ex(
+            &dst,
+            DatasetOptions {
+                open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| format!("Output format cannot be updated in place: {}", e))?;
+
+        progress.set_range(0.2, 1.0);
+        proximity(
+            &source,
+            &output,
+            target_values.as_deref(),
+            distance_units.unwrap_or_default(),
+            max_distance,
+            &progress,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let info = dataset_info(&output);
+        output.close().map_err(|e| e.to_string())?;
+        Ok(info)
+    })
+    .await
+}
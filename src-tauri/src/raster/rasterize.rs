@@ -0,0 +1,235 @@
+use gdal::vector::LayerAccess;
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use std::ptr;
+use tauri::AppHandle;
+
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, Extent, GdalError};
+
+// Written where no feature was burned in newly created rasters
+const NODATA: f64 = -9999.0;
+
+// Grid of the rasterized output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RasterTarget {
+    // New raster with square cells of `resolution` CRS units, covering the layer
+    // unless `extent` is given
+    Resolution {
+        resolution: f64,
+        #[serde(default)]
+        extent: Option<Extent>,
+    },
+    // New raster on the same grid and CRS as the raster at `path`
+    Template {
+        path: String,
+    },
+    // Burns into `dst` itself, which must already exist; other pixels keep their values
+    Existing,
+}
+
+// A fixed value, or the name of a numeric attribute to take each feature's value from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BurnValue {
+    Value(f64),
+    Attribute(String),
+}
+
+// Switches describing the grid of a new raster
+fn target_args(target: &RasterTarget) -> Result<Vec<String>, String> {
+    match target {
+        RasterTarget::Resolution { resolution, .. } if *resolution <= 0.0 => {
+            Err("Resolution must be positive".to_string())
+        }
+        RasterTarget::Resolution { resolution, extent } => {
+            let mut args = vec![
+                "-tr".to_string(),
+                resolution.to_string(),
+                resolution.to_string(),
+            ];
+            if let Some(extent) = extent {
+                args.push("-te".to_string());
+                args.extend(extent.to_args());
+            }
+            Ok(args)
+        }
+        RasterTarget::Template { path } => {
+            if !Path::new(path).exists() {
+                return Err(format!("File not found: {}", path));
+            }
+            let template = Dataset::open(path).map_err(|e| e.to_string())?;
+            let gt = template.geo_transform().map_err(|e| e.to_string())?;
+            if gt[2] != 0.0 || gt[4] != 0.0 {
+                return Err("Rotated template rasters are not supported".to_string());
+            }
+            let (width, height) = template.raster_size();
+            let extent = Extent {
+                min_x: gt[0],
+                min_y: gt[3] + gt[5] * height as f64,
+                max_x: gt[0] + gt[1] * width as f64,
+                max_y: gt[3],
+            };
+
+            let mut args = vec!["-te".to_string()];
+            args.extend(extent.to_args());
+            args.extend(["-ts".to_string(), width.to_string(), height.to_string()]);
+            if let Ok(srs) = template.spatial_ref() {
+                args.extend([
+                    "-a_srs".to_string(),
+                    srs.to_wkt().map_err(|e| e.to_string())?,
+                ]);
+            }
+            Ok(args)
+        }
+        RasterTarget::Existing => Ok(Vec::new()),
+    }
+}
+
+// Runs GDALRasterize (the library form of gdal_rasterize), writing a new raster at
+// `dst` or burning into `existing`
+fn rasterize_layer(
+    source: &Dataset,
+    dst: &str,
+    existing: Option<Dataset>,
+    args: &[String],
+    progress: &Progress,
+) -> Result<Dataset, GdalError> {
+    record_command_line(
+        "gdal_rasterize",
+        &[args, &[dataset_name(source), dst.to_string()]].concat(),
+    );
+    let argv = ffi::arg_list(args)?;
+    let c_dst = ffi::c_string(dst)?;
+
+    unsafe {
+        let options = gdal_sys::GDALRasterizeOptionsNew(argv.as_ptr(), ptr::null_mut());
+        if options.is_null() {
+            return Err(ffi::last_error("GDALRasterizeOptionsNew"));
+        }
+        gdal_sys::GDALRasterizeOptionsSetProgress(options, Some(gdal_progress), progress.as_arg());
+
+        let mut usage_error = 0;
+        let result = match &existing {
+            Some(dataset) => gdal_sys::GDALRasterize(
+                ptr::null(),
+                dataset.c_dataset(),
+                source.c_dataset(),
+                options,
+                &mut usage_error,
+            ),
+            None => gdal_sys::GDALRasterize(
+                c_dst.as_ptr(),
+                ptr::null_mut(),
+                source.c_dataset(),
+                options,
+                &mut usage_error,
+            ),
+        };
+        gdal_sys::GDALRasterizeOptionsFree(options);
+
+        if result.is_null() {
+            return Err(ffi::last_error("GDALRasterize"));
+        }
+        // When burning into an existing raster the handle returned is the one passed in
+        Ok(existing.unwrap_or_else(|| Dataset::from_c_dataset(result)))
+    }
+}
+
+// Burns the features of a vector layer into a raster, either as one value or from an
+// attribute. `all_touched` burns every cell a feature touches rather than those whose
+// centre it covers.
+#[tauri::command]
+pub async fn rasterize(
+    app: AppHandle,
+    src_vector: String,
+    layer: Option<String>,
+    dst: String,
+    resolution_or_template: RasterTarget,
+    burn_value_or_attribute: BurnValue,
+    all_touched: Option<bool>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src_vector": src_vector,
+        "layer": layer,
+        "dst": dst,
+        "resolution_or_template": resolution_or_template,
+        "burn_value_or_attribute": burn_value_or_attribute,
+        "all_touched": all_touched,
+    });
+    run_job(app.clone(), "rasterize", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src_vector).exists() {
+            return Err(format!("File not found: {}", src_vector));
+        }
+
+        let source = Dataset::open_ex(
+            &src_vector,
+            DatasetOptions {
+                open_flags: GdalOpenFlags::GDAL_OF_VECTOR,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| e.to_string())?;
+        // gdal_rasterize needs a layer name, default to the first layer
+        let layer = match layer {
+            Some(layer) => layer,
+            None => source
+                .layer(0)
+                .map_err(|_| "Dataset has no vector layers".to_string())?
+                .name(),
+        };
+
+        let mut args = vec!["-l".to_string(), layer];
+        match &burn_value_or_attribute {
+            BurnValue::Value(value) => args.extend(["-burn".to_string(), value.to_string()]),
+            BurnValue::Attribute(field) => args.extend(["-a".to_string(), field.clone()]),
+        }
+        if all_touched.unwrap_or(false) {
+            args.push("-at".to_string());
+        }
+
+        let existing = match resolution_or_template {
+            RasterTarget::Existing => {
+                if !Path::new(&dst).exists() {
+                    return Err(format!("File not found: {}", dst));
+                }
+                let dataset = Dataset::open_ex(
+                    &dst,
+                    DatasetOptions {
+                        open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+                        ..Default::default()
+                    },
+                )
+                .map_err(|e| e.to_string())?;
+                Some(dataset)
+            }
+            ref target => {
+                args.extend(target_args(target)?);
+                args.extend([
+                    "-ot".to_string(),
+                    "Float32".to_string(),
+                    "-init".to_string(),
+                    NODATA.to_string(),
+                    "-a_nodata".to_string(),
+                    NODATA.to_string(),
+                ]);
+                None
+            }
+        };
+
+        let progress = Progress::new(&app, "rasterize");
+        let output = rasterize_layer(&source, &dst, existing, &args, &progress)
+            .map_err(|e| e.to_string())?;
+        let info = dataset_info(&output);
+        output.close().map_err(|e| e.to_string())?;
+        Ok(info)
+    })
+    .await
+}
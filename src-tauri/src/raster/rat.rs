@@ -0,0 +1,128 @@
+use gdal::raster::RasterBand;
+use gdal_sys::{GDALRATFieldType, GDALRATFieldUsage, GDALRasterAttributeTableH};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::ffi::CStr;
+use tauri::State;
+
+use crate::datasets::DatasetRegistry;
+use crate::{run_blocking, setup_gdal_runtime};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RatColumn {
+    pub name: String,
+    // integer, real or string
+    pub field_type: String,
+    // What the column holds, e.g. name, pixel_count, min_max or red; generic otherwise
+    pub usage: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RasterAttributeTable {
+    pub columns: Vec<RatColumn>,
+    // One array per row with a value per column, in column order
+    pub rows: Vec<Vec<Value>>,
+}
+
+fn usage_name(usage: GDALRATFieldUsage::Type) -> &'static str {
+    match usage {
+        GDALRATFieldUsage::GFU_PixelCount => "pixel_count",
+        GDALRATFieldUsage::GFU_Name => "name",
+        GDALRATFieldUsage::GFU_Min => "min",
+        GDALRATFieldUsage::GFU_Max => "max",
+        GDALRATFieldUsage::GFU_MinMax => "min_max",
+        GDALRATFieldUsage::GFU_Red => "red",
+        GDALRATFieldUsage::GFU_Green => "green",
+        GDALRATFieldUsage::GFU_Blue => "blue",
+        GDALRATFieldUsage::GFU_Alpha => "alpha",
+        GDALRATFieldUsage::GFU_RedMin => "red_min",
+        GDALRATFieldUsage::GFU_GreenMin => "green_min",
+        GDALRATFieldUsage::GFU_BlueMin => "blue_min",
+        GDALRATFieldUsage::GFU_AlphaMin => "alpha_min",
+        GDALRATFieldUsage::GFU_RedMax => "red_max",
+        GDALRATFieldUsage::GFU_GreenMax => "green_max",
+        GDALRATFieldUsage::GFU_BlueMax => "blue_max",
+        GDALRATFieldUsage::GFU_AlphaMax => "alpha_max",
+        _ => "generic",
+    }
+}
+
+fn c_text(ptr: *const std::ffi::c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+// Reads the whole table; gdal-rs has no RAT bindings
+fn read_rat(band: &RasterBand) -> Option<RasterAttributeTable> {
+    unsafe {
+        let rat: GDALRasterAttributeTableH = gdal_sys::GDALGetDefaultRAT(band.c_rasterband());
+        if rat.is_null() {
+            return None;
+        }
+
+        let column_count = gdal_sys::GDALRATGetColumnCount(rat);
+        let types: Vec<_> = (0..column_count)
+            .map(|column| gdal_sys::GDALRATGetTypeOfCol(rat, column))
+            .collect();
+        let columns = (0..column_count)
+            .map(|column| RatColumn {
+                name: c_text(gdal_sys::GDALRATGetNameOfCol(rat, column)),
+                field_type: match types[column as usize] {
+                    GDALRATFieldType::GFT_Integer => "integer",
+                    GDALRATFieldType::GFT_Real => "real",
+                    _ => "string",
+                }
+                .to_string(),
+                usage: usage_name(gdal_sys::GDALRATGetUsageOfCol(rat, column)).to_string(),
+            })
+            .collect();
+
+        let rows = (0..gdal_sys::GDALRATGetRowCount(rat))
+            .map(|row| {
+                (0..column_count)
+                    .map(|column| match types[column as usize] {
+                        GDALRATFieldType::GFT_Integer => {
+                            json!(gdal_sys::GDALRATGetValueAsInt(rat, row, column))
+                        }
+                        GDALRATFieldType::GFT_Real => {
+                            json!(gdal_sys::GDALRATGetValueAsDouble(rat, row, column))
+                        }
+                        _ => json!(c_text(gdal_sys::GDALRATGetValueAsString(rat, row, column))),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Some(RasterAttributeTable { columns, rows })
+    }
+}
+
+// Returns the raster attribute table of a band, typically the class names, pixel counts
+// and colours of a classified raster, to show next to its colour table. None when the
+// band has no attribute table.
+#[tauri::command]
+pub async fn get_raster_attribute_table(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    band: Option<usize>,
+) -> Result<Option<RasterAttributeTable>, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let raster_band = open
+            .dataset
+            .rasterband(band.unwrap_or(1))
+            .map_err(|e| e.to_string())?;
+        Ok(read_rat(&raster_band))
+    })
+    .await
+}
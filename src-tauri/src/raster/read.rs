@@ -0,0 +1,221 @@
+use gdal::raster::{GdalDataType, GdalType, RasterBand};
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Response;
+use tauri::State;
+
+use super::calc::parse_data_type;
+use super::Resampling;
+use crate::datasets::DatasetRegistry;
+use crate::ipc::Frame;
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+// Pixel window of a band: column and row offset, then width and height
+pub(crate) type Window = (isize, isize, usize, usize);
+
+// Coarsest overview that still has at least `size` pixels over `window`, with the window
+// scaled to its grid. None when only the full resolution band is fine enough.
+fn best_overview<'a>(
+    band: &RasterBand<'a>,
+    window: Window,
+    size: (usize, usize),
+) -> Result<Option<(RasterBand<'a>, Window)>, GdalError> {
+    let (full_width, full_height) = band.size();
+    let mut best: Option<(RasterBand<'a>, Window)> = None;
+    for index in 0..band.overview_count()?.max(0) as usize {
+        let overview = band.overview(index)?;
+        let (width, height) = overview.size();
+        let (scale_x, scale_y) = (
+            width as f64 / full_width as f64,
+            height as f64 / full_height as f64,
+        );
+        let scaled = (
+            (window.0 as f64 * scale_x).floor() as isize,
+            (window.1 as f64 * scale_y).floor() as isize,
+            ((window.2 as f64 * scale_x).round() as usize).max(1),
+            ((window.3 as f64 * scale_y).round() as usize).max(1),
+        );
+        let fine_enough = scaled.2 >= size.0 && scaled.3 >= size.1;
+        let coarser = best.as_ref().is_none_or(|(best, _)| width < best.size().0);
+        if fine_enough && coarser {
+            // Rounding must not push the window past the overview's edge
+            let scaled = (
+                scaled.0,
+                scaled.1,
+                scaled.2.min(width.saturating_sub(scaled.0.max(0) as usize)),
+                scaled
+                    .3
+                    .min(height.saturating_sub(scaled.1.max(0) as usize)),
+            );
+            if scaled.2 > 0 && scaled.3 > 0 {
+                best = Some((overview, scaled));
+            }
+        }
+    }
+    Ok(best)
+}
+
+// Reads `window` of `band` resampled to `size`, from the coarsest overview with enough
+// detail, so a thumbnail of a 50 GB raster touches a few overview blocks rather than
+// every full resolution block. Falls back to the band itself when it has no suitable
+// overview.
+pub(crate) fn read_reduced<T: Copy + GdalType>(
+    band: &RasterBand,
+    window: Window,
+    size: (usize, usize),
+    resampling: Resampling,
+) -> Result<Vec<T>, GdalError> {
+    let overview = best_overview(band, window, size)?;
+    let (source, window) = match &overview {
+        Some((overview, scaled)) => (overview, *scaled),
+        None => (band, window),
+    };
+    Ok(source
+        .read_as::<T>(
+            (window.0, window.1),
+            (window.2, window.3),
+            size,
+            Some(resampling.as_resample_alg()),
+        )?
+        .into_shape_and_vec()
+        .1)
+}
+
+// Validity mask of `window` at `size`, 0 where pixels are nodata or transparent, read
+// through the same overview as `read_reduced` would use
+pub(crate) fn read_reduced_mask(
+    band: &RasterBand,
+    window: Window,
+    size: (usize, usize),
+) -> Result<Vec<u8>, GdalError> {
+    let overview = best_overview(band, window, size)?;
+    let (source, window) = match &overview {
+        Some((overview, scaled)) => (overview, *scaled),
+        None => (band, window),
+    };
+    Ok(source
+        .open_mask_band()?
+        .read_as::<u8>((window.0, window.1), (window.2, window.3), size, None)?
+        .into_shape_and_vec()
+        .1)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RasterWindow {
+    pub width: usize,
+    pub height: usize,
+    // GDAL data type name of the values, e.g. "Float32"
+    pub data_type: String,
+    pub nodata: Option<f64>,
+}
+
+fn read_bytes<T: Copy + GdalType, const N: usize>(
+    band: &RasterBand,
+    window: Window,
+    size: (usize, usize),
+    resampling: Resampling,
+    to_bytes: fn(T) -> [u8; N],
+) -> Result<Vec<u8>, GdalError> {
+    Ok(read_reduced::<T>(band, window, size, resampling)?
+        .into_iter()
+        .flat_map(to_bytes)
+        .collect())
+}
+
+fn read_window(
+    band: &RasterBand,
+    window: Window,
+    size: (usize, usize),
+    data_type: GdalDataType,
+    resampling: Resampling,
+) -> Result<Vec<u8>, GdalError> {
+    match data_type {
+        GdalDataType::UInt8 => read_bytes(band, window, size, resampling, u8::to_le_bytes),
+        GdalDataType::Int8 => read_bytes(band, window, size, resampling, i8::to_le_bytes),
+        GdalDataType::UInt16 => read_bytes(band, window, size, resampling, u16::to_le_bytes),
+        GdalDataType::Int16 => read_bytes(band, window, size, resampling, i16::to_le_bytes),
+        GdalDataType::UInt32 => read_bytes(band, window, size, resampling, u32::to_le_bytes),
+        GdalDataType::Int32 => read_bytes(band, window, size, resampling, i32::to_le_bytes),
+        GdalDataType::UInt64 => read_bytes(band, window, size, resampling, u64::to_le_bytes),
+        GdalDataType::Int64 => read_bytes(band, window, size, resampling, i64::to_le_bytes),
+        GdalDataType::Float32 => read_bytes(band, window, size, resampling, f32::to_le_bytes),
+        GdalDataType::Float64 => read_bytes(band, window, size, resampling, f64::to_le_bytes),
+        other => Err(GdalError::InvalidArgument(format!(
+            "Cannot read {} as a typed array",
+            other.name()
+        ))),
+    }
+}
+
+// Reads the `x_size` x `y_size` pixel window at (`x_off`, `y_off`) of a band as raw
+// typed values, so large rasters can be streamed a piece at a time. `out_size` ([width,
+// height]) resamples the window, through an overview when one is fine enough; it
+// defaults to the window's own size. `dtype` is a GDAL type name and defaults to the
+// band's type. Returns a binary frame whose only part holds the row-major values in
+// little-endian byte order, ready to wrap in the matching JavaScript typed array
+// (Float32Array, Uint16Array, ...).
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn read_raster_window(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    band: usize,
+    x_off: isize,
+    y_off: isize,
+    x_size: usize,
+    y_size: usize,
+    out_size: Option<(usize, usize)>,
+    dtype: Option<String>,
+    resampling: Option<Resampling>,
+) -> Result<Response, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        if band == 0 || band > open.dataset.raster_count() {
+            return Err(format!("Band {} does not exist", band));
+        }
+        let (width, height) = open.dataset.raster_size();
+        let inside = x_off >= 0
+            && y_off >= 0
+            && x_size > 0
+            && y_size > 0
+            && x_off as usize + x_size <= width
+            && y_off as usize + y_size <= height;
+        if !inside {
+            return Err(format!(
+                "Window {}x{} at ({}, {}) is outside the {}x{} raster",
+                x_size, y_size, x_off, y_off, width, height
+            ));
+        }
+        let size = out_size.unwrap_or((x_size, y_size));
+        if size.0 == 0 || size.1 == 0 {
+            return Err("Output size must be at least 1x1".to_string());
+        }
+
+        let raster_band = open.dataset.rasterband(band).map_err(|e| e.to_string())?;
+        let data_type = match dtype {
+            Some(name) => parse_data_type(&name).map_err(|e| e.to_string())?,
+            None => raster_band.band_type(),
+        };
+        let bytes = read_window(
+            &raster_band,
+            (x_off, y_off, x_size, y_size),
+            size,
+            data_type,
+            resampling.unwrap_or_default(),
+        )
+        .map_err(|e| e.to_string())?;
+
+        let meta = RasterWindow {
+            width: size.0,
+            height: size.1,
+            data_type: data_type.name(),
+            nodata: raster_band.no_data_value(),
+        };
+        Ok(Frame::new(&meta).part(&bytes).into_response())
+    })
+    .await
+}
@@ -0,0 +1,218 @@
+use gdal::raster::Buffer;
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::calc::{default_nodata, parse_data_type, write_raster};
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::Progress;
+use crate::{dataset_info, setup_gdal_runtime, DatasetInfo, GdalError};
+
+// Pixels reclassified per block
+const BLOCK_PIXELS: usize = 1 << 20;
+
+// Maps input values from `min` to `max`, both inclusive, to `value`. A missing bound is
+// open-ended and a missing value makes the range nodata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReclassRule {
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    pub value: Option<f64>,
+}
+
+impl ReclassRule {
+    fn matches(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+
+    // `[min,max]=value` as in the mapping of `gdal raster reclassify`
+    fn to_mapping(&self) -> String {
+        let bound =
+            |bound: Option<f64>, open: &str| bound.map_or(open.to_string(), |b| b.to_string());
+        format!(
+            "[{},{}]={}",
+            bound(self.min, "-inf"),
+            bound(self.max, "inf"),
+            output_mapping(self.value)
+        )
+    }
+}
+
+fn output_mapping(value: Option<f64>) -> String {
+    value.map_or("NO_DATA".to_string(), |value| value.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReclassOptions {
+    pub band: usize,
+    // Output for values no rule matches; nodata when unset
+    pub default: Option<f64>,
+    // GDAL data type name of the output
+    pub output_type: String,
+    // Defaults to a value suited to the output type
+    pub nodata: Option<f64>,
+    pub creation_options: Vec<String>,
+}
+
+impl Default for ReclassOptions {
+    fn default() -> Self {
+        Self {
+            band: 1,
+            default: None,
+            output_type: "Int16".to_string(),
+            nodata: None,
+            creation_options: Vec::new(),
+        }
+    }
+}
+
+fn reclassify(
+    source: &Dataset,
+    band: usize,
+    rules: &[ReclassRule],
+    default: Option<f64>,
+    output: &Dataset,
+    nodata: f64,
+    progress: &Progress,
+) -> Result<(), GdalError> {
+    let (width, height) = source.raster_size();
+    let rows = (BLOCK_PIXELS / width.max(1)).clamp(1, height.max(1));
+    let input_band = source.rasterband(band)?;
+    let input_nodata = input_band.no_data_value();
+    let mut output_band = output.rasterband(1)?;
+    output_band.set_no_data_value(Some(nodata))?;
+
+    let mut y = 0;
+    while y < height {
+        let block_rows = rows.min(height - y);
+        let values = input_band
+            .read_as::<f64>(
+                (0, y as isize),
+                (width, block_rows),
+                (width, block_rows),
+                None,
+            )?
+            .into_shape_and_vec()
+            .1;
+
+        let classes = values
+            .into_iter()
+            .map(|value| {
+                if value.is_nan() || input_nodata == Some(value) {
+                    return nodata;
+                }
+                match rules.iter().find(|rule| rule.matches(value)) {
+                    Some(rule) => rule.value,
+                    None => default,
+                }
+                .unwrap_or(nodata)
+            })
+            .collect();
+        let mut buffer = Buffer::new((width, block_rows), classes);
+        output_band.write((0, y as isize), (width, block_rows), &mut buffer)?;
+
+        y += block_rows;
+        progress.report(y as f64 / height as f64, None);
+    }
+    Ok(())
+}
+
+// Replaces value ranges with class values, e.g. elevation bands or landcover codes
+// grouped into suitability scores. The first matching rule wins; input nodata stays
+// nodata.
+#[tauri::command]
+pub async fn reclassify_raster(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    rules: Vec<ReclassRule>,
+    options: Option<ReclassOptions>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "rules": rules,
+        "options": options,
+    });
+    run_job(app.clone(), "reclassify_raster", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        if rules.is_empty() {
+            return Err("At least one rule is required".to_string());
+        }
+        for rule in &rules {
+            if let (Some(min), Some(max)) = (rule.min, rule.max) {
+                if min > max {
+                    return Err(format!("Rule minimum {} is above its maximum {}", min, max));
+                }
+            }
+        }
+
+        let options = options.unwrap_or_default();
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if options.band == 0 || options.band > source.raster_count() {
+            return Err(format!("Band {} does not exist", options.band));
+        }
+        let data_type = parse_data_type(&options.output_type).map_err(|e| e.to_string())?;
+        let nodata = options.nodata.unwrap_or_else(|| default_nodata(data_type));
+
+        let mapping = rules
+            .iter()
+            .map(ReclassRule::to_mapping)
+            .chain([
+                format!("DEFAULT={}", output_mapping(options.default)),
+                "NO_DATA=NO_DATA".to_string(),
+            ])
+            .collect::<Vec<_>>()
+            .join(";");
+        record_command_line(
+            "gdal",
+            &[
+                "raster".to_string(),
+                "reclassify".to_string(),
+                "--mapping".to_string(),
+                mapping,
+                "--ot".to_string(),
+                options.output_type.clone(),
+                dataset_name(&source),
+                dst.clone(),
+            ],
+        );
+
+        let progress = Progress::new(&app, "reclassify_raster");
+        let output = write_raster(
+            &dst,
+            &source,
+            1,
+            data_type,
+            &options.creation_options,
+            &progress,
+            |output, progress| {
+                reclassify(
+                    &source,
+                    options.band,
+                    &rules,
+                    options.default,
+                    output,
+                    nodata,
+                    progress,
+                )
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+        let info = dataset_info(&output);
+        output.close().map_err(|e| e.to_string())?;
+        Ok(info)
+    })
+    .await
+}
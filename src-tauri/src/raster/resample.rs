@@ -0,0 +1,122 @@
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::warp::warp;
+use super::Resampling;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::qa::raster_bounds;
+use crate::{dataset_info, setup_gdal_runtime, DatasetInfo, GdalError};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResampleTarget {
+    // Pixel size in CRS units, square unless `y` is given
+    Resolution { x: f64, y: Option<f64> },
+    // Output size in pixels, the other dimension keeps the aspect ratio when 0
+    Size { width: usize, height: usize },
+    // Snaps onto the pixel grid (origin, resolution and CRS) of another raster
+    MatchRaster { path: String },
+}
+
+fn target_args(source: &Dataset, target: &ResampleTarget) -> Result<Vec<String>, GdalError> {
+    let args = match target {
+        ResampleTarget::Resolution { x, y } => {
+            let y = y.unwrap_or(*x);
+            if *x <= 0.0 || y <= 0.0 {
+                return Err(GdalError::InvalidArgument(
+                    "Resolution must be positive".to_string(),
+                ));
+            }
+            vec!["-tr".to_string(), x.to_string(), y.to_string()]
+        }
+        ResampleTarget::Size { width, height } => {
+            if *width == 0 && *height == 0 {
+                return Err(GdalError::InvalidArgument(
+                    "Width or height must be set".to_string(),
+                ));
+            }
+            vec!["-ts".to_string(), width.to_string(), height.to_string()]
+        }
+        ResampleTarget::MatchRaster { path } => {
+            if !Path::new(path).exists() {
+                return Err(GdalError::FileNotFound(path.clone()));
+            }
+            let reference = Dataset::open(path)?;
+            let gt = reference.geo_transform()?;
+            if gt[2] != 0.0 || gt[4] != 0.0 {
+                return Err(GdalError::InvalidArgument(
+                    "Reference raster has a rotated grid".to_string(),
+                ));
+            }
+            let reference_srs = reference.spatial_ref()?;
+
+            let bounds = raster_bounds(source)
+                .ok_or_else(|| {
+                    GdalError::InvalidArgument("Source raster is not georeferenced".to_string())
+                })?
+                .transform(&source.spatial_ref()?, &reference_srs)?;
+
+            // Expand the source footprint outwards to whole reference pixels
+            let (res_x, res_y) = (gt[1], gt[5].abs());
+            let min_x = gt[0] + ((bounds.min_x - gt[0]) / res_x).floor() * res_x;
+            let max_x = gt[0] + ((bounds.max_x - gt[0]) / res_x).ceil() * res_x;
+            let max_y = gt[3] - ((gt[3] - bounds.max_y) / res_y).floor() * res_y;
+            let min_y = gt[3] - ((gt[3] - bounds.min_y) / res_y).ceil() * res_y;
+
+            vec![
+                "-t_srs".to_string(),
+                reference_srs.to_wkt()?,
+                "-tr".to_string(),
+                res_x.to_string(),
+                res_y.to_string(),
+                "-te".to_string(),
+                min_x.to_string(),
+                min_y.to_string(),
+                max_x.to_string(),
+                max_y.to_string(),
+            ]
+        }
+    };
+    Ok(args)
+}
+
+#[tauri::command]
+pub async fn resample_raster(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    target: ResampleTarget,
+    method: Option<Resampling>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({ "src": src, "dst": dst, "target": target, "method": method });
+    run_job(app.clone(), "resample_raster", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        let method = method.unwrap_or_default();
+
+        let mut args = vec![
+            "-overwrite".to_string(),
+            "-r".to_string(),
+            method.as_gdal_arg().to_string(),
+            "-multi".to_string(),
+            "-wo".to_string(),
+            "NUM_THREADS=ALL_CPUS".to_string(),
+        ];
+        args.extend(target_args(&source, &target).map_err(|e| e.to_string())?);
+
+        let progress = Progress::new(&app, "resample_raster");
+        let output = warp(&[&source], &dst, &args, &progress).map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
@@ -0,0 +1,522 @@
+use gdal::vector::{
+    Feature, FieldDefn, Geometry, LayerAccess, LayerOptions, OGRFieldType, OGRwkbGeometryType,
+};
+use gdal::{Dataset, DriverManager, Metadata};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+use super::translate::translate;
+use crate::crs::transformer;
+use crate::datasets::DatasetRegistry;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+// Tiles written by one call
+const MAX_TILES: usize = 100_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RetileScheme {
+    // Regular tiles of this many pixels; tiles on the right and bottom edges are smaller
+    Grid {
+        tile_width: usize,
+        tile_height: usize,
+        // Pixels shared with the neighbouring tile on each side
+        #[serde(default)]
+        overlap: usize,
+    },
+    // One chunk per polygon of a vector layer, cut to the polygon's bounding box
+    Polygons {
+        path: String,
+        #[serde(default)]
+        layer: Option<String>,
+        // Field naming each chunk for the `{id}` placeholder, the FID when unset
+        #[serde(default)]
+        id_field: Option<String>,
+    },
+}
+
+// Format of the index of the written tiles, as gdaltindex would produce
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TileIndexFormat {
+    #[default]
+    Shapefile,
+    Geojson,
+}
+
+impl TileIndexFormat {
+    fn driver_name(self) -> &'static str {
+        match self {
+            TileIndexFormat::Shapefile => "ESRI Shapefile",
+            TileIndexFormat::Geojson => "GeoJSON",
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            TileIndexFormat::Shapefile => "tile_index.shp",
+            TileIndexFormat::Geojson => "tile_index.geojson",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetiledTile {
+    pub path: String,
+    // 1-based grid position, for grid tiles
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    // Polygon id, for polygon chunks
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetileResult {
+    pub index_path: String,
+    pub tiles: Vec<RetiledTile>,
+}
+
+struct Chunk {
+    row: Option<usize>,
+    column: Option<usize>,
+    id: Option<String>,
+    args: Vec<String>,
+}
+
+fn grid_chunks(
+    size: (usize, usize),
+    tile: (usize, usize),
+    overlap: usize,
+) -> Result<Vec<Chunk>, GdalError> {
+    if tile.0 == 0 || tile.1 == 0 {
+        return Err(GdalError::InvalidArgument(
+            "Tile size must be at least 1x1".to_string(),
+        ));
+    }
+    if overlap >= tile.0 || overlap >= tile.1 {
+        return Err(GdalError::InvalidArgument(
+            "Overlap must be smaller than the tile size".to_string(),
+        ));
+    }
+    // Tiles start one tile size apart less the overlap, as in gdal_retile
+    let (step_x, step_y) = (tile.0 - overlap, tile.1 - overlap);
+    let columns = (size.0.saturating_sub(overlap)).div_ceil(step_x).max(1);
+    let rows = (size.1.saturating_sub(overlap)).div_ceil(step_y).max(1);
+    if columns * rows > MAX_TILES {
+        return Err(GdalError::InvalidArgument(format!(
+            "Retiling would write {} tiles, more than the limit of {}",
+            columns * rows,
+            MAX_TILES
+        )));
+    }
+
+    let mut chunks = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let (x, y) = (column * step_x, row * step_y);
+            let width = tile.0.min(size.0 - x);
+            let height = tile.1.min(size.1 - y);
+            chunks.push(Chunk {
+                row: Some(row + 1),
+                column: Some(column + 1),
+                id: None,
+                args: vec![
+                    "-srcwin".to_string(),
+                    x.to_string(),
+                    y.to_string(),
+                    width.to_string(),
+                    height.to_string(),
+                ],
+            });
+        }
+    }
+    Ok(chunks)
+}
+
+fn raster_extent(source: &Dataset) -> Result<Extent, GdalError> {
+    let gt = source.geo_transform()?;
+    if gt[2] != 0.0 || gt[4] != 0.0 {
+        return Err(GdalError::InvalidArgument(
+            "Rotated rasters cannot be cut by polygons".to_string(),
+        ));
+    }
+    let (width, height) = source.raster_size();
+    let (x0, x1) = (gt[0], gt[0] + width as f64 * gt[1]);
+    let (y0, y1) = (gt[3], gt[3] + height as f64 * gt[5]);
+    Ok(Extent {
+        min_x: x0.min(x1),
+        min_y: y0.min(y1),
+        max_x: x0.max(x1),
+        max_y: y0.max(y1),
+    })
+}
+
+// Bounding boxes of the polygons in the raster's CRS, clipped to the raster. Polygons
+// off the raster produce no chunk.
+fn polygon_chunks(
+    source: &Dataset,
+    path: &str,
+    layer: Option<&str>,
+    id_field: Option<&str>,
+) -> Result<Vec<Chunk>, GdalError> {
+    if !Path::new(path).exists() {
+        return Err(GdalError::FileNotFound(path.to_string()));
+    }
+    let bounds = raster_extent(source)?;
+    let polygons = Dataset::open(path)?;
+    let mut layer = match layer {
+        Some(name) => polygons.layer_by_name(name)?,
+        None => polygons.layer(0)?,
+    };
+    let transform = match (layer.spatial_ref(), source.spatial_ref()) {
+        (Some(layer_srs), Ok(raster_srs)) => Some(transformer(&layer_srs, &raster_srs)?),
+        _ => None,
+    };
+    let id_index = id_field
+        .map(|field| layer.defn().field_index(field))
+        .transpose()?;
+
+    let mut chunks = Vec::new();
+    for feature in layer.features() {
+        let Some(geometry) = feature.geometry() else {
+            continue;
+        };
+        let envelope = match &transform {
+            Some(transform) => geometry.transform(transform)?.envelope(),
+            None => geometry.envelope(),
+        };
+        let min_x = envelope.MinX.max(bounds.min_x);
+        let max_x = envelope.MaxX.min(bounds.max_x);
+        let min_y = envelope.MinY.max(bounds.min_y);
+        let max_y = envelope.MaxY.min(bounds.max_y);
+        if min_x >= max_x || min_y >= max_y {
+            continue;
+        }
+        let id = match id_index {
+            Some(index) => feature.field_as_string(index)?,
+            None => None,
+        }
+        .or_else(|| feature.fid().map(|fid| fid.to_string()))
+        .unwrap_or_default();
+        chunks.push(Chunk {
+            row: None,
+            column: None,
+            id: Some(id),
+            args: vec![
+                "-projwin".to_string(),
+                min_x.to_string(),
+                max_y.to_string(),
+                max_x.to_string(),
+                min_y.to_string(),
+            ],
+        });
+        if chunks.len() > MAX_TILES {
+            return Err(GdalError::InvalidArgument(format!(
+                "Retiling would write more than {} tiles",
+                MAX_TILES
+            )));
+        }
+    }
+    Ok(chunks)
+}
+
+// Fills `{name}`, `{row}`, `{column}`, `{index}` and `{id}`; `extension` is added when
+// the pattern has none. Characters that are unsafe in file names are replaced.
+fn tile_file_name(
+    pattern: &str,
+    name: &str,
+    index: usize,
+    chunk: &Chunk,
+    extension: &str,
+) -> String {
+    let text = |value: Option<String>| value.unwrap_or_default();
+    let file_name = pattern
+        .replace("{name}", name)
+        .replace("{row}", &text(chunk.row.map(|row| row.to_string())))
+        .replace(
+            "{column}",
+            &text(chunk.column.map(|column| column.to_string())),
+        )
+        .replace("{index}", &(index + 1).to_string())
+        .replace("{id}", &text(chunk.id.clone()));
+    let file_name: String = file_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    if Path::new(&file_name).extension().is_some() {
+        file_name
+    } else {
+        format!("{}.{}", file_name, extension)
+    }
+}
+
+// File names of every chunk, refusing patterns that would write two tiles to one file
+fn tile_file_names(
+    pattern: &str,
+    source_path: &str,
+    chunks: &[Chunk],
+    extension: &str,
+) -> Result<Vec<String>, GdalError> {
+    let name = Path::new(source_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "tile".to_string());
+    let file_names: Vec<String> = chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| tile_file_name(pattern, &name, index, chunk, extension))
+        .collect();
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = file_names.iter().find(|file| !seen.insert(*file)) {
+        return Err(GdalError::InvalidArgument(format!(
+            "The naming pattern gives several tiles the file name {}",
+            duplicate
+        )));
+    }
+    Ok(file_names)
+}
+
+// Outline of a written tile from its geotransform
+fn footprint(tile: &Dataset) -> Result<Geometry, GdalError> {
+    let gt = tile.geo_transform()?;
+    let (width, height) = tile.raster_size();
+    let corner = |x: f64, y: f64| {
+        format!(
+            "{} {}",
+            gt[0] + x * gt[1] + y * gt[2],
+            gt[3] + x * gt[4] + y * gt[5]
+        )
+    };
+    let (w, h) = (width as f64, height as f64);
+    Ok(Geometry::from_wkt(&format!(
+        "POLYGON (({}, {}, {}, {}, {}))",
+        corner(0.0, 0.0),
+        corner(w, 0.0),
+        corner(w, h),
+        corner(0.0, h),
+        corner(0.0, 0.0)
+    ))?)
+}
+
+fn write_index(
+    path: &Path,
+    format: TileIndexFormat,
+    source: &Dataset,
+    tiles: &[(RetiledTile, Geometry)],
+) -> Result<(), GdalError> {
+    let driver = DriverManager::get_driver_by_name(format.driver_name())?;
+    if path.exists() {
+        // Deleting through the driver also removes the .shx and .dbf
+        driver.delete(path)?;
+    }
+    let srs = source.spatial_ref().ok();
+    let mut output = driver.create_vector_only(path)?;
+    let layer = output.create_layer(LayerOptions {
+        name: "tile_index",
+        srs: srs.as_ref(),
+        ty: OGRwkbGeometryType::wkbPolygon,
+        ..Default::default()
+    })?;
+    FieldDefn::new("location", OGRFieldType::OFTString)?.add_to_layer(&layer)?;
+    FieldDefn::new("row", OGRFieldType::OFTInteger)?.add_to_layer(&layer)?;
+    FieldDefn::new("column", OGRFieldType::OFTInteger)?.add_to_layer(&layer)?;
+    FieldDefn::new("id", OGRFieldType::OFTString)?.add_to_layer(&layer)?;
+
+    for (tile, outline) in tiles {
+        let mut feature = Feature::new(layer.defn())?;
+        feature.set_geometry(outline.clone())?;
+        feature.set_field_string(0, &tile.path)?;
+        if let (Some(row), Some(column)) = (tile.row, tile.column) {
+            feature.set_field_integer(1, row as i32)?;
+            feature.set_field_integer(2, column as i32)?;
+        }
+        if let Some(id) = &tile.id {
+            feature.set_field_string(3, id)?;
+        }
+        feature.create(&layer)?;
+    }
+    output.close()?;
+    Ok(())
+}
+
+// Cuts every chunk out of `source` into `out_dir`, translating with `format_args`, then
+// writes the index of the tiles
+fn write_tiles(
+    source: &Dataset,
+    chunks: Vec<Chunk>,
+    file_names: Vec<String>,
+    out_dir: &str,
+    format_args: &[String],
+    index_format: TileIndexFormat,
+    progress: &Progress,
+) -> Result<RetileResult, GdalError> {
+    fs::create_dir_all(out_dir)?;
+    let total = chunks.len() as f64;
+    let mut tiles = Vec::with_capacity(chunks.len());
+    for (index, (chunk, file_name)) in chunks.into_iter().zip(file_names).enumerate() {
+        progress.set_range(index as f64 / total, (index + 1) as f64 / total);
+        let path = Path::new(out_dir)
+            .join(file_name)
+            .to_string_lossy()
+            .to_string();
+        let mut args = chunk.args;
+        args.extend_from_slice(format_args);
+        let output = translate(source, &path, &args, progress)?;
+        let outline = footprint(&output)?;
+        output.close()?;
+        let tile = RetiledTile {
+            path,
+            row: chunk.row,
+            column: chunk.column,
+            id: chunk.id,
+        };
+        tiles.push((tile, outline));
+    }
+
+    let index_path = Path::new(out_dir).join(index_format.file_name());
+    write_index(&index_path, index_format, source, &tiles)?;
+
+    Ok(RetileResult {
+        index_path: index_path.to_string_lossy().to_string(),
+        tiles: tiles.into_iter().map(|(tile, _)| tile).collect(),
+    })
+}
+
+// Splits a raster file into a grid of `tile_size` pixel tiles overlapping by `overlap`
+// pixels, like gdal_retile. Tiles are written as `format` (a GDAL driver name, GeoTIFF
+// when unset) and named `{name}_{row}_{column}`, with an index of the tiles beside them.
+#[tauri::command]
+pub async fn retile_raster(
+    app: AppHandle,
+    src: String,
+    out_dir: String,
+    tile_size: (usize, usize),
+    overlap: Option<usize>,
+    format: Option<String>,
+    index_format: Option<TileIndexFormat>,
+) -> Result<RetileResult, String> {
+    let params = json!({
+        "src": src,
+        "out_dir": out_dir,
+        "tile_size": tile_size,
+        "overlap": overlap,
+        "format": format,
+        "index_format": index_format,
+    });
+    run_job(app.clone(), "retile_raster", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if source.raster_count() == 0 {
+            return Err("Only raster datasets can be retiled".to_string());
+        }
+
+        let format = format.unwrap_or_else(|| "GTiff".to_string());
+        let driver = DriverManager::get_driver_by_name(&format)
+            .ok()
+            .filter(|driver| driver.metadata_item("DCAP_RASTER", "").is_some())
+            .ok_or_else(|| format!("Unknown raster format: {}", format))?;
+        let extension = driver
+            .metadata_item("DMD_EXTENSION", "")
+            .filter(|extension| !extension.is_empty())
+            .unwrap_or_else(|| "tif".to_string());
+
+        let chunks = grid_chunks(source.raster_size(), tile_size, overlap.unwrap_or(0))
+            .map_err(|e| e.to_string())?;
+        let file_names = tile_file_names("{name}_{row}_{column}", &src, &chunks, &extension)
+            .map_err(|e| e.to_string())?;
+        let progress = Progress::new(&app, "retile_raster");
+        write_tiles(
+            &source,
+            chunks,
+            file_names,
+            &out_dir,
+            &["-of".to_string(), format],
+            index_format.unwrap_or_default(),
+            &progress,
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
+
+// Splits an open raster into regular tiles or per-polygon chunks, writing GeoTIFF tiles
+// and `tile_index.shp` with the outline and file of every tile. The naming pattern may
+// use `{name}` (the source file name), `{row}`, `{column}`, `{index}` and `{id}`; it
+// defaults to `{name}_{row}_{column}` for grids and `{name}_{id}` for polygons.
+#[tauri::command]
+pub async fn retile_dataset(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    tiling: RetileScheme,
+    out_dir: String,
+    naming_pattern: Option<String>,
+) -> Result<RetileResult, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        if open.dataset.raster_count() == 0 {
+            return Err("Only raster datasets can be retiled".to_string());
+        }
+        let chunks = match &tiling {
+            RetileScheme::Grid {
+                tile_width,
+                tile_height,
+                overlap,
+            } => grid_chunks(
+                open.dataset.raster_size(),
+                (*tile_width, *tile_height),
+                *overlap,
+            ),
+            RetileScheme::Polygons {
+                path,
+                layer,
+                id_field,
+            } => polygon_chunks(&open.dataset, path, layer.as_deref(), id_field.as_deref()),
+        }
+        .map_err(|e| e.to_string())?;
+        if chunks.is_empty() {
+            return Err("No polygon overlaps the raster".to_string());
+        }
+
+        let pattern = naming_pattern.unwrap_or_else(|| {
+            match tiling {
+                RetileScheme::Grid { .. } => "{name}_{row}_{column}",
+                RetileScheme::Polygons { .. } => "{name}_{id}",
+            }
+            .to_string()
+        });
+        let file_names =
+            tile_file_names(&pattern, &open.path, &chunks, "tif").map_err(|e| e.to_string())?;
+        let progress = Progress::new(&app, "retile_dataset");
+        write_tiles(
+            &open.dataset,
+            chunks,
+            file_names,
+            &out_dir,
+            &[],
+            TileIndexFormat::Shapefile,
+            &progress,
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,111 @@
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use serde_json::json;
+use std::path::Path;
+use std::ptr;
+use tauri::AppHandle;
+
+use super::translate::translate;
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, GdalError};
+
+// Replaces regions smaller than `threshold` pixels with their largest neighbour, in place
+fn sieve(
+    dataset: &Dataset,
+    threshold: u32,
+    connectedness: u8,
+    progress: &Progress,
+) -> Result<(), GdalError> {
+    record_command_line(
+        "gdal_sieve.py",
+        &[
+            "-st".to_string(),
+            threshold.to_string(),
+            format!("-{}", connectedness),
+            dataset_name(dataset),
+        ],
+    );
+    let band = dataset.rasterband(1)?;
+    let rv = unsafe {
+        let handle = band.c_rasterband();
+        // Nodata pixels stay out of the regions, like gdal_sieve without -nomask
+        let mask = gdal_sys::GDALGetMaskBand(handle);
+        gdal_sys::GDALSieveFilter(
+            handle,
+            mask,
+            handle,
+            threshold as i32,
+            connectedness as i32,
+            ptr::null_mut(),
+            Some(gdal_progress),
+            progress.as_arg(),
+        )
+    };
+    if rv != gdal_sys::CPLErr::CE_None {
+        return Err(ffi::last_error("GDALSieveFilter"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn sieve_raster(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    threshold: u32,
+    connectedness: Option<u8>,
+    band: Option<usize>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "threshold": threshold,
+        "connectedness": connectedness,
+        "band": band,
+    });
+    run_job(app.clone(), "sieve_raster", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let connectedness = connectedness.unwrap_or(4);
+        if connectedness != 4 && connectedness != 8 {
+            return Err("Connectedness must be 4 or 8".to_string());
+        }
+        if threshold < 2 {
+            return Err("Threshold must be at least 2 pixels".to_string());
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        let band = band.unwrap_or(1);
+        if band == 0 || band > source.raster_count() {
+            return Err(format!("Band {} does not exist", band));
+        }
+
+        // The classified band is copied out first and sieved in the copy
+        let progress = Progress::new(&app, "sieve_raster");
+        progress.set_range(0.0, 0.2);
+        let args = vec!["-b".to_string(), band.to_string()];
+        let copy = translate(&source, &dst, &args, &progress).map_err(|e| e.to_string())?;
+        copy.close().map_err(|e| e.to_string())?;
+
+        let output = Dataset::open_ex(
+            &dst,
+            DatasetOptions {
+                open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| format!("Output format cannot be updated in place: {}", e))?;
+
+        progress.set_range(0.2, 1.0);
+        sieve(&output, threshold, connectedness, &progress).map_err(|e| e.to_string())?;
+
+        let info = dataset_info(&output);
+        output.close().map_err(|e| e.to_string())?;
+        Ok(info)
+    })
+    .await
+}
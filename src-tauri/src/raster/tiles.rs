@@ -0,0 +1,332 @@
+use gdal::cpl::CslStringList;
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::sql::Dialect;
+use gdal::{Dataset, DriverManager};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::render::raster::{render_raster, RasterStyle};
+use crate::render::tiles::{tile_viewport, MAX_ZOOM, MERCATOR_HALF_WIDTH, TILE_SIZE};
+use crate::render::RenderTicket;
+use crate::{setup_gdal_runtime, Extent, GdalError};
+
+// Refuse pyramids beyond this many tiles, usually a max zoom picked far too deep
+const MAX_TILES: u64 = 2_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TileFormat {
+    // Single SQLite file following the MBTiles 1.3 spec
+    Mbtiles,
+    // `{z}/{x}/{y}.png` files under the destination directory
+    Directory,
+}
+
+// Row numbering of directory trees. MBTiles always numbers rows from the bottom.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TilingScheme {
+    // Rows from the top, as used by OpenStreetMap, Leaflet and MapLibre
+    #[default]
+    Xyz,
+    // Rows from the bottom
+    Tms,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TileExport {
+    pub path: String,
+    pub format: TileFormat,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    // Tiles written; fully transparent tiles are skipped
+    pub tile_count: u64,
+    // Longitude/latitude bounds of the data
+    pub bounds: Extent,
+}
+
+// Tile columns and rows of `extent` (EPSG:3857) at zoom `z`, inclusive
+fn tile_range(extent: &Extent, z: u8) -> ((u32, u32), (u32, u32)) {
+    let count = 1u64 << z;
+    let size = 2.0 * MERCATOR_HALF_WIDTH / count as f64;
+    let index = |value: f64| (value.floor().max(0.0) as u64).min(count - 1) as u32;
+    let columns = (
+        index((extent.min_x + MERCATOR_HALF_WIDTH) / size),
+        index((extent.max_x + MERCATOR_HALF_WIDTH) / size - 1e-9),
+    );
+    let rows = (
+        index((MERCATOR_HALF_WIDTH - extent.max_y) / size),
+        index((MERCATOR_HALF_WIDTH - extent.min_y) / size - 1e-9),
+    );
+    (columns, rows)
+}
+
+// Deepest zoom whose pixels are still no smaller than the source pixels, and shallowest
+// zoom at which the whole extent fits in one tile
+fn default_zooms(extent: &Extent, size: (usize, usize)) -> (u8, u8) {
+    let world = 2.0 * MERCATOR_HALF_WIDTH;
+    let resolution = ((extent.max_x - extent.min_x) / size.0 as f64)
+        .min((extent.max_y - extent.min_y) / size.1 as f64);
+    let max_zoom = (world / (TILE_SIZE as f64 * resolution))
+        .log2()
+        .ceil()
+        .clamp(0.0, MAX_ZOOM as f64) as u8;
+    let span = (extent.max_x - extent.min_x).max(extent.max_y - extent.min_y);
+    let min_zoom = (world / span).log2().floor().clamp(0.0, max_zoom as f64) as u8;
+    (min_zoom, max_zoom)
+}
+
+// Tile destination, either a directory tree or an MBTiles database
+enum TileSink {
+    Directory(String, TilingScheme),
+    Mbtiles(Dataset),
+}
+
+impl TileSink {
+    fn create(dst: &str, format: TileFormat, scheme: TilingScheme) -> Result<Self, GdalError> {
+        match format {
+            TileFormat::Directory => {
+                fs::create_dir_all(dst)?;
+                Ok(TileSink::Directory(dst.to_string(), scheme))
+            }
+            TileFormat::Mbtiles => {
+                if Path::new(dst).exists() {
+                    fs::remove_file(dst)?;
+                }
+                // A plain SQLite file, without the OGR metadata tables
+                let driver = DriverManager::get_driver_by_name("SQLite")?;
+                let mut options = CslStringList::new();
+                options.set_name_value("METADATA", "NO")?;
+                let dataset =
+                    driver.create_with_band_type_with_options::<u8, _>(dst, 0, 0, 0, &options)?;
+                for statement in [
+                    "CREATE TABLE metadata (name TEXT, value TEXT)",
+                    "CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB)",
+                    "CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row)",
+                    "BEGIN",
+                ] {
+                    dataset.execute_sql(statement, None, Dialect::DEFAULT)?;
+                }
+                Ok(TileSink::Mbtiles(dataset))
+            }
+        }
+    }
+
+    fn write(&self, (z, x, y): (u8, u32, u32), png: &[u8]) -> Result<(), GdalError> {
+        match self {
+            TileSink::Directory(dst, scheme) => {
+                let row = match scheme {
+                    TilingScheme::Xyz => y,
+                    TilingScheme::Tms => (1u32 << z) - 1 - y,
+                };
+                let dir = Path::new(dst).join(z.to_string()).join(x.to_string());
+                fs::create_dir_all(&dir)?;
+                fs::write(dir.join(format!("{}.png", row)), png)?;
+            }
+            TileSink::Mbtiles(dataset) => {
+                let mut hex = String::with_capacity(png.len() * 2);
+                for byte in png {
+                    let _ = write!(hex, "{:02X}", byte);
+                }
+                let sql = format!(
+                    "INSERT INTO tiles VALUES ({}, {}, {}, X'{}')",
+                    z,
+                    x,
+                    (1u32 << z) - 1 - y,
+                    hex
+                );
+                dataset.execute_sql(sql, None, Dialect::DEFAULT)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, metadata: &[(&str, String)]) -> Result<(), GdalError> {
+        if let TileSink::Mbtiles(dataset) = self {
+            for (name, value) in metadata {
+                let sql = format!(
+                    "INSERT INTO metadata VALUES ('{}', '{}')",
+                    name,
+                    value.replace('\'', "''")
+                );
+                dataset.execute_sql(sql, None, Dialect::DEFAULT)?;
+            }
+            dataset.execute_sql("COMMIT", None, Dialect::DEFAULT)?;
+            dataset.close()?;
+        }
+        Ok(())
+    }
+}
+
+// Renders a raster into a web mercator tile pyramid, like gdal2tiles: an MBTiles file
+// or a `{z}/{x}/{y}.png` directory tree for web maps. Tiles are drawn with the same
+// renderer as the map, so `style` picks bands and the stretch. `zoom_range` defaults
+// from the one-tile overview down to the source resolution. The format defaults to
+// MBTiles for `.mbtiles` destinations and a directory otherwise.
+#[tauri::command]
+pub async fn export_tiles(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    format: Option<TileFormat>,
+    zoom_range: Option<(u8, u8)>,
+    tiling_scheme: Option<TilingScheme>,
+    style: Option<RasterStyle>,
+) -> Result<TileExport, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "format": format,
+        "zoom_range": zoom_range,
+        "tiling_scheme": tiling_scheme,
+        "style": style,
+    });
+    run_job(app.clone(), "export_tiles", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if source.raster_count() == 0 {
+            return Err("Only raster datasets can be exported as tiles".to_string());
+        }
+        let srs = source
+            .spatial_ref()
+            .map_err(|_| "The raster has no CRS".to_string())?;
+        let gt = source.geo_transform().map_err(|e| e.to_string())?;
+        let (width, height) = source.raster_size();
+        let corner = |col: f64, row: f64| {
+            (
+                gt[0] + col * gt[1] + row * gt[2],
+                gt[3] + col * gt[4] + row * gt[5],
+            )
+        };
+        let (w, h) = (width as f64, height as f64);
+        let native = [
+            corner(0.0, 0.0),
+            corner(w, 0.0),
+            corner(w, h),
+            corner(0.0, h),
+        ]
+        .iter()
+        .fold(
+            Extent {
+                min_x: f64::INFINITY,
+                min_y: f64::INFINITY,
+                max_x: f64::NEG_INFINITY,
+                max_y: f64::NEG_INFINITY,
+            },
+            |e, (x, y)| Extent {
+                min_x: e.min_x.min(*x),
+                min_y: e.min_y.min(*y),
+                max_x: e.max_x.max(*x),
+                max_y: e.max_y.max(*y),
+            },
+        );
+        let mercator = SpatialRef::from_epsg(3857).map_err(|e| e.to_string())?;
+        let wgs84 = SpatialRef::from_epsg(4326).map_err(|e| e.to_string())?;
+        let extent = native
+            .transform(&srs, &mercator)
+            .map_err(|e| e.to_string())?;
+        let extent = Extent {
+            min_x: extent.min_x.max(-MERCATOR_HALF_WIDTH),
+            min_y: extent.min_y.max(-MERCATOR_HALF_WIDTH),
+            max_x: extent.max_x.min(MERCATOR_HALF_WIDTH),
+            max_y: extent.max_y.min(MERCATOR_HALF_WIDTH),
+        };
+        let bounds = extent
+            .transform(&mercator, &wgs84)
+            .map_err(|e| e.to_string())?;
+
+        let (min_zoom, max_zoom) =
+            zoom_range.unwrap_or_else(|| default_zooms(&extent, (width, height)));
+        if min_zoom > max_zoom || max_zoom > MAX_ZOOM {
+            return Err(format!(
+                "Zoom range must satisfy 0 <= min <= max <= {}",
+                MAX_ZOOM
+            ));
+        }
+        let ranges: Vec<_> = (min_zoom..=max_zoom)
+            .map(|z| (z, tile_range(&extent, z)))
+            .collect();
+        let total: u64 = ranges
+            .iter()
+            .map(|(_, ((x0, x1), (y0, y1)))| (x1 - x0 + 1) as u64 * (y1 - y0 + 1) as u64)
+            .sum();
+        if total > MAX_TILES {
+            return Err(format!(
+                "Zoom levels {} to {} need {} tiles, more than the limit of {}",
+                min_zoom, max_zoom, total, MAX_TILES
+            ));
+        }
+
+        let format = format.unwrap_or(if dst.to_ascii_lowercase().ends_with(".mbtiles") {
+            TileFormat::Mbtiles
+        } else {
+            TileFormat::Directory
+        });
+        let sink = TileSink::create(&dst, format, tiling_scheme.unwrap_or_default())
+            .map_err(|e| e.to_string())?;
+        let style = style.unwrap_or_default();
+        let progress = Progress::new(&app, "export_tiles");
+        let ticket = RenderTicket::detached();
+
+        let (mut done, mut written) = (0u64, 0u64);
+        for (z, ((x0, x1), (y0, y1))) in ranges {
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    let viewport = tile_viewport(z, x, y, TILE_SIZE).map_err(|e| e.to_string())?;
+                    let canvas = render_raster(&source, &viewport, &style, &ticket)
+                        .map_err(|e| e.to_string())?;
+                    // Tiles at the edge of the data can be entirely outside it
+                    if canvas.pixels.chunks_exact(4).any(|pixel| pixel[3] != 0) {
+                        let png = canvas.encode_png().map_err(|e| e.to_string())?;
+                        sink.write((z, x, y), &png).map_err(|e| e.to_string())?;
+                        written += 1;
+                    }
+                    done += 1;
+                    progress.report(done as f64 / total as f64, Some(format!("Zoom {}", z)));
+                }
+            }
+        }
+
+        let name = Path::new(&src)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        sink.finish(&[
+            ("name", name),
+            ("format", "png".to_string()),
+            ("type", "overlay".to_string()),
+            ("version", "1.1".to_string()),
+            ("minzoom", min_zoom.to_string()),
+            ("maxzoom", max_zoom.to_string()),
+            (
+                "bounds",
+                format!(
+                    "{},{},{},{}",
+                    bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y
+                ),
+            ),
+        ])
+        .map_err(|e| e.to_string())?;
+
+        Ok(TileExport {
+            path: dst,
+            format,
+            min_zoom,
+            max_zoom,
+            tile_count: written,
+            bounds,
+        })
+    })
+    .await
+}
@@ -0,0 +1,541 @@
+use gdal::{Dataset, GeoTransformEx, Metadata};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::crs::{parse_srs, transformer};
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+// Metadata items holding the acquisition time, most specific first
+const TIME_METADATA: &[(&str, &str)] = &[
+    ("ACQUISITIONDATETIME", "IMAGERY"),
+    ("ACQUISITION_DATE", ""),
+    ("DATE_ACQUIRED", ""),
+    ("TIMESTAMP", ""),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackInput {
+    pub path: String,
+    // ISO 8601 date or date-time; read from the raster's metadata or its file name when
+    // unset
+    #[serde(default)]
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampSource {
+    Given,
+    Metadata,
+    FileName,
+    // TIFFTAG_DATETIME, which is often when the file was written rather than acquired
+    FileDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scene {
+    // Position in the stack, which is ordered by time
+    pub index: usize,
+    pub path: String,
+    // Normalised to UTC as `YYYY-MM-DDTHH:MM:SSZ`, applying any time zone offset
+    pub timestamp: String,
+    pub source: TimestampSource,
+    #[serde(skip)]
+    seconds: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneMatch {
+    #[default]
+    Nearest,
+    // Latest scene at or before the time
+    Before,
+    // Earliest scene at or after the time
+    After,
+    Exact,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeSeriesStackInfo {
+    pub handle: u64,
+    pub scenes: Vec<Scene>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeSeriesValue {
+    pub timestamp: String,
+    // None where the point is outside the scene or the pixel is nodata
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PixelTimeSeries {
+    pub band: usize,
+    pub values: Vec<TimeSeriesValue>,
+}
+
+pub(crate) struct TimeSeriesStack {
+    scenes: Vec<Scene>,
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Proleptic Gregorian year, month and day of a count of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Seconds since the epoch and the normalised timestamp, None for invalid dates
+fn timestamp(fields: [i64; 6]) -> Option<(i64, String)> {
+    let [year, month, day, hour, minute, second] = fields;
+    let valid = (1..=12).contains(&month)
+        && (1..=days_in_month(year, month)).contains(&day)
+        && (0..24).contains(&hour)
+        && (0..60).contains(&minute)
+        && (0..=60).contains(&second);
+    if !valid {
+        return None;
+    }
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    let text = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    );
+    Some((seconds, text))
+}
+
+// Seconds east of UTC of a zone designator: `Z`, `+02:00`, `-0500` or `+02`
+fn parse_offset(zone: &str) -> Option<i64> {
+    let zone = zone.trim();
+    if zone.eq_ignore_ascii_case("z") {
+        return Some(0);
+    }
+    let sign = match zone.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = zone[1..].chars().filter(|&c| c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i64>().ok()?, 0),
+        4 => (digits[..2].parse::<i64>().ok()?, digits[2..].parse().ok()?),
+        _ => return None,
+    };
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+// Reads dates such as `2024-05-17`, `2024-05-17T10:30:00+02:00`, `2024:05:17 10:30:00`
+// (the TIFF form) or `20240517T103000Z` from the digit groups of `text`. Times with a
+// UTC offset are converted to UTC; an offset that cannot be read rejects the timestamp.
+fn parse_timestamp(text: &str) -> Option<(i64, String)> {
+    let text = text.trim();
+    // The zone can only follow the time of day, as dates themselves contain `-`
+    let (text, offset) = match text.find(['T', 't', ' ']) {
+        Some(time) => match text[time + 1..].find(['+', '-', 'Z', 'z']) {
+            Some(zone) => {
+                let zone = time + 1 + zone;
+                (&text[..zone], parse_offset(&text[zone..])?)
+            }
+            None => (text, 0),
+        },
+        None => (text, 0),
+    };
+
+    let groups: Vec<&str> = text
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|group| !group.is_empty())
+        .collect();
+    let mut fields = [0i64; 6];
+    match groups.first()?.len() {
+        8 => {
+            let date = groups[0];
+            fields[0] = date[..4].parse().ok()?;
+            fields[1] = date[4..6].parse().ok()?;
+            fields[2] = date[6..].parse().ok()?;
+            if let Some(time) = groups
+                .get(1)
+                .filter(|time| time.len() == 6 || time.len() == 4)
+            {
+                fields[3] = time[..2].parse().ok()?;
+                fields[4] = time[2..4].parse().ok()?;
+                if time.len() == 6 {
+                    fields[5] = time[4..].parse().ok()?;
+                }
+            }
+        }
+        4 => {
+            // Year, month and day, then optional hours, minutes and seconds; fractions
+            // of a second are ignored
+            for (field, group) in fields.iter_mut().zip(groups.iter().take(6)) {
+                *field = group.parse().ok()?;
+            }
+            if groups.len() < 3 {
+                return None;
+            }
+        }
+        _ => return None,
+    }
+    let (seconds, normalised) = timestamp(fields)?;
+    if offset == 0 {
+        return Some((seconds, normalised));
+    }
+    let seconds = seconds - offset;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+    timestamp([year, month, day, time / 3600, time % 3600 / 60, time % 60])
+}
+
+// First date in a file name: `20240517` (optionally followed by `T103000` or
+// `_103000`), `2024-05-17` / `2024_05_17`, or a year and day of year such as the
+// `2024138` in MODIS names. Years outside 1900-2100 are not taken as dates.
+fn timestamp_from_name(path: &str) -> Option<(i64, String)> {
+    let name = Path::new(path).file_stem()?.to_string_lossy().to_string();
+    let bytes = name.as_bytes();
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (index, byte) in bytes.iter().chain(std::iter::once(&b' ')).enumerate() {
+        match (byte.is_ascii_digit(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(first)) => {
+                runs.push((first, index));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    let digits = |(first, end): (usize, usize)| &name[first..end];
+    let plausible = |year: &str| {
+        year.parse::<i64>()
+            .is_ok_and(|year| (1900..=2100).contains(&year))
+    };
+
+    for (position, &run) in runs.iter().enumerate() {
+        let text = digits(run);
+        let next = runs.get(position + 1).copied();
+        // The next run directly after a one character separator
+        let joined = |separators: &[u8]| {
+            next.filter(|&(first, _)| first == run.1 + 1 && separators.contains(&bytes[run.1]))
+        };
+        match text.len() {
+            8 if plausible(&text[..4]) => {
+                let time = joined(b"T_").map(digits).filter(|time| time.len() == 6);
+                let candidate = match time {
+                    Some(time) => format!("{}T{}", text, time),
+                    None => text.to_string(),
+                };
+                if let Some(parsed) = parse_timestamp(&candidate) {
+                    return Some(parsed);
+                }
+            }
+            7 if plausible(&text[..4]) => {
+                let year: i64 = text[..4].parse().ok()?;
+                let day_of_year: i64 = text[4..].parse().ok()?;
+                let days = if days_in_month(year, 2) == 29 {
+                    366
+                } else {
+                    365
+                };
+                if (1..=days).contains(&day_of_year) {
+                    let (mut month, mut day) = (1, day_of_year);
+                    while day > days_in_month(year, month) {
+                        day -= days_in_month(year, month);
+                        month += 1;
+                    }
+                    return timestamp([year, month, day, 0, 0, 0]);
+                }
+            }
+            4 if plausible(text) => {
+                let Some(month) = joined(b"-_") else {
+                    continue;
+                };
+                let day = runs
+                    .get(position + 2)
+                    .copied()
+                    .filter(|&(first, _)| first == month.1 + 1 && bytes[month.1] == bytes[run.1]);
+                if let Some(day) = day.filter(|_| digits(month).len() == 2) {
+                    let candidate = format!("{}-{}-{}", text, digits(month), digits(day));
+                    if let Some(parsed) = parse_timestamp(&candidate) {
+                        return Some(parsed);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn scene_time(input: &StackInput) -> Result<(i64, String, TimestampSource), GdalError> {
+    if let Some(text) = &input.timestamp {
+        let (seconds, timestamp) = parse_timestamp(text).ok_or_else(|| {
+            GdalError::InvalidArgument(format!("Unrecognised timestamp '{}'", text))
+        })?;
+        return Ok((seconds, timestamp, TimestampSource::Given));
+    }
+
+    let dataset = Dataset::open(&input.path)?;
+    if dataset.raster_count() == 0 {
+        return Err(GdalError::InvalidArgument(format!(
+            "{} is not a raster",
+            input.path
+        )));
+    }
+    let from_metadata = TIME_METADATA.iter().find_map(|(key, domain)| {
+        dataset
+            .metadata_item(key, domain)
+            .and_then(|value| parse_timestamp(&value))
+    });
+    if let Some((seconds, timestamp)) = from_metadata {
+        return Ok((seconds, timestamp, TimestampSource::Metadata));
+    }
+    if let Some((seconds, timestamp)) = timestamp_from_name(&input.path) {
+        return Ok((seconds, timestamp, TimestampSource::FileName));
+    }
+    if let Some((seconds, timestamp)) = dataset
+        .metadata_item("TIFFTAG_DATETIME", "")
+        .and_then(|value| parse_timestamp(&value))
+    {
+        return Ok((seconds, timestamp, TimestampSource::FileDate));
+    }
+    Err(GdalError::InvalidArgument(format!(
+        "No timestamp found for {}, pass one with the path",
+        input.path
+    )))
+}
+
+impl TimeSeriesStack {
+    // Scenes ordered by time
+    pub(crate) fn scenes(&self) -> &[Scene] {
+        &self.scenes
+    }
+
+    fn build(inputs: &[StackInput]) -> Result<Self, GdalError> {
+        if inputs.is_empty() {
+            return Err(GdalError::InvalidArgument(
+                "A time series stack needs at least one raster".to_string(),
+            ));
+        }
+        let mut scenes = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            if !Path::new(&input.path).exists() {
+                return Err(GdalError::InvalidArgument(format!(
+                    "File not found: {}",
+                    input.path
+                )));
+            }
+            let (seconds, timestamp, source) = scene_time(input)?;
+            scenes.push(Scene {
+                index: 0,
+                path: input.path.clone(),
+                timestamp,
+                source,
+                seconds,
+            });
+        }
+        // Stable, so scenes sharing a time keep the order they were given in
+        scenes.sort_by_key(|scene| scene.seconds);
+        for (index, scene) in scenes.iter_mut().enumerate() {
+            scene.index = index;
+        }
+        Ok(Self { scenes })
+    }
+
+    fn scene_at(&self, seconds: i64, mode: SceneMatch) -> Option<&Scene> {
+        let scenes = self.scenes.iter();
+        match mode {
+            SceneMatch::Exact => scenes.clone().find(|scene| scene.seconds == seconds),
+            SceneMatch::Before => scenes.rev().find(|scene| scene.seconds <= seconds),
+            SceneMatch::After => scenes.clone().find(|scene| scene.seconds >= seconds),
+            SceneMatch::Nearest => scenes.min_by_key(|scene| (scene.seconds - seconds).abs()),
+        }
+    }
+}
+
+// Value of `band` at a point in the raster's CRS; None outside the raster or on nodata
+fn pixel_value(
+    dataset: &Dataset,
+    band: usize,
+    (x, y): (f64, f64),
+) -> Result<Option<f64>, GdalError> {
+    let inverse = dataset.geo_transform()?.invert()?;
+    let (column, row) = inverse.apply(x, y);
+    let (width, height) = dataset.raster_size();
+    if column < 0.0 || row < 0.0 || column >= width as f64 || row >= height as f64 {
+        return Ok(None);
+    }
+    let raster_band = dataset.rasterband(band)?;
+    let value = raster_band
+        .read_as::<f64>((column as isize, row as isize), (1, 1), (1, 1), None)?
+        .data()[0];
+    let nodata = raster_band.no_data_value();
+    Ok(Some(value).filter(|value| !value.is_nan() && Some(*value) != nodata))
+}
+
+// Time series stacks kept between queries, addressed by handle
+#[derive(Default)]
+pub struct TimeSeriesStore {
+    next_handle: Mutex<u64>,
+    stacks: Mutex<HashMap<u64, Arc<TimeSeriesStack>>>,
+}
+
+impl TimeSeriesStore {
+    fn insert(&self, stack: TimeSeriesStack) -> u64 {
+        let handle = {
+            let mut next_handle = self.next_handle.lock().unwrap();
+            *next_handle += 1;
+            *next_handle
+        };
+        self.stacks.lock().unwrap().insert(handle, Arc::new(stack));
+        handle
+    }
+
+    pub(crate) fn get(&self, handle: u64) -> Result<Arc<TimeSeriesStack>, GdalError> {
+        self.stacks
+            .lock()
+            .unwrap()
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| {
+                GdalError::InvalidArgument(format!("Unknown time series stack {}", handle))
+            })
+    }
+
+    fn remove(&self, handle: u64) -> bool {
+        self.stacks.lock().unwrap().remove(&handle).is_some()
+    }
+}
+
+// Registers rasters of the same area taken at different times, e.g. a year of Sentinel-2
+// scenes, ordered by their timestamps. Each scene keeps its own grid and CRS.
+#[tauri::command]
+pub async fn create_timeseries_stack(
+    stacks: State<'_, TimeSeriesStore>,
+    inputs: Vec<StackInput>,
+) -> Result<TimeSeriesStackInfo, String> {
+    let stack = run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        TimeSeriesStack::build(&inputs).map_err(|e| e.to_string())
+    })
+    .await?;
+
+    let scenes = stack.scenes.clone();
+    Ok(TimeSeriesStackInfo {
+        handle: stacks.insert(stack),
+        scenes,
+    })
+}
+
+// Values of one band at a point through every scene, oldest first. The point is in
+// `crs`, or in each scene's own CRS when unset.
+#[tauri::command]
+pub async fn get_pixel_timeseries(
+    stacks: State<'_, TimeSeriesStore>,
+    handle: u64,
+    x: f64,
+    y: f64,
+    crs: Option<String>,
+    band: Option<usize>,
+) -> Result<PixelTimeSeries, String> {
+    let stack = stacks.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let band = band.unwrap_or(1);
+        let query_srs = crs
+            .as_deref()
+            .map(parse_srs)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let mut values = Vec::with_capacity(stack.scenes.len());
+        for scene in &stack.scenes {
+            let dataset = Dataset::open(&scene.path).map_err(|e| e.to_string())?;
+            if band == 0 || band > dataset.raster_count() {
+                return Err(format!("{} has no band {}", scene.path, band));
+            }
+            let mut point = (x, y);
+            if let (Some(from), Ok(to)) = (&query_srs, dataset.spatial_ref()) {
+                let transform = transformer(from, &to).map_err(|e| e.to_string())?;
+                let (mut xs, mut ys) = ([x], [y]);
+                transform
+                    .transform_coords(&mut xs, &mut ys, &mut [])
+                    .map_err(|e| e.to_string())?;
+                point = (xs[0], ys[0]);
+            }
+            values.push(TimeSeriesValue {
+                timestamp: scene.timestamp.clone(),
+                value: pixel_value(&dataset, band, point).map_err(|e| e.to_string())?,
+            });
+        }
+        Ok(PixelTimeSeries { band, values })
+    })
+    .await
+}
+
+// Scene of the stack closest to `timestamp`, or the latest before / earliest after it,
+// for opening with `open_dataset`
+#[tauri::command]
+pub fn get_scene_at(
+    stacks: State<'_, TimeSeriesStore>,
+    handle: u64,
+    timestamp: String,
+    mode: Option<SceneMatch>,
+) -> Result<Scene, String> {
+    let stack = stacks.get(handle).map_err(|e| e.to_string())?;
+    let (seconds, _) = parse_timestamp(&timestamp)
+        .ok_or_else(|| format!("Unrecognised timestamp '{}'", timestamp))?;
+    stack
+        .scene_at(seconds, mode.unwrap_or_default())
+        .cloned()
+        .ok_or_else(|| format!("No scene matches {}", timestamp))
+}
+
+// Frees a time series stack; false when the handle was unknown
+#[tauri::command]
+pub fn close_timeseries_stack(stacks: State<'_, TimeSeriesStore>, handle: u64) -> bool {
+    stacks.remove(handle)
+}
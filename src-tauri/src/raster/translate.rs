@@ -0,0 +1,43 @@
+use gdal::Dataset;
+use std::ptr;
+
+use crate::jobs::{dataset_name, record_command_line};
+use crate::progress::{gdal_progress, Progress};
+use crate::{ffi, GdalError};
+
+// Runs GDALTranslate (the library form of gdal_translate) from `source` into `dst`
+pub(crate) fn translate(
+    source: &Dataset,
+    dst: &str,
+    args: &[String],
+    progress: &Progress,
+) -> Result<Dataset, GdalError> {
+    record_command_line(
+        "gdal_translate",
+        &[args, &[dataset_name(source), dst.to_string()]].concat(),
+    );
+    let argv = ffi::arg_list(args)?;
+    let c_dst = ffi::c_string(dst)?;
+
+    unsafe {
+        let options = gdal_sys::GDALTranslateOptionsNew(argv.as_ptr(), ptr::null_mut());
+        if options.is_null() {
+            return Err(ffi::last_error("GDALTranslateOptionsNew"));
+        }
+        gdal_sys::GDALTranslateOptionsSetProgress(options, Some(gdal_progress), progress.as_arg());
+
+        let mut usage_error = 0;
+        let result = gdal_sys::GDALTranslate(
+            c_dst.as_ptr(),
+            source.c_dataset(),
+            options,
+            &mut usage_error,
+        );
+        gdal_sys::GDALTranslateOptionsFree(options);
+
+        if result.is_null() {
+            return Err(ffi::last_error("GDALTranslate"));
+        }
+        Ok(Dataset::from_c_dataset(result))
+    }
+}
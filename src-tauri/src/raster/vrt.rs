@@ -0,0 +1,186 @@
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use std::ptr;
+use tauri::AppHandle;
+
+use super::Resampling;
+use crate::jobs::{record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, Extent, GdalError};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolutionStrategy {
+    Highest,
+    Lowest,
+    #[default]
+    Average,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VrtOptions {
+    pub resolution: ResolutionStrategy,
+    // Explicit output pixel size, overrides the resolution strategy
+    pub target_resolution: Option<f64>,
+    pub extent: Option<Extent>,
+    // Stack each input as its own band instead of mosaicking them
+    pub separate: bool,
+    pub src_nodata: Option<f64>,
+    pub vrt_nodata: Option<f64>,
+    pub resampling: Option<Resampling>,
+    pub allow_projection_difference: bool,
+}
+
+impl VrtOptions {
+    fn to_args(&self) -> Vec<String> {
+        let mut args = vec!["-overwrite".to_string()];
+
+        match self.target_resolution {
+            Some(resolution) => {
+                args.push("-tr".to_string());
+                args.push(resolution.to_string());
+                args.push(resolution.to_string());
+            }
+            None => {
+                let strategy = match self.resolution {
+                    ResolutionStrategy::Highest => "highest",
+                    ResolutionStrategy::Lowest => "lowest",
+                    ResolutionStrategy::Average => "average",
+                };
+                args.push("-resolution".to_string());
+                args.push(strategy.to_string());
+            }
+        }
+
+        if let Some(extent) = &self.extent {
+            args.push("-te".to_string());
+            args.extend(extent.to_args());
+        }
+        if self.separate {
+            args.push("-separate".to_string());
+        }
+        if let Some(nodata) = self.src_nodata {
+            args.push("-srcnodata".to_string());
+            args.push(nodata.to_string());
+        }
+        if let Some(nodata) = self.vrt_nodata {
+            args.push("-vrtnodata".to_string());
+            args.push(nodata.to_string());
+        }
+        if let Some(resampling) = self.resampling {
+            args.push("-r".to_string());
+            args.push(resampling.as_gdal_arg().to_string());
+        }
+        if self.allow_projection_difference {
+            args.push("-allow_projection_difference".to_string());
+        }
+        args
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuiltVrt {
+    pub path: String,
+    pub inputs: Vec<String>,
+    pub info: DatasetInfo,
+}
+
+// Expands glob patterns among the inputs, keeping plain paths in the order given
+pub(crate) fn expand_inputs(inputs: &[String]) -> Result<Vec<String>, GdalError> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        if input.contains(['*', '?', '[']) {
+            let paths = glob::glob(input)
+                .map_err(|e| GdalError::InvalidArgument(format!("{}: {}", input, e)))?;
+            let mut matches: Vec<String> = paths
+                .flatten()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+            matches.sort();
+            expanded.extend(matches);
+        } else if Path::new(input).exists() || input.starts_with("/vsi") {
+            expanded.push(input.clone());
+        } else {
+            return Err(GdalError::FileNotFound(input.clone()));
+        }
+    }
+    Ok(expanded)
+}
+
+// Runs GDALBuildVRT (the library form of gdalbuildvrt) over the named inputs
+pub(crate) fn build(
+    inputs: &[String],
+    dst: &str,
+    args: &[String],
+    progress: &Progress,
+) -> Result<Dataset, GdalError> {
+    record_command_line("gdalbuildvrt", &[args, &[dst.to_string()], inputs].concat());
+    let argv = ffi::arg_list(args)?;
+    let c_dst = ffi::c_string(dst)?;
+    let c_inputs = inputs
+        .iter()
+        .map(|input| ffi::c_string(input))
+        .collect::<Result<Vec<_>, _>>()?;
+    let input_ptrs: Vec<_> = c_inputs.iter().map(|input| input.as_ptr()).collect();
+
+    unsafe {
+        let options = gdal_sys::GDALBuildVRTOptionsNew(argv.as_ptr(), ptr::null_mut());
+        if options.is_null() {
+            return Err(ffi::last_error("GDALBuildVRTOptionsNew"));
+        }
+        gdal_sys::GDALBuildVRTOptionsSetProgress(options, Some(gdal_progress), progress.as_arg());
+
+        let mut usage_error = 0;
+        let result = gdal_sys::GDALBuildVRT(
+            c_dst.as_ptr(),
+            input_ptrs.len() as i32,
+            ptr::null_mut(),
+            input_ptrs.as_ptr(),
+            options,
+            &mut usage_error,
+        );
+        gdal_sys::GDALBuildVRTOptionsFree(options);
+
+        if result.is_null() {
+            return Err(ffi::last_error("GDALBuildVRT"));
+        }
+        Ok(Dataset::from_c_dataset(result))
+    }
+}
+
+#[tauri::command]
+pub async fn build_vrt(
+    app: AppHandle,
+    inputs: Vec<String>,
+    dst: String,
+    options: Option<VrtOptions>,
+) -> Result<BuiltVrt, String> {
+    let params = json!({ "inputs": inputs, "dst": dst, "options": options });
+    run_job(app.clone(), "build_vrt", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let inputs = expand_inputs(&inputs).map_err(|e| e.to_string())?;
+        if inputs.is_empty() {
+            return Err("No input rasters matched".to_string());
+        }
+
+        let args = options.unwrap_or_default().to_args();
+        let progress = Progress::new(&app, "build_vrt");
+        let vrt = build(&inputs, &dst, &args, &progress).map_err(|e| e.to_string())?;
+
+        let info = dataset_info(&vrt);
+        // Closing flushes the VRT XML to disk
+        vrt.close().map_err(|e| e.to_string())?;
+
+        Ok(BuiltVrt {
+            path: dst,
+            inputs,
+            info,
+        })
+    })
+    .await
+}
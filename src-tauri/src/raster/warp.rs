@@ -0,0 +1,257 @@
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{LayerAccess, LayerOptions, OGRwkbGeometryType};
+use gdal::{Dataset, DriverManager};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use std::ptr;
+use tauri::AppHandle;
+
+use super::Resampling;
+use crate::jobs::{dataset_name, record_command_line, run_job};
+use crate::progress::{gdal_progress, Progress};
+use crate::vector::parse_geometries;
+use crate::{dataset_info, ffi, setup_gdal_runtime, DatasetInfo, Extent, GdalError};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Cutline {
+    // GeoJSON or WKT polygon(s), in the raster CRS unless `srs` is given
+    Geometry {
+        geometry: String,
+        srs: Option<String>,
+    },
+    // Polygons from an existing vector dataset, e.g. an administrative boundaries file
+    Layer {
+        path: String,
+        layer: Option<String>,
+        where_clause: Option<String>,
+    },
+}
+
+// Runs GDALWarp (the library form of gdalwarp) over `sources` into `dst`
+pub(crate) fn warp(
+    sources: &[&Dataset],
+    dst: &str,
+    args: &[String],
+    progress: &Progress,
+) -> Result<Dataset, GdalError> {
+    let mut command_args = args.to_vec();
+    command_args.extend(sources.iter().map(|source| dataset_name(source)));
+    command_args.push(dst.to_string());
+    record_command_line("gdalwarp", &command_args);
+    let argv = ffi::arg_list(args)?;
+    let c_dst = ffi::c_string(dst)?;
+    let mut handles: Vec<_> = sources.iter().map(|ds| ds.c_dataset()).collect();
+
+    unsafe {
+        let options = gdal_sys::GDALWarpAppOptionsNew(argv.as_ptr(), ptr::null_mut());
+        if options.is_null() {
+            return Err(ffi::last_error("GDALWarpAppOptionsNew"));
+        }
+        gdal_sys::GDALWarpAppOptionsSetProgress(options, Some(gdal_progress), progress.as_arg());
+
+        let mut usage_error = 0;
+        let result = gdal_sys::GDALWarp(
+            c_dst.as_ptr(),
+            ptr::null_mut(),
+            handles.len() as i32,
+            handles.as_mut_ptr(),
+            options,
+            &mut usage_error,
+        );
+        gdal_sys::GDALWarpAppOptionsFree(options);
+
+        if result.is_null() {
+            return Err(ffi::last_error("GDALWarp"));
+        }
+        Ok(Dataset::from_c_dataset(result))
+    }
+}
+
+fn warp_args(
+    target_epsg: u32,
+    resampling: Resampling,
+    resolution: Option<f64>,
+    extent: Option<&Extent>,
+) -> Vec<String> {
+    let mut args = vec![
+        "-overwrite".to_string(),
+        "-t_srs".to_string(),
+        format!("EPSG:{}", target_epsg),
+        "-r".to_string(),
+        resampling.as_gdal_arg().to_string(),
+        // Warp chunks in parallel and compute the transformation on all cores
+        "-multi".to_string(),
+        "-wo".to_string(),
+        "NUM_THREADS=ALL_CPUS".to_string(),
+    ];
+
+    if let Some(resolution) = resolution {
+        args.push("-tr".to_string());
+        args.push(resolution.to_string());
+        args.push(resolution.to_string());
+    }
+
+    // The extent is expressed in the target CRS
+    if let Some(extent) = extent {
+        args.push("-te".to_string());
+        args.extend(extent.to_args());
+    }
+
+    args
+}
+
+#[tauri::command]
+pub async fn warp_raster(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    target_epsg: u32,
+    resampling: Option<Resampling>,
+    resolution: Option<f64>,
+    extent: Option<Extent>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "target_epsg": target_epsg,
+        "resampling": resampling,
+        "resolution": resolution,
+        "extent": extent,
+    });
+    run_job(app.clone(), "warp_raster", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        if resolution.is_some_and(|r| r <= 0.0) {
+            return Err("Resolution must be positive".to_string());
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        let args = warp_args(
+            target_epsg,
+            resampling.unwrap_or_default(),
+            resolution,
+            extent.as_ref(),
+        );
+
+        let progress = Progress::new(&app, "warp_raster");
+        let output = warp(&[&source], &dst, &args, &progress).map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
+
+// Writes inline cutline geometries to an in-memory GeoJSON file gdalwarp can read.
+// Without `srs` they are tagged with the raster CRS, as GeoJSON without one is read
+// back as WGS84.
+fn write_cutline(geometry: &str, srs: Option<&str>, source: &Dataset) -> Result<String, GdalError> {
+    let geometries = parse_geometries(geometry)?;
+    let srs = match srs {
+        Some(definition) => Some(SpatialRef::from_definition(definition)?),
+        None => source.spatial_ref().ok(),
+    };
+
+    let path = ffi::vsimem_path("cutline.geojson");
+    let driver = DriverManager::get_driver_by_name("GeoJSON")?;
+    let mut dataset = driver.create_vector_only(&path)?;
+    let mut layer = dataset.create_layer(LayerOptions {
+        name: "cutline",
+        srs: srs.as_ref(),
+        ty: OGRwkbGeometryType::wkbUnknown,
+        ..Default::default()
+    })?;
+    for geometry in geometries {
+        layer.create_feature(geometry)?;
+    }
+    dataset.close()?;
+
+    Ok(path)
+}
+
+#[tauri::command]
+pub async fn clip_raster_by_geometry(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    cutline: Cutline,
+    crop_to_cutline: Option<bool>,
+    blend_distance: Option<f64>,
+) -> Result<DatasetInfo, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "cutline": cutline,
+        "crop_to_cutline": crop_to_cutline,
+        "blend_distance": blend_distance,
+    });
+    run_job(app.clone(), "clip_raster_by_geometry", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+
+        let mut args = vec![
+            "-overwrite".to_string(),
+            "-multi".to_string(),
+            "-wo".to_string(),
+            "NUM_THREADS=ALL_CPUS".to_string(),
+        ];
+        let mut temp_cutline = None;
+
+        match &cutline {
+            Cutline::Geometry { geometry, srs } => {
+                let path =
+                    write_cutline(geometry, srs.as_deref(), &source).map_err(|e| e.to_string())?;
+                args.extend(["-cutline".to_string(), path.clone()]);
+                temp_cutline = Some(path);
+            }
+            Cutline::Layer {
+                path,
+                layer,
+                where_clause,
+            } => {
+                if !Path::new(path).exists() {
+                    return Err(format!("File not found: {}", path));
+                }
+                args.extend(["-cutline".to_string(), path.clone()]);
+                if let Some(layer) = layer {
+                    args.extend(["-cl".to_string(), layer.clone()]);
+                }
+                if let Some(where_clause) = where_clause {
+                    args.extend(["-cwhere".to_string(), where_clause.clone()]);
+                }
+            }
+        }
+
+        if crop_to_cutline.unwrap_or(true) {
+            args.push("-crop_to_cutline".to_string());
+        }
+        if let Some(distance) = blend_distance {
+            if distance < 0.0 {
+                return Err("Blend distance must not be negative".to_string());
+            }
+            // Distance in pixels over which the cutline edge is feathered
+            args.extend(["-cblend".to_string(), distance.to_string()]);
+        }
+
+        let progress = Progress::new(&app, "clip_raster_by_geometry");
+        let result = warp(&[&source], &dst, &args, &progress);
+
+        if let Some(path) = temp_cutline {
+            let _ = gdal::vsi::unlink_mem_file(path);
+        }
+
+        let output = result.map_err(|e| e.to_string())?;
+        Ok(dataset_info(&output))
+    })
+    .await
+}
@@ -0,0 +1,395 @@
+use gdal::raster::rasterize;
+use gdal::spatial_ref::CoordTransform;
+use gdal::vector::{
+    Defn, Feature, FieldDefn, Geometry, Layer, LayerAccess, LayerOptions, OGRFieldType,
+};
+use gdal::{Dataset, DriverManager, GeoTransform};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::crs::transformer;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::vector::selection::selected_fids;
+use crate::vector::{create_output, field_value_json, layer_by_name};
+use crate::{setup_gdal_runtime, GdalError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZonalStat {
+    Count,
+    Min,
+    Max,
+    Mean,
+    Sum,
+    Stddev,
+}
+
+pub(crate) const ALL_STATS: &[ZonalStat] = &[
+    ZonalStat::Count,
+    ZonalStat::Min,
+    ZonalStat::Max,
+    ZonalStat::Mean,
+    ZonalStat::Sum,
+    ZonalStat::Stddev,
+];
+
+impl ZonalStat {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ZonalStat::Count => "count",
+            ZonalStat::Min => "min",
+            ZonalStat::Max => "max",
+            ZonalStat::Mean => "mean",
+            ZonalStat::Sum => "sum",
+            ZonalStat::Stddev => "stddev",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZonalOptions {
+    // Every statistic when unset or empty
+    pub stats: Option<Vec<ZonalStat>>,
+    // Only the zones selected in the layer
+    pub selected_only: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ZoneStatistics {
+    pub fid: Option<u64>,
+    pub properties: Map<String, Value>,
+    // Keyed by statistic name; null where the zone covers no valid pixel
+    pub stats: BTreeMap<String, Option<f64>>,
+}
+
+// Running totals over the valid pixels of one zone, or the field values of one bin
+#[derive(Default)]
+pub(crate) struct Accumulator {
+    count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl Accumulator {
+    pub(crate) fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+        self.sum += value;
+        self.sum_squares += value * value;
+    }
+
+    pub(crate) fn get(&self, stat: ZonalStat) -> Option<f64> {
+        if stat == ZonalStat::Count {
+            return Some(self.count as f64);
+        }
+        if self.count == 0 {
+            return None;
+        }
+        let n = self.count as f64;
+        let mean = self.sum / n;
+        Some(match stat {
+            ZonalStat::Count => n,
+            ZonalStat::Min => self.min,
+            ZonalStat::Max => self.max,
+            ZonalStat::Mean => mean,
+            ZonalStat::Sum => self.sum,
+            // Population standard deviation, as reported by GDAL and QGIS
+            ZonalStat::Stddev => (self.sum_squares / n - mean * mean).max(0.0).sqrt(),
+        })
+    }
+}
+
+// Pixel window covering `geometry`, clipped to the raster; None when they do not overlap
+fn pixel_window(
+    geometry: &Geometry,
+    gt: &GeoTransform,
+    size: (usize, usize),
+) -> Option<(usize, usize, usize, usize)> {
+    let envelope = geometry.envelope();
+    let col = |x: f64| ((x - gt[0]) / gt[1]).clamp(0.0, size.0 as f64);
+    let row = |y: f64| ((y - gt[3]) / gt[5]).clamp(0.0, size.1 as f64);
+    let (col0, col1) = (col(envelope.MinX).floor(), col(envelope.MaxX).ceil());
+    let (row0, row1) = (row(envelope.MaxY).floor(), row(envelope.MinY).ceil());
+    if col1 <= col0 || row1 <= row0 {
+        return None;
+    }
+    Some((
+        col0 as usize,
+        row0 as usize,
+        (col1 - col0) as usize,
+        (row1 - row0) as usize,
+    ))
+}
+
+// Accumulates the pixels of `source` band `band` whose centres fall inside `geometry`
+fn zone_statistics(
+    source: &Dataset,
+    band: usize,
+    geometry: &Geometry,
+) -> Result<Accumulator, GdalError> {
+    let mut accumulator = Accumulator::default();
+    let gt = source.geo_transform()?;
+    let Some((col0, row0, width, height)) = pixel_window(geometry, &gt, source.raster_size())
+    else {
+        return Ok(accumulator);
+    };
+
+    // Burn the zone into a mask covering the window
+    let driver = DriverManager::get_driver_by_name("MEM")?;
+    let mut mask = driver.create_with_band_type::<u8, _>("", width, height, 1)?;
+    mask.set_geo_transform(&[
+        gt[0] + col0 as f64 * gt[1],
+        gt[1],
+        0.0,
+        gt[3] + row0 as f64 * gt[5],
+        0.0,
+        gt[5],
+    ])?;
+    rasterize(
+        &mut mask,
+        &[1],
+        std::slice::from_ref(geometry),
+        &[1.0],
+        None,
+    )?;
+    let inside = mask
+        .rasterband(1)?
+        .read_as::<u8>((0, 0), (width, height), (width, height), None)?
+        .into_shape_and_vec()
+        .1;
+
+    let raster_band = source.rasterband(band)?;
+    let nodata = raster_band.no_data_value();
+    let values = raster_band
+        .read_as::<f64>(
+            (col0 as isize, row0 as isize),
+            (width, height),
+            (width, height),
+            None,
+        )?
+        .into_shape_and_vec()
+        .1;
+    for (value, inside) in values.into_iter().zip(inside) {
+        if inside != 0 && !value.is_nan() && nodata != Some(value) {
+            accumulator.add(value);
+        }
+    }
+    Ok(accumulator)
+}
+
+// Output layer with the zone attributes plus one field per statistic
+fn create_zones_output<'a>(
+    output: &'a mut Dataset,
+    zones: &Layer,
+    stats: &[ZonalStat],
+) -> Result<Layer<'a>, GdalError> {
+    let defn = zones.defn();
+    for stat in stats {
+        if defn.field_index(stat.name()).is_ok() {
+            return Err(GdalError::InvalidArgument(format!(
+                "Zones already have a '{}' field",
+                stat.name()
+            )));
+        }
+    }
+
+    let srs = zones.spatial_ref();
+    let layer = output.create_layer(LayerOptions {
+        name: &zones.name(),
+        srs: srs.as_ref(),
+        ty: defn.geometry_type(),
+        ..Default::default()
+    })?;
+    for field in defn.fields() {
+        let field_defn = FieldDefn::new(&field.name(), field.field_type())?;
+        field_defn.set_width(field.width());
+        field_defn.set_precision(field.precision());
+        field_defn.add_to_layer(&layer)?;
+    }
+    for stat in stats {
+        let field_type = match stat {
+            ZonalStat::Count => OGRFieldType::OFTInteger64,
+            _ => OGRFieldType::OFTReal,
+        };
+        FieldDefn::new(stat.name(), field_type)?.add_to_layer(&layer)?;
+    }
+    Ok(layer)
+}
+
+fn write_zone(
+    layer: &Layer,
+    defn: &Defn,
+    zone: &Feature,
+    stats: &[ZonalStat],
+    accumulator: &Accumulator,
+) -> Result<(), GdalError> {
+    let mut feature = Feature::new(defn)?;
+    if let Some(geometry) = zone.geometry() {
+        feature.set_geometry(geometry.clone())?;
+    }
+    let field_count = zone.field_count();
+    for (index, (_, value)) in zone.fields().enumerate() {
+        if let Some(value) = value {
+            feature.set_field(index, &value)?;
+        }
+    }
+    for (index, stat) in stats.iter().enumerate() {
+        match (stat, accumulator.get(*stat)) {
+            (ZonalStat::Count, _) => {
+                feature.set_field_integer64(field_count + index, accumulator.count as i64)?
+            }
+            (_, Some(value)) => feature.set_field_double(field_count + index, value)?,
+            (_, None) => feature.set_field_null(field_count + index)?,
+        }
+    }
+    feature.create(layer)?;
+    Ok(())
+}
+
+fn compute(
+    source: &Dataset,
+    band: usize,
+    zones: &mut Layer,
+    selection: Option<&BTreeSet<u64>>,
+    stats: &[ZonalStat],
+    dst: Option<&str>,
+    progress: &Progress,
+) -> Result<Vec<ZoneStatistics>, GdalError> {
+    let gt = source.geo_transform()?;
+    if gt[2] != 0.0 || gt[4] != 0.0 {
+        return Err(GdalError::InvalidArgument(
+            "Rotated rasters are not supported".to_string(),
+        ));
+    }
+    // Zones are reprojected to the raster's CRS rather than the other way around
+    let transform: Option<CoordTransform> = match (zones.spatial_ref(), source.spatial_ref()) {
+        (Some(zones_srs), Ok(raster_srs)) => Some(transformer(&zones_srs, &raster_srs)?),
+        _ => None,
+    };
+
+    let mut output = dst.map(create_output).transpose()?;
+    let output_layer = match output.as_mut() {
+        Some(output) => Some(create_zones_output(output, zones, stats)?),
+        None => None,
+    };
+    let output_defn = output_layer.as_ref().map(Defn::from_layer);
+
+    let total = selection.map_or_else(|| zones.feature_count(), |fids| fids.len() as u64);
+    let total = total.max(1) as f64;
+    let mut results = Vec::new();
+    let zones = zones.features().filter(|zone| {
+        selection.is_none_or(|fids| zone.fid().is_some_and(|fid| fids.contains(&fid)))
+    });
+    for (index, zone) in zones.enumerate() {
+        let accumulator = match zone.geometry() {
+            Some(geometry) => {
+                let geometry = match &transform {
+                    Some(transform) => geometry.transform(transform)?,
+                    None => geometry.clone(),
+                };
+                zone_statistics(source, band, &geometry)?
+            }
+            None => Accumulator::default(),
+        };
+
+        if let (Some(layer), Some(defn)) = (&output_layer, &output_defn) {
+            write_zone(layer, defn, &zone, stats, &accumulator)?;
+        }
+        results.push(ZoneStatistics {
+            fid: zone.fid(),
+            properties: zone
+                .fields()
+                .map(|(name, value)| (name, value.map(field_value_json).unwrap_or(Value::Null)))
+                .collect(),
+            stats: stats
+                .iter()
+                .map(|stat| (stat.name().to_string(), accumulator.get(*stat)))
+                .collect(),
+        });
+        progress.report((index + 1) as f64 / total, None);
+    }
+
+    if let Some(output) = output {
+        output.close()?;
+    }
+    Ok(results)
+}
+
+// Summarizes the pixels of `raster` under each polygon of `zones_vector`. Pixels count
+// towards a zone when their centre lies inside it; nodata pixels are skipped. With
+// `dst` the zones are also written there with one attribute per statistic.
+#[tauri::command]
+pub async fn zonal_statistics(
+    app: AppHandle,
+    raster: String,
+    band: Option<usize>,
+    zones_vector: String,
+    layer: Option<String>,
+    options: Option<ZonalOptions>,
+    dst: Option<String>,
+) -> Result<Vec<ZoneStatistics>, String> {
+    let options = options.unwrap_or_default();
+    let params = json!({
+        "raster": raster,
+        "band": band,
+        "zones_vector": zones_vector,
+        "layer": layer,
+        "options": options,
+        "dst": dst,
+    });
+    run_job(app.clone(), "zonal_statistics", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        for path in [&raster, &zones_vector] {
+            if !Path::new(path).exists() {
+                return Err(format!("File not found: {}", path));
+            }
+        }
+
+        let source = Dataset::open(&raster).map_err(|e| e.to_string())?;
+        let band = band.unwrap_or(1);
+        if band == 0 || band > source.raster_count() {
+            return Err(format!("Band {} does not exist", band));
+        }
+        let stats = match options.stats {
+            Some(stats) if !stats.is_empty() => stats,
+            _ => ALL_STATS.to_vec(),
+        };
+
+        let zones_dataset = Dataset::open(&zones_vector).map_err(|e| e.to_string())?;
+        let mut zones =
+            layer_by_name(&zones_dataset, layer.as_deref()).map_err(|e| e.to_string())?;
+        let selection = if options.selected_only {
+            Some(selected_fids(&app, &zones_vector, &zones.name()).map_err(|e| e.to_string())?)
+        } else {
+            None
+        };
+
+        let progress = Progress::new(&app, "zonal_statistics");
+        compute(
+            &source,
+            band,
+            &mut zones,
+            selection.as_ref(),
+            &stats,
+            dst.as_deref(),
+            &progress,
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,315 @@
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+use super::canvas::{parse_color, Canvas, Viewport};
+use super::gif::{self, Quantizer};
+use super::glyphs::{draw_text, font_scale, text_size};
+use super::ramp::{ColorLut, ColorRamp};
+use super::raster::{render_raster, warp_to_viewport, RasterStyle};
+use super::RenderTicket;
+use crate::progress::Progress;
+use crate::raster::timeseries::{Scene, TimeSeriesStore};
+use crate::raster::Resampling;
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+// Pixels held in memory, over all frames, while an animated GIF is assembled
+const MAX_GIF_PIXELS: usize = 100_000_000;
+// Frame delay when none is given, in milliseconds
+const DEFAULT_FRAME_DELAY: u32 = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrameStyle {
+    // As on the map. Leaving `min` and `max` unset stretches every frame on its own, so
+    // set them when frames should be comparable.
+    Raster(RasterStyle),
+    // One band coloured through a ramp. The range spans every scene when `min` or `max`
+    // is unset, so a colour means the same value in every frame.
+    Ramp {
+        #[serde(default)]
+        band: Option<usize>,
+        #[serde(default)]
+        ramp: ColorRamp,
+        #[serde(default)]
+        min: Option<f64>,
+        #[serde(default)]
+        max: Option<f64>,
+        #[serde(default)]
+        resampling: Resampling,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationOutput {
+    // Directory the PNG frames are written to, created when missing
+    pub directory: String,
+    // `#rrggbb` or `#rrggbbaa` behind each frame, transparent when unset
+    #[serde(default)]
+    pub background: Option<String>,
+    // Stamps each frame's date in its top left corner
+    #[serde(default)]
+    pub label: bool,
+    // Path of an animated GIF of all frames, none is written when unset
+    #[serde(default)]
+    pub gif: Option<String>,
+    // How long each frame is shown in the GIF, in milliseconds
+    #[serde(default)]
+    pub frame_delay: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnimationFrame {
+    pub path: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnimationExport {
+    pub frames: Vec<AnimationFrame>,
+    pub width: usize,
+    pub height: usize,
+    pub gif: Option<String>,
+}
+
+// How every frame is drawn, with a ramp's colours fixed across all scenes
+enum FrameRenderer<'a> {
+    Raster(&'a RasterStyle),
+    Ramp {
+        band: usize,
+        lut: ColorLut,
+        resampling: Resampling,
+    },
+}
+
+// Colour lookup for a ramp style, with the range taken over all scenes when not given
+fn ramp_lut(
+    scenes: &[Scene],
+    band: usize,
+    ramp: &ColorRamp,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> Result<ColorLut, GdalError> {
+    let range = match (min, max) {
+        (Some(min), Some(max)) => (min, max),
+        _ => {
+            let mut range = (f64::INFINITY, f64::NEG_INFINITY);
+            for scene in scenes {
+                let source = Dataset::open(&scene.path)?;
+                let scene_range = source.rasterband(band)?.compute_raster_min_max(true)?;
+                range = (range.0.min(scene_range.min), range.1.max(scene_range.max));
+            }
+            (min.unwrap_or(range.0), max.unwrap_or(range.1))
+        }
+    };
+    ColorLut::new(ramp, range)
+}
+
+fn render_ramp(
+    source: &Dataset,
+    band: usize,
+    lut: &ColorLut,
+    resampling: Resampling,
+    viewport: &Viewport,
+) -> Result<Canvas, GdalError> {
+    let warped = warp_to_viewport(
+        source,
+        &[band],
+        viewport,
+        resampling,
+        &RenderTicket::detached(),
+    )?;
+    let (width, height) = (viewport.width, viewport.height);
+    let read = |index: usize| -> Result<Vec<f64>, GdalError> {
+        Ok(warped
+            .rasterband(index)?
+            .read_as::<f64>((0, 0), (width, height), (width, height), None)?
+            .into_shape_and_vec()
+            .1)
+    };
+    let (values, alpha) = (read(1)?, read(2)?);
+
+    let mut canvas = Canvas::new(width, height);
+    for ((pixel, value), alpha) in canvas.pixels.chunks_exact_mut(4).zip(values).zip(alpha) {
+        if alpha <= 0.0 || value.is_nan() {
+            continue;
+        }
+        pixel.copy_from_slice(&lut.color(value));
+    }
+    Ok(canvas)
+}
+
+// The date alone for scenes at midnight, as most imagery is dated by day
+fn label_text(timestamp: &str) -> &str {
+    timestamp.strip_suffix("T00:00:00Z").unwrap_or(timestamp)
+}
+
+// Layers `frame` over the background and stamps the label
+fn finish_frame(frame: &Canvas, background: Option<[u8; 4]>, label: Option<&str>) -> Canvas {
+    let mut canvas = match background {
+        Some(color) => Canvas::filled(frame.width, frame.height, color),
+        None => Canvas::new(frame.width, frame.height),
+    };
+    for (index, pixel) in frame.pixels.chunks_exact(4).enumerate() {
+        let (x, y) = (index % frame.width, index / frame.width);
+        canvas.blend_pixel(
+            x as isize,
+            y as isize,
+            [pixel[0], pixel[1], pixel[2], pixel[3]],
+        );
+    }
+    if let Some(text) = label {
+        let scale = font_scale(frame.height as f64 / 24.0);
+        let (width, height) = text_size(text, scale);
+        let margin = 4.0 * scale as f64;
+        draw_text(
+            &mut canvas,
+            margin + width as f64 / 2.0,
+            margin + height as f64 / 2.0,
+            text,
+            scale,
+            [255, 255, 255, 255],
+            Some([0, 0, 0, 255]),
+        );
+    }
+    canvas
+}
+
+// Renders every scene of a time series stack over `extent` in `crs` at `size` pixels
+// and writes one PNG per date, oldest first, for change-over-time visualisations.
+// Scenes in other CRSs are warped onto the same grid. With `output.gif` the frames are
+// also assembled into a looping animated GIF sharing one 255 colour palette.
+#[tauri::command]
+pub async fn export_animation_frames(
+    app: AppHandle,
+    stack: u64,
+    style: FrameStyle,
+    extent: Extent,
+    crs: String,
+    size: (usize, usize),
+    output: AnimationOutput,
+) -> Result<AnimationExport, String> {
+    let viewport = Viewport {
+        extent,
+        crs,
+        width: size.0,
+        height: size.1,
+    };
+    viewport.validate().map_err(|e| e.to_string())?;
+    let background = output
+        .background
+        .as_deref()
+        .map(parse_color)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let stack = app
+        .state::<TimeSeriesStore>()
+        .get(stack)
+        .map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let scenes = stack.scenes();
+        if let Some(gif) = &output.gif {
+            if !gif.to_ascii_lowercase().ends_with(".gif") {
+                return Err("Animations can only be written as GIF".to_string());
+            }
+            if viewport.width * viewport.height * scenes.len() > MAX_GIF_PIXELS {
+                return Err(format!(
+                    "An animated GIF can hold at most {} pixels over all frames",
+                    MAX_GIF_PIXELS
+                ));
+            }
+        }
+        let renderer = match &style {
+            FrameStyle::Raster(style) => FrameRenderer::Raster(style),
+            FrameStyle::Ramp {
+                band,
+                ramp,
+                min,
+                max,
+                resampling,
+            } => {
+                let band = band.unwrap_or(1);
+                let lut = ramp_lut(scenes, band, ramp, *min, *max).map_err(|e| e.to_string())?;
+                FrameRenderer::Ramp {
+                    band,
+                    lut,
+                    resampling: *resampling,
+                }
+            }
+        };
+
+        let directory = Path::new(&output.directory);
+        fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+        let progress = Progress::new(&app, "export_animation_frames");
+        let mut quantizer = output.gif.as_ref().map(|_| Quantizer::new());
+        let mut keys = Vec::new();
+        let mut frames = Vec::with_capacity(scenes.len());
+        for (index, scene) in scenes.iter().enumerate() {
+            progress.report(index as f64 / scenes.len() as f64, None);
+            let source = Dataset::open(&scene.path).map_err(|e| e.to_string())?;
+            let rendered = match &renderer {
+                FrameRenderer::Raster(style) => {
+                    render_raster(&source, &viewport, style, &RenderTicket::detached())
+                }
+                FrameRenderer::Ramp {
+                    band,
+                    lut,
+                    resampling,
+                } => render_ramp(&source, *band, lut, *resampling, &viewport),
+            }
+            .map_err(|e| format!("{}: {}", scene.path, e))?;
+
+            let label = output.label.then(|| label_text(&scene.timestamp));
+            let canvas = finish_frame(&rendered, background, label);
+            let name = format!(
+                "frame_{:04}_{}.png",
+                index,
+                scene.timestamp.replace([':', '-'], "")
+            );
+            let path = directory.join(name);
+            let png = canvas.encode_png().map_err(|e| e.to_string())?;
+            fs::write(&path, png).map_err(|e| e.to_string())?;
+            if let Some(quantizer) = &mut quantizer {
+                keys.push(quantizer.add(&canvas));
+            }
+
+            frames.push(AnimationFrame {
+                path: path.to_string_lossy().to_string(),
+                timestamp: scene.timestamp.clone(),
+            });
+        }
+
+        if let (Some(path), Some(quantizer)) = (&output.gif, &quantizer) {
+            let (palette, lookup) = quantizer.palette();
+            let indices: Vec<Vec<u8>> = keys
+                .iter()
+                .map(|frame| gif::indices(frame, &lookup))
+                .collect();
+            // GIF delays are in hundredths of a second
+            let delay = output.frame_delay.unwrap_or(DEFAULT_FRAME_DELAY) / 10;
+            let bytes = gif::encode(
+                viewport.width as u16,
+                viewport.height as u16,
+                &palette,
+                &indices,
+                delay.min(u16::MAX as u32) as u16,
+            );
+            fs::write(path, bytes).map_err(|e| e.to_string())?;
+        }
+        progress.report(1.0, None);
+
+        Ok(AnimationExport {
+            frames,
+            width: viewport.width,
+            height: viewport.height,
+            gif: output.gif,
+        })
+    })
+    .await
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+// Default memory budget for cached tiles, in megabytes
+pub const DEFAULT_BUDGET_MB: u64 = 256;
+
+// One rendered tile: the dataset handle, what kind of render and its style as JSON,
+// the tile address and its size in pixels
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub handle: u64,
+    pub kind: &'static str,
+    pub style: String,
+    pub tile: (u8, u32, u32),
+    pub size: usize,
+}
+
+struct CacheEntry {
+    png: Arc<Vec<u8>>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<TileKey, CacheEntry>,
+    // Keys by last use, oldest first
+    recency: BTreeMap<u64, TileKey>,
+    clock: u64,
+    bytes: u64,
+    budget: u64,
+}
+
+impl CacheState {
+    fn touch(&mut self, key: &TileKey) -> Option<Arc<Vec<u8>>> {
+        self.clock += 1;
+        let clock = self.clock;
+        let entry = self.entries.get_mut(key)?;
+        self.recency.remove(&entry.last_used);
+        entry.last_used = clock;
+        self.recency.insert(clock, key.clone());
+        Some(entry.png.clone())
+    }
+
+    fn remove(&mut self, key: &TileKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.last_used);
+            self.bytes -= entry.png.len() as u64;
+        }
+    }
+
+    fn evict_to_budget(&mut self) {
+        while self.bytes > self.budget {
+            let Some((_, key)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.bytes -= entry.png.len() as u64;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderCacheInfo {
+    pub entries: usize,
+    pub bytes: u64,
+    pub budget_bytes: u64,
+}
+
+// Encoded PNG tiles, least recently used evicted first once over the memory budget,
+// so panning back over areas already viewed skips rendering
+pub struct RenderCache {
+    state: Mutex<CacheState>,
+}
+
+impl Default for RenderCache {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                budget: DEFAULT_BUDGET_MB * 1024 * 1024,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl RenderCache {
+    pub fn get(&self, key: &TileKey) -> Option<Arc<Vec<u8>>> {
+        self.state.lock().unwrap().touch(key)
+    }
+
+    pub fn insert(&self, key: TileKey, png: Arc<Vec<u8>>) {
+        let mut state = self.state.lock().unwrap();
+        // Tiles larger than the whole budget would only evict everything else
+        if png.len() as u64 > state.budget {
+            return;
+        }
+        state.remove(&key);
+        state.clock += 1;
+        let clock = state.clock;
+        state.bytes += png.len() as u64;
+        state.recency.insert(clock, key.clone());
+        state.entries.insert(
+            key,
+            CacheEntry {
+                png,
+                last_used: clock,
+            },
+        );
+        state.evict_to_budget();
+    }
+
+    // Returns the cached tile or renders and caches it
+    pub fn get_or_render<F, E>(&self, key: TileKey, render: F) -> Result<Arc<Vec<u8>>, E>
+    where
+        F: FnOnce() -> Result<Vec<u8>, E>,
+    {
+        if let Some(png) = self.get(&key) {
+            return Ok(png);
+        }
+        let png = Arc::new(render()?);
+        self.insert(key, png.clone());
+        Ok(png)
+    }
+
+    // Drops every tile of `handle`, for when the dataset is closed or its georeferencing
+    // changes
+    pub fn invalidate(&self, handle: u64) {
+        let mut state = self.state.lock().unwrap();
+        let keys: Vec<TileKey> = state
+            .entries
+            .keys()
+            .filter(|key| key.handle == handle)
+            .cloned()
+            .collect();
+        for key in keys {
+            state.remove(&key);
+        }
+    }
+
+    pub fn set_budget_mb(&self, budget_mb: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.budget = budget_mb * 1024 * 1024;
+        state.evict_to_budget();
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        let budget = state.budget;
+        *state = CacheState {
+            budget,
+            ..Default::default()
+        };
+    }
+
+    pub fn info(&self) -> RenderCacheInfo {
+        let state = self.state.lock().unwrap();
+        RenderCacheInfo {
+            entries: state.entries.len(),
+            bytes: state.bytes,
+            budget_bytes: state.budget,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_render_cache(cache: State<'_, RenderCache>) -> RenderCacheInfo {
+    cache.info()
+}
+
+#[tauri::command]
+pub fn clear_render_cache(cache: State<'_, RenderCache>) -> RenderCacheInfo {
+    cache.clear();
+    cache.info()
+}
@@ -0,0 +1,190 @@
+use gdal::raster::{Buffer, ColorInterpretation};
+use gdal::{Dataset, DriverManager, GeoTransform};
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Response;
+
+use super::draw::Mask;
+use crate::crs::parse_srs;
+use crate::ipc::Frame;
+use crate::{ffi, Extent, GdalError};
+
+// Largest image a single render may produce, per side
+const MAX_SIZE: usize = 8192;
+
+// The area of the map being drawn and the pixel size of the image it is drawn into
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Viewport {
+    pub extent: Extent,
+    // Anything OSRSetFromUserInput understands, e.g. "EPSG:3857"
+    pub crs: String,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    pub(crate) fn validate(&self) -> Result<(), GdalError> {
+        if self.width == 0 || self.height == 0 || self.width > MAX_SIZE || self.height > MAX_SIZE {
+            return Err(GdalError::InvalidArgument(format!(
+                "Viewport size must be between 1 and {} pixels per side",
+                MAX_SIZE
+            )));
+        }
+        if self.extent.max_x <= self.extent.min_x || self.extent.max_y <= self.extent.min_y {
+            return Err(GdalError::InvalidArgument(
+                "Viewport extent is empty".to_string(),
+            ));
+        }
+        parse_srs(&self.crs)?;
+        Ok(())
+    }
+
+    pub(crate) fn geo_transform(&self) -> GeoTransform {
+        let extent = &self.extent;
+        [
+            extent.min_x,
+            (extent.max_x - extent.min_x) / self.width as f64,
+            0.0,
+            extent.max_y,
+            0.0,
+            -(extent.max_y - extent.min_y) / self.height as f64,
+        ]
+    }
+}
+
+// `#rrggbb` or `#rrggbbaa` as RGBA
+pub(crate) fn parse_color(color: &str) -> Result<[u8; 4], GdalError> {
+    let invalid = || GdalError::InvalidArgument(format!("Invalid colour '{}'", color));
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut rgba = [255; 4];
+    for (index, channel) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(rgba)
+}
+
+fn source_over(backdrop: &mut [u8], source: [u8; 4]) {
+    let source_alpha = source[3] as f32 / 255.0;
+    if source_alpha <= 0.0 {
+        return;
+    }
+    let backdrop_alpha = backdrop[3] as f32 / 255.0;
+    let alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+    for channel in 0..3 {
+        let value = (source[channel] as f32 * source_alpha
+            + backdrop[channel] as f32 * backdrop_alpha * (1.0 - source_alpha))
+            / alpha;
+        backdrop[channel] = value.round() as u8;
+    }
+    backdrop[3] = (alpha * 255.0).round() as u8;
+}
+
+// Straight (not premultiplied) RGBA pixels, row by row
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    pub fn filled(width: usize, height: usize, color: [u8; 4]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: color.repeat(width * height),
+        }
+    }
+
+    // Composites `color` over one pixel; pixels off the canvas are ignored
+    pub fn blend_pixel(&mut self, x: isize, y: isize, color: [u8; 4]) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let index = (y as usize * self.width + x as usize) * 4;
+        source_over(&mut self.pixels[index..index + 4], color);
+    }
+
+    // Composites `color` over every pixel set in `mask`
+    pub fn paint(&mut self, mask: &Mask, color: [u8; 4]) {
+        for (pixel, _) in self
+            .pixels
+            .chunks_exact_mut(4)
+            .zip(&mask.covered)
+            .filter(|(_, covered)| **covered)
+        {
+            source_over(pixel, color);
+        }
+    }
+
+    // The pixels as a four band MEM dataset, the last band marked as alpha
+    pub(crate) fn to_dataset(&self) -> Result<Dataset, GdalError> {
+        let (width, height) = (self.width, self.height);
+        let driver = DriverManager::get_driver_by_name("MEM")?;
+        let image = driver.create_with_band_type::<u8, _>("", width, height, 4)?;
+        for band_index in 0..4 {
+            let mut band = image.rasterband(band_index + 1)?;
+            let channel = self
+                .pixels
+                .iter()
+                .skip(band_index)
+                .step_by(4)
+                .copied()
+                .collect();
+            band.write(
+                (0, 0),
+                (width, height),
+                &mut Buffer::new((width, height), channel),
+            )?;
+        }
+        image
+            .rasterband(4)?
+            .set_color_interpretation(ColorInterpretation::AlphaBand)?;
+        Ok(image)
+    }
+
+    // PNG encoding through GDAL's PNG driver, written to /vsimem/ rather than disk
+    pub fn encode_png(&self) -> Result<Vec<u8>, GdalError> {
+        let image = self.to_dataset()?;
+        let path = ffi::vsimem_path("render.png");
+        let png = DriverManager::get_driver_by_name("PNG")?;
+        image
+            .create_copy(&png, &path, &Default::default())?
+            .close()?;
+        Ok(gdal::vsi::get_vsi_mem_file_bytes_owned(&path)?)
+    }
+}
+
+// A rendered PNG. Sent as a binary frame with the size as metadata and the PNG as the
+// only part, which the frontend can wrap in a Blob for an <img> source.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderedImage {
+    pub width: usize,
+    pub height: usize,
+    #[serde(skip)]
+    pub png: Vec<u8>,
+}
+
+impl RenderedImage {
+    pub(crate) fn from_canvas(canvas: &Canvas) -> Result<Self, GdalError> {
+        let png = canvas.encode_png()?;
+        Ok(Self::from_png(canvas.width, canvas.height, png))
+    }
+
+    pub(crate) fn from_png(width: usize, height: usize, png: Vec<u8>) -> Self {
+        Self { width, height, png }
+    }
+
+    pub(crate) fn into_response(self) -> Response {
+        Frame::new(&self).part(&self.png).into_response()
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Response;
+use tauri::State;
+
+use super::canvas::{parse_color, Canvas, RenderedImage, Viewport};
+use super::labels::{Label, LabelPlacer};
+use super::raster::{render_raster, RasterStyle};
+use super::vector::{render_vector_layer, VectorStyle};
+use super::{RenderQueue, RenderTicket};
+use crate::datasets::{DatasetEntry, DatasetRegistry};
+use crate::ipc::Frame;
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+// How a layer's colours combine with the layers below it, as in CSS mix-blend-mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    Add,
+}
+
+impl BlendMode {
+    // Blended colour channel, all values in 0..=1
+    fn blend(self, backdrop: f32, source: f32) -> f32 {
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => backdrop * source,
+            BlendMode::Screen => backdrop + source - backdrop * source,
+            BlendMode::Overlay if backdrop <= 0.5 => 2.0 * backdrop * source,
+            BlendMode::Overlay => 1.0 - 2.0 * (1.0 - backdrop) * (1.0 - source),
+            BlendMode::Darken => backdrop.min(source),
+            BlendMode::Lighten => backdrop.max(source),
+            BlendMode::Add => (backdrop + source).min(1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LayerStyle {
+    Raster(RasterStyle),
+    Vector(VectorStyle),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeLayer {
+    pub handle: u64,
+    #[serde(default = "full_opacity")]
+    pub opacity: f64,
+    #[serde(default)]
+    pub blend: BlendMode,
+    pub style: LayerStyle,
+}
+
+fn full_opacity() -> f64 {
+    1.0
+}
+
+// Draws `layer` over `canvas` using the W3C compositing model: the blended colour is
+// mixed in according to the backdrop's alpha, then composited source-over
+fn draw(canvas: &mut Canvas, layer: &Canvas, opacity: f32, blend: BlendMode) {
+    for (backdrop, source) in canvas
+        .pixels
+        .chunks_exact_mut(4)
+        .zip(layer.pixels.chunks_exact(4))
+    {
+        let source_alpha = source[3] as f32 / 255.0 * opacity;
+        if source_alpha <= 0.0 {
+            continue;
+        }
+        let backdrop_alpha = backdrop[3] as f32 / 255.0;
+        let alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+
+        for channel in 0..3 {
+            let cb = backdrop[channel] as f32 / 255.0;
+            let cs = source[channel] as f32 / 255.0;
+            let mixed = (1.0 - backdrop_alpha) * cs + backdrop_alpha * blend.blend(cb, cs);
+            let premultiplied = source_alpha * mixed + backdrop_alpha * cb * (1.0 - source_alpha);
+            backdrop[channel] = (premultiplied / alpha * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+        backdrop[3] = (alpha * 255.0).round() as u8;
+    }
+}
+
+// None when the ticket was superseded part way through
+pub(crate) fn composite(
+    entries: &[DatasetEntry],
+    layers: &[CompositeLayer],
+    viewport: &Viewport,
+    background: Option<[u8; 4]>,
+    ticket: &RenderTicket,
+) -> Result<Option<Canvas>, GdalError> {
+    let (width, height) = (viewport.width, viewport.height);
+    let mut canvas = match background {
+        Some(color) => Canvas::filled(width, height, color),
+        None => Canvas::new(width, height),
+    };
+
+    let mut labels: Vec<Vec<Label>> = Vec::new();
+    for (entry, layer) in entries.iter().zip(layers) {
+        if layer.opacity <= 0.0 {
+            continue;
+        }
+        let open = entry.lock().unwrap();
+        let rendered = match &layer.style {
+            LayerStyle::Raster(style) => render_raster(&open.dataset, viewport, style, ticket),
+            LayerStyle::Vector(style) => {
+                render_vector_layer(&open.dataset, viewport, style, ticket).map(
+                    |(canvas, layer_labels)| {
+                        labels.push(layer_labels);
+                        canvas
+                    },
+                )
+            }
+        };
+        // An aborted warp or rasterize surfaces as an error, which is not one worth reporting
+        if ticket.is_superseded() {
+            return Ok(None);
+        }
+        draw(
+            &mut canvas,
+            &rendered?,
+            layer.opacity.clamp(0.0, 1.0) as f32,
+            layer.blend,
+        );
+    }
+
+    // Labels go above every layer; those of upper layers get the first pick of positions
+    let mut placer = LabelPlacer::new(width, height);
+    for layer_labels in labels.into_iter().rev() {
+        placer.draw(&mut canvas, layer_labels);
+    }
+    Ok(Some(canvas))
+}
+
+// Renders the open datasets in `layers` into one image of the viewport, the first layer
+// at the bottom, as a binary frame. Its metadata is null when a newer render for the same
+// view superseded this one.
+#[tauri::command]
+pub async fn render_composite(
+    queue: State<'_, RenderQueue>,
+    registry: State<'_, DatasetRegistry>,
+    view_id: String,
+    viewport: Viewport,
+    layers: Vec<CompositeLayer>,
+    background: Option<String>,
+) -> Result<Response, String> {
+    viewport.validate().map_err(|e| e.to_string())?;
+    let background = background
+        .as_deref()
+        .map(parse_color)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let entries = layers
+        .iter()
+        .map(|layer| registry.get(layer.handle))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let ticket = queue.begin(&view_id);
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !ticket.debounce() {
+            return Ok(Frame::empty().into_response());
+        }
+        let canvas = composite(&entries, &layers, &viewport, background, &ticket)
+            .map_err(|e| e.to_string())?;
+        match canvas {
+            Some(canvas) => RenderedImage::from_canvas(&canvas)
+                .map(RenderedImage::into_response)
+                .map_err(|e| e.to_string()),
+            None => Ok(Frame::empty().into_response()),
+        }
+    })
+    .await
+}
@@ -0,0 +1,94 @@
+// Pixel coverage of shapes drawn in one colour. Shapes are accumulated into a mask and
+// painted once, so overlapping parts of a semi-transparent stroke do not darken.
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    pub covered: Vec<bool>,
+}
+
+impl Mask {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            covered: vec![false; width * height],
+        }
+    }
+
+    // Pixel range covering `min..=max`, clipped to `0..size`
+    fn span(min: f64, max: f64, size: usize) -> std::ops::Range<usize> {
+        let start = min.floor().max(0.0) as usize;
+        let end = (max.ceil() + 1.0).clamp(0.0, size as f64) as usize;
+        start.min(end)..end
+    }
+
+    // Fills a polygon given as rings in pixel coordinates with the even-odd rule, so
+    // holes stay empty. Pixels are covered when their centre is inside.
+    pub fn fill_polygon(&mut self, rings: &[Vec<(f64, f64)>]) {
+        let (min_y, max_y) = rings
+            .iter()
+            .flatten()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (_, y)| {
+                (min.min(*y), max.max(*y))
+            });
+        let mut crossings = Vec::new();
+        for row in Self::span(min_y, max_y, self.height) {
+            let y = row as f64 + 0.5;
+            crossings.clear();
+            for ring in rings {
+                for edge in ring.windows(2) {
+                    let ((x0, y0), (x1, y1)) = (edge[0], edge[1]);
+                    if (y0 <= y) != (y1 <= y) {
+                        crossings.push(x0 + (y - y0) / (y1 - y0) * (x1 - x0));
+                    }
+                }
+            }
+            crossings.sort_by(f64::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                let start = (pair[0] - 0.5).ceil().max(0.0) as usize;
+                let end = ((pair[1] - 0.5).floor() + 1.0).clamp(0.0, self.width as f64) as usize;
+                if start < end {
+                    self.covered[row * self.width + start..row * self.width + end].fill(true);
+                }
+            }
+        }
+    }
+
+    // Covers pixels whose centre lies within `width / 2` of the line through `points`
+    pub fn stroke_line(&mut self, points: &[(f64, f64)], width: f64) {
+        let radius = (width / 2.0).max(0.5);
+        for segment in points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (segment[0], segment[1]);
+            let (dx, dy) = (x1 - x0, y1 - y0);
+            let length_squared = dx * dx + dy * dy;
+            for row in Self::span(y0.min(y1) - radius, y0.max(y1) + radius, self.height) {
+                let py = row as f64 + 0.5;
+                for column in Self::span(x0.min(x1) - radius, x0.max(x1) + radius, self.width) {
+                    let px = column as f64 + 0.5;
+                    let t = if length_squared > 0.0 {
+                        (((px - x0) * dx + (py - y0) * dy) / length_squared).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    let (cx, cy) = (x0 + t * dx - px, y0 + t * dy - py);
+                    if cx * cx + cy * cy <= radius * radius {
+                        self.covered[row * self.width + column] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, (x, y): (f64, f64), radius: f64) {
+        let radius = radius.max(0.5);
+        for row in Self::span(y - radius, y + radius, self.height) {
+            let dy = row as f64 + 0.5 - y;
+            for column in Self::span(x - radius, x + radius, self.width) {
+                let dx = column as f64 + 0.5 - x;
+                if dx * dx + dy * dy <= radius * radius {
+                    self.covered[row * self.width + column] = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+use super::canvas::{parse_color, Viewport};
+use super::composite::{composite, CompositeLayer, LayerStyle};
+use super::raster::RasterStyle;
+use super::vector::VectorStyle;
+use super::RenderTicket;
+use crate::crs::parse_srs;
+use crate::datasets::{DatasetEntry, DatasetRegistry};
+use crate::progress::Progress;
+use crate::raster::translate::translate;
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+// One open dataset drawn with its default style, or a stack of styled layers as in
+// `render_composite`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ViewLayers {
+    Handle(u64),
+    Layers(Vec<CompositeLayer>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewFormat {
+    // RGBA GeoTIFF carrying its georeferencing
+    Geotiff,
+    // RGBA PNG with a `.pgw` world file; the CRS goes in a `.aux.xml` sidecar
+    Png,
+}
+
+impl ViewFormat {
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            ViewFormat::Geotiff => "tif",
+            ViewFormat::Png => "png",
+        }
+    }
+
+    pub(crate) fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("png") => ViewFormat::Png,
+            _ => ViewFormat::Geotiff,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewOutput {
+    pub path: String,
+    // Picked from the extension when unset, GeoTIFF unless it is `.png`
+    #[serde(default)]
+    pub format: Option<ViewFormat>,
+    // `#rrggbb` or `#rrggbbaa` behind the layers, transparent when unset
+    #[serde(default)]
+    pub background: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ViewExport {
+    pub path: String,
+    pub format: ViewFormat,
+    pub width: usize,
+    pub height: usize,
+    pub world_file: Option<String>,
+}
+
+// ESRI world file: pixel size and rotation, then the centre of the top left pixel
+fn write_world_file(path: &Path, viewport: &Viewport) -> Result<(), GdalError> {
+    let [x_origin, pixel_width, row_rotation, y_origin, column_rotation, pixel_height] =
+        viewport.geo_transform();
+    let lines = [
+        pixel_width,
+        column_rotation,
+        row_rotation,
+        pixel_height,
+        x_origin + pixel_width / 2.0,
+        y_origin + pixel_height / 2.0,
+    ];
+    let text: String = lines.iter().map(|value| format!("{}\n", value)).collect();
+    fs::write(path, text)?;
+    Ok(())
+}
+
+impl ViewLayers {
+    // Registry entries of the layers, in drawing order
+    pub(crate) fn entries(
+        &self,
+        registry: &DatasetRegistry,
+    ) -> Result<Vec<DatasetEntry>, GdalError> {
+        match self {
+            ViewLayers::Handle(handle) => Ok(vec![registry.get(*handle)?]),
+            ViewLayers::Layers(layers) => layers
+                .iter()
+                .map(|layer| registry.get(layer.handle))
+                .collect(),
+        }
+    }
+
+    // The layers to composite; a lone dataset gets the style the map starts out with
+    pub(crate) fn into_layers(self, entries: &[DatasetEntry]) -> Vec<CompositeLayer> {
+        match self {
+            ViewLayers::Layers(layers) => layers,
+            ViewLayers::Handle(handle) => {
+                let is_raster = entries[0].lock().unwrap().dataset.raster_count() > 0;
+                let style = if is_raster {
+                    LayerStyle::Raster(RasterStyle::default())
+                } else {
+                    LayerStyle::Vector(VectorStyle::default())
+                };
+                vec![CompositeLayer {
+                    handle,
+                    opacity: 1.0,
+                    blend: Default::default(),
+                    style,
+                }]
+            }
+        }
+    }
+}
+
+// Renders `layers` into the viewport and writes the georeferenced image to `path`.
+// Returns the world file written beside PNGs.
+pub(crate) fn write_view(
+    entries: &[DatasetEntry],
+    layers: &[CompositeLayer],
+    viewport: &Viewport,
+    background: Option<[u8; 4]>,
+    path: &str,
+    format: ViewFormat,
+    progress: &Progress,
+) -> Result<Option<String>, GdalError> {
+    let canvas = composite(
+        entries,
+        layers,
+        viewport,
+        background,
+        &RenderTicket::detached(),
+    )?
+    .ok_or_else(|| GdalError::OperationFailed("Export was cancelled".to_string()))?;
+
+    let mut image = canvas.to_dataset()?;
+    image.set_geo_transform(&viewport.geo_transform())?;
+    image.set_spatial_ref(&parse_srs(&viewport.crs)?)?;
+
+    let args: Vec<String> = match format {
+        ViewFormat::Geotiff => vec![
+            "-of",
+            "GTiff",
+            "-co",
+            "COMPRESS=DEFLATE",
+            "-co",
+            "TILED=YES",
+        ],
+        ViewFormat::Png => vec!["-of", "PNG"],
+    }
+    .into_iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    translate(&image, path, &args, progress)?.close()?;
+
+    match format {
+        ViewFormat::Geotiff => Ok(None),
+        ViewFormat::Png => {
+            let world_file = Path::new(path).with_extension("pgw");
+            write_world_file(&world_file, viewport)?;
+            Ok(Some(world_file.to_string_lossy().to_string()))
+        }
+    }
+}
+
+// Saves what the map shows: renders `layers` over `bbox` in `crs` at `width` x `height`
+// pixels, the same way `render_composite` does, and writes the image with its
+// georeferencing so it lines up in other GIS software
+#[tauri::command]
+pub async fn export_view(
+    app: AppHandle,
+    layers: ViewLayers,
+    bbox: Extent,
+    crs: String,
+    width: usize,
+    height: usize,
+    output: ViewOutput,
+) -> Result<ViewExport, String> {
+    let viewport = Viewport {
+        extent: bbox,
+        crs,
+        width,
+        height,
+    };
+    viewport.validate().map_err(|e| e.to_string())?;
+    let background = output
+        .background
+        .as_deref()
+        .map(parse_color)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let entries = layers
+        .entries(&app.state::<DatasetRegistry>())
+        .map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let layers = layers.into_layers(&entries);
+        let format = output
+            .format
+            .unwrap_or_else(|| ViewFormat::from_path(&output.path));
+        let progress = Progress::new(&app, "export_view");
+        let world_file = write_view(
+            &entries,
+            &layers,
+            &viewport,
+            background,
+            &output.path,
+            format,
+            &progress,
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(ViewExport {
+            path: output.path,
+            format,
+            width: viewport.width,
+            height: viewport.height,
+            world_file,
+        })
+    })
+    .await
+}
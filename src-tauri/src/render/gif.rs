@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use super::canvas::Canvas;
+
+// Colours are counted at five bits per channel, which keeps the histogram small
+const KEY_COUNT: usize = 1 << 15;
+// Key of pixels that are mostly transparent
+const TRANSPARENT: u16 = u16::MAX;
+// Palette index reserved for transparency
+const TRANSPARENT_INDEX: u8 = 0;
+
+const CLEAR_CODE: u16 = 256;
+const END_CODE: u16 = 257;
+const MAX_CODE: u16 = 4096;
+
+// Builds one 255 colour palette for a whole animation from the most frequent colours of
+// every frame, so colours do not shift between frames
+pub(crate) struct Quantizer {
+    histogram: Vec<u64>,
+}
+
+impl Quantizer {
+    pub fn new() -> Self {
+        Self {
+            histogram: vec![0; KEY_COUNT],
+        }
+    }
+
+    // Counts the colours of `canvas` and returns its pixels as reduced colour keys
+    pub fn add(&mut self, canvas: &Canvas) -> Vec<u16> {
+        canvas
+            .pixels
+            .chunks_exact(4)
+            .map(|pixel| {
+                if pixel[3] < 128 {
+                    return TRANSPARENT;
+                }
+                let key = ((pixel[0] as u16 >> 3) << 10)
+                    | ((pixel[1] as u16 >> 3) << 5)
+                    | (pixel[2] as u16 >> 3);
+                self.histogram[key as usize] += 1;
+                key
+            })
+            .collect()
+    }
+
+    // The palette, index 0 being transparent, and the palette index of every key
+    pub fn palette(&self) -> (Vec<[u8; 3]>, Vec<u8>) {
+        let mut keys: Vec<usize> = (0..KEY_COUNT)
+            .filter(|&key| self.histogram[key] > 0)
+            .collect();
+        keys.sort_by_key(|&key| std::cmp::Reverse(self.histogram[key]));
+        keys.truncate(255);
+
+        // Centre of each five bit bin
+        let color = |key: usize| {
+            [
+                ((key >> 10) as u8) << 3 | 4,
+                (((key >> 5) & 31) as u8) << 3 | 4,
+                ((key & 31) as u8) << 3 | 4,
+            ]
+        };
+        let mut palette = vec![[0u8; 3]; 256];
+        for (index, &key) in keys.iter().enumerate() {
+            palette[index + 1] = color(key);
+        }
+
+        // Every counted colour maps to its nearest palette entry
+        let mut lookup = vec![TRANSPARENT_INDEX; KEY_COUNT];
+        for key in (0..KEY_COUNT).filter(|&key| self.histogram[key] > 0) {
+            let [r, g, b] = color(key).map(|channel| channel as i32);
+            lookup[key] = (1..=keys.len())
+                .min_by_key(|&index| {
+                    let [pr, pg, pb] = palette[index].map(|channel| channel as i32);
+                    (r - pr).pow(2) + (g - pg).pow(2) + (b - pb).pow(2)
+                })
+                .unwrap_or(0) as u8;
+        }
+        (palette, lookup)
+    }
+}
+
+// Palette indices of a frame's colour keys
+pub(crate) fn indices(keys: &[u16], lookup: &[u8]) -> Vec<u8> {
+    keys.iter()
+        .map(|&key| match key {
+            TRANSPARENT => TRANSPARENT_INDEX,
+            key => lookup[key as usize],
+        })
+        .collect()
+}
+
+// Variable width codes packed least significant bit first
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += width;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+// GIF flavoured LZW with eight bit symbols. The code width grows once the next code to
+// be assigned no longer fits, and the table is reset when it is full.
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    let mut table: HashMap<u32, u16> = HashMap::new();
+    let mut width = 9;
+    let mut next_code = END_CODE + 1;
+    writer.write(CLEAR_CODE, width);
+
+    let Some((&first, rest)) = indices.split_first() else {
+        writer.write(END_CODE, width);
+        return writer.finish();
+    };
+    let mut prefix = first as u16;
+    for &index in rest {
+        let key = (prefix as u32) << 8 | index as u32;
+        if let Some(&code) = table.get(&key) {
+            prefix = code;
+            continue;
+        }
+        writer.write(prefix, width);
+        if next_code >= 1 << width && width < 12 {
+            width += 1;
+        }
+        if next_code < MAX_CODE {
+            table.insert(key, next_code);
+            next_code += 1;
+        } else {
+            writer.write(CLEAR_CODE, width);
+            table.clear();
+            width = 9;
+            next_code = END_CODE + 1;
+        }
+        prefix = index as u16;
+    }
+    writer.write(prefix, width);
+    if next_code >= 1 << width && width < 12 {
+        width += 1;
+    }
+    writer.write(END_CODE, width);
+    writer.finish()
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+// Animated GIF89a looping forever, each frame shown for `delay` hundredths of a second.
+// Frames hold one palette index per pixel and index 0 is transparent.
+pub(crate) fn encode(
+    width: u16,
+    height: u16,
+    palette: &[[u8; 3]],
+    frames: &[Vec<u8>],
+    delay: u16,
+) -> Vec<u8> {
+    let mut bytes = b"GIF89a".to_vec();
+    push_u16(&mut bytes, width);
+    push_u16(&mut bytes, height);
+    // Global colour table of 256 entries
+    bytes.extend_from_slice(&[0xf7, TRANSPARENT_INDEX, 0]);
+    for color in palette.iter().take(256) {
+        bytes.extend_from_slice(color);
+    }
+    // NETSCAPE2.0 application extension, with a loop count of zero for forever
+    bytes.extend_from_slice(&[0x21, 0xff, 0x0b]);
+    bytes.extend_from_slice(b"NETSCAPE2.0");
+    bytes.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    for frame in frames {
+        // Graphic control extension: restore to background between frames, with
+        // transparency so areas outside the data stay see-through
+        bytes.extend_from_slice(&[0x21, 0xf9, 0x04, 0x09]);
+        push_u16(&mut bytes, delay);
+        bytes.extend_from_slice(&[TRANSPARENT_INDEX, 0x00]);
+
+        bytes.push(0x2c);
+        push_u16(&mut bytes, 0);
+        push_u16(&mut bytes, 0);
+        push_u16(&mut bytes, width);
+        push_u16(&mut bytes, height);
+        bytes.push(0x00);
+
+        bytes.push(8);
+        for block in lzw(frame).chunks(255) {
+            bytes.push(block.len() as u8);
+            bytes.extend_from_slice(block);
+        }
+        bytes.push(0x00);
+    }
+    bytes.push(0x3b);
+    bytes
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::canvas::Canvas;
+
+// Classic 5x7 bitmap font for printable ASCII (0x20-0x7e), one byte per column with the
+// least significant bit at the top. Bit 7 holds descenders.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5f, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7f, 0x14, 0x7f, 0x14],
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x56, 0x20, 0x50],
+    [0x00, 0x08, 0x07, 0x03, 0x00],
+    [0x00, 0x1c, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1c, 0x00],
+    [0x2a, 0x1c, 0x7f, 0x1c, 0x2a],
+    [0x08, 0x08, 0x3e, 0x08, 0x08],
+    [0x00, 0x80, 0x70, 0x30, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x00, 0x60, 0x60, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3e, 0x51, 0x49, 0x45, 0x3e],
+    [0x00, 0x42, 0x7f, 0x40, 0x00],
+    [0x72, 0x49, 0x49, 0x49, 0x46],
+    [0x21, 0x41, 0x49, 0x4d, 0x33],
+    [0x18, 0x14, 0x12, 0x7f, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3c, 0x4a, 0x49, 0x49, 0x31],
+    [0x41, 0x21, 0x11, 0x09, 0x07],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x46, 0x49, 0x49, 0x29, 0x1e],
+    [0x00, 0x00, 0x14, 0x00, 0x00],
+    [0x00, 0x40, 0x34, 0x00, 0x00],
+    [0x00, 0x08, 0x14, 0x22, 0x41],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x59, 0x09, 0x06],
+    [0x3e, 0x41, 0x5d, 0x59, 0x4e],
+    [0x7c, 0x12, 0x11, 0x12, 0x7c],
+    [0x7f, 0x49, 0x49, 0x49, 0x36],
+    [0x3e, 0x41, 0x41, 0x41, 0x22],
+    [0x7f, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x49, 0x49, 0x49, 0x41],
+    [0x7f, 0x09, 0x09, 0x09, 0x01],
+    [0x3e, 0x41, 0x41, 0x51, 0x73],
+    [0x7f, 0x08, 0x08, 0x08, 0x7f],
+    [0x00, 0x41, 0x7f, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3f, 0x01],
+    [0x7f, 0x08, 0x14, 0x22, 0x41],
+    [0x7f, 0x40, 0x40, 0x40, 0x40],
+    [0x7f, 0x02, 0x1c, 0x02, 0x7f],
+    [0x7f, 0x04, 0x08, 0x10, 0x7f],
+    [0x3e, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x09, 0x09, 0x09, 0x06],
+    [0x3e, 0x41, 0x51, 0x21, 0x5e],
+    [0x7f, 0x09, 0x19, 0x29, 0x46],
+    [0x26, 0x49, 0x49, 0x49, 0x32],
+    [0x03, 0x01, 0x7f, 0x01, 0x03],
+    [0x3f, 0x40, 0x40, 0x40, 0x3f],
+    [0x1f, 0x20, 0x40, 0x20, 0x1f],
+    [0x3f, 0x40, 0x38, 0x40, 0x3f],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x03, 0x04, 0x78, 0x04, 0x03],
+    [0x61, 0x59, 0x49, 0x4d, 0x43],
+    [0x00, 0x7f, 0x41, 0x41, 0x41],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x41, 0x7f],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x03, 0x07, 0x08, 0x00],
+    [0x20, 0x54, 0x54, 0x78, 0x40],
+    [0x7f, 0x28, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x28],
+    [0x38, 0x44, 0x44, 0x28, 0x7f],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x00, 0x08, 0x7e, 0x09, 0x02],
+    [0x18, 0xa4, 0xa4, 0x9c, 0x78],
+    [0x7f, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7d, 0x40, 0x00],
+    [0x20, 0x40, 0x40, 0x3d, 0x00],
+    [0x7f, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7f, 0x40, 0x00],
+    [0x7c, 0x04, 0x78, 0x04, 0x78],
+    [0x7c, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0xfc, 0x18, 0x24, 0x24, 0x18],
+    [0x18, 0x24, 0x24, 0x18, 0xfc],
+    [0x7c, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x24],
+    [0x04, 0x04, 0x3f, 0x44, 0x24],
+    [0x3c, 0x40, 0x40, 0x20, 0x7c],
+    [0x1c, 0x20, 0x40, 0x20, 0x1c],
+    [0x3c, 0x40, 0x30, 0x40, 0x3c],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x4c, 0x90, 0x90, 0x90, 0x7c],
+    [0x44, 0x64, 0x54, 0x4c, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x77, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x02, 0x01, 0x02, 0x04, 0x02],
+];
+
+// Font cell in unscaled pixels: five columns plus one of spacing, eight rows
+const CELL_WIDTH: usize = 6;
+const CELL_HEIGHT: usize = 8;
+
+// Coverage mask of one character at one scale
+pub(crate) struct Glyph {
+    pub width: usize,
+    pub height: usize,
+    pub mask: Vec<bool>,
+}
+
+fn rasterize_glyph(character: char, scale: usize) -> Glyph {
+    let code = character as usize;
+    // Characters outside printable ASCII are shown as '?'
+    let columns = FONT[if (0x20..0x7f).contains(&code) {
+        code - 0x20
+    } else {
+        b'?' as usize - 0x20
+    }];
+
+    let (width, height) = (CELL_WIDTH * scale, CELL_HEIGHT * scale);
+    let mut mask = vec![false; width * height];
+    for (column, bits) in columns.iter().enumerate() {
+        for row in 0..CELL_HEIGHT {
+            if bits & (1 << row) == 0 {
+                continue;
+            }
+            for dy in 0..scale {
+                let start = (row * scale + dy) * width + column * scale;
+                mask[start..start + scale].fill(true);
+            }
+        }
+    }
+    Glyph {
+        width,
+        height,
+        mask,
+    }
+}
+
+// Glyphs are scaled up once per size and reused by every tile and label afterwards
+type GlyphCache = Mutex<HashMap<(char, usize), Arc<Glyph>>>;
+
+static GLYPHS: OnceLock<GlyphCache> = OnceLock::new();
+
+pub(crate) fn glyph(character: char, scale: usize) -> Arc<Glyph> {
+    let mut glyphs = GLYPHS.get_or_init(Default::default).lock().unwrap();
+    glyphs
+        .entry((character, scale))
+        .or_insert_with(|| Arc::new(rasterize_glyph(character, scale)))
+        .clone()
+}
+
+// Integer scale of the bitmap font closest to a text height in pixels
+pub(crate) fn font_scale(size: f64) -> usize {
+    ((size / CELL_HEIGHT as f64).round() as usize).clamp(1, 8)
+}
+
+// Width and height of `text` in pixels
+pub(crate) fn text_size(text: &str, scale: usize) -> (usize, usize) {
+    (
+        text.chars().count() * CELL_WIDTH * scale,
+        CELL_HEIGHT * scale,
+    )
+}
+
+// Draws `text` centred on (x, y), with a one pixel (per scale step) halo when given
+pub(crate) fn draw_text(
+    canvas: &mut Canvas,
+    x: f64,
+    y: f64,
+    text: &str,
+    scale: usize,
+    color: [u8; 4],
+    halo: Option<[u8; 4]>,
+) {
+    let (width, height) = text_size(text, scale);
+    let left = (x - width as f64 / 2.0).round() as isize;
+    let top = (y - height as f64 / 2.0).round() as isize;
+
+    let passes: &[(Option<[u8; 4]>, isize)] = &[(halo, scale as isize), (Some(color), 0)];
+    for &(color, spread) in passes {
+        let Some(color) = color else {
+            continue;
+        };
+        let mut covered =
+            vec![false; (width + 2 * spread as usize) * (height + 2 * spread as usize)];
+        let stride = width + 2 * spread as usize;
+        for (index, character) in text.chars().enumerate() {
+            let glyph = glyph(character, scale);
+            let origin = index * CELL_WIDTH * scale;
+            for gy in 0..glyph.height {
+                for gx in 0..glyph.width {
+                    if !glyph.mask[gy * glyph.width + gx] {
+                        continue;
+                    }
+                    for dy in 0..=2 * spread as usize {
+                        for dx in 0..=2 * spread as usize {
+                            covered[(gy + dy) * stride + origin + gx + dx] = true;
+                        }
+                    }
+                }
+            }
+        }
+        for (index, _) in covered.iter().enumerate().filter(|(_, covered)| **covered) {
+            let px = left - spread + (index % stride) as isize;
+            let py = top - spread + (index / stride) as isize;
+            canvas.blend_pixel(px, py, color);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use super::canvas::Canvas;
+use super::glyphs::{draw_text, text_size};
+
+// Empty space kept around each label, in pixels
+const PADDING: f64 = 2.0;
+
+// Positions along a line tried for its label, as fractions of its length
+const LINE_POSITIONS: &[f64] = &[0.5, 0.35, 0.65, 0.2, 0.8];
+
+// Rows across a polygon tried for its label, as fractions of its height
+const POLYGON_ROWS: &[f64] = &[0.5, 0.4, 0.6, 0.3, 0.7, 0.2, 0.8];
+
+// A label waiting to be placed. Coordinates are pixels of the image being drawn.
+pub(crate) struct Label {
+    pub text: String,
+    // Centres to try, most preferred first
+    pub candidates: Vec<(f64, f64)>,
+    pub scale: usize,
+    pub color: [u8; 4],
+    pub halo: Option<[u8; 4]>,
+    // Labels of larger features are placed first
+    pub priority: f64,
+}
+
+#[derive(Clone, Copy)]
+struct Rect {
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+}
+
+impl Rect {
+    fn intersects(&self, other: &Rect) -> bool {
+        self.min_x < other.max_x
+            && other.min_x < self.max_x
+            && self.min_y < other.max_y
+            && other.min_y < self.max_y
+    }
+}
+
+// Greedy placement: each label takes its first candidate position that lies fully on
+// the image and overlaps no label placed before it, or is dropped
+pub(crate) struct LabelPlacer {
+    width: f64,
+    height: f64,
+    placed: Vec<Rect>,
+}
+
+impl LabelPlacer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width: width as f64,
+            height: height as f64,
+            placed: Vec::new(),
+        }
+    }
+
+    fn place(&mut self, label: &Label) -> Option<(f64, f64)> {
+        let (width, height) = text_size(&label.text, label.scale);
+        let (half_width, half_height) =
+            (width as f64 / 2.0 + PADDING, height as f64 / 2.0 + PADDING);
+        for &(x, y) in &label.candidates {
+            let rect = Rect {
+                min_x: x - half_width,
+                min_y: y - half_height,
+                max_x: x + half_width,
+                max_y: y + half_height,
+            };
+            let on_image = rect.min_x >= 0.0
+                && rect.min_y >= 0.0
+                && rect.max_x <= self.width
+                && rect.max_y <= self.height;
+            if on_image && !self.placed.iter().any(|placed| placed.intersects(&rect)) {
+                self.placed.push(rect);
+                return Some((x, y));
+            }
+        }
+        None
+    }
+
+    // Places and draws `labels`, highest priority first
+    pub fn draw(&mut self, canvas: &mut Canvas, mut labels: Vec<Label>) {
+        labels.sort_by(|a, b| b.priority.total_cmp(&a.priority));
+        for label in labels {
+            if let Some((x, y)) = self.place(&label) {
+                draw_text(
+                    canvas,
+                    x,
+                    y,
+                    &label.text,
+                    label.scale,
+                    label.color,
+                    label.halo,
+                );
+            }
+        }
+    }
+}
+
+// Right, left, above and below a point symbol of `radius`, then on the point itself
+pub(crate) fn point_candidates(
+    (x, y): (f64, f64),
+    radius: f64,
+    text: (usize, usize),
+) -> Vec<(f64, f64)> {
+    let dx = radius + PADDING + text.0 as f64 / 2.0;
+    let dy = radius + PADDING + text.1 as f64 / 2.0;
+    vec![(x + dx, y), (x - dx, y), (x, y - dy), (x, y + dy), (x, y)]
+}
+
+fn line_length(points: &[(f64, f64)]) -> f64 {
+    points
+        .windows(2)
+        .map(|segment| (segment[1].0 - segment[0].0).hypot(segment[1].1 - segment[0].1))
+        .sum()
+}
+
+// Positions spread along the line, centred on it. Lines shorter than the text are
+// not labelled.
+pub(crate) fn line_candidates(points: &[(f64, f64)], text: (usize, usize)) -> Vec<(f64, f64)> {
+    let length = line_length(points);
+    if length < text.0 as f64 {
+        return Vec::new();
+    }
+    LINE_POSITIONS
+        .iter()
+        .filter_map(|fraction| {
+            let mut remaining = length * fraction;
+            for segment in points.windows(2) {
+                let ((x0, y0), (x1, y1)) = (segment[0], segment[1]);
+                let segment_length = (x1 - x0).hypot(y1 - y0);
+                if remaining <= segment_length && segment_length > 0.0 {
+                    let t = remaining / segment_length;
+                    return Some((x0 + t * (x1 - x0), y0 + t * (y1 - y0)));
+                }
+                remaining -= segment_length;
+            }
+            None
+        })
+        .collect()
+}
+
+// Where row `y` crosses the rings, sorted; consecutive pairs are inside the polygon
+fn crossings(rings: &[Vec<(f64, f64)>], y: f64) -> Vec<f64> {
+    let mut crossings = Vec::new();
+    for ring in rings {
+        for edge in ring.windows(2) {
+            let ((x0, y0), (x1, y1)) = (edge[0], edge[1]);
+            if (y0 <= y) != (y1 <= y) {
+                crossings.push(x0 + (y - y0) / (y1 - y0) * (x1 - x0));
+            }
+        }
+    }
+    crossings.sort_by(f64::total_cmp);
+    crossings
+}
+
+fn inside(rings: &[Vec<(f64, f64)>], y: f64, min_x: f64, max_x: f64) -> bool {
+    crossings(rings, y)
+        .chunks_exact(2)
+        .any(|span| span[0] <= min_x && max_x <= span[1])
+}
+
+// Centres of interior spans wide enough for the text, where the whole label box stays
+// inside the polygon; the middle of the widest middle span comes last as a fallback
+pub(crate) fn polygon_candidates(
+    rings: &[Vec<(f64, f64)>],
+    text: (usize, usize),
+) -> Vec<(f64, f64)> {
+    let Some(exterior) = rings.first() else {
+        return Vec::new();
+    };
+    let (min_y, max_y) = exterior
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (_, y)| {
+            (min.min(*y), max.max(*y))
+        });
+    let (half_width, half_height) = (text.0 as f64 / 2.0, text.1 as f64 / 2.0);
+
+    let mut candidates = Vec::new();
+    for fraction in POLYGON_ROWS {
+        let y = min_y + (max_y - min_y) * fraction;
+        for span in crossings(rings, y).chunks_exact(2) {
+            let x = (span[0] + span[1]) / 2.0;
+            let (min_x, max_x) = (x - half_width, x + half_width);
+            if inside(rings, y, min_x, max_x)
+                && inside(rings, y - half_height, min_x, max_x)
+                && inside(rings, y + half_height, min_x, max_x)
+            {
+                candidates.push((x, y));
+            }
+        }
+    }
+    let middle = crossings(rings, (min_y + max_y) / 2.0);
+    if let Some(span) = middle
+        .chunks_exact(2)
+        .max_by(|a, b| (a[1] - a[0]).total_cmp(&(b[1] - b[0])))
+    {
+        candidates.push(((span[0] + span[1]) / 2.0, (min_y + max_y) / 2.0));
+    }
+    candidates
+}
@@ -0,0 +1,168 @@
+use gdal::spatial_ref::SpatialRef;
+use gdal::Dataset;
+use std::ptr;
+
+use super::canvas::Viewport;
+use crate::raster::Resampling;
+use crate::{ffi, GdalError};
+
+// Largest reprojection error of the approximate transformer, in source pixels
+const MAX_ERROR: f64 = 0.125;
+
+// Wraps `source` in a warped VRT in EPSG:3857 with an alpha band after the source bands,
+// transparent outside the source and on nodata. Nothing is written to disk and pixels
+// are only warped when read, using the source overviews when zoomed out. The VRT points
+// at `source`, which must outlive it.
+pub(crate) fn mercator_vrt(source: &Dataset) -> Result<Dataset, GdalError> {
+    if source.raster_count() == 0 {
+        return Err(GdalError::InvalidArgument(
+            "Only raster datasets can be shown in Web Mercator".to_string(),
+        ));
+    }
+    if source.spatial_ref().is_err() {
+        return Err(GdalError::InvalidArgument(
+            "Dataset has no CRS, assign one to show it on the map".to_string(),
+        ));
+    }
+    let dst_wkt = ffi::c_string(&SpatialRef::from_epsg(3857)?.to_wkt()?)?;
+    let alpha_max = ffi::c_string("DST_ALPHA_MAX")?;
+    let alpha_max_value = ffi::c_string("255")?;
+
+    unsafe {
+        let options = gdal_sys::GDALCreateWarpOptions();
+        (*options).nDstAlphaBand = source.raster_count() as i32 + 1;
+        (*options).papszWarpOptions = gdal_sys::CSLSetNameValue(
+            (*options).papszWarpOptions,
+            alpha_max.as_ptr(),
+            alpha_max_value.as_ptr(),
+        );
+        let vrt = gdal_sys::GDALAutoCreateWarpedVRTEx(
+            source.c_dataset(),
+            ptr::null(),
+            dst_wkt.as_ptr(),
+            gdal_sys::GDALResampleAlg::GRA_NearestNeighbour,
+            MAX_ERROR,
+            options,
+            ptr::null_mut(),
+        );
+        gdal_sys::GDALDestroyWarpOptions(options);
+
+        if vrt.is_null() {
+            return Err(ffi::last_error("GDALAutoCreateWarpedVRTEx"));
+        }
+        Ok(Dataset::from_c_dataset(vrt))
+    }
+}
+
+// Reads `band` of `dataset` over a fractional pixel window into a `width` x `height`
+// buffer
+fn read_window(
+    dataset: &Dataset,
+    band: usize,
+    window: (f64, f64, f64, f64),
+    (width, height): (usize, usize),
+    resampling: Resampling,
+) -> Result<Vec<f64>, GdalError> {
+    let (x_off, y_off, x_size, y_size) = window;
+    let (raster_width, raster_height) = dataset.raster_size();
+    // GDAL wants the enclosing whole-pixel window as well
+    let x0 = x_off.floor().max(0.0) as usize;
+    let y0 = y_off.floor().max(0.0) as usize;
+    let x1 = ((x_off + x_size).ceil() as usize).clamp(x0 + 1, raster_width);
+    let y1 = ((y_off + y_size).ceil() as usize).clamp(y0 + 1, raster_height);
+
+    let raster_band = dataset.rasterband(band)?;
+    let mut data = vec![0.0f64; width * height];
+    let mut extra = gdal_sys::GDALRasterIOExtraArg {
+        nVersion: 1,
+        eResampleAlg: resampling.as_resample_alg().to_gdal(),
+        pfnProgress: None,
+        pProgressData: ptr::null_mut(),
+        bFloatingPointWindowValidity: 1,
+        dfXOff: x_off,
+        dfYOff: y_off,
+        dfXSize: x_size,
+        dfYSize: y_size,
+    };
+    let result = unsafe {
+        gdal_sys::GDALRasterIOEx(
+            raster_band.c_rasterband(),
+            gdal_sys::GDALRWFlag::GF_Read,
+            x0 as i32,
+            y0 as i32,
+            (x1 - x0) as i32,
+            (y1 - y0) as i32,
+            data.as_mut_ptr() as *mut _,
+            width as i32,
+            height as i32,
+            gdal_sys::GDALDataType::GDT_Float64,
+            0,
+            0,
+            &mut extra,
+        )
+    };
+    if result != gdal_sys::CPLErr::CE_None {
+        return Err(ffi::last_error("GDALRasterIOEx"));
+    }
+    Ok(data)
+}
+
+// Samples `bands` and the alpha band of a Web Mercator VRT onto the viewport grid,
+// returning one value vector per band and the alpha values. Parts of the viewport
+// outside the VRT stay transparent.
+pub(crate) fn read_viewport(
+    vrt: &Dataset,
+    bands: &[usize],
+    viewport: &Viewport,
+    resampling: Resampling,
+) -> Result<(Vec<Vec<f64>>, Vec<f64>), GdalError> {
+    let (width, height) = (viewport.width, viewport.height);
+    let mut channels = vec![vec![0.0; width * height]; bands.len()];
+    let mut alpha = vec![0.0; width * height];
+
+    // The VRT is north up, so the viewport maps to an axis-aligned pixel window
+    let transform = vrt.geo_transform()?;
+    let (raster_width, raster_height) = vrt.raster_size();
+    let extent = &viewport.extent;
+    let x_off = (extent.min_x - transform[0]) / transform[1];
+    let y_off = (extent.max_y - transform[3]) / transform[5];
+    let x_scale = (extent.max_x - extent.min_x) / transform[1] / width as f64;
+    let y_scale = (extent.min_y - extent.max_y) / transform[5] / height as f64;
+
+    // Viewport pixels covered by the VRT
+    let column = |x: f64| ((x - x_off) / x_scale).round().clamp(0.0, width as f64) as usize;
+    let row = |y: f64| ((y - y_off) / y_scale).round().clamp(0.0, height as f64) as usize;
+    let (left, right) = (column(0.0), column(raster_width as f64));
+    let (top, bottom) = (row(0.0), row(raster_height as f64));
+    if left >= right || top >= bottom {
+        return Ok((channels, alpha));
+    }
+
+    let clamp_x = |x: f64| x.clamp(0.0, raster_width as f64);
+    let clamp_y = |y: f64| y.clamp(0.0, raster_height as f64);
+    let window_x = clamp_x(x_off + left as f64 * x_scale);
+    let window_y = clamp_y(y_off + top as f64 * y_scale);
+    let window = (
+        window_x,
+        window_y,
+        clamp_x(x_off + right as f64 * x_scale) - window_x,
+        clamp_y(y_off + bottom as f64 * y_scale) - window_y,
+    );
+    if window.2 <= 0.0 || window.3 <= 0.0 {
+        return Ok((channels, alpha));
+    }
+    let size = (right - left, bottom - top);
+
+    let place = |target: &mut Vec<f64>, data: Vec<f64>| {
+        for (index, line) in data.chunks_exact(size.0).enumerate() {
+            let start = (top + index) * width + left;
+            target[start..start + size.0].copy_from_slice(line);
+        }
+    };
+    for (channel, &band) in channels.iter_mut().zip(bands) {
+        place(channel, read_window(vrt, band, window, size, resampling)?);
+    }
+    let alpha_data = read_window(vrt, vrt.raster_count(), window, size, resampling)?;
+    place(&mut alpha, alpha_data);
+    Ok((channels, alpha))
+}
@@ -0,0 +1,114 @@
+pub mod animation;
+pub mod cache;
+pub mod canvas;
+pub mod composite;
+pub mod draw;
+pub mod export;
+pub(crate) mod gif;
+pub(crate) mod glyphs;
+pub(crate) mod labels;
+pub(crate) mod mercator;
+pub mod palette;
+pub mod preview;
+pub mod query;
+pub mod ramp;
+pub mod raster;
+pub mod rgb;
+pub mod sheets;
+pub mod stretch;
+pub mod styled;
+pub mod swipe;
+pub mod tiles;
+pub mod vector;
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_void};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::State;
+
+// How long a render waits for a newer request on the same view before doing any work
+pub const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(40);
+
+// Tracks the latest render generation per view so superseded renders can bail out early.
+// Rapid pans produce one request per frame; only the newest one per view is worth finishing.
+#[derive(Default)]
+pub struct RenderQueue {
+    views: Mutex<HashMap<String, Arc<AtomicU64>>>,
+}
+
+impl RenderQueue {
+    // Registers a new render for `view`, superseding every render already in flight for it
+    pub fn begin(&self, view: &str) -> RenderTicket {
+        let mut views = self.views.lock().unwrap();
+        let latest = views.entry(view.to_string()).or_default().clone();
+        let generation = latest.fetch_add(1, Ordering::SeqCst) + 1;
+
+        RenderTicket { latest, generation }
+    }
+
+    // Supersedes all outstanding renders for `view` without starting a new one
+    pub fn cancel(&self, view: &str) {
+        if let Some(latest) = self.views.lock().unwrap().get(view) {
+            latest.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+pub struct RenderTicket {
+    latest: Arc<AtomicU64>,
+    generation: u64,
+}
+
+impl RenderTicket {
+    // Ticket that is never superseded, for renders not tied to a view such as tiles
+    pub fn detached() -> Self {
+        RenderTicket {
+            latest: Arc::new(AtomicU64::new(0)),
+            generation: 0,
+        }
+    }
+
+    pub fn is_superseded(&self) -> bool {
+        self.latest.load(Ordering::SeqCst) != self.generation
+    }
+
+    // Waits out the debounce interval, returns false if a newer request arrived meanwhile
+    pub fn debounce(&self) -> bool {
+        thread::sleep(DEBOUNCE_INTERVAL);
+        !self.is_superseded()
+    }
+
+    // Pointer handed to GDAL as pProgressArg for `abort_if_superseded`
+    pub fn as_arg(&self) -> *mut c_void {
+        self as *const RenderTicket as *mut c_void
+    }
+}
+
+/// GDAL progress callback that aborts RasterIO/warp work once the ticket is superseded.
+///
+/// # Safety
+/// `arg` must be null or come from `RenderTicket::as_arg` on a ticket that outlives the call.
+pub unsafe extern "C" fn abort_if_superseded(
+    _complete: f64,
+    _message: *const c_char,
+    arg: *mut c_void,
+) -> c_int {
+    if arg.is_null() {
+        return 1;
+    }
+
+    let ticket = &*(arg as *const RenderTicket);
+    if ticket.is_superseded() {
+        0
+    } else {
+        1
+    }
+}
+
+#[tauri::command]
+pub fn cancel_render(queue: State<'_, RenderQueue>, view_id: String) {
+    queue.cancel(&view_id);
+}
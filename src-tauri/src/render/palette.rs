@@ -0,0 +1,89 @@
+use gdal::raster::{PaletteInterpretation, RasterBand};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::datasets::DatasetRegistry;
+use crate::{run_blocking, setup_gdal_runtime};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaletteEntry {
+    // Pixel value the colour is used for
+    pub value: usize,
+    // `#rrggbbaa`
+    pub color: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColorTableInfo {
+    // How GDAL stores the entries: gray, rgba, cmyk or hls. Colours are always
+    // returned as RGBA.
+    pub interpretation: String,
+    pub entries: Vec<PaletteEntry>,
+}
+
+// RGBA colour per pixel value of a band's colour table, None when the band has none
+pub(crate) fn band_palette(band: &RasterBand) -> Option<Vec<[u8; 4]>> {
+    let table = band.color_table()?;
+    let colors = (0..table.entry_count())
+        .map(|index| {
+            table.entry_as_rgb(index).map_or([0; 4], |entry| {
+                [entry.r, entry.g, entry.b, entry.a].map(|channel| channel.clamp(0, 255) as u8)
+            })
+        })
+        .collect();
+    Some(colors)
+}
+
+// Colour of `value` in `palette`; values without an entry are transparent
+pub(crate) fn palette_color(palette: &[[u8; 4]], value: f64) -> [u8; 4] {
+    if value.is_nan() || value < 0.0 {
+        return [0; 4];
+    }
+    palette.get(value as usize).copied().unwrap_or([0; 4])
+}
+
+// Reads the colour table of a band, e.g. the class colours of a land cover GeoTIFF.
+// Returns None when the band has no colour table.
+#[tauri::command]
+pub async fn get_color_table(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    band: Option<usize>,
+) -> Result<Option<ColorTableInfo>, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let raster_band = open
+            .dataset
+            .rasterband(band.unwrap_or(1))
+            .map_err(|e| e.to_string())?;
+        let Some(table) = raster_band.color_table() else {
+            return Ok(None);
+        };
+        let interpretation = match table.palette_interpretation() {
+            PaletteInterpretation::Gray => "gray",
+            PaletteInterpretation::Rgba => "rgba",
+            PaletteInterpretation::Cmyk => "cmyk",
+            PaletteInterpretation::Hls => "hls",
+        };
+        let entries = band_palette(&raster_band)
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(value, [r, g, b, a])| PaletteEntry {
+                value,
+                color: format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+            })
+            .collect();
+
+        Ok(Some(ColorTableInfo {
+            interpretation: interpretation.to_string(),
+            entries,
+        }))
+    })
+    .await
+}
@@ -0,0 +1,109 @@
+use gdal::Dataset;
+use tauri::ipc::Response;
+use tauri::State;
+
+use super::canvas::{Canvas, RenderedImage};
+use super::raster::select_bands;
+use super::stretch::{scale, Stretch};
+use crate::datasets::DatasetRegistry;
+use crate::raster::read::{read_reduced, read_reduced_mask};
+use crate::raster::Resampling;
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+const DEFAULT_MAX_SIZE: usize = 512;
+
+// Output size fitting the raster within `max_size` pixels, keeping its aspect ratio
+pub(crate) fn preview_size((width, height): (usize, usize), max_size: usize) -> (usize, usize) {
+    let factor = (max_size as f64 / width.max(height) as f64).min(1.0);
+    (
+        ((width as f64 * factor).round() as usize).max(1),
+        ((height as f64 * factor).round() as usize).max(1),
+    )
+}
+
+// Reads the whole raster reduced to `size` through its overviews and stretches each band with the matching
+// entry of `stretches`; pixels masked by nodata, an alpha band or a mask file are
+// transparent
+pub(crate) fn render_bands(
+    source: &Dataset,
+    bands: &[usize],
+    size: (usize, usize),
+    stretches: &[Stretch],
+) -> Result<Canvas, GdalError> {
+    let (width, height) = source.raster_size();
+    let window = (0, 0, width, height);
+    let mut channels = Vec::new();
+    for &band in bands {
+        let values =
+            read_reduced::<f64>(&source.rasterband(band)?, window, size, Resampling::Nearest)?;
+        channels.push(values);
+    }
+    // GDAL's mask band covers nodata, alpha bands and .msk files alike
+    let mask = read_reduced_mask(&source.rasterband(bands[0])?, window, size)?;
+
+    let ranges = channels
+        .iter()
+        .zip(stretches)
+        .map(|(values, stretch)| {
+            let valid: Vec<f64> = values
+                .iter()
+                .zip(&mask)
+                .filter(|(value, mask)| **mask != 0 && value.is_finite())
+                .map(|(value, _)| *value)
+                .collect();
+            stretch.range(&valid).unwrap_or((0.0, 0.0))
+        })
+        .collect::<Vec<_>>();
+
+    let mut canvas = Canvas::new(size.0, size.1);
+    for (index, pixel) in canvas.pixels.chunks_exact_mut(4).enumerate() {
+        if mask[index] == 0 {
+            continue;
+        }
+        for (channel, value) in pixel.iter_mut().take(3).enumerate() {
+            let band = channel.min(channels.len() - 1);
+            *value = scale(channels[band][index], ranges[band]);
+        }
+        pixel[3] = 255;
+    }
+    Ok(canvas)
+}
+
+// Quick look of an open raster for showing right after it is opened: the whole extent
+// at no more than `max_size` pixels per side, one band as grayscale or three as RGB,
+// stretched over the preview's own values (2-98% percentiles by default)
+#[tauri::command]
+pub async fn render_preview(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    max_size: Option<usize>,
+    band_selection: Option<Vec<usize>>,
+    stretch: Option<Stretch>,
+) -> Result<Response, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let max_size = max_size.unwrap_or(DEFAULT_MAX_SIZE);
+        if max_size == 0 {
+            return Err("Preview size must be positive".to_string());
+        }
+        let open = entry.lock().unwrap();
+        if open.dataset.raster_count() == 0 {
+            return Err("Dataset has no raster bands".to_string());
+        }
+        let bands =
+            select_bands(&open.dataset, band_selection.as_deref()).map_err(|e| e.to_string())?;
+        let size = preview_size(open.dataset.raster_size(), max_size);
+
+        let stretches = vec![stretch.unwrap_or_default(); bands.len()];
+        let canvas =
+            render_bands(&open.dataset, &bands, size, &stretches).map_err(|e| e.to_string())?;
+        RenderedImage::from_canvas(&canvas)
+            .map(RenderedImage::into_response)
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,223 @@
+use gdal::spatial_ref::CoordTransform;
+use gdal::vector::{geometry_type_flatten, Geometry, LayerAccess, OGRwkbGeometryType};
+use gdal::GeoTransform;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use super::canvas::Viewport;
+use super::vector::VectorStyle;
+use crate::crs::{parse_srs, transformer};
+use crate::datasets::DatasetRegistry;
+use crate::vector::filter::FeatureFilter;
+use crate::vector::{feature_to_geojson, layer_by_name};
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+// Extra pixels around a symbol that still count as a hit, so thin lines can be clicked
+const DEFAULT_TOLERANCE: f64 = 3.0;
+
+// Most features returned for one click
+const MAX_HITS: usize = 50;
+
+// Pixel position in the rendered image, from its top left corner
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScreenPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+// Tests geometries against a click in pixel space, with the symbol sizes they were
+// drawn with
+struct HitTest {
+    gt: GeoTransform,
+    point: (f64, f64),
+    point_radius: f64,
+    half_stroke: f64,
+    tolerance: f64,
+}
+
+fn distance_to_segment((px, py): (f64, f64), (x0, y0): (f64, f64), (x1, y1): (f64, f64)) -> f64 {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((px - x0) * dx + (py - y0) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (x0 + t * dx - px).hypot(y0 + t * dy - py)
+}
+
+impl HitTest {
+    fn pixels(&self, geometry: &Geometry) -> Vec<(f64, f64)> {
+        let mut points = Vec::new();
+        geometry.get_points(&mut points);
+        points
+            .into_iter()
+            .map(|(x, y, _)| ((x - self.gt[0]) / self.gt[1], (y - self.gt[3]) / self.gt[5]))
+            .collect()
+    }
+
+    fn near_line(&self, points: &[(f64, f64)]) -> bool {
+        points.windows(2).any(|segment| {
+            distance_to_segment(self.point, segment[0], segment[1])
+                <= self.half_stroke + self.tolerance
+        })
+    }
+
+    // Even-odd test, matching how polygons are filled
+    fn inside(&self, rings: &[Vec<(f64, f64)>]) -> bool {
+        let (px, py) = self.point;
+        let mut inside = false;
+        for ring in rings {
+            for edge in ring.windows(2) {
+                let ((x0, y0), (x1, y1)) = (edge[0], edge[1]);
+                if (y0 <= py) != (y1 <= py) && px < x0 + (py - y0) / (y1 - y0) * (x1 - x0) {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    fn hits(&self, geometry: &Geometry) -> bool {
+        match geometry_type_flatten(geometry.geometry_type()) {
+            OGRwkbGeometryType::wkbPoint => {
+                let center = self.pixels(geometry)[0];
+                (center.0 - self.point.0).hypot(center.1 - self.point.1)
+                    <= self.point_radius + self.half_stroke + self.tolerance
+            }
+            OGRwkbGeometryType::wkbLineString | OGRwkbGeometryType::wkbLinearRing => {
+                self.near_line(&self.pixels(geometry))
+            }
+            OGRwkbGeometryType::wkbPolygon => {
+                let rings: Vec<_> = (0..geometry.geometry_count())
+                    .map(|index| self.pixels(&geometry.get_geometry(index)))
+                    .collect();
+                self.inside(&rings) || rings.iter().any(|ring| self.near_line(ring))
+            }
+            _ => {
+                (0..geometry.geometry_count()).any(|index| self.hits(&geometry.get_geometry(index)))
+            }
+        }
+    }
+}
+
+fn query(
+    dataset: &gdal::Dataset,
+    layer: Option<&str>,
+    screen_point: ScreenPoint,
+    viewport: &Viewport,
+    style: &VectorStyle,
+    tolerance: f64,
+) -> Result<Vec<Value>, GdalError> {
+    viewport.validate()?;
+    let viewport_srs = parse_srs(&viewport.crs)?;
+    let gt = viewport.geo_transform();
+    let test = HitTest {
+        gt,
+        point: (screen_point.x, screen_point.y),
+        point_radius: style.point_radius,
+        half_stroke: style.stroke_width / 2.0,
+        tolerance,
+    };
+
+    // Only features whose envelope comes within reach of the click are read
+    let reach = style.point_radius + style.stroke_width + tolerance;
+    let x = gt[0] + screen_point.x * gt[1];
+    let y = gt[3] + screen_point.y * gt[5];
+    let mut filter = Extent {
+        min_x: x - reach * gt[1],
+        min_y: y + reach * gt[5],
+        max_x: x + reach * gt[1],
+        max_y: y - reach * gt[5],
+    };
+
+    let mut layer = layer_by_name(dataset, layer)?;
+    let transform: Option<CoordTransform> = match layer.spatial_ref() {
+        Some(layer_srs) => {
+            filter = filter.transform(&viewport_srs, &layer_srs)?;
+            Some(transformer(&layer_srs, &viewport_srs)?)
+        }
+        None => None,
+    };
+    // Features hidden by the style's filter cannot be clicked
+    let feature_filter = style
+        .filter
+        .as_deref()
+        .map(|filter| FeatureFilter::parse(filter, &layer))
+        .transpose()?;
+    layer.set_spatial_filter_rect(filter.min_x, filter.min_y, filter.max_x, filter.max_y);
+
+    let mut hits = Vec::new();
+    let result = (|| {
+        if let Some(filter) = &feature_filter {
+            filter.apply(&mut layer)?;
+        }
+        for feature in layer.features() {
+            let Some(geometry) = feature.geometry() else {
+                continue;
+            };
+            if feature_filter
+                .as_ref()
+                .is_some_and(|filter| !filter.matches(Some(geometry)))
+            {
+                continue;
+            }
+            let Ok(geometry) = transform
+                .as_ref()
+                .map_or_else(|| Ok(geometry.clone()), |t| geometry.transform(t))
+            else {
+                continue;
+            };
+            if test.hits(&geometry) {
+                hits.push(feature_to_geojson(&feature, transform.as_ref())?);
+            }
+        }
+        Ok::<_, GdalError>(())
+    })();
+    // The layer stays open in the registry, so later reads must see every feature again
+    layer.clear_spatial_filter();
+    layer.clear_attribute_filter();
+    result?;
+
+    // Features drawn last are on top
+    hits.reverse();
+    hits.truncate(MAX_HITS);
+    Ok(hits)
+}
+
+// Maps a click on a server-rendered vector layer back to the features under it, so
+// identify and selection work without the features being loaded in the webview. Pass
+// the style the layer was rendered with so hits match the drawn symbol sizes. Returns
+// GeoJSON features in the viewport CRS, topmost first.
+#[tauri::command]
+pub async fn query_rendered_features(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    layer: Option<String>,
+    screen_point: ScreenPoint,
+    viewport: Viewport,
+    style: Option<VectorStyle>,
+    tolerance: Option<f64>,
+) -> Result<Vec<Value>, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let style = style.unwrap_or_default();
+        let layer = layer.or_else(|| style.layer.clone());
+        let open = entry.lock().unwrap();
+        query(
+            &open.dataset,
+            layer.as_deref(),
+            screen_point,
+            &viewport,
+            &style,
+            tolerance.unwrap_or(DEFAULT_TOLERANCE),
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use super::canvas::parse_color;
+use super::stretch::scale;
+use crate::GdalError;
+
+// Matplotlib's viridis, perceptually uniform and readable with colour blindness
+const VIRIDIS: &[&str] = &[
+    "#440154", "#472d7b", "#3b528b", "#2c728e", "#21918c", "#28ae80", "#5ec962", "#addc30",
+    "#fde725",
+];
+
+// Matplotlib's terrain: water blues through lowland greens to brown peaks and snow
+const TERRAIN: &[(f64, &str)] = &[
+    (0.0, "#333399"),
+    (0.15, "#0099ff"),
+    (0.25, "#00cc66"),
+    (0.5, "#ffff99"),
+    (0.75, "#805c54"),
+    (1.0, "#ffffff"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorStop {
+    // In band units
+    pub value: f64,
+    pub color: String,
+}
+
+// Named ramps span the display range; custom stops carry their own values
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ColorRamp {
+    Viridis,
+    Terrain,
+    #[default]
+    Grayscale,
+    Custom {
+        stops: Vec<ColorStop>,
+    },
+}
+
+// 256 colours evenly spread over a value range, looked up per pixel
+pub(crate) struct ColorLut {
+    range: (f64, f64),
+    colors: Vec<[u8; 4]>,
+}
+
+impl ColorLut {
+    // Lookup table for `ramp`, with named ramps stretched over `range`
+    pub fn new(ramp: &ColorRamp, range: (f64, f64)) -> Result<Self, GdalError> {
+        let at = |fraction: f64| range.0 + fraction * (range.1 - range.0);
+        let stops: Vec<(f64, [u8; 4])> = match ramp {
+            ColorRamp::Viridis => VIRIDIS
+                .iter()
+                .enumerate()
+                .map(|(index, color)| {
+                    let fraction = index as f64 / (VIRIDIS.len() - 1) as f64;
+                    Ok((at(fraction), parse_color(color)?))
+                })
+                .collect::<Result<_, GdalError>>()?,
+            ColorRamp::Terrain => TERRAIN
+                .iter()
+                .map(|(fraction, color)| Ok((at(*fraction), parse_color(color)?)))
+                .collect::<Result<_, GdalError>>()?,
+            ColorRamp::Grayscale => {
+                vec![(range.0, [0, 0, 0, 255]), (range.1, [255, 255, 255, 255])]
+            }
+            ColorRamp::Custom { stops } => {
+                let mut stops = stops
+                    .iter()
+                    .map(|stop| Ok((stop.value, parse_color(&stop.color)?)))
+                    .collect::<Result<Vec<_>, GdalError>>()?;
+                stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+                stops
+            }
+        };
+        let (Some(first), Some(last)) = (stops.first(), stops.last()) else {
+            return Err(GdalError::InvalidArgument(
+                "A colour ramp needs at least one stop".to_string(),
+            ));
+        };
+        let range = (first.0, last.0);
+
+        let colors = (0..256)
+            .map(|index| {
+                let value = range.0 + index as f64 / 255.0 * (range.1 - range.0);
+                let upper = stops
+                    .iter()
+                    .position(|stop| stop.0 >= value)
+                    .unwrap_or(stops.len() - 1);
+                if upper == 0 {
+                    return stops[0].1;
+                }
+                let ((v0, c0), (v1, c1)) = (stops[upper - 1], stops[upper]);
+                let t = if v1 > v0 {
+                    (value - v0) / (v1 - v0)
+                } else {
+                    1.0
+                };
+                std::array::from_fn(|channel| {
+                    let (from, to) = (c0[channel] as f64, c1[channel] as f64);
+                    (from + t * (to - from)).round() as u8
+                })
+            })
+            .collect();
+        Ok(Self { range, colors })
+    }
+
+    // Values outside the ramp take the colour of its nearest end
+    pub fn color(&self, value: f64) -> [u8; 4] {
+        self.colors[scale(value, self.range) as usize]
+    }
+}
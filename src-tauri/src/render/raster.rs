@@ -0,0 +1,246 @@
+use gdal::raster::{ColorInterpretation, GdalDataType};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use std::ptr;
+
+use super::canvas::{Canvas, Viewport};
+use super::mercator::read_viewport;
+use super::palette::{band_palette, palette_color};
+use super::stretch::scale;
+use super::{abort_if_superseded, RenderTicket};
+use crate::datasets::OpenDataset;
+use crate::raster::Resampling;
+use crate::{ffi, GdalError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RasterStyle {
+    // One band drawn as grayscale or three drawn as red, green and blue. Defaults to the
+    // first three bands when there are at least three, the first band otherwise.
+    pub bands: Option<Vec<usize>>,
+    // Linear stretch applied to every band; the band's range when unset, 0-255 for bytes
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub resampling: Resampling,
+    // Colours a single band through its colour table when it has one, instead of the
+    // stretch. Paletted bands are always resampled with nearest neighbour.
+    pub color_table: bool,
+}
+
+impl Default for RasterStyle {
+    fn default() -> Self {
+        Self {
+            bands: None,
+            min: None,
+            max: None,
+            resampling: Resampling::default(),
+            color_table: true,
+        }
+    }
+}
+
+// Warps `bands` of `source` onto the viewport grid as a MEM dataset with an extra alpha
+// band that is transparent outside the source and on nodata. Aborts once `ticket` is
+// superseded.
+pub(crate) fn warp_to_viewport(
+    source: &Dataset,
+    bands: &[usize],
+    viewport: &Viewport,
+    resampling: Resampling,
+    ticket: &RenderTicket,
+) -> Result<Dataset, GdalError> {
+    let mut args = vec![
+        "-of".to_string(),
+        "MEM".to_string(),
+        "-t_srs".to_string(),
+        viewport.crs.clone(),
+        "-te".to_string(),
+    ];
+    args.extend(viewport.extent.to_args());
+    args.extend([
+        "-ts".to_string(),
+        viewport.width.to_string(),
+        viewport.height.to_string(),
+        "-r".to_string(),
+        resampling.as_gdal_arg().to_string(),
+        "-dstalpha".to_string(),
+    ]);
+    for band in bands {
+        args.extend(["-srcband".to_string(), band.to_string()]);
+    }
+    // A source alpha band, e.g. from an RGBA PNG, keeps masking the warped pixels
+    let alpha_band = (1..=source.raster_count()).find(|&index| {
+        source
+            .rasterband(index)
+            .is_ok_and(|band| band.color_interpretation() == ColorInterpretation::AlphaBand)
+    });
+    if let Some(alpha_band) = alpha_band.filter(|band| !bands.contains(band)) {
+        args.extend([
+            "-srcband".to_string(),
+            alpha_band.to_string(),
+            "-srcalpha".to_string(),
+        ]);
+    }
+
+    let argv = ffi::arg_list(&args)?;
+    let c_dst = ffi::c_string("")?;
+    let mut handles = [source.c_dataset()];
+    unsafe {
+        let options = gdal_sys::GDALWarpAppOptionsNew(argv.as_ptr(), ptr::null_mut());
+        if options.is_null() {
+            return Err(ffi::last_error("GDALWarpAppOptionsNew"));
+        }
+        gdal_sys::GDALWarpAppOptionsSetProgress(
+            options,
+            Some(abort_if_superseded),
+            ticket.as_arg(),
+        );
+
+        let mut usage_error = 0;
+        let result = gdal_sys::GDALWarp(
+            c_dst.as_ptr(),
+            ptr::null_mut(),
+            1,
+            handles.as_mut_ptr(),
+            options,
+            &mut usage_error,
+        );
+        gdal_sys::GDALWarpAppOptionsFree(options);
+
+        if result.is_null() {
+            return Err(ffi::last_error("GDALWarp"));
+        }
+        Ok(Dataset::from_c_dataset(result))
+    }
+}
+
+// One band for grayscale or three for RGB; by default the first three bands when there
+// are at least three, the first band otherwise
+pub(crate) fn select_bands(
+    source: &Dataset,
+    bands: Option<&[usize]>,
+) -> Result<Vec<usize>, GdalError> {
+    let count = source.raster_count();
+    let bands = match bands {
+        Some(bands) => bands.to_vec(),
+        None if count >= 3 => vec![1, 2, 3],
+        None => vec![1],
+    };
+    if bands.len() != 1 && bands.len() != 3 {
+        return Err(GdalError::InvalidArgument(
+            "Select one band, or three for an RGB composite".to_string(),
+        ));
+    }
+    if let Some(band) = bands.iter().find(|&&band| band == 0 || band > count) {
+        return Err(GdalError::InvalidArgument(format!(
+            "Band {} does not exist",
+            band
+        )));
+    }
+    Ok(bands)
+}
+
+// Stretch range of one source band
+fn band_range(source: &Dataset, band: usize, style: &RasterStyle) -> Result<(f64, f64), GdalError> {
+    if let (Some(min), Some(max)) = (style.min, style.max) {
+        return Ok((min, max));
+    }
+    let raster_band = source.rasterband(band)?;
+    let (min, max) = if raster_band.band_type() == GdalDataType::UInt8 {
+        (0.0, 255.0)
+    } else {
+        let range = raster_band.compute_raster_min_max(true)?;
+        (range.min, range.max)
+    };
+    Ok((style.min.unwrap_or(min), style.max.unwrap_or(max)))
+}
+
+// What a raster render reads and how its values become colours
+struct RasterPlan {
+    bands: Vec<usize>,
+    palette: Option<Vec<[u8; 4]>>,
+    ranges: Vec<(f64, f64)>,
+    resampling: Resampling,
+}
+
+fn plan(source: &Dataset, style: &RasterStyle) -> Result<RasterPlan, GdalError> {
+    let bands = select_bands(source, style.bands.as_deref())?;
+    let palette = match bands.as_slice() {
+        [band] if style.color_table => band_palette(&source.rasterband(*band)?),
+        _ => None,
+    };
+    let ranges = bands
+        .iter()
+        .map(|&band| band_range(source, band, style))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Interpolating between class values would produce unrelated classes
+    let resampling = match (&palette, style.resampling) {
+        (Some(_), Resampling::Mode) => Resampling::Mode,
+        (Some(_), _) => Resampling::Nearest,
+        (None, resampling) => resampling,
+    };
+    Ok(RasterPlan {
+        bands,
+        palette,
+        ranges,
+        resampling,
+    })
+}
+
+// Colours band values already sampled onto the viewport grid
+fn paint(plan: &RasterPlan, channels: &[Vec<f64>], alpha: &[f64], viewport: &Viewport) -> Canvas {
+    let mut canvas = Canvas::new(viewport.width, viewport.height);
+    if let Some(palette) = &plan.palette {
+        for (index, pixel) in canvas.pixels.chunks_exact_mut(4).enumerate() {
+            let [r, g, b, a] = palette_color(palette, channels[0][index]);
+            let alpha = alpha[index].clamp(0.0, 255.0) as u16;
+            pixel.copy_from_slice(&[r, g, b, (a as u16 * alpha / 255) as u8]);
+        }
+        return canvas;
+    }
+    for (index, pixel) in canvas.pixels.chunks_exact_mut(4).enumerate() {
+        for (channel, value) in pixel.iter_mut().take(3).enumerate() {
+            let band = channel.min(channels.len() - 1);
+            *value = scale(channels[band][index], plan.ranges[band]);
+        }
+        pixel[3] = alpha[index].clamp(0.0, 255.0) as u8;
+    }
+    canvas
+}
+
+// Draws a raster dataset into the viewport with a linear stretch, or through the colour
+// table of a paletted band
+pub(crate) fn render_raster(
+    source: &Dataset,
+    viewport: &Viewport,
+    style: &RasterStyle,
+    ticket: &RenderTicket,
+) -> Result<Canvas, GdalError> {
+    let plan = plan(source, style)?;
+    let warped = warp_to_viewport(source, &plan.bands, viewport, plan.resampling, ticket)?;
+
+    let (width, height) = (viewport.width, viewport.height);
+    let read = |index: usize| -> Result<Vec<f64>, GdalError> {
+        Ok(warped
+            .rasterband(index)?
+            .read_as::<f64>((0, 0), (width, height), (width, height), None)?
+            .into_shape_and_vec()
+            .1)
+    };
+    let channels = (1..=plan.bands.len()).map(read).collect::<Result<Vec<_>, _>>()?;
+    let alpha = read(warped.raster_count())?;
+    Ok(paint(&plan, &channels, &alpha, viewport))
+}
+
+// Same as `render_raster` for EPSG:3857 viewports, sampling the dataset's cached Web
+// Mercator VRT instead of warping the source for every render
+pub(crate) fn render_raster_mercator(
+    open: &mut OpenDataset,
+    viewport: &Viewport,
+    style: &RasterStyle,
+) -> Result<Canvas, GdalError> {
+    let plan = plan(&open.dataset, style)?;
+    let vrt = open.mercator()?;
+    let (channels, alpha) = read_viewport(vrt, &plan.bands, viewport, plan.resampling)?;
+    Ok(paint(&plan, &channels, &alpha, viewport))
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Response;
+use tauri::State;
+
+use super::canvas::RenderedImage;
+use super::preview::{preview_size, render_bands};
+use super::stretch::Stretch;
+use crate::datasets::DatasetRegistry;
+use crate::{run_blocking, setup_gdal_runtime};
+
+// Larger rasters are read through overviews down to this many pixels per side
+const MAX_SIZE: usize = 4096;
+
+// One stretch for all three bands, or one per band in red, green, blue order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StretchParams {
+    PerBand([Stretch; 3]),
+    All(Stretch),
+}
+
+impl Default for StretchParams {
+    fn default() -> Self {
+        StretchParams::All(Stretch::default())
+    }
+}
+
+impl StretchParams {
+    fn per_band(self) -> Vec<Stretch> {
+        match self {
+            StretchParams::PerBand(stretches) => stretches.to_vec(),
+            StretchParams::All(stretch) => vec![stretch; 3],
+        }
+    }
+}
+
+// Composites three bands as red, green and blue, e.g. 4/3/2 for Landsat 8 true colour or
+// 8/4/3 for Sentinel-2 false colour infrared. Each band is stretched on its own values
+// (2-98% percentiles by default), which balances bands with different radiometry.
+#[tauri::command]
+pub async fn render_rgb(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    r_band: usize,
+    g_band: usize,
+    b_band: usize,
+    stretch_params: Option<StretchParams>,
+) -> Result<Response, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let bands = [r_band, g_band, b_band];
+        for band in bands {
+            if band == 0 || band > open.dataset.raster_count() {
+                return Err(format!("Band {} does not exist", band));
+            }
+        }
+        let size = preview_size(open.dataset.raster_size(), MAX_SIZE);
+
+        let stretches = stretch_params.unwrap_or_default().per_band();
+        let canvas =
+            render_bands(&open.dataset, &bands, size, &stretches).map_err(|e| e.to_string())?;
+        RenderedImage::from_canvas(&canvas)
+            .map(RenderedImage::into_response)
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
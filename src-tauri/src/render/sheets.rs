@@ -0,0 +1,383 @@
+use gdal::vector::{
+    Feature, FieldDefn, Geometry, LayerAccess, LayerOptions, OGRFieldType, OGRwkbGeometryType,
+};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+use super::canvas::{parse_color, Viewport};
+use super::export::{write_view, ViewFormat, ViewLayers};
+use crate::crs::parse_srs;
+use crate::datasets::DatasetRegistry;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::vector::create_output;
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+const LAYER_NAME: &str = "sheets";
+
+// Sheets in one index, enough for a national 1:25k series
+const MAX_SHEETS: usize = 20_000;
+
+// Ground size of one sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SheetSize {
+    // Width and height in CRS units
+    Ground {
+        width: f64,
+        height: f64,
+    },
+    // The map area of a paper sheet at a scale, e.g. 1:25000 on 400 x 400 mm. Needs a
+    // projected CRS.
+    Scale {
+        scale: f64,
+        paper_width_mm: f64,
+        paper_height_mm: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SheetNaming {
+    // Row letters from the top and column numbers from the left, e.g. B7 or AA12
+    #[default]
+    RowColumn,
+    // 1, 2, 3... row by row from the top left, zero padded to the same width
+    Numbered,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheetGrid {
+    pub size: SheetSize,
+    #[serde(default)]
+    pub naming: SheetNaming,
+    // Put in front of every sheet name, e.g. "NT-"
+    #[serde(default)]
+    pub prefix: Option<String>,
+    // Snaps sheet edges to multiples of the sheet size, as fixed national series do,
+    // instead of starting at the top left corner of the area of interest
+    #[serde(default)]
+    pub align: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sheet {
+    pub name: String,
+    pub row: usize,
+    pub column: usize,
+    pub extent: Extent,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SheetIndex {
+    pub path: String,
+    pub layer: String,
+    pub sheets: Vec<Sheet>,
+}
+
+// A, B, ... Z, AA, AB, ...
+fn row_letters(mut row: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'A' + (row % 26) as u8);
+        if row < 26 {
+            break;
+        }
+        row = row / 26 - 1;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap_or_default()
+}
+
+// Width and height of a sheet in units of `crs`
+fn ground_size(size: &SheetSize, crs: &str) -> Result<(f64, f64), GdalError> {
+    let (width, height) = match *size {
+        SheetSize::Ground { width, height } => (width, height),
+        SheetSize::Scale {
+            scale,
+            paper_width_mm,
+            paper_height_mm,
+        } => {
+            let srs = parse_srs(crs)?;
+            if !srs.is_projected() {
+                return Err(GdalError::InvalidArgument(
+                    "Sheets at a map scale need a projected CRS".to_string(),
+                ));
+            }
+            let metres_per_unit = srs.linear_units();
+            (
+                paper_width_mm / 1000.0 * scale / metres_per_unit,
+                paper_height_mm / 1000.0 * scale / metres_per_unit,
+            )
+        }
+    };
+    if !(width > 0.0 && height > 0.0) {
+        return Err(GdalError::InvalidArgument(
+            "Sheet size must be positive".to_string(),
+        ));
+    }
+    Ok((width, height))
+}
+
+// Lays sheets over `aoi`, row by row from the top left
+pub(crate) fn plan_sheets(
+    aoi: &Extent,
+    crs: &str,
+    grid: &SheetGrid,
+) -> Result<Vec<Sheet>, GdalError> {
+    if aoi.max_x <= aoi.min_x || aoi.max_y <= aoi.min_y {
+        return Err(GdalError::InvalidArgument(
+            "Area of interest is empty".to_string(),
+        ));
+    }
+    let (width, height) = ground_size(&grid.size, crs)?;
+    let (left, top) = if grid.align {
+        (
+            (aoi.min_x / width).floor() * width,
+            (aoi.max_y / height).ceil() * height,
+        )
+    } else {
+        (aoi.min_x, aoi.max_y)
+    };
+    let columns = ((aoi.max_x - left) / width).ceil().max(1.0);
+    let rows = ((top - aoi.min_y) / height).ceil().max(1.0);
+    if columns * rows > MAX_SHEETS as f64 {
+        return Err(GdalError::InvalidArgument(format!(
+            "The grid would have {} sheets, more than the limit of {}",
+            columns * rows,
+            MAX_SHEETS
+        )));
+    }
+    let (columns, rows) = (columns as usize, rows as usize);
+
+    let prefix = grid.prefix.as_deref().unwrap_or("");
+    let digits = (columns * rows).to_string().len();
+    let mut sheets = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let name = match grid.naming {
+                SheetNaming::RowColumn => format!("{}{}{}", prefix, row_letters(row), column + 1),
+                SheetNaming::Numbered => {
+                    format!("{}{:0digits$}", prefix, row * columns + column + 1)
+                }
+            };
+            let min_x = left + column as f64 * width;
+            let max_y = top - row as f64 * height;
+            sheets.push(Sheet {
+                name,
+                row,
+                column,
+                extent: Extent {
+                    min_x,
+                    min_y: max_y - height,
+                    max_x: min_x + width,
+                    max_y,
+                },
+            });
+        }
+    }
+    Ok(sheets)
+}
+
+fn write_index(dst: &str, crs: &str, sheets: &[Sheet]) -> Result<(), GdalError> {
+    let srs = parse_srs(crs)?;
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer = transaction.create_layer(LayerOptions {
+        name: LAYER_NAME,
+        srs: Some(&srs),
+        ty: OGRwkbGeometryType::wkbPolygon,
+        ..Default::default()
+    })?;
+    FieldDefn::new("name", OGRFieldType::OFTString)?.add_to_layer(&layer)?;
+    FieldDefn::new("row", OGRFieldType::OFTInteger)?.add_to_layer(&layer)?;
+    FieldDefn::new("column", OGRFieldType::OFTInteger)?.add_to_layer(&layer)?;
+
+    for sheet in sheets {
+        let e = &sheet.extent;
+        let polygon = Geometry::from_wkt(&format!(
+            "POLYGON (({0} {1}, {2} {1}, {2} {3}, {0} {3}, {0} {1}))",
+            e.min_x, e.min_y, e.max_x, e.max_y
+        ))?;
+        let mut feature = Feature::new(layer.defn())?;
+        feature.set_geometry(polygon)?;
+        feature.set_field_string(0, &sheet.name)?;
+        feature.set_field_integer(1, sheet.row as i32)?;
+        feature.set_field_integer(2, sheet.column as i32)?;
+        feature.create(&layer)?;
+    }
+    transaction.commit()?;
+    output.close()?;
+    Ok(())
+}
+
+// Generates a sheet index over an area of interest: one polygon per map sheet with its
+// name, row and column, e.g. for 1:25k sheets or a custom print grid. Export the
+// sheets with `export_sheets`.
+#[tauri::command]
+pub async fn generate_sheet_index(
+    app: AppHandle,
+    aoi: Extent,
+    crs: String,
+    grid: SheetGrid,
+    dst: String,
+) -> Result<SheetIndex, String> {
+    let params = json!({
+        "aoi": aoi,
+        "crs": crs,
+        "grid": grid,
+        "dst": dst,
+    });
+    run_job(app.clone(), "generate_sheet_index", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let sheets = plan_sheets(&aoi, &crs, &grid).map_err(|e| e.to_string())?;
+        write_index(&dst, &crs, &sheets).map_err(|e| e.to_string())?;
+
+        Ok(SheetIndex {
+            path: dst,
+            layer: LAYER_NAME.to_string(),
+            sheets,
+        })
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SheetExportOptions {
+    // Image width in pixels, 2000 when unset; the height follows the sheet's shape
+    pub width: Option<usize>,
+    // GeoTIFF when unset
+    pub format: Option<ViewFormat>,
+    // `#rrggbb` or `#rrggbbaa` behind the layers, transparent when unset
+    pub background: Option<String>,
+    // Field holding sheet names, `name` when unset; sheets without one use their FID
+    pub name_field: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedSheet {
+    pub name: String,
+    pub path: String,
+    pub world_file: Option<String>,
+}
+
+// Keeps sheet names usable as file names on every platform
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// Batch plotting: renders the map once per sheet of a sheet index, in the index's CRS,
+// and writes `<sheet name>.tif` (or `.png` with a world file) into `output_dir`
+#[tauri::command]
+pub async fn export_sheets(
+    app: AppHandle,
+    layers: ViewLayers,
+    index: String,
+    output_dir: String,
+    options: Option<SheetExportOptions>,
+) -> Result<Vec<ExportedSheet>, String> {
+    let options = options.unwrap_or_default();
+    let background = options
+        .background
+        .as_deref()
+        .map(parse_color)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let entries = layers
+        .entries(&app.state::<DatasetRegistry>())
+        .map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&index).exists() {
+            return Err(format!("File not found: {}", index));
+        }
+        let layers = layers.into_layers(&entries);
+        let index_dataset = Dataset::open(&index).map_err(|e| e.to_string())?;
+        let mut sheet_layer = index_dataset.layer(0).map_err(|e| e.to_string())?;
+        let crs = sheet_layer
+            .spatial_ref()
+            .ok_or("The sheet index has no CRS")?
+            .to_wkt()
+            .map_err(|e| e.to_string())?;
+        let name_field = options.name_field.as_deref().unwrap_or("name");
+
+        let sheets: Vec<(String, Extent)> = sheet_layer
+            .features()
+            .filter_map(|feature| {
+                let envelope = feature.geometry()?.envelope();
+                let name = feature
+                    .field_index(name_field)
+                    .and_then(|index| feature.field_as_string(index))
+                    .ok()
+                    .flatten()
+                    .unwrap_or_else(|| feature.fid().unwrap_or_default().to_string());
+                Some((
+                    name,
+                    Extent {
+                        min_x: envelope.MinX,
+                        min_y: envelope.MinY,
+                        max_x: envelope.MaxX,
+                        max_y: envelope.MaxY,
+                    },
+                ))
+            })
+            .collect();
+        if sheets.is_empty() {
+            return Err("The sheet index has no sheets".to_string());
+        }
+
+        fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
+        let format = options.format.unwrap_or(ViewFormat::Geotiff);
+        let width = options.width.unwrap_or(2000);
+        let progress = Progress::new(&app, "export_sheets");
+        let total = sheets.len() as f64;
+        let mut exported = Vec::with_capacity(sheets.len());
+        for (position, (name, extent)) in sheets.into_iter().enumerate() {
+            let aspect = (extent.max_y - extent.min_y) / (extent.max_x - extent.min_x);
+            let viewport = Viewport {
+                extent,
+                crs: crs.clone(),
+                width,
+                height: ((width as f64 * aspect).round() as usize).max(1),
+            };
+            viewport
+                .validate()
+                .map_err(|e| format!("Sheet {}: {}", name, e))?;
+
+            progress.set_range(position as f64 / total, (position + 1) as f64 / total);
+            let path = Path::new(&output_dir)
+                .join(format!("{}.{}", file_stem(&name), format.extension()))
+                .to_string_lossy()
+                .to_string();
+            let world_file = write_view(
+                &entries, &layers, &viewport, background, &path, format, &progress,
+            )
+            .map_err(|e| format!("Sheet {}: {}", name, e))?;
+            exported.push(ExportedSheet {
+                name,
+                path,
+                world_file,
+            });
+        }
+        Ok(exported)
+    })
+    .await
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+// How band values are mapped onto 0-255 for display
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Stretch {
+    // Linear between `min` and `max`, each defaulting to the data's own extreme
+    MinMax {
+        #[serde(default)]
+        min: Option<f64>,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+    // Linear between two percentiles, clipping outliers such as clouds or sensor noise
+    Percentile {
+        #[serde(default = "default_low_percentile")]
+        low: f64,
+        #[serde(default = "default_high_percentile")]
+        high: f64,
+    },
+    // Linear over the mean plus or minus `factor` standard deviations
+    StdDev {
+        #[serde(default = "default_stddev_factor")]
+        factor: f64,
+    },
+}
+
+fn default_low_percentile() -> f64 {
+    2.0
+}
+
+fn default_high_percentile() -> f64 {
+    98.0
+}
+
+fn default_stddev_factor() -> f64 {
+    2.0
+}
+
+impl Default for Stretch {
+    fn default() -> Self {
+        Stretch::Percentile {
+            low: default_low_percentile(),
+            high: default_high_percentile(),
+        }
+    }
+}
+
+impl Stretch {
+    // Display range for `values`, which must already exclude nodata; None without values
+    pub fn range(&self, values: &[f64]) -> Option<(f64, f64)> {
+        if values.is_empty() {
+            return None;
+        }
+        match self {
+            Stretch::MinMax { min, max } => {
+                let (data_min, data_max) = values
+                    .iter()
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                        (lo.min(*v), hi.max(*v))
+                    });
+                Some((min.unwrap_or(data_min), max.unwrap_or(data_max)))
+            }
+            Stretch::Percentile { low, high } => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let at = |percentile: f64| {
+                    let rank = percentile.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+                    sorted[rank.round() as usize]
+                };
+                Some((at(*low), at(*high)))
+            }
+            Stretch::StdDev { factor } => {
+                let n = values.len() as f64;
+                let mean = values.iter().sum::<f64>() / n;
+                let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+                let spread = factor * variance.sqrt();
+                Some((mean - spread, mean + spread))
+            }
+        }
+    }
+}
+
+// Maps `value` into 0-255 over `min..max`
+pub fn scale(value: f64, (min, max): (f64, f64)) -> u8 {
+    let scaled = if max > min {
+        (value - min) / (max - min)
+    } else {
+        0.0
+    };
+    (scaled.clamp(0.0, 1.0) * 255.0).round() as u8
+}
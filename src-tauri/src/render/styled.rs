@@ -0,0 +1,92 @@
+use gdal::Dataset;
+use tauri::ipc::Response;
+use tauri::State;
+
+use super::canvas::{Canvas, RenderedImage};
+use super::preview::preview_size;
+use super::ramp::{ColorLut, ColorRamp};
+use crate::datasets::DatasetRegistry;
+use crate::raster::read::{read_reduced, read_reduced_mask};
+use crate::raster::Resampling;
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+// Larger rasters are read through overviews down to this many pixels per side
+const MAX_SIZE: usize = 4096;
+
+fn render(
+    source: &Dataset,
+    band: usize,
+    lut: &ColorLut,
+    size: (usize, usize),
+    nodata_transparent: bool,
+) -> Result<Canvas, GdalError> {
+    let (width, height) = source.raster_size();
+    let window = (0, 0, width, height);
+    let raster_band = source.rasterband(band)?;
+    let values = read_reduced::<f64>(&raster_band, window, size, Resampling::Nearest)?;
+    let mask = read_reduced_mask(&raster_band, window, size)?;
+
+    let mut canvas = Canvas::new(size.0, size.1);
+    for ((pixel, value), mask) in canvas.pixels.chunks_exact_mut(4).zip(values).zip(mask) {
+        if nodata_transparent && (mask == 0 || value.is_nan()) {
+            continue;
+        }
+        pixel.copy_from_slice(&lut.color(value));
+    }
+    Ok(canvas)
+}
+
+// Colours one band through a ramp, for DEMs and analysis outputs such as slope or
+// NDVI. Named ramps span `min` to `max`, which default to the band's approximate
+// range; custom stops are placed at their own values. Nodata is transparent unless
+// `nodata_transparent` is false.
+#[tauri::command]
+pub async fn render_styled(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    band: Option<usize>,
+    ramp: Option<ColorRamp>,
+    min: Option<f64>,
+    max: Option<f64>,
+    nodata_transparent: Option<bool>,
+) -> Result<Response, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let band = band.unwrap_or(1);
+        if band == 0 || band > open.dataset.raster_count() {
+            return Err(format!("Band {} does not exist", band));
+        }
+
+        let range = match (min, max) {
+            (Some(min), Some(max)) => (min, max),
+            _ => {
+                let range = open
+                    .dataset
+                    .rasterband(band)
+                    .and_then(|band| band.compute_raster_min_max(true))
+                    .map_err(|e| e.to_string())?;
+                (min.unwrap_or(range.min), max.unwrap_or(range.max))
+            }
+        };
+        let lut = ColorLut::new(&ramp.unwrap_or_default(), range).map_err(|e| e.to_string())?;
+        let size = preview_size(open.dataset.raster_size(), MAX_SIZE);
+
+        let canvas = render(
+            &open.dataset,
+            band,
+            &lut,
+            size,
+            nodata_transparent.unwrap_or(true),
+        )
+        .map_err(|e| e.to_string())?;
+        RenderedImage::from_canvas(&canvas)
+            .map(RenderedImage::into_response)
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
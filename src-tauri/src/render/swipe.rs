@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Response;
+use tauri::State;
+
+use super::canvas::{parse_color, RenderedImage, Viewport};
+use super::composite::{composite, CompositeLayer};
+use super::RenderQueue;
+use crate::datasets::DatasetRegistry;
+use crate::ipc::Frame;
+use crate::{run_blocking, setup_gdal_runtime};
+
+// Metadata of the swipe frame, whose two parts are the PNGs in this order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SwipeImages {
+    // Left of the swipe handle, or the "before" state
+    pub before: RenderedImage,
+    // Right of the swipe handle, or the "after" state
+    pub after: RenderedImage,
+}
+
+// Renders two layer stacks for the same viewport, for swipe and before/after comparison.
+// Both sides share one render request, so panning supersedes them together and the
+// frontend never shows halves from different viewports. The frame's metadata is null when
+// superseded.
+#[tauri::command]
+pub async fn render_swipe(
+    queue: State<'_, RenderQueue>,
+    registry: State<'_, DatasetRegistry>,
+    view_id: String,
+    viewport: Viewport,
+    before: Vec<CompositeLayer>,
+    after: Vec<CompositeLayer>,
+    background: Option<String>,
+) -> Result<Response, String> {
+    viewport.validate().map_err(|e| e.to_string())?;
+    let background = background
+        .as_deref()
+        .map(parse_color)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let entries = |layers: &[CompositeLayer]| {
+        layers
+            .iter()
+            .map(|layer| registry.get(layer.handle))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())
+    };
+    let (before_entries, after_entries) = (entries(&before)?, entries(&after)?);
+    let ticket = queue.begin(&view_id);
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !ticket.debounce() {
+            return Ok(Frame::empty().into_response());
+        }
+        let render = |entries, layers| {
+            composite(entries, layers, &viewport, background, &ticket)
+                .map_err(|e| e.to_string())?
+                .map(|canvas| RenderedImage::from_canvas(&canvas).map_err(|e| e.to_string()))
+                .transpose()
+        };
+        let Some(before) = render(&before_entries, &before)? else {
+            return Ok(Frame::empty().into_response());
+        };
+        let Some(after) = render(&after_entries, &after)? else {
+            return Ok(Frame::empty().into_response());
+        };
+        let images = SwipeImages { before, after };
+        Ok(Frame::new(&images)
+            .part(&images.before.png)
+            .part(&images.after.png)
+            .into_response())
+    })
+    .await
+}
@@ -0,0 +1,215 @@
+use std::sync::Arc;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{ipc, AppHandle, Manager};
+
+use super::cache::{RenderCache, TileKey};
+use super::canvas::{RenderedImage, Viewport};
+use super::raster::{render_raster_mercator, RasterStyle};
+use super::vector::{render_vector, VectorStyle};
+use super::RenderTicket;
+use crate::datasets::DatasetRegistry;
+use crate::raster::Resampling;
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+pub const TILE_SIZE: usize = 256;
+
+// Half the width of the web mercator square, in metres
+pub(crate) const MERCATOR_HALF_WIDTH: f64 = 20_037_508.342_789_244;
+
+// Deepest zoom level accepted, well past anything a map can display
+pub(crate) const MAX_ZOOM: u8 = 30;
+
+// Extent of XYZ tile z/x/y in EPSG:3857, rows counted from the top
+pub(crate) fn tile_extent(z: u8, x: u32, y: u32) -> Result<Extent, GdalError> {
+    if z > MAX_ZOOM {
+        return Err(GdalError::InvalidArgument(format!(
+            "Zoom level {} is above {}",
+            z, MAX_ZOOM
+        )));
+    }
+    let count = 1u64 << z;
+    if x as u64 >= count || y as u64 >= count {
+        return Err(GdalError::InvalidArgument(format!(
+            "Tile {}/{}/{} does not exist",
+            z, x, y
+        )));
+    }
+
+    let size = 2.0 * MERCATOR_HALF_WIDTH / count as f64;
+    let min_x = -MERCATOR_HALF_WIDTH + x as f64 * size;
+    let max_y = MERCATOR_HALF_WIDTH - y as f64 * size;
+    Ok(Extent {
+        min_x,
+        min_y: max_y - size,
+        max_x: min_x + size,
+        max_y,
+    })
+}
+
+pub(crate) fn tile_viewport(z: u8, x: u32, y: u32, size: usize) -> Result<Viewport, GdalError> {
+    let viewport = Viewport {
+        extent: tile_extent(z, x, y)?,
+        crs: "EPSG:3857".to_string(),
+        width: size,
+        height: size,
+    };
+    viewport.validate()?;
+    Ok(viewport)
+}
+
+// Renders XYZ tile z/x/y of a vector layer, so large layers can be shown as image tiles
+// instead of sending every feature to the webview as GeoJSON. Labels are clipped at
+// tile edges. Tiles are kept in the render cache.
+#[tauri::command]
+pub async fn render_vector_tile(
+    app: AppHandle,
+    handle: u64,
+    z: u8,
+    x: u32,
+    y: u32,
+    style: Option<VectorStyle>,
+    tile_size: Option<usize>,
+) -> Result<ipc::Response, String> {
+    let entry = app
+        .state::<DatasetRegistry>()
+        .get(handle)
+        .map_err(|e| e.to_string())?;
+    let style = style.unwrap_or_default();
+    let size = tile_size.unwrap_or(TILE_SIZE);
+    let key = TileKey {
+        handle,
+        kind: "vector",
+        style: serde_json::to_string(&style).unwrap_or_default(),
+        tile: (z, x, y),
+        size,
+    };
+
+    run_blocking(move || {
+        let png = app
+            .state::<RenderCache>()
+            .get_or_render(key, || {
+                // Ensure GDAL runtime is set up
+                setup_gdal_runtime();
+
+                let viewport = tile_viewport(z, x, y, size)?;
+                let open = entry.lock().unwrap();
+                let canvas =
+                    render_vector(&open.dataset, &viewport, &style, &RenderTicket::detached())?;
+                canvas.encode_png()
+            })
+            .map_err(|e: GdalError| e.to_string())?;
+        Ok(RenderedImage::from_png(size, size, Arc::unwrap_or_clone(png)).into_response())
+    })
+    .await
+}
+
+// Custom URI scheme serving raster tiles to map libraries in the webview
+pub const TILE_SCHEME: &str = "tiles";
+
+// Handle and z/x/y from `tiles://localhost/{handle}/{z}/{x}/{y}.png`. The handle may also
+// be the host, `tiles://{handle}/{z}/{x}/{y}.png`, where the platform allows it; Windows
+// rewrites custom schemes to `http://tiles.localhost/...`.
+fn parse_tile_path(request: &Request<Vec<u8>>) -> Result<(u64, u8, u32, u32), GdalError> {
+    let uri = request.uri();
+    let host = uri
+        .host()
+        .filter(|host| host.parse::<u64>().is_ok())
+        .into_iter();
+    let segments: Vec<&str> = host
+        .chain(uri.path().split('/').filter(|segment| !segment.is_empty()))
+        .collect();
+    let invalid = || {
+        GdalError::InvalidArgument(format!(
+            "Expected {}://localhost/{{handle}}/{{z}}/{{x}}/{{y}}.png, got {}",
+            TILE_SCHEME, uri
+        ))
+    };
+    let [handle, z, x, y] = segments.as_slice() else {
+        return Err(invalid());
+    };
+    let y = y.strip_suffix(".png").unwrap_or(y);
+    Ok((
+        handle.parse().map_err(|_| invalid())?,
+        z.parse().map_err(|_| invalid())?,
+        x.parse().map_err(|_| invalid())?,
+        y.parse().map_err(|_| invalid())?,
+    ))
+}
+
+// Raster style from the query string: `bands=4,3,2`, `min`, `max`, `resampling` and
+// `color_table=false`, all optional
+fn parse_tile_style(request: &Request<Vec<u8>>) -> Result<RasterStyle, GdalError> {
+    let mut style = RasterStyle::default();
+    let query = request.uri().query().unwrap_or_default();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let invalid = || GdalError::InvalidArgument(format!("Invalid tile parameter '{}'", pair));
+        match key {
+            "bands" => {
+                style.bands = Some(
+                    value
+                        .split(',')
+                        .map(|band| band.parse().map_err(|_| invalid()))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            "min" => style.min = Some(value.parse().map_err(|_| invalid())?),
+            "max" => style.max = Some(value.parse().map_err(|_| invalid())?),
+            "resampling" => {
+                style.resampling =
+                    serde_json::from_value::<Resampling>(value.into()).map_err(|_| invalid())?
+            }
+            "color_table" => style.color_table = value != "false",
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(style)
+}
+
+fn render_tile_png(app: &AppHandle, request: &Request<Vec<u8>>) -> Result<Arc<Vec<u8>>, GdalError> {
+    let (handle, z, x, y) = parse_tile_path(request)?;
+    let style = parse_tile_style(request)?;
+    let entry = app.state::<DatasetRegistry>().get(handle)?;
+    let key = TileKey {
+        handle,
+        kind: "raster",
+        style: serde_json::to_string(&style).unwrap_or_default(),
+        tile: (z, x, y),
+        size: TILE_SIZE,
+    };
+
+    app.state::<RenderCache>().get_or_render(key, || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let viewport = tile_viewport(z, x, y, TILE_SIZE)?;
+        let mut open = entry.lock().unwrap();
+        let canvas = render_raster_mercator(&mut open, &viewport, &style)?;
+        canvas.encode_png()
+    })
+}
+
+// Answers one tile request of the `tiles://` scheme. Blocks while rendering, so call it
+// off the main thread.
+pub(crate) fn tile_response(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let (status, content_type, body) = match render_tile_png(app, request) {
+        Ok(png) => (StatusCode::OK, "image/png", Arc::unwrap_or_clone(png)),
+        Err(e @ (GdalError::InvalidArgument(_) | GdalError::UnknownHandle(_))) => (
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            e.to_string().into_bytes(),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "text/plain",
+            e.to_string().into_bytes(),
+        ),
+    };
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        // Webview origins differ per platform and none of them is `tiles://`
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(body)
+        .unwrap()
+}
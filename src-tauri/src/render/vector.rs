@@ -0,0 +1,319 @@
+use gdal::spatial_ref::CoordTransform;
+use gdal::vector::{geometry_type_flatten, Geometry, LayerAccess, OGRwkbGeometryType};
+use gdal::{Dataset, GeoTransform};
+use serde::{Deserialize, Serialize};
+
+use super::canvas::{parse_color, Canvas, Viewport};
+use super::draw::Mask;
+use super::glyphs::{font_scale, text_size};
+use super::labels::{line_candidates, point_candidates, polygon_candidates, Label, LabelPlacer};
+use super::RenderTicket;
+use crate::crs::{parse_srs, transformer};
+use crate::vector::filter::FeatureFilter;
+use crate::vector::layer_by_name;
+use crate::GdalError;
+
+// Features read between checks of the render ticket
+const TICKET_CHECK_INTERVAL: usize = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LabelStyle {
+    // Attribute shown as the label text
+    pub field: String,
+    // Text height in pixels, rounded to a multiple of the bitmap font size
+    pub size: f64,
+    pub color: String,
+    // Outline drawn around the text for legibility on busy backgrounds
+    pub halo: Option<String>,
+}
+
+impl Default for LabelStyle {
+    fn default() -> Self {
+        Self {
+            field: String::new(),
+            size: 8.0,
+            color: "#000000".to_string(),
+            halo: Some("#ffffff".to_string()),
+        }
+    }
+}
+
+// Colours are `#rrggbb` or `#rrggbbaa`; a part without a colour is not drawn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VectorStyle {
+    // First layer when unset
+    pub layer: Option<String>,
+    // Polygon interiors and point symbols
+    pub fill: Option<String>,
+    // Lines, polygon outlines and point symbol outlines
+    pub stroke: Option<String>,
+    // In pixels
+    pub stroke_width: f64,
+    pub point_radius: f64,
+    pub label: Option<LabelStyle>,
+    // Filter expression; only matching features are drawn
+    pub filter: Option<String>,
+}
+
+impl Default for VectorStyle {
+    fn default() -> Self {
+        Self {
+            layer: None,
+            fill: Some("#3388ff66".to_string()),
+            stroke: Some("#3388ff".to_string()),
+            stroke_width: 1.0,
+            point_radius: 3.0,
+            label: None,
+            filter: None,
+        }
+    }
+}
+
+// Accumulates the fills and strokes of every feature in pixel space
+struct Painter {
+    gt: GeoTransform,
+    fill: Option<Mask>,
+    stroke: Option<Mask>,
+    stroke_width: f64,
+    point_radius: f64,
+}
+
+impl Painter {
+    fn pixel(&self, x: f64, y: f64) -> (f64, f64) {
+        ((x - self.gt[0]) / self.gt[1], (y - self.gt[3]) / self.gt[5])
+    }
+
+    fn pixels(&self, geometry: &Geometry) -> Vec<(f64, f64)> {
+        let mut points = Vec::new();
+        geometry.get_points(&mut points);
+        points
+            .into_iter()
+            .map(|(x, y, _)| self.pixel(x, y))
+            .collect()
+    }
+
+    fn draw(&mut self, geometry: &Geometry) {
+        match geometry_type_flatten(geometry.geometry_type()) {
+            OGRwkbGeometryType::wkbPoint => {
+                let (x, y, _) = geometry.get_point(0);
+                let center = self.pixel(x, y);
+                if let Some(fill) = self.fill.as_mut() {
+                    fill.fill_circle(center, self.point_radius);
+                }
+                if let Some(stroke) = self.stroke.as_mut() {
+                    stroke.stroke_line(&circle(center, self.point_radius), self.stroke_width);
+                }
+            }
+            OGRwkbGeometryType::wkbLineString | OGRwkbGeometryType::wkbLinearRing => {
+                let points = self.pixels(geometry);
+                if let Some(stroke) = self.stroke.as_mut() {
+                    stroke.stroke_line(&points, self.stroke_width);
+                }
+            }
+            OGRwkbGeometryType::wkbPolygon => {
+                let rings: Vec<_> = (0..geometry.geometry_count())
+                    .map(|index| self.pixels(&geometry.get_geometry(index)))
+                    .collect();
+                if let Some(fill) = self.fill.as_mut() {
+                    fill.fill_polygon(&rings);
+                }
+                if let Some(stroke) = self.stroke.as_mut() {
+                    for ring in &rings {
+                        stroke.stroke_line(ring, self.stroke_width);
+                    }
+                }
+            }
+            // Multi-part geometries and collections
+            _ => {
+                for index in 0..geometry.geometry_count() {
+                    self.draw(&geometry.get_geometry(index));
+                }
+            }
+        }
+    }
+}
+
+// Closed polyline approximating a circle, for point symbol outlines
+fn circle((x, y): (f64, f64), radius: f64) -> Vec<(f64, f64)> {
+    (0..=16)
+        .map(|step| {
+            let angle = step as f64 / 16.0 * std::f64::consts::TAU;
+            (x + radius * angle.cos(), y + radius * angle.sin())
+        })
+        .collect()
+}
+
+// Candidate label positions for a feature and its priority, in pixel units
+fn label_candidates(
+    painter: &Painter,
+    geometry: &Geometry,
+    text: (usize, usize),
+) -> (Vec<(f64, f64)>, f64) {
+    match geometry_type_flatten(geometry.geometry_type()) {
+        OGRwkbGeometryType::wkbPoint => {
+            let (x, y, _) = geometry.get_point(0);
+            (
+                point_candidates(painter.pixel(x, y), painter.point_radius, text),
+                0.0,
+            )
+        }
+        OGRwkbGeometryType::wkbLineString => (
+            line_candidates(&painter.pixels(geometry), text),
+            geometry.length() / painter.gt[1].abs(),
+        ),
+        OGRwkbGeometryType::wkbPolygon => {
+            let rings: Vec<_> = (0..geometry.geometry_count())
+                .map(|index| painter.pixels(&geometry.get_geometry(index)))
+                .collect();
+            (
+                polygon_candidates(&rings, text),
+                geometry.area() / (painter.gt[1] * painter.gt[5]).abs(),
+            )
+        }
+        // Every part of multi-part geometries, the largest part first
+        _ => {
+            let mut parts: Vec<_> = (0..geometry.geometry_count())
+                .map(|index| label_candidates(painter, &geometry.get_geometry(index), text))
+                .collect();
+            parts.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let priority = parts.first().map_or(0.0, |part| part.1);
+            (
+                parts.into_iter().flat_map(|part| part.0).collect(),
+                priority,
+            )
+        }
+    }
+}
+
+// Draws the fills and strokes of a vector layer into the viewport and returns its labels
+// unplaced, so labels of several layers can avoid each other. Only features
+// intersecting the viewport are read.
+pub(crate) fn render_vector_layer(
+    source: &Dataset,
+    viewport: &Viewport,
+    style: &VectorStyle,
+    ticket: &RenderTicket,
+) -> Result<(Canvas, Vec<Label>), GdalError> {
+    let fill = style.fill.as_deref().map(parse_color).transpose()?;
+    let stroke = style.stroke.as_deref().map(parse_color).transpose()?;
+    let label_colors = match &style.label {
+        Some(label) => Some((
+            parse_color(&label.color)?,
+            label.halo.as_deref().map(parse_color).transpose()?,
+        )),
+        None => None,
+    };
+    let (width, height) = (viewport.width, viewport.height);
+    let viewport_srs = parse_srs(&viewport.crs)?;
+
+    let mut layer = layer_by_name(source, style.layer.as_deref())?;
+    let label_field = match &style.label {
+        Some(label) => Some(layer.defn().field_index(&label.field)?),
+        None => None,
+    };
+    let transform: Option<CoordTransform> = match layer.spatial_ref() {
+        Some(layer_srs) => Some(transformer(&layer_srs, &viewport_srs)?),
+        None => None,
+    };
+    let feature_filter = style
+        .filter
+        .as_deref()
+        .map(|filter| FeatureFilter::parse(filter, &layer))
+        .transpose()?;
+
+    // Symbols of features just outside the viewport may still reach into it
+    let gt = viewport.geo_transform();
+    let margin = gt[1] * (style.point_radius + style.stroke_width);
+    let mut filter = viewport.extent;
+    filter.min_x -= margin;
+    filter.min_y -= margin;
+    filter.max_x += margin;
+    filter.max_y += margin;
+    if let Some(layer_srs) = layer.spatial_ref() {
+        filter = filter.transform(&viewport_srs, &layer_srs)?;
+    }
+    if let Some(filter) = &feature_filter {
+        filter.apply(&mut layer)?;
+    }
+    layer.set_spatial_filter_rect(filter.min_x, filter.min_y, filter.max_x, filter.max_y);
+
+    let mut painter = Painter {
+        gt,
+        fill: fill.map(|_| Mask::new(width, height)),
+        stroke: stroke.map(|_| Mask::new(width, height)),
+        stroke_width: style.stroke_width,
+        point_radius: style.point_radius,
+    };
+    let mut labels = Vec::new();
+    let mut superseded = false;
+    for (index, feature) in layer.features().enumerate() {
+        if index % TICKET_CHECK_INTERVAL == 0 && ticket.is_superseded() {
+            superseded = true;
+            break;
+        }
+        let Some(geometry) = feature.geometry() else {
+            continue;
+        };
+        if feature_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.matches(Some(geometry)))
+        {
+            continue;
+        }
+        // Features outside the area where the transformation is defined are skipped
+        let Ok(geometry) = transform
+            .as_ref()
+            .map_or_else(|| Ok(geometry.clone()), |t| geometry.transform(t))
+        else {
+            continue;
+        };
+        painter.draw(&geometry);
+
+        if let (Some(field), Some(label), Some((color, halo))) =
+            (label_field, &style.label, label_colors)
+        {
+            if let Ok(Some(text)) = feature.field_as_string(field) {
+                let scale = font_scale(label.size);
+                let (candidates, priority) =
+                    label_candidates(&painter, &geometry, text_size(&text, scale));
+                labels.push(Label {
+                    text,
+                    candidates,
+                    scale,
+                    color,
+                    halo,
+                    priority,
+                });
+            }
+        }
+    }
+    // The layer stays open in the registry, so later reads must see every feature again
+    layer.clear_spatial_filter();
+    layer.clear_attribute_filter();
+    if superseded {
+        return Err(GdalError::OperationFailed("Render superseded".to_string()));
+    }
+
+    let mut canvas = Canvas::new(width, height);
+    if let (Some(mask), Some(color)) = (&painter.fill, fill) {
+        canvas.paint(mask, color);
+    }
+    if let (Some(mask), Some(color)) = (&painter.stroke, stroke) {
+        canvas.paint(mask, color);
+    }
+    Ok((canvas, labels))
+}
+
+// Draws a vector layer with its labels placed on top
+pub(crate) fn render_vector(
+    source: &Dataset,
+    viewport: &Viewport,
+    style: &VectorStyle,
+    ticket: &RenderTicket,
+) -> Result<Canvas, GdalError> {
+    let (mut canvas, labels) = render_vector_layer(source, viewport, style, ticket)?;
+    LabelPlacer::new(viewport.width, viewport.height).draw(&mut canvas, labels);
+    Ok(canvas)
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::bookmarks::Bookmark;
+use crate::catalog::CatalogSettings;
+use crate::ingest::{default_recipes, IngestRecipe};
+use crate::network::{apply_network_settings, NetworkSettings};
+use crate::notifications::NotificationSettings;
+use crate::policy::{CommandGroup, CommandPolicy};
+use crate::presets::Preset;
+use crate::render::cache::{RenderCache, DEFAULT_BUDGET_MB};
+use crate::watch::WatchFolder;
+use crate::GdalError;
+
+// Persisted application settings. New fields need `#[serde(default)]` semantics so
+// settings files written by older versions keep loading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub ingest_recipes: Vec<IngestRecipe>,
+    // Writes a checksummed `<output>.provenance.json` next to each processing output
+    pub provenance_sidecars: bool,
+    // Saved parameter sets, run with `run_preset`
+    pub presets: Vec<Preset>,
+    // Directories whose new files are run through a preset
+    pub watch_folders: Vec<WatchFolder>,
+    // Desktop and webhook notifications when long jobs finish
+    pub notifications: NotificationSettings,
+    // Named map extents, recalled with `recall_bookmark`
+    pub bookmarks: Vec<Bookmark>,
+    // Retry policy for remote datasets
+    pub network: NetworkSettings,
+    // Memory budget of the rendered tile cache, in megabytes
+    pub render_cache_mb: u64,
+    // Where the dataset catalog lives, optionally on a shared drive
+    pub catalog: CatalogSettings,
+    // Command groups turned off for this user, on top of any installation policy file
+    pub disabled_command_groups: BTreeSet<CommandGroup>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ingest_recipes: default_recipes(),
+            provenance_sidecars: true,
+            presets: Vec::new(),
+            watch_folders: Vec::new(),
+            notifications: NotificationSettings::default(),
+            bookmarks: Vec::new(),
+            network: NetworkSettings::default(),
+            render_cache_mb: DEFAULT_BUDGET_MB,
+            catalog: CatalogSettings::default(),
+            disabled_command_groups: BTreeSet::new(),
+        }
+    }
+}
+
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Mutex<Settings>,
+}
+
+impl SettingsStore {
+    // Loads settings from `path`, falling back to defaults when missing or unreadable
+    pub fn load(path: PathBuf) -> Self {
+        let settings = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update<F>(&self, f: F) -> Result<Settings, GdalError>
+    where
+        F: FnOnce(&mut Settings),
+    {
+        let mut settings = self.settings.lock().unwrap();
+        f(&mut settings);
+        self.save(&settings)?;
+        Ok(settings.clone())
+    }
+
+    fn save(&self, settings: &Settings) -> Result<(), GdalError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = serde_json::to_string_pretty(settings)
+            .map_err(|e| GdalError::OperationFailed(e.to_string()))?;
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub fn get_settings(store: State<'_, SettingsStore>) -> Settings {
+    store.get()
+}
+
+#[tauri::command]
+pub fn update_settings(
+    store: State<'_, SettingsStore>,
+    cache: State<'_, RenderCache>,
+    policy: State<'_, CommandPolicy>,
+    settings: Settings,
+) -> Result<Settings, String> {
+    apply_network_settings(&settings.network).map_err(|e| e.to_string())?;
+    cache.set_budget_mb(settings.render_cache_mb);
+    policy.set_configured(settings.disabled_command_groups.clone());
+    store
+        .update(|current| *current = settings)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,294 @@
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{field_type_to_name, geometry_type_to_name, Geometry, LayerAccess};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::crs::{crs_key, transformer};
+use crate::datasets::DatasetRegistry;
+use crate::raster::read::{read_reduced, read_reduced_mask};
+use crate::raster::Resampling;
+use crate::render::preview::preview_size;
+use crate::{dataset_info, run_blocking, setup_gdal_runtime, DatasetInfo, Extent, GdalError};
+
+// Band statistics are computed from a decimated read of at most this many pixels per
+// side, using overviews where present
+const SAMPLE_SIZE: usize = 512;
+
+const HISTOGRAM_BUCKETS: usize = 32;
+
+// Points per edge when reprojecting the footprint, so curved edges stay curved
+const FOOTPRINT_EDGE_POINTS: usize = 16;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrsSummary {
+    pub name: Option<String>,
+    // e.g. "EPSG:32633"
+    pub code: Option<String>,
+    pub geographic: bool,
+    pub units: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BandSummary {
+    pub band: usize,
+    pub data_type: String,
+    pub nodata: Option<f64>,
+    // Approximate, from the sample; None when the sample has no valid pixel
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub stddev: Option<f64>,
+    // Counts of equal-width buckets from min to max, for a small histogram chart
+    pub histogram: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldSummary {
+    pub name: String,
+    pub field_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VectorLayerSummary {
+    pub name: String,
+    pub geometry_type: String,
+    pub feature_count: u64,
+    pub fields: Vec<FieldSummary>,
+    pub extent: Option<Extent>,
+}
+
+// Everything a layer properties panel shows, in one round trip
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerSummary {
+    pub path: String,
+    pub info: DatasetInfo,
+    pub crs: Option<CrsSummary>,
+    // In the dataset's CRS
+    pub extent: Option<Extent>,
+    // GeoJSON polygon of the covered area in WGS84 longitude/latitude
+    pub footprint: Option<Value>,
+    pub bands: Vec<BandSummary>,
+    pub layers: Vec<VectorLayerSummary>,
+}
+
+fn crs_summary(srs: &SpatialRef) -> CrsSummary {
+    let units = if srs.is_geographic() {
+        srs.angular_units_name()
+    } else {
+        srs.linear_units_name()
+    };
+    CrsSummary {
+        name: srs.name(),
+        code: crs_key(srs),
+        geographic: srs.is_geographic(),
+        units,
+    }
+}
+
+fn band_summary(dataset: &Dataset, band: usize) -> Result<BandSummary, GdalError> {
+    let (width, height) = dataset.raster_size();
+    let window = (0, 0, width, height);
+    let size = preview_size((width, height), SAMPLE_SIZE);
+    let raster_band = dataset.rasterband(band)?;
+    let values = read_reduced::<f64>(&raster_band, window, size, Resampling::Nearest)?;
+    let mask = read_reduced_mask(&raster_band, window, size)?;
+    let valid: Vec<f64> = values
+        .into_iter()
+        .zip(mask)
+        .filter(|(value, mask)| *mask != 0 && value.is_finite())
+        .map(|(value, _)| value)
+        .collect();
+
+    let (min, max, mean, stddev, histogram) = if valid.is_empty() {
+        (None, None, None, None, Vec::new())
+    } else {
+        let n = valid.len() as f64;
+        let (min, max) = valid
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(*v), hi.max(*v))
+            });
+        let mean = valid.iter().sum::<f64>() / n;
+        let variance = valid.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+
+        let mut histogram = vec![0; HISTOGRAM_BUCKETS];
+        let width = (max - min) / HISTOGRAM_BUCKETS as f64;
+        for value in &valid {
+            let bucket = if width > 0.0 {
+                (((value - min) / width) as usize).min(HISTOGRAM_BUCKETS - 1)
+            } else {
+                0
+            };
+            histogram[bucket] += 1;
+        }
+        (
+            Some(min),
+            Some(max),
+            Some(mean),
+            Some(variance.sqrt()),
+            histogram,
+        )
+    };
+
+    Ok(BandSummary {
+        band,
+        data_type: raster_band.band_type().name(),
+        nodata: raster_band.no_data_value(),
+        min,
+        max,
+        mean,
+        stddev,
+        histogram,
+    })
+}
+
+fn layer_summaries(dataset: &Dataset) -> Vec<VectorLayerSummary> {
+    dataset
+        .layers()
+        .map(|layer| VectorLayerSummary {
+            name: layer.name(),
+            geometry_type: geometry_type_to_name(layer.defn().geometry_type()),
+            feature_count: layer.feature_count(),
+            fields: layer
+                .defn()
+                .fields()
+                .map(|field| FieldSummary {
+                    name: field.name(),
+                    field_type: field_type_to_name(field.field_type()),
+                })
+                .collect(),
+            extent: layer.get_extent().ok().map(|envelope| Extent {
+                min_x: envelope.MinX,
+                min_y: envelope.MinY,
+                max_x: envelope.MaxX,
+                max_y: envelope.MaxY,
+            }),
+        })
+        .collect()
+}
+
+// Union of the layer extents
+fn vector_extent(layers: &[VectorLayerSummary]) -> Option<Extent> {
+    layers
+        .iter()
+        .filter_map(|layer| layer.extent)
+        .reduce(|a, b| Extent {
+            min_x: a.min_x.min(b.min_x),
+            min_y: a.min_y.min(b.min_y),
+            max_x: a.max_x.max(b.max_x),
+            max_y: a.max_y.max(b.max_y),
+        })
+}
+
+// Outline through `corners` in order, densified and reprojected to WGS84
+fn footprint(corners: [(f64, f64); 4], srs: &SpatialRef) -> Result<Value, GdalError> {
+    let mut points = Vec::new();
+    for index in 0..4 {
+        let ((x0, y0), (x1, y1)) = (corners[index], corners[(index + 1) % 4]);
+        for step in 0..FOOTPRINT_EDGE_POINTS {
+            let t = step as f64 / FOOTPRINT_EDGE_POINTS as f64;
+            points.push(format!("{} {}", x0 + t * (x1 - x0), y0 + t * (y1 - y0)));
+        }
+    }
+    points.push(points[0].clone());
+    let polygon = Geometry::from_wkt(&format!("POLYGON (({}))", points.join(", ")))?;
+    let wgs84 = SpatialRef::from_epsg(4326)?;
+    let json = polygon.transform(&transformer(srs, &wgs84)?)?.json()?;
+    Ok(serde_json::from_str(&json).unwrap_or(Value::Null))
+}
+
+fn summarize(path: &str, dataset: &Dataset) -> Result<LayerSummary, GdalError> {
+    let layers = layer_summaries(dataset);
+    let bands = (1..=dataset.raster_count())
+        .map(|band| band_summary(dataset, band))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Rasters carry their CRS on the dataset, vector data on each layer
+    let srs = if dataset.raster_count() > 0 {
+        dataset.spatial_ref().ok()
+    } else {
+        dataset.layers().find_map(|layer| layer.spatial_ref())
+    };
+
+    let corners = if dataset.raster_count() > 0 {
+        dataset.geo_transform().ok().map(|gt| {
+            let (width, height) = dataset.raster_size();
+            let (width, height) = (width as f64, height as f64);
+            let corner = |col: f64, row: f64| {
+                (
+                    gt[0] + col * gt[1] + row * gt[2],
+                    gt[3] + col * gt[4] + row * gt[5],
+                )
+            };
+            [
+                corner(0.0, 0.0),
+                corner(width, 0.0),
+                corner(width, height),
+                corner(0.0, height),
+            ]
+        })
+    } else {
+        vector_extent(&layers).map(|e| {
+            [
+                (e.min_x, e.max_y),
+                (e.max_x, e.max_y),
+                (e.max_x, e.min_y),
+                (e.min_x, e.min_y),
+            ]
+        })
+    };
+    let extent = corners.map(|corners| {
+        corners.iter().fold(
+            Extent {
+                min_x: f64::INFINITY,
+                min_y: f64::INFINITY,
+                max_x: f64::NEG_INFINITY,
+                max_y: f64::NEG_INFINITY,
+            },
+            |e, (x, y)| Extent {
+                min_x: e.min_x.min(*x),
+                min_y: e.min_y.min(*y),
+                max_x: e.max_x.max(*x),
+                max_y: e.max_y.max(*y),
+            },
+        )
+    });
+    // A footprint that cannot be reprojected is left out rather than failing the panel
+    let footprint = match (corners, &srs) {
+        (Some(corners), Some(srs)) => footprint(corners, srs).ok(),
+        _ => None,
+    };
+
+    Ok(LayerSummary {
+        path: path.to_string(),
+        info: dataset_info(dataset),
+        crs: srs.as_ref().map(crs_summary),
+        extent,
+        footprint,
+        bands,
+        layers,
+    })
+}
+
+// Collects what a layer properties panel shows: dataset info, CRS, extent and WGS84
+// footprint, per-band statistics with a histogram, and per-layer fields and counts.
+// Band statistics are approximate, computed from an overview-sized read, and nothing
+// is written to .aux.xml sidecars.
+#[tauri::command]
+pub async fn get_layer_summary(
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+) -> Result<LayerSummary, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        summarize(&open.path, &open.dataset).map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,303 @@
+use gdal::vector::{
+    Feature, FieldDefn, Geometry, LayerAccess, LayerOptions, OGRFieldType, OGRwkbGeometryType,
+};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::{create_output, layer_by_name, point_parts};
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::raster::zonal::{Accumulator, ZonalStat, ALL_STATS};
+use crate::{setup_gdal_runtime, GdalError};
+
+// Bins created in memory before writing, a few hundred bytes each
+const MAX_BINS: usize = 5_000_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinShape {
+    // Pointy-topped hexagons, alternate rows shifted by half a bin
+    #[default]
+    Hexagon,
+    Square,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinOptions {
+    // Point layer, the first one when unset
+    pub layer: Option<String>,
+    pub shape: BinShape,
+    // Numeric fields summarised per bin, written as `<field>_<stat>`
+    pub fields: Vec<String>,
+    // Every statistic when unset or empty
+    pub stats: Option<Vec<ZonalStat>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BinReport {
+    pub path: String,
+    pub layer: String,
+    pub point_count: u64,
+    pub bin_count: u64,
+    // Features without a point geometry
+    pub skipped: u64,
+}
+
+// Points falling in one bin, with running totals of each summarised field
+struct Bin {
+    count: u64,
+    values: Vec<Accumulator>,
+}
+
+// Bins are addressed by integer coordinates: column and row for squares, axial
+// coordinates for hexagons. The grid is anchored at the CRS origin so bins of the same
+// size line up across layers.
+struct BinGrid {
+    shape: BinShape,
+    size: f64,
+}
+
+impl BinGrid {
+    // Circumradius of a hexagon whose width across the flats is the bin size
+    fn radius(&self) -> f64 {
+        self.size / 3f64.sqrt()
+    }
+
+    fn key(&self, (x, y): (f64, f64)) -> (i64, i64) {
+        match self.shape {
+            BinShape::Square => (
+                (x / self.size).floor() as i64,
+                (y / self.size).floor() as i64,
+            ),
+            BinShape::Hexagon => {
+                let radius = self.radius();
+                let q = (3f64.sqrt() / 3.0 * x - y / 3.0) / radius;
+                let r = 2.0 / 3.0 * y / radius;
+                // Round in cube coordinates, fixing the component that moved the most
+                let s = -q - r;
+                let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+                let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+                if dq > dr && dq > ds {
+                    rq = -rr - rs;
+                } else if dr > ds {
+                    rr = -rq - rs;
+                }
+                (rq as i64, rr as i64)
+            }
+        }
+    }
+
+    fn polygon(&self, (a, b): (i64, i64)) -> Result<Geometry, GdalError> {
+        let corners: Vec<(f64, f64)> = match self.shape {
+            BinShape::Square => {
+                let (x, y) = (a as f64 * self.size, b as f64 * self.size);
+                vec![
+                    (x, y),
+                    (x, y + self.size),
+                    (x + self.size, y + self.size),
+                    (x + self.size, y),
+                ]
+            }
+            BinShape::Hexagon => {
+                let radius = self.radius();
+                let x = radius * 3f64.sqrt() * (a as f64 + b as f64 / 2.0);
+                let y = radius * 1.5 * b as f64;
+                (0..6)
+                    .map(|corner| {
+                        let angle = (30.0 + 60.0 * corner as f64).to_radians();
+                        (x + radius * angle.cos(), y + radius * angle.sin())
+                    })
+                    .collect()
+            }
+        };
+        let mut ring = Geometry::empty(OGRwkbGeometryType::wkbLinearRing)?;
+        for &point in corners.iter().chain(corners.first()) {
+            ring.add_point_2d(point);
+        }
+        let mut polygon = Geometry::empty(OGRwkbGeometryType::wkbPolygon)?;
+        polygon.add_geometry(ring)?;
+        Ok(polygon)
+    }
+}
+
+fn aggregate(
+    src: &str,
+    size: f64,
+    dst: &str,
+    options: &BinOptions,
+    progress: &Progress,
+) -> Result<BinReport, GdalError> {
+    let source = Dataset::open(src)?;
+    let mut input = layer_by_name(&source, options.layer.as_deref())?;
+    let fields = options
+        .fields
+        .iter()
+        .map(|name| {
+            let index = input.defn().field_index(name).map_err(|_| {
+                GdalError::InvalidArgument(format!(
+                    "Layer {} has no field named {}",
+                    input.name(),
+                    name
+                ))
+            })?;
+            let numeric = input.defn().fields().nth(index).is_some_and(|field| {
+                matches!(
+                    field.field_type(),
+                    OGRFieldType::OFTInteger | OGRFieldType::OFTInteger64 | OGRFieldType::OFTReal
+                )
+            });
+            if !numeric {
+                return Err(GdalError::InvalidArgument(format!(
+                    "Field {} is not numeric",
+                    name
+                )));
+            }
+            Ok(index)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let stats = match &options.stats {
+        Some(stats) if !stats.is_empty() => stats.clone(),
+        _ => ALL_STATS.to_vec(),
+    };
+
+    let grid = BinGrid {
+        shape: options.shape,
+        size,
+    };
+    let mut bins: HashMap<(i64, i64), Bin> = HashMap::new();
+    let total = input.feature_count().max(1) as f64;
+    let (mut done, mut point_count, mut skipped) = (0u64, 0u64, 0u64);
+    for feature in input.features() {
+        done += 1;
+        if done.is_multiple_of(1000) {
+            progress.report(0.8 * done as f64 / total, None);
+        }
+        let mut points = Vec::new();
+        let is_point = feature
+            .geometry()
+            .is_some_and(|geometry| point_parts(geometry, &mut points));
+        if !is_point || points.is_empty() {
+            skipped += 1;
+            continue;
+        }
+        let values = fields
+            .iter()
+            .map(|&index| feature.field_as_double(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        for point in points {
+            let key = grid.key(point);
+            if !bins.contains_key(&key) && bins.len() >= MAX_BINS {
+                return Err(GdalError::InvalidArgument(format!(
+                    "More than {} bins, use a bigger bin size",
+                    MAX_BINS
+                )));
+            }
+            let bin = bins.entry(key).or_insert_with(|| Bin {
+                count: 0,
+                values: fields.iter().map(|_| Accumulator::default()).collect(),
+            });
+            bin.count += 1;
+            for (accumulator, value) in bin.values.iter_mut().zip(&values) {
+                if let Some(value) = value.filter(|value| value.is_finite()) {
+                    accumulator.add(value);
+                }
+            }
+            point_count += 1;
+        }
+    }
+
+    // The point count, then one field per summarised field and statistic
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer_name = format!("{}_bins", input.name());
+    let srs = input.spatial_ref();
+    let target = transaction.create_layer(LayerOptions {
+        name: &layer_name,
+        srs: srs.as_ref(),
+        ty: OGRwkbGeometryType::wkbPolygon,
+        ..Default::default()
+    })?;
+    FieldDefn::new("count", OGRFieldType::OFTInteger64)?.add_to_layer(&target)?;
+    for name in &options.fields {
+        for stat in &stats {
+            let field_type = match stat {
+                ZonalStat::Count => OGRFieldType::OFTInteger64,
+                _ => OGRFieldType::OFTReal,
+            };
+            FieldDefn::new(&format!("{}_{}", name, stat.name()), field_type)?
+                .add_to_layer(&target)?;
+        }
+    }
+
+    // Sorted so the output does not depend on hash order
+    progress.set_range(0.8, 1.0);
+    let mut keys: Vec<_> = bins.keys().copied().collect();
+    keys.sort_by_key(|&(a, b)| (b, a));
+    let bin_total = keys.len().max(1) as f64;
+    for (done, key) in keys.iter().enumerate() {
+        if done.is_multiple_of(1000) {
+            progress.report(done as f64 / bin_total, None);
+        }
+        let bin = &bins[key];
+        let mut feature = Feature::new(target.defn())?;
+        feature.set_geometry(grid.polygon(*key)?)?;
+        feature.set_field_integer64(0, bin.count as i64)?;
+        let mut index = 1;
+        for accumulator in &bin.values {
+            for &stat in &stats {
+                match (stat, accumulator.get(stat)) {
+                    (ZonalStat::Count, Some(value)) => {
+                        feature.set_field_integer64(index, value as i64)?
+                    }
+                    (_, Some(value)) => feature.set_field_double(index, value)?,
+                    (_, None) => {}
+                }
+                index += 1;
+            }
+        }
+        feature.create(&target)?;
+    }
+    transaction.commit()?;
+    output.close()?;
+
+    Ok(BinReport {
+        path: dst.to_string(),
+        layer: layer_name,
+        point_count,
+        bin_count: keys.len() as u64,
+        skipped,
+    })
+}
+
+// Counts the points of a layer in hexagonal or square bins `size` CRS units across, and
+// summarises numeric fields per bin. Only bins containing points are written.
+#[tauri::command]
+pub async fn bin_points(
+    app: AppHandle,
+    src: String,
+    size: f64,
+    dst: String,
+    options: Option<BinOptions>,
+) -> Result<BinReport, String> {
+    let params = json!({ "src": src, "size": size, "dst": dst, "options": options });
+    run_job(app.clone(), "bin_points", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        if !(size > 0.0 && size.is_finite()) {
+            return Err("Bin size must be positive".to_string());
+        }
+        let progress = Progress::new(&app, "bin_points");
+        aggregate(&src, size, &dst, &options.unwrap_or_default(), &progress)
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,402 @@
+use gdal::vector::{Feature, FieldDefn, LayerAccess, OGRFieldType};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::{create_layer_like, create_output, layer_by_name, point_parts};
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, Extent, GdalError};
+
+const CLUSTER_FIELD: &str = "cluster_id";
+
+const DEFAULT_MAX_ITERATIONS: usize = 100;
+
+type Point = (f64, f64);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum ClusterMethod {
+    // Density based: points with at least `min_points` points within `distance`, counting
+    // themselves, grow clusters through their neighbours. Points reached by no cluster
+    // are noise.
+    Dbscan {
+        distance: f64,
+        min_points: usize,
+    },
+    // `k` clusters around centres placed by Lloyd's algorithm, every point in one
+    KMeans {
+        k: usize,
+        #[serde(default)]
+        max_iterations: Option<usize>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClusterSummary {
+    pub id: i64,
+    pub count: u64,
+    // Mean of the cluster's points
+    pub centroid: Point,
+    pub extent: Extent,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClusterReport {
+    pub path: String,
+    pub layer: String,
+    pub point_count: u64,
+    pub cluster_count: u64,
+    pub noise_count: u64,
+    // Features without a point geometry, written without a cluster
+    pub skipped: u64,
+    pub clusters: Vec<ClusterSummary>,
+}
+
+fn distance_squared(a: Point, b: Point) -> f64 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+// Points bucketed by cells of the search distance, so a neighbourhood query only looks
+// at the 3x3 cells around a point
+struct PointIndex<'a> {
+    points: &'a [Point],
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl<'a> PointIndex<'a> {
+    fn new(points: &'a [Point], cell: f64) -> Self {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (index, &point) in points.iter().enumerate() {
+            cells.entry(Self::key(point, cell)).or_default().push(index);
+        }
+        Self {
+            points,
+            cell,
+            cells,
+        }
+    }
+
+    fn key(point: Point, cell: f64) -> (i64, i64) {
+        (
+            (point.0 / cell).floor() as i64,
+            (point.1 / cell).floor() as i64,
+        )
+    }
+
+    fn neighbours(&self, index: usize) -> Vec<usize> {
+        let point = self.points[index];
+        let (column, row) = Self::key(point, self.cell);
+        let limit = self.cell * self.cell;
+        let mut found = Vec::new();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if let Some(cell) = self.cells.get(&(column + dx, row + dy)) {
+                    found.extend(
+                        cell.iter()
+                            .copied()
+                            .filter(|&other| distance_squared(point, self.points[other]) <= limit),
+                    );
+                }
+            }
+        }
+        found
+    }
+}
+
+// Cluster of every point, None for noise; clusters are numbered from 1 in the order
+// they are found
+fn dbscan(
+    points: &[Point],
+    distance: f64,
+    min_points: usize,
+    progress: &Progress,
+) -> Vec<Option<i64>> {
+    let index = PointIndex::new(points, distance);
+    let mut labels: Vec<Option<i64>> = vec![None; points.len()];
+    let mut visited = vec![false; points.len()];
+    let mut next_id = 1;
+    let total = points.len().max(1) as f64;
+    for start in 0..points.len() {
+        if start.is_multiple_of(1000) {
+            progress.report(start as f64 / total, None);
+        }
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let neighbours = index.neighbours(start);
+        if neighbours.len() < min_points {
+            continue;
+        }
+        let id = next_id;
+        next_id += 1;
+        labels[start] = Some(id);
+        let mut queue: VecDeque<usize> = neighbours.into();
+        while let Some(point) = queue.pop_front() {
+            // Noise reached from a core point becomes a border point of the cluster
+            if labels[point].is_none() {
+                labels[point] = Some(id);
+            }
+            if visited[point] {
+                continue;
+            }
+            visited[point] = true;
+            let neighbours = index.neighbours(point);
+            if neighbours.len() >= min_points {
+                queue.extend(neighbours);
+            }
+        }
+    }
+    labels
+}
+
+// Cluster of every point, numbered from 1 by the order of the initial centres. The first
+// centre is the point nearest the mean and each next one the point farthest from those
+// already chosen, so runs are repeatable.
+fn k_means(
+    points: &[Point],
+    k: usize,
+    max_iterations: usize,
+    progress: &Progress,
+) -> Vec<Option<i64>> {
+    let count = points.len() as f64;
+    let mean = points.iter().fold((0.0, 0.0), |sum, point| {
+        (sum.0 + point.0 / count, sum.1 + point.1 / count)
+    });
+    let mut nearest = vec![f64::INFINITY; points.len()];
+    let mut centres: Vec<Point> = Vec::with_capacity(k);
+    let mut next = (0..points.len()).min_by(|&a, &b| {
+        distance_squared(points[a], mean).total_cmp(&distance_squared(points[b], mean))
+    });
+    while let Some(index) = next.filter(|_| centres.len() < k) {
+        let centre = points[index];
+        centres.push(centre);
+        for (distance, &point) in nearest.iter_mut().zip(points) {
+            *distance = distance.min(distance_squared(point, centre));
+        }
+        // Stop early when every point already sits on a centre
+        next = (0..points.len())
+            .filter(|&index| nearest[index] > 0.0)
+            .max_by(|&a, &b| nearest[a].total_cmp(&nearest[b]));
+    }
+
+    let mut assignment = vec![usize::MAX; points.len()];
+    for iteration in 0..max_iterations {
+        progress.report(iteration as f64 / max_iterations as f64, None);
+        let mut changed = false;
+        for (slot, &point) in assignment.iter_mut().zip(points) {
+            let closest = (0..centres.len())
+                .min_by(|&a, &b| {
+                    distance_squared(point, centres[a])
+                        .total_cmp(&distance_squared(point, centres[b]))
+                })
+                .unwrap_or(0);
+            if *slot != closest {
+                *slot = closest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        // Centres move to the mean of their points; an emptied cluster keeps its centre
+        let mut sums = vec![(0.0, 0.0, 0u64); centres.len()];
+        for (&cluster, &point) in assignment.iter().zip(points) {
+            sums[cluster].0 += point.0;
+            sums[cluster].1 += point.1;
+            sums[cluster].2 += 1;
+        }
+        for (centre, (x, y, n)) in centres.iter_mut().zip(sums) {
+            if n > 0 {
+                *centre = (x / n as f64, y / n as f64);
+            }
+        }
+    }
+    assignment
+        .into_iter()
+        .map(|cluster| Some(cluster as i64 + 1))
+        .collect()
+}
+
+fn summarise(points: &[Point], labels: &[Option<i64>]) -> Vec<ClusterSummary> {
+    let mut clusters: HashMap<i64, ClusterSummary> = HashMap::new();
+    for (&point, label) in points.iter().zip(labels) {
+        let Some(id) = *label else {
+            continue;
+        };
+        let summary = clusters.entry(id).or_insert(ClusterSummary {
+            id,
+            count: 0,
+            centroid: (0.0, 0.0),
+            extent: Extent {
+                min_x: point.0,
+                min_y: point.1,
+                max_x: point.0,
+                max_y: point.1,
+            },
+        });
+        summary.count += 1;
+        summary.centroid.0 += point.0;
+        summary.centroid.1 += point.1;
+        summary.extent.min_x = summary.extent.min_x.min(point.0);
+        summary.extent.min_y = summary.extent.min_y.min(point.1);
+        summary.extent.max_x = summary.extent.max_x.max(point.0);
+        summary.extent.max_y = summary.extent.max_y.max(point.1);
+    }
+    let mut clusters: Vec<ClusterSummary> = clusters.into_values().collect();
+    for summary in &mut clusters {
+        summary.centroid.0 /= summary.count as f64;
+        summary.centroid.1 /= summary.count as f64;
+    }
+    clusters.sort_by_key(|summary| summary.id);
+    clusters
+}
+
+fn cluster(
+    src: &str,
+    layer: Option<&str>,
+    dst: &str,
+    method: &ClusterMethod,
+    progress: &Progress,
+) -> Result<ClusterReport, GdalError> {
+    match method {
+        ClusterMethod::Dbscan {
+            distance,
+            min_points,
+        } => {
+            if !(*distance > 0.0 && distance.is_finite()) || *min_points == 0 {
+                return Err(GdalError::InvalidArgument(
+                    "DBSCAN needs a positive distance and at least 1 point".to_string(),
+                ));
+            }
+        }
+        ClusterMethod::KMeans { k, .. } => {
+            if *k == 0 {
+                return Err(GdalError::InvalidArgument(
+                    "k-means needs at least 1 cluster".to_string(),
+                ));
+            }
+        }
+    }
+
+    let source = Dataset::open(src)?;
+    let mut input = layer_by_name(&source, layer)?;
+    if input.defn().field_index(CLUSTER_FIELD).is_ok() {
+        return Err(GdalError::InvalidArgument(format!(
+            "Layer {} already has a {} field",
+            input.name(),
+            CLUSTER_FIELD
+        )));
+    }
+
+    // Location of every feature in reading order; multipoints count as the mean of
+    // their points
+    let mut locations: Vec<Option<usize>> = Vec::new();
+    let mut points: Vec<Point> = Vec::new();
+    for feature in input.features() {
+        let mut parts = Vec::new();
+        let is_point = feature
+            .geometry()
+            .is_some_and(|geometry| point_parts(geometry, &mut parts));
+        if !is_point || parts.is_empty() {
+            locations.push(None);
+            continue;
+        }
+        let n = parts.len() as f64;
+        let (x, y) = parts
+            .iter()
+            .fold((0.0, 0.0), |sum, point| (sum.0 + point.0, sum.1 + point.1));
+        locations.push(Some(points.len()));
+        points.push((x / n, y / n));
+    }
+
+    progress.set_range(0.0, 0.7);
+    let labels = match method {
+        ClusterMethod::Dbscan {
+            distance,
+            min_points,
+        } => dbscan(&points, *distance, *min_points, progress),
+        ClusterMethod::KMeans { k, max_iterations } => {
+            if points.is_empty() {
+                Vec::new()
+            } else {
+                let iterations = max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS).max(1);
+                k_means(&points, *k, iterations, progress)
+            }
+        }
+    };
+
+    // A copy of the layer with the cluster of each feature, null for noise
+    progress.set_range(0.7, 1.0);
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer_name = input.name();
+    let target = create_layer_like(&mut transaction, &input, &layer_name)?;
+    FieldDefn::new(CLUSTER_FIELD, OGRFieldType::OFTInteger64)?.add_to_layer(&target)?;
+    let field_count = input.defn().fields().count();
+    let total = locations.len().max(1) as f64;
+    input.reset_feature_reading();
+    for (done, (feature, location)) in input.features().zip(&locations).enumerate() {
+        if done.is_multiple_of(1000) {
+            progress.report(done as f64 / total, None);
+        }
+        let mut copy = Feature::new(target.defn())?;
+        if let Some(geometry) = feature.geometry() {
+            copy.set_geometry(geometry.clone())?;
+        }
+        for index in 0..field_count {
+            if let Some(value) = feature.field(index)? {
+                copy.set_field(index, &value)?;
+            }
+        }
+        if let Some(id) = location.and_then(|location| labels[location]) {
+            copy.set_field_integer64(field_count, id)?;
+        }
+        copy.create(&target)?;
+    }
+    transaction.commit()?;
+    output.close()?;
+
+    let clusters = summarise(&points, &labels);
+    Ok(ClusterReport {
+        path: dst.to_string(),
+        layer: layer_name,
+        point_count: points.len() as u64,
+        cluster_count: clusters.len() as u64,
+        noise_count: labels.iter().filter(|label| label.is_none()).count() as u64,
+        skipped: locations
+            .iter()
+            .filter(|location| location.is_none())
+            .count() as u64,
+        clusters,
+    })
+}
+
+// Writes a copy of a point layer with a cluster_id field from DBSCAN or k-means on the
+// point coordinates, and summarises each cluster
+#[tauri::command]
+pub async fn cluster_points(
+    app: AppHandle,
+    src: String,
+    layer: Option<String>,
+    dst: String,
+    method: ClusterMethod,
+) -> Result<ClusterReport, String> {
+    let params = json!({ "src": src, "layer": layer, "dst": dst, "method": method });
+    run_job(app.clone(), "cluster_points", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let progress = Progress::new(&app, "cluster_points");
+        cluster(&src, layer.as_deref(), &dst, &method, &progress).map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,294 @@
+use gdal::vector::{Feature, FieldDefn, Geometry, Layer, LayerAccess, OGRFieldType};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::{create_layer_like, create_output, layer_by_name};
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, GdalError};
+
+// What makes two features duplicates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum DuplicateKey {
+    // Same vertices, each coordinate snapped to a grid of `tolerance` layer units; 0
+    // compares coordinates exactly. Features without a geometry are never duplicates.
+    Geometry {
+        #[serde(default)]
+        tolerance: f64,
+    },
+    // Same values in `fields`, or in every field when empty
+    Attributes {
+        #[serde(default)]
+        fields: Vec<String>,
+    },
+    // Both of the above
+    GeometryAndAttributes {
+        #[serde(default)]
+        tolerance: f64,
+        #[serde(default)]
+        fields: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateAction {
+    // Leave duplicates out of the output
+    #[default]
+    Remove,
+    // Keep every feature and record the FID of the first occurrence on duplicates
+    Flag,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DedupeOptions {
+    pub action: DuplicateAction,
+    // Field written by `flag`, `duplicate_of` when unset
+    pub flag_field: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Duplicate {
+    pub fid: u64,
+    // The first feature with the same key, which is kept
+    pub duplicate_of: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DedupeReport {
+    pub path: String,
+    pub layer: String,
+    pub input_count: u64,
+    pub output_count: u64,
+    pub duplicates: Vec<Duplicate>,
+}
+
+// Coordinates of every vertex, snapped to the tolerance grid, with the type of each
+// part so a polygon never matches the linestring along its boundary
+fn push_geometry_key(geometry: &Geometry, tolerance: f64, key: &mut Vec<u8>) {
+    key.extend_from_slice(&geometry.geometry_type().to_le_bytes());
+    let parts = geometry.geometry_count();
+    if parts > 0 {
+        for index in 0..parts {
+            push_geometry_key(&geometry.get_geometry(index), tolerance, key);
+        }
+        return;
+    }
+    let mut points = Vec::new();
+    geometry.get_points(&mut points);
+    for (x, y, z) in points {
+        for value in [x, y, z] {
+            let value = if tolerance > 0.0 {
+                (value / tolerance).round()
+            } else {
+                value
+            };
+            // -0.0 and 0.0 are the same position
+            key.extend_from_slice(&(value + 0.0).to_bits().to_le_bytes());
+        }
+    }
+}
+
+fn push_attribute_key(
+    feature: &Feature,
+    fields: &[usize],
+    key: &mut Vec<u8>,
+) -> Result<(), GdalError> {
+    for &index in fields {
+        match feature.field_as_string(index)? {
+            Some(value) => {
+                key.push(1);
+                key.extend_from_slice(value.as_bytes());
+                key.push(0);
+            }
+            None => key.push(0),
+        }
+    }
+    Ok(())
+}
+
+// Key of a feature, None when it cannot have duplicates
+fn feature_key(
+    feature: &Feature,
+    tolerance: Option<f64>,
+    fields: Option<&[usize]>,
+) -> Result<Option<Vec<u8>>, GdalError> {
+    let mut key = Vec::new();
+    if let Some(tolerance) = tolerance {
+        match feature.geometry() {
+            Some(geometry) if !geometry.is_empty() => {
+                push_geometry_key(geometry, tolerance, &mut key)
+            }
+            _ => return Ok(None),
+        }
+    }
+    if let Some(fields) = fields {
+        push_attribute_key(feature, fields, &mut key)?;
+    }
+    Ok(Some(key))
+}
+
+fn field_indexes(layer: &Layer, names: &[String]) -> Result<Vec<usize>, GdalError> {
+    if names.is_empty() {
+        return Ok((0..layer.defn().fields().count()).collect());
+    }
+    names
+        .iter()
+        .map(|name| {
+            layer.defn().field_index(name).map_err(|_| {
+                GdalError::InvalidArgument(format!(
+                    "Layer {} has no field named {}",
+                    layer.name(),
+                    name
+                ))
+            })
+        })
+        .collect()
+}
+
+fn dedupe(
+    src: &str,
+    layer: Option<&str>,
+    dst: &str,
+    key: &DuplicateKey,
+    options: &DedupeOptions,
+    progress: &Progress,
+) -> Result<DedupeReport, GdalError> {
+    let source = Dataset::open(src)?;
+    let mut input = layer_by_name(&source, layer)?;
+    let (tolerance, field_names) = match key {
+        DuplicateKey::Geometry { tolerance } => (Some(*tolerance), None),
+        DuplicateKey::Attributes { fields } => (None, Some(fields.as_slice())),
+        DuplicateKey::GeometryAndAttributes { tolerance, fields } => {
+            (Some(*tolerance), Some(fields.as_slice()))
+        }
+    };
+    if tolerance.is_some_and(|tolerance| !(tolerance >= 0.0 && tolerance.is_finite())) {
+        return Err(GdalError::InvalidArgument(
+            "Tolerance must be zero or positive".to_string(),
+        ));
+    }
+    let fields = field_names
+        .map(|names| field_indexes(&input, names))
+        .transpose()?;
+    let flag_field = options.flag_field.as_deref().unwrap_or("duplicate_of");
+    if options.action == DuplicateAction::Flag && input.defn().field_index(flag_field).is_ok() {
+        return Err(GdalError::InvalidArgument(format!(
+            "Layer {} already has a field named {}",
+            input.name(),
+            flag_field
+        )));
+    }
+
+    // Same schema as the input, plus the flag field when flagging
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer_name = input.name();
+    let target = create_layer_like(&mut transaction, &input, &layer_name)?;
+    let flag_index = input.defn().fields().count();
+    if options.action == DuplicateAction::Flag {
+        FieldDefn::new(flag_field, OGRFieldType::OFTInteger64)?.add_to_layer(&target)?;
+    }
+
+    let total = input.feature_count().max(1) as f64;
+    let mut first_seen: HashMap<Vec<u8>, u64> = HashMap::new();
+    let mut duplicates = Vec::new();
+    let (mut input_count, mut output_count) = (0u64, 0u64);
+    for feature in input.features() {
+        input_count += 1;
+        if input_count.is_multiple_of(1000) {
+            progress.report(input_count as f64 / total, None);
+        }
+        let fid = feature.fid().unwrap_or(input_count - 1);
+        let duplicate_of = match feature_key(&feature, tolerance, fields.as_deref())? {
+            Some(key) => match first_seen.get(&key) {
+                Some(&first) => Some(first),
+                None => {
+                    first_seen.insert(key, fid);
+                    None
+                }
+            },
+            None => None,
+        };
+        if let Some(first) = duplicate_of {
+            duplicates.push(Duplicate {
+                fid,
+                duplicate_of: first,
+            });
+            if options.action == DuplicateAction::Remove {
+                continue;
+            }
+        }
+
+        let mut copy = Feature::new(target.defn())?;
+        if let Some(geometry) = feature.geometry() {
+            copy.set_geometry(geometry.clone())?;
+        }
+        for index in 0..flag_index {
+            if let Some(value) = feature.field(index)? {
+                copy.set_field(index, &value)?;
+            }
+        }
+        if let Some(first) = duplicate_of {
+            copy.set_field_integer64(flag_index, first as i64)?;
+        }
+        copy.create(&target)?;
+        output_count += 1;
+    }
+    transaction.commit()?;
+    output.close()?;
+
+    Ok(DedupeReport {
+        path: dst.to_string(),
+        layer: layer_name,
+        input_count,
+        output_count,
+        duplicates,
+    })
+}
+
+// Finds features repeating an earlier one, by geometry, attributes or both, and writes
+// the layer to `dst` without them or with them flagged. The first occurrence is always
+// kept and the report lists every duplicate against it.
+#[tauri::command]
+pub async fn deduplicate_features(
+    app: AppHandle,
+    src: String,
+    layer: Option<String>,
+    dst: String,
+    key: DuplicateKey,
+    options: Option<DedupeOptions>,
+) -> Result<DedupeReport, String> {
+    let params = json!({
+        "src": src,
+        "layer": layer,
+        "dst": dst,
+        "key": key,
+        "options": options,
+    });
+    run_job(app.clone(), "deduplicate_features", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let progress = Progress::new(&app, "deduplicate_features");
+        dedupe(
+            &src,
+            layer.as_deref(),
+            &dst,
+            &key,
+            &options.unwrap_or_default(),
+            &progress,
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
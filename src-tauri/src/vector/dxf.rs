@@ -0,0 +1,349 @@
+use gdal::config::{clear_thread_local_config_option, set_thread_local_config_option};
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{Feature, LayerAccess, LayerOptions, OGRwkbGeometryType};
+use gdal::{Dataset, DriverManager, DriverType};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::vector_translate;
+use crate::crs::{crs_key, parse_srs, transformer};
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, GdalError};
+
+// Field of the DXF driver's `entities` layer holding the CAD layer of each entity
+const LAYER_FIELD: &str = "Layer";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DxfBlocks {
+    // Block references are expanded into the geometries of the block
+    #[default]
+    Inline,
+    // Block references stay points with a `BlockName` field, and the block definitions
+    // go to a separate `blocks` layer
+    References,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DxfImportOptions {
+    // One output layer per CAD layer instead of a single `entities` layer
+    pub split_layers: bool,
+    pub blocks: DxfBlocks,
+    // DXF files carry no CRS; read from a `.prj` beside the file when unset
+    pub crs: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DxfLayer {
+    pub name: String,
+    pub feature_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DxfImport {
+    pub path: String,
+    pub layers: Vec<DxfLayer>,
+    pub crs: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DxfExport {
+    pub path: String,
+    // CAD layers written, with their entity counts
+    pub layers: Vec<DxfLayer>,
+    // `.prj` sidecar holding the CRS, which DXF itself cannot store
+    pub prj: Option<String>,
+}
+
+// Output layer name for a CAD layer; GeoPackage and shapefiles dislike some characters
+fn layer_name(cad_layer: &str) -> String {
+    let name: String = cad_layer
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() {
+        "layer_0".to_string()
+    } else {
+        name
+    }
+}
+
+fn sidecar_srs(path: &Path) -> Option<SpatialRef> {
+    let text = fs::read_to_string(path.with_extension("prj")).ok()?;
+    SpatialRef::from_definition(text.trim()).ok()
+}
+
+// Converts a DXF drawing's entities into a GIS layer, or one layer per CAD layer with
+// `split_layers`. The destination format follows its extension, GeoPackage when
+// unknown; shapefiles only work when the drawing has a single geometry type per layer.
+fn import(
+    src: &str,
+    dst: &str,
+    options: &DxfImportOptions,
+    progress: &Progress,
+) -> Result<DxfImport, GdalError> {
+    let source = Dataset::open(src)?;
+    if source.driver().short_name() != "DXF" {
+        return Err(GdalError::InvalidArgument(format!(
+            "{} is not a DXF file",
+            src
+        )));
+    }
+    let srs = match &options.crs {
+        Some(crs) => Some(parse_srs(crs)?),
+        None => sidecar_srs(Path::new(src)),
+    };
+    let driver = DriverManager::get_output_driver_for_dataset_name(dst, DriverType::Vector)
+        .map(|driver| driver.short_name())
+        .unwrap_or_else(|| "GPKG".to_string());
+    if Path::new(dst).exists() {
+        DriverManager::get_driver_by_name(&driver)?.delete(dst)?;
+    }
+    let mut base_args = vec!["-f".to_string(), driver];
+    if let Some(srs) = &srs {
+        base_args.extend(["-a_srs".to_string(), srs.to_wkt()?]);
+    }
+
+    if !options.split_layers {
+        vector_translate(&[&source], dst, &base_args, progress)?.close()?;
+    } else {
+        // Entity counts per CAD layer, in name order
+        let mut entities = source.layer_by_name("entities")?;
+        let field = entities.defn().field_index(LAYER_FIELD)?;
+        let mut cad_layers: BTreeMap<String, u64> = BTreeMap::new();
+        for feature in entities.features() {
+            let name = feature.field_as_string(field)?.unwrap_or_default();
+            *cad_layers.entry(name).or_default() += 1;
+        }
+        let total = cad_layers.len().max(1) as f64;
+        let mut names = BTreeSet::new();
+        for (index, cad_layer) in cad_layers.keys().enumerate() {
+            progress.set_range(index as f64 / total, (index + 1) as f64 / total);
+            // Distinct CAD layers can map to the same name once sanitised
+            let base = layer_name(cad_layer);
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while names.contains(&name) {
+                suffix += 1;
+                name = format!("{}_{}", base, suffix);
+            }
+            names.insert(name.clone());
+
+            let mut args = base_args.clone();
+            if index > 0 {
+                args.push("-update".to_string());
+            }
+            args.extend([
+                "-where".to_string(),
+                format!("{} = '{}'", LAYER_FIELD, cad_layer.replace('\'', "''")),
+                "-nln".to_string(),
+                name,
+                "entities".to_string(),
+            ]);
+            vector_translate(&[&source], dst, &args, progress)?.close()?;
+        }
+        if options.blocks == DxfBlocks::References {
+            let mut args = base_args.clone();
+            if !cad_layers.is_empty() {
+                args.push("-update".to_string());
+            }
+            args.push("blocks".to_string());
+            vector_translate(&[&source], dst, &args, progress)?.close()?;
+        }
+    }
+
+    let output = Dataset::open(dst)?;
+    let layers = output
+        .layers()
+        .map(|layer| DxfLayer {
+            name: layer.name(),
+            feature_count: layer.feature_count(),
+        })
+        .collect();
+    Ok(DxfImport {
+        path: dst.to_string(),
+        layers,
+        crs: srs.as_ref().and_then(crs_key),
+    })
+}
+
+// Writes the chosen layers of `src` into one DXF drawing, each becoming a CAD layer named
+// after the source layer or after the value of `layer_field`
+fn export(
+    src: &str,
+    dst: &str,
+    layers: Option<&[String]>,
+    crs: Option<&str>,
+    layer_field: Option<&str>,
+    progress: &Progress,
+) -> Result<DxfExport, GdalError> {
+    let source = Dataset::open(src)?;
+    let names: Vec<String> = match layers {
+        Some(names) if !names.is_empty() => names.to_vec(),
+        _ => source.layers().map(|layer| layer.name()).collect(),
+    };
+    let target = crs.map(parse_srs).transpose()?;
+
+    let driver = DriverManager::get_driver_by_name("DXF")?;
+    if Path::new(dst).exists() {
+        fs::remove_file(dst)?;
+    }
+    let mut output = driver.create_vector_only(dst)?;
+    let entities = output.create_layer(LayerOptions {
+        name: "entities",
+        ty: OGRwkbGeometryType::wkbUnknown,
+        ..Default::default()
+    })?;
+    let cad_field = entities.defn().field_index(LAYER_FIELD)?;
+
+    let mut written: BTreeMap<String, u64> = BTreeMap::new();
+    let mut output_srs = None;
+    let total = names.len().max(1) as f64;
+    for (index, name) in names.iter().enumerate() {
+        progress.set_range(index as f64 / total, (index + 1) as f64 / total);
+        let mut layer = source.layer_by_name(name)?;
+        let layer_srs = layer.spatial_ref();
+        let transform = match (&layer_srs, &target) {
+            (Some(from), Some(to)) => Some(transformer(from, to)?),
+            _ => None,
+        };
+        // The drawing's CRS, which must be the same for every layer when not reprojecting
+        let srs = target.clone().or(layer_srs);
+        match (&output_srs, &srs) {
+            (None, Some(srs)) => output_srs = Some(srs.clone()),
+            (Some(first), Some(srs)) if crs_key(first) != crs_key(srs) => {
+                return Err(GdalError::InvalidArgument(
+                    "The layers use different CRSs, pass a CRS to reproject them to".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        let field = layer_field
+            .map(|field| layer.defn().field_index(field))
+            .transpose()?;
+
+        let count = layer.feature_count().max(1) as f64;
+        for (done, feature) in layer.features().enumerate() {
+            if done.is_multiple_of(1000) {
+                progress.report(done as f64 / count, None);
+            }
+            let Some(geometry) = feature.geometry() else {
+                continue;
+            };
+            let cad_layer = match field {
+                Some(field) => feature.field_as_string(field)?.unwrap_or_default(),
+                None => name.clone(),
+            };
+            let mut entity = Feature::new(entities.defn())?;
+            entity.set_geometry(match &transform {
+                Some(transform) => geometry.transform(transform)?,
+                None => geometry.clone(),
+            })?;
+            entity.set_field_string(cad_field, &cad_layer)?;
+            entity.create(&entities)?;
+            *written.entry(cad_layer).or_default() += 1;
+        }
+    }
+    output.close()?;
+
+    // Written like a shapefile's, which CAD-to-GIS tools generally pick up
+    let prj = match &output_srs {
+        Some(srs) => {
+            let esri = srs.clone();
+            esri.morph_to_esri()?;
+            let path = Path::new(dst).with_extension("prj");
+            fs::write(&path, esri.to_wkt()?)?;
+            Some(path.to_string_lossy().to_string())
+        }
+        None => None,
+    };
+
+    Ok(DxfExport {
+        path: dst.to_string(),
+        layers: written
+            .into_iter()
+            .map(|(name, feature_count)| DxfLayer {
+                name,
+                feature_count,
+            })
+            .collect(),
+        prj,
+    })
+}
+
+// Imports the entities of a DXF drawing as vector layers for survey and CAD workflows,
+// with block references either expanded or kept as points
+#[tauri::command]
+pub async fn import_dxf(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    options: Option<DxfImportOptions>,
+) -> Result<DxfImport, String> {
+    let params = json!({ "src": src, "dst": dst, "options": options });
+    run_job(app.clone(), "import_dxf", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let options = options.unwrap_or_default();
+        // The driver reads blocks according to these, scoped to this job's thread
+        let inline = match options.blocks {
+            DxfBlocks::Inline => "TRUE",
+            DxfBlocks::References => "FALSE",
+        };
+        set_thread_local_config_option("DXF_INLINE_BLOCKS", inline).map_err(|e| e.to_string())?;
+        let progress = Progress::new(&app, "import_dxf");
+        let result = import(&src, &dst, &options, &progress);
+        clear_thread_local_config_option("DXF_INLINE_BLOCKS").map_err(|e| e.to_string())?;
+        result.map_err(|e| e.to_string())
+    })
+    .await
+}
+
+// Exports vector layers to DXF for CAD software, reprojected to `crs` when given. DXF
+// has no place for a CRS, so it is written to a `.prj` sidecar.
+#[tauri::command]
+pub async fn export_dxf(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    layers: Option<Vec<String>>,
+    crs: Option<String>,
+    layer_field: Option<String>,
+) -> Result<DxfExport, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "layers": layers,
+        "crs": crs,
+        "layer_field": layer_field,
+    });
+    run_job(app.clone(), "export_dxf", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let progress = Progress::new(&app, "export_dxf");
+        export(
+            &src,
+            &dst,
+            layers.as_deref(),
+            crs.as_deref(),
+            layer_field.as_deref(),
+            &progress,
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
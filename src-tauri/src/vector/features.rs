@@ -0,0 +1,238 @@
+use gdal::spatial_ref::CoordTransform;
+use gdal::vector::{Layer, LayerAccess};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::ipc::{Channel, Response};
+
+use super::filter::FeatureFilter;
+use super::geojson::GeoJsonWriter;
+use super::{feature_to_geojson, layer_by_name};
+use crate::crs::{parse_srs, transformer};
+use crate::ipc::Frame;
+use crate::network::check_remote;
+use crate::{run_blocking, setup_gdal_runtime, Extent, GdalError};
+
+// Caps a single read so a zoomed-out viewport cannot flood the IPC channel
+const DEFAULT_FEATURE_LIMIT: usize = 10_000;
+// Features per streamed chunk when not given
+const DEFAULT_CHUNK_SIZE: usize = 5_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeaturePage {
+    // GeoJSON FeatureCollection
+    pub collection: Value,
+    pub count: usize,
+    // More features matched than the limit allowed
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    pub index: usize,
+    // Features in this chunk
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StreamSummary {
+    pub count: usize,
+    pub chunks: usize,
+}
+
+// A layer prepared for reading: filtered, restricted to the bbox, and with the transform
+// into the viewport CRS
+struct FeatureQuery<'a> {
+    layer: Layer<'a>,
+    filter: Option<FeatureFilter>,
+    transform: Option<CoordTransform>,
+}
+
+fn open_source(path: &str) -> Result<Dataset, GdalError> {
+    if !path.starts_with("/vsi") && !Path::new(path).exists() {
+        return Err(GdalError::FileNotFound(path.to_string()));
+    }
+    check_remote(path)?;
+    Ok(Dataset::open(path)?)
+}
+
+fn feature_query<'a>(
+    dataset: &'a Dataset,
+    layer: Option<&str>,
+    bbox: Option<Extent>,
+    bbox_crs: Option<&str>,
+    filter: Option<&str>,
+) -> Result<FeatureQuery<'a>, GdalError> {
+    let mut layer = layer_by_name(dataset, layer)?;
+
+    // With a viewport CRS the box is given, and geometries returned, in that CRS
+    let view_srs = bbox_crs.map(parse_srs).transpose()?;
+    let reprojection = match (&view_srs, layer.spatial_ref()) {
+        (Some(view_srs), Some(layer_srs)) => Some((view_srs.clone(), layer_srs)),
+        _ => None,
+    };
+
+    let filter = filter
+        .map(|filter| FeatureFilter::parse(filter, &layer))
+        .transpose()?;
+    if let Some(filter) = &filter {
+        filter.apply(&mut layer)?;
+        if let Some(geometry) = filter.prefilter() {
+            layer.set_spatial_filter(geometry);
+        }
+    }
+
+    // The bbox replaces the filter's coarser spatial filter, its predicates are still
+    // tested on every feature
+    if let Some(bbox) = bbox {
+        let filter = match &reprojection {
+            Some((view_srs, layer_srs)) => bbox.transform(view_srs, layer_srs)?,
+            None => bbox,
+        };
+        layer.set_spatial_filter_rect(filter.min_x, filter.min_y, filter.max_x, filter.max_y);
+    }
+
+    let transform = reprojection
+        .as_ref()
+        .map(|(view_srs, layer_srs)| transformer(layer_srs, view_srs))
+        .transpose()?;
+
+    Ok(FeatureQuery {
+        layer,
+        filter,
+        transform,
+    })
+}
+
+// Reads the features intersecting `bbox`. Formats with a spatial index (FlatGeobuf,
+// GeoPackage, shapefiles with .qix) only read the matching part of the file. `filter`
+// is a filter expression, such as the attribute table's search.
+#[tauri::command]
+pub async fn read_features(
+    path: String,
+    layer: Option<String>,
+    bbox: Option<Extent>,
+    bbox_crs: Option<String>,
+    filter: Option<String>,
+    limit: Option<usize>,
+) -> Result<FeaturePage, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let dataset = open_source(&path).map_err(|e| e.to_string())?;
+        let FeatureQuery {
+            mut layer,
+            filter,
+            transform,
+        } = feature_query(
+            &dataset,
+            layer.as_deref(),
+            bbox,
+            bbox_crs.as_deref(),
+            filter.as_deref(),
+        )
+        .map_err(|e| e.to_string())?;
+
+        let limit = limit.unwrap_or(DEFAULT_FEATURE_LIMIT);
+        let mut features = Vec::new();
+        let mut truncated = false;
+        for feature in layer.features() {
+            if filter
+                .as_ref()
+                .is_some_and(|filter| !filter.matches(feature.geometry()))
+            {
+                continue;
+            }
+            if features.len() == limit {
+                truncated = true;
+                break;
+            }
+            features
+                .push(feature_to_geojson(&feature, transform.as_ref()).map_err(|e| e.to_string())?);
+        }
+
+        Ok(FeaturePage {
+            count: features.len(),
+            collection: json!({ "type": "FeatureCollection", "features": features }),
+            truncated,
+        })
+    })
+    .await
+}
+
+// Sends every matching feature to the frontend, `chunk_size` at a time, for layers too
+// big for one `read_features` page. Each message on `on_chunk` is a binary frame with a
+// `ChunkInfo` header and a complete GeoJSON FeatureCollection as its only part, written
+// straight to bytes so no more than one chunk is held in memory on either side.
+#[tauri::command]
+pub async fn stream_features(
+    path: String,
+    layer: Option<String>,
+    bbox: Option<Extent>,
+    bbox_crs: Option<String>,
+    filter: Option<String>,
+    chunk_size: Option<usize>,
+    on_chunk: Channel<Response>,
+) -> Result<StreamSummary, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).max(1);
+        let dataset = open_source(&path).map_err(|e| e.to_string())?;
+        let FeatureQuery {
+            mut layer,
+            filter,
+            transform,
+        } = feature_query(
+            &dataset,
+            layer.as_deref(),
+            bbox,
+            bbox_crs.as_deref(),
+            filter.as_deref(),
+        )
+        .map_err(|e| e.to_string())?;
+
+        let send = |writer: GeoJsonWriter<Vec<u8>>, index: usize| -> Result<(), String> {
+            let count = writer.count();
+            let bytes = writer.finish().map_err(|e| e.to_string())?;
+            let frame = Frame::new(&ChunkInfo { index, count }).part(&bytes);
+            on_chunk
+                .send(frame.into_response())
+                .map_err(|e| e.to_string())
+        };
+
+        let (mut count, mut chunks) = (0, 0);
+        let mut writer = GeoJsonWriter::new(Vec::new()).map_err(|e| e.to_string())?;
+        for feature in layer.features() {
+            if filter
+                .as_ref()
+                .is_some_and(|filter| !filter.matches(feature.geometry()))
+            {
+                continue;
+            }
+            writer
+                .write(&feature, transform.as_ref())
+                .map_err(|e| e.to_string())?;
+            count += 1;
+            if writer.count() == chunk_size {
+                let full = std::mem::replace(
+                    &mut writer,
+                    GeoJsonWriter::new(Vec::new()).map_err(|e| e.to_string())?,
+                );
+                send(full, chunks)?;
+                chunks += 1;
+            }
+        }
+        // The last partial chunk, or an empty collection when nothing matched at all
+        if writer.count() > 0 || chunks == 0 {
+            send(writer, chunks)?;
+            chunks += 1;
+        }
+
+        Ok(StreamSummary { count, chunks })
+    })
+    .await
+}
@@ -0,0 +1,512 @@
+use gdal::vector::{Geometry, Layer, LayerAccess};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::GdalError;
+
+// How a feature must relate to a query geometry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpatialPredicate {
+    #[default]
+    Intersects,
+    // The feature lies completely inside the query geometry
+    Within,
+    // The feature completely covers the query geometry
+    Contains,
+}
+
+impl SpatialPredicate {
+    pub(crate) fn test(self, feature: &Geometry, query: &Geometry) -> bool {
+        match self {
+            SpatialPredicate::Intersects => feature.intersects(query),
+            SpatialPredicate::Within => feature.within(query),
+            SpatialPredicate::Contains => feature.contains(query),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    // "quoted" field name
+    Field(String),
+    Text(String),
+    Number(String),
+    Operator(&'static str),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, GdalError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '\'' | '"' => {
+                // Quotes inside are doubled, as in SQL
+                let mut text = String::new();
+                loop {
+                    i += 1;
+                    match chars.get(i) {
+                        Some(&q) if q == c && chars.get(i + 1) == Some(&c) => {
+                            text.push(c);
+                            i += 1;
+                        }
+                        Some(&q) if q == c => break,
+                        Some(&other) => text.push(other),
+                        None => {
+                            return Err(GdalError::InvalidArgument(
+                                "Unterminated quote in filter".to_string(),
+                            ))
+                        }
+                    }
+                }
+                tokens.push(if c == '\'' {
+                    Token::Text(text)
+                } else {
+                    Token::Field(text)
+                });
+            }
+            '=' => tokens.push(Token::Operator("=")),
+            '!' | '<' | '>' => {
+                let next = chars.get(i + 1).copied();
+                let operator = match (c, next) {
+                    ('!', Some('=')) | ('<', Some('>')) => "<>",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    _ => {
+                        return Err(GdalError::InvalidArgument(
+                            "Unexpected '!' in filter".to_string(),
+                        ))
+                    }
+                };
+                if operator.len() == 2 {
+                    i += 1;
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            c if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let start = i;
+                while i + 1 < chars.len()
+                    && (chars[i + 1].is_ascii_alphanumeric()
+                        || matches!(chars[i + 1], '.' | '-' | '+'))
+                {
+                    i += 1;
+                }
+                let number: String = chars[start..=i].iter().collect();
+                if number.parse::<f64>().is_err() {
+                    return Err(GdalError::InvalidArgument(format!(
+                        "Invalid number '{}' in filter",
+                        number
+                    )));
+                }
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i + 1 < chars.len() && (chars[i + 1].is_alphanumeric() || chars[i + 1] == '_')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Word(chars[start..=i].iter().collect()));
+            }
+            other => {
+                return Err(GdalError::InvalidArgument(format!(
+                    "Unexpected '{}' in filter",
+                    other
+                )))
+            }
+        }
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Text(String),
+    Number(String),
+}
+
+impl Literal {
+    fn sql(&self) -> String {
+        match self {
+            Literal::Text(text) => format!("'{}'", text.replace('\'', "''")),
+            Literal::Number(number) => number.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        field: String,
+        operator: &'static str,
+        value: Literal,
+    },
+    Like {
+        field: String,
+        pattern: String,
+        negated: bool,
+    },
+    In {
+        field: String,
+        values: Vec<Literal>,
+        negated: bool,
+    },
+    IsNull {
+        field: String,
+        negated: bool,
+    },
+    Spatial {
+        predicate: SpatialPredicate,
+        wkt: String,
+    },
+}
+
+fn quote_field(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+impl Expr {
+    // OGR SQL for attribute expressions; geometry predicates have no OGR SQL form
+    fn sql(&self) -> Result<String, GdalError> {
+        Ok(match self {
+            Expr::And(a, b) => format!("({} AND {})", a.sql()?, b.sql()?),
+            Expr::Or(a, b) => format!("({} OR {})", a.sql()?, b.sql()?),
+            Expr::Not(a) => format!("(NOT {})", a.sql()?),
+            Expr::Compare {
+                field,
+                operator,
+                value,
+            } => format!("{} {} {}", quote_field(field), operator, value.sql()),
+            Expr::Like {
+                field,
+                pattern,
+                negated,
+            } => format!(
+                "{} {}LIKE {}",
+                quote_field(field),
+                if *negated { "NOT " } else { "" },
+                Literal::Text(pattern.clone()).sql()
+            ),
+            Expr::In {
+                field,
+                values,
+                negated,
+            } => format!(
+                "{} {}IN ({})",
+                quote_field(field),
+                if *negated { "NOT " } else { "" },
+                values
+                    .iter()
+                    .map(Literal::sql)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expr::IsNull { field, negated } => format!(
+                "{} IS {}NULL",
+                quote_field(field),
+                if *negated { "NOT " } else { "" }
+            ),
+            Expr::Spatial { .. } => {
+                return Err(GdalError::InvalidArgument(
+                    "Geometry predicates can only be combined with AND".to_string(),
+                ))
+            }
+        })
+    }
+
+    fn fields<'a>(&'a self, fields: &mut Vec<&'a str>) {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                a.fields(fields);
+                b.fields(fields);
+            }
+            Expr::Not(a) => a.fields(fields),
+            Expr::Compare { field, .. }
+            | Expr::Like { field, .. }
+            | Expr::In { field, .. }
+            | Expr::IsNull { field, .. } => fields.push(field),
+            Expr::Spatial { .. } => {}
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), GdalError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, GdalError> {
+        let mut expr = self.and()?;
+        while self.keyword("OR") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, GdalError> {
+        let mut expr = self.not()?;
+        while self.keyword("AND") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, GdalError> {
+        if self.keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn literal(&mut self) -> Result<Literal, GdalError> {
+        match self.next() {
+            Some(Token::Text(text)) => Ok(Literal::Text(text)),
+            Some(Token::Number(number)) => Ok(Literal::Number(number)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, GdalError> {
+        let field = match self.next() {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                self.expect(Token::Close)?;
+                return Ok(expr);
+            }
+            Some(Token::Field(field)) => field,
+            Some(Token::Word(word)) => {
+                let predicate = match word.to_ascii_lowercase().as_str() {
+                    "intersects" => Some(SpatialPredicate::Intersects),
+                    "within" => Some(SpatialPredicate::Within),
+                    "contains" => Some(SpatialPredicate::Contains),
+                    _ => None,
+                };
+                match predicate {
+                    Some(predicate) if self.peek() == Some(&Token::Open) => {
+                        self.position += 1;
+                        let wkt = match self.next() {
+                            Some(Token::Text(wkt)) => wkt,
+                            other => return Err(unexpected(other)),
+                        };
+                        self.expect(Token::Close)?;
+                        return Ok(Expr::Spatial { predicate, wkt });
+                    }
+                    _ => word,
+                }
+            }
+            other => return Err(unexpected(other)),
+        };
+
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            if !self.keyword("NULL") {
+                return Err(unexpected(self.next()));
+            }
+            return Ok(Expr::IsNull { field, negated });
+        }
+        let negated = self.keyword("NOT");
+        if self.keyword("LIKE") {
+            return match self.next() {
+                Some(Token::Text(pattern)) => Ok(Expr::Like {
+                    field,
+                    pattern,
+                    negated,
+                }),
+                other => Err(unexpected(other)),
+            };
+        }
+        if self.keyword("IN") {
+            self.expect(Token::Open)?;
+            let mut values = vec![self.literal()?];
+            while self.peek() == Some(&Token::Comma) {
+                self.position += 1;
+                values.push(self.literal()?);
+            }
+            self.expect(Token::Close)?;
+            return Ok(Expr::In {
+                field,
+                values,
+                negated,
+            });
+        }
+        if negated {
+            return Err(GdalError::InvalidArgument(
+                "Expected LIKE or IN after NOT".to_string(),
+            ));
+        }
+        match self.next() {
+            Some(Token::Operator(operator)) => Ok(Expr::Compare {
+                field,
+                operator,
+                value: self.literal()?,
+            }),
+            other => Err(unexpected(other)),
+        }
+    }
+}
+
+fn unexpected(token: Option<Token>) -> GdalError {
+    GdalError::InvalidArgument(match token {
+        Some(token) => format!("Unexpected {:?} in filter", token),
+        None => "Filter ended unexpectedly".to_string(),
+    })
+}
+
+// Terms of the top level AND chain
+fn conjuncts(expr: Expr, terms: &mut Vec<Expr>) {
+    match expr {
+        Expr::And(a, b) => {
+            conjuncts(*a, terms);
+            conjuncts(*b, terms);
+        }
+        other => terms.push(other),
+    }
+}
+
+// A filter expression such as
+// `population > 10000 AND (name LIKE 'San%' OR kind IN ('city', 'town')) AND
+// intersects('POLYGON ((...))')`, compiled to an OGR attribute filter plus geometry
+// predicates. Geometries are WKT in the layer's CRS, and geometry predicates can only be
+// joined to the rest with AND since OGR applies them separately.
+pub(crate) struct FeatureFilter {
+    pub where_clause: Option<String>,
+    spatial: Vec<(SpatialPredicate, Geometry)>,
+}
+
+impl FeatureFilter {
+    // Parses `text` and checks that the fields it names exist in `layer`
+    pub fn parse(text: &str, layer: &Layer) -> Result<Self, GdalError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        if parser.peek().is_some() {
+            return Err(unexpected(parser.next()));
+        }
+
+        let mut fields = Vec::new();
+        expr.fields(&mut fields);
+        for field in fields {
+            if !field.eq_ignore_ascii_case("FID") && layer.defn().field_index(field).is_err() {
+                return Err(GdalError::InvalidArgument(format!(
+                    "Unknown field '{}' in filter",
+                    field
+                )));
+            }
+        }
+
+        let mut terms = Vec::new();
+        conjuncts(expr, &mut terms);
+        let mut clauses = Vec::new();
+        let mut spatial = Vec::new();
+        for term in terms {
+            match term {
+                Expr::Spatial { predicate, wkt } => {
+                    spatial.push((predicate, Geometry::from_wkt(&wkt)?))
+                }
+                other => clauses.push(other.sql()?),
+            }
+        }
+        Ok(Self {
+            where_clause: (!clauses.is_empty()).then(|| clauses.join(" AND ")),
+            spatial,
+        })
+    }
+
+    pub fn has_geometry_predicates(&self) -> bool {
+        !self.spatial.is_empty()
+    }
+
+    // Geometry the layer can be spatially filtered on before the exact tests; every
+    // predicate implies the envelopes intersect
+    pub fn prefilter(&self) -> Option<&Geometry> {
+        self.spatial.first().map(|(_, geometry)| geometry)
+    }
+
+    // Exact test of the geometry predicates; the attribute part is left to OGR
+    pub fn matches(&self, geometry: Option<&Geometry>) -> bool {
+        match geometry {
+            Some(geometry) => self
+                .spatial
+                .iter()
+                .all(|(predicate, query)| predicate.test(geometry, query)),
+            None => self.spatial.is_empty(),
+        }
+    }
+
+    // Sets the attribute filter on `layer`; undo with `clear_attribute_filter`
+    pub fn apply(&self, layer: &mut Layer) -> Result<(), GdalError> {
+        if let Some(where_clause) = &self.where_clause {
+            layer.set_attribute_filter(where_clause)?;
+        }
+        Ok(())
+    }
+}
+
+// IDs of the features of `layer` matching `filter`, leaving the layer unfiltered
+pub(crate) fn matching_fids(
+    layer: &mut Layer,
+    filter: &FeatureFilter,
+) -> Result<BTreeSet<u64>, GdalError> {
+    filter.apply(layer)?;
+    if let Some(geometry) = filter.prefilter() {
+        layer.set_spatial_filter(geometry);
+    }
+    let fids = layer
+        .features()
+        .filter(|feature| filter.matches(feature.geometry()))
+        .filter_map(|feature| feature.fid())
+        .collect();
+    // Layers can stay open in the registry, so later reads must see every feature again
+    layer.clear_attribute_filter();
+    layer.clear_spatial_filter();
+    Ok(fids)
+}
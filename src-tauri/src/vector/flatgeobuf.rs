@@ -0,0 +1,115 @@
+use gdal::vector::LayerAccess;
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::filter::{matching_fids, FeatureFilter};
+use super::layer_by_name;
+use super::selection::{fid_filter, selected_fids};
+use super::translate::vector_translate;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::setup_gdal_runtime;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedLayer {
+    pub path: String,
+    pub layer: String,
+    pub feature_count: u64,
+}
+
+// Writes one layer as FlatGeobuf. The packed R-tree written with `spatial_index` is
+// what makes bbox reads through `read_features` fast on large layers. With
+// `selected_only` only the features selected in the layer are written, and with
+// `filter` only those matching the filter expression.
+#[tauri::command]
+pub async fn export_flatgeobuf(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    layer: Option<String>,
+    spatial_index: Option<bool>,
+    selected_only: Option<bool>,
+    filter: Option<String>,
+) -> Result<ExportedLayer, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "layer": layer,
+        "spatial_index": spatial_index,
+        "selected_only": selected_only,
+        "filter": filter,
+    });
+    run_job(app.clone(), "export_flatgeobuf", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if layer.is_none() && source.layer_count() > 1 {
+            return Err(
+                "FlatGeobuf files hold a single layer, choose which one to export".to_string(),
+            );
+        }
+
+        let spatial_index = if spatial_index.unwrap_or(true) {
+            "YES"
+        } else {
+            "NO"
+        };
+        let mut args = vec![
+            "-f".to_string(),
+            "FlatGeobuf".to_string(),
+            "-lco".to_string(),
+            format!("SPATIAL_INDEX={}", spatial_index),
+        ];
+        let mut source_layer =
+            layer_by_name(&source, layer.as_deref()).map_err(|e| e.to_string())?;
+        let mut clauses = Vec::new();
+        if selected_only.unwrap_or(false) {
+            let fids =
+                selected_fids(&app, &src, &source_layer.name()).map_err(|e| e.to_string())?;
+            clauses.push(fid_filter(&source_layer, &fids));
+        }
+        if let Some(filter) = filter {
+            let filter = FeatureFilter::parse(&filter, &source_layer).map_err(|e| e.to_string())?;
+            // ogr2ogr has no exact geometry predicates, so those filters become a FID list
+            if filter.has_geometry_predicates() {
+                let fids = matching_fids(&mut source_layer, &filter).map_err(|e| e.to_string())?;
+                if fids.is_empty() {
+                    return Err("No features match the filter".to_string());
+                }
+                clauses.push(fid_filter(&source_layer, &fids));
+            } else {
+                clauses.extend(filter.where_clause);
+            }
+        }
+        if !clauses.is_empty() {
+            args.push("-where".to_string());
+            args.push(format!("({})", clauses.join(") AND (")));
+        }
+        // Layer names are positional arguments, as on the ogr2ogr command line
+        args.extend(layer);
+
+        if Path::new(&dst).exists() {
+            std::fs::remove_file(&dst).map_err(|e| e.to_string())?;
+        }
+
+        let progress = Progress::new(&app, "export_flatgeobuf");
+        let output =
+            vector_translate(&[&source], &dst, &args, &progress).map_err(|e| e.to_string())?;
+        let written = output.layer(0).map_err(|e| e.to_string())?;
+
+        Ok(ExportedLayer {
+            path: dst,
+            layer: written.name(),
+            feature_count: written.feature_count(),
+        })
+    })
+    .await
+}
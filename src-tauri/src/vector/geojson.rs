@@ -0,0 +1,182 @@
+use gdal::spatial_ref::CoordTransform;
+use gdal::vector::{Feature, LayerAccess};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::field_value_json;
+use super::flatgeobuf::ExportedLayer;
+use super::translate::vector_translate;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, GdalError};
+
+// GDAL release that added `-xyRes` to ogr2ogr
+const QUANTIZE_VERSION: u32 = 3_090_000;
+
+// Writes a FeatureCollection one feature at a time, so only the feature being written is
+// held in memory. Geometries are copied in as GDAL serialises them rather than parsed
+// into JSON values first. The output is only valid once `finish` has run.
+pub(crate) struct GeoJsonWriter<W: Write> {
+    out: W,
+    count: usize,
+}
+
+impl<W: Write> GeoJsonWriter<W> {
+    pub fn new(mut out: W) -> Result<Self, GdalError> {
+        out.write_all(br#"{"type":"FeatureCollection","features":["#)?;
+        Ok(Self { out, count: 0 })
+    }
+
+    // Features written so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // Appends `feature`, with its geometry reprojected by `transform` when given
+    pub fn write(
+        &mut self,
+        feature: &Feature,
+        transform: Option<&CoordTransform>,
+    ) -> Result<(), GdalError> {
+        let geometry = match feature.geometry() {
+            Some(geometry) => match transform {
+                Some(transform) => geometry.transform(transform)?.json()?,
+                None => geometry.json()?,
+            },
+            None => "null".to_string(),
+        };
+        let properties: Map<String, Value> = feature
+            .fields()
+            .map(|(name, value)| (name, value.map(field_value_json).unwrap_or(Value::Null)))
+            .collect();
+
+        if self.count > 0 {
+            self.out.write_all(b",")?;
+        }
+        write!(self.out, r#"{{"type":"Feature","id":"#)?;
+        serde_json::to_writer(&mut self.out, &feature.fid()).map_err(std::io::Error::from)?;
+        write!(self.out, r#","geometry":{},"properties":"#, geometry)?;
+        serde_json::to_writer(&mut self.out, &properties).map_err(std::io::Error::from)?;
+        self.out.write_all(b"}")?;
+        self.count += 1;
+        Ok(())
+    }
+
+    // Closes the collection and hands back the underlying writer
+    pub fn finish(mut self) -> Result<W, GdalError> {
+        self.out.write_all(b"]}")?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeoJsonOptions {
+    // Layer to export, required when the source has several
+    pub layer: Option<String>,
+    // Decimal places written per coordinate; 15 significant digits when unset, or 7
+    // decimals with `rfc7946`
+    pub precision: Option<u8>,
+    // Simplification tolerance in units of the layer's CRS. Topology is kept within each
+    // feature but not between neighbouring ones.
+    pub simplify: Option<f64>,
+    // Snaps coordinates to a grid of this size in units of the output CRS, dropping the
+    // repeated vertices that creates
+    pub quantize: Option<f64>,
+    // Reprojects to WGS84 longitude/latitude and writes RFC 7946 GeoJSON, the form web
+    // maps expect
+    pub rfc7946: bool,
+    // Newline-delimited GeoJSONSeq, one feature per line
+    pub sequence: bool,
+}
+
+// Writes one layer as GeoJSON with web-friendly coordinate handling: fewer decimals,
+// simplified outlines and snapped vertices can each shrink the payload several times
+// over without separate tooling.
+#[tauri::command]
+pub async fn export_geojson(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    options: Option<GeoJsonOptions>,
+) -> Result<ExportedLayer, String> {
+    let params = json!({ "src": src, "dst": dst, "options": options });
+    run_job(app.clone(), "export_geojson", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let options = options.unwrap_or_default();
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if options.layer.is_none() && source.layer_count() > 1 {
+            return Err(
+                "GeoJSON files hold a single layer, choose which one to export".to_string(),
+            );
+        }
+
+        let driver = if options.sequence {
+            "GeoJSONSeq"
+        } else {
+            "GeoJSON"
+        };
+        let mut args = vec!["-f".to_string(), driver.to_string()];
+        if options.rfc7946 {
+            // GeoJSONSeq always reprojects, the option only exists for GeoJSON
+            args.extend(["-t_srs".to_string(), "EPSG:4326".to_string()]);
+            if !options.sequence {
+                args.extend(["-lco".to_string(), "RFC7946=YES".to_string()]);
+            }
+        }
+        if let Some(precision) = options.precision {
+            if precision > 15 {
+                return Err("Coordinate precision must be at most 15 decimals".to_string());
+            }
+            args.extend([
+                "-lco".to_string(),
+                format!("COORDINATE_PRECISION={}", precision),
+            ]);
+        }
+        if let Some(tolerance) = options.simplify {
+            if !(tolerance > 0.0 && tolerance.is_finite()) {
+                return Err("Simplification tolerance must be positive".to_string());
+            }
+            args.extend(["-simplify".to_string(), tolerance.to_string()]);
+        }
+        if let Some(resolution) = options.quantize {
+            if !(resolution > 0.0 && resolution.is_finite()) {
+                return Err("Quantization grid size must be positive".to_string());
+            }
+            let version: u32 = gdal::version_info("VERSION_NUM").parse().unwrap_or(0);
+            if version < QUANTIZE_VERSION {
+                return Err("Quantizing coordinates needs GDAL 3.9 or later".to_string());
+            }
+            args.extend(["-xyRes".to_string(), resolution.to_string()]);
+        }
+        // Layer names are positional arguments, as on the ogr2ogr command line
+        args.extend(options.layer);
+
+        if Path::new(&dst).exists() {
+            std::fs::remove_file(&dst).map_err(|e| e.to_string())?;
+        }
+
+        let progress = Progress::new(&app, "export_geojson");
+        let output =
+            vector_translate(&[&source], &dst, &args, &progress).map_err(|e| e.to_string())?;
+        let written = output.layer(0).map_err(|e| e.to_string())?;
+
+        Ok(ExportedLayer {
+            path: dst,
+            layer: written.name(),
+            feature_count: written.feature_count(),
+        })
+    })
+    .await
+}
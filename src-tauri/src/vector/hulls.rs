@@ -0,0 +1,435 @@
+use gdal::vector::{
+    geometry_type_flatten, Feature, FieldDefn, FieldValue, Geometry, LayerAccess, LayerOptions,
+    OGRFieldType, OGRwkbGeometryType,
+};
+use gdal::version::VersionInfo;
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::{create_output, layer_by_name};
+use crate::ffi::last_error;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, GdalError};
+
+// Vertices of the polygon written for a minimum bounding circle
+const CIRCLE_SEGMENTS: usize = 72;
+
+const DEFAULT_HULL_RATIO: f64 = 0.3;
+
+type Point = (f64, f64);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BoundingKind {
+    ConvexHull,
+    // Hull following the outline of the vertices; `ratio` runs from 0 for the tightest
+    // hull to 1 for the convex hull (0.3 when unset). Needs GEOS 3.11.
+    ConcaveHull {
+        #[serde(default)]
+        ratio: Option<f64>,
+        #[serde(default)]
+        allow_holes: bool,
+    },
+    // Axis-aligned bounding rectangle
+    Envelope,
+    // Smallest rectangle at any angle
+    OrientedRectangle,
+    // Smallest enclosing circle, as a polygon
+    Circle,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BoundingReport {
+    pub path: String,
+    pub layer: String,
+    pub feature_count: u64,
+    pub group_count: u64,
+    // Groups whose vertices do not span an area, e.g. a single point or collinear points
+    pub degenerate: u64,
+}
+
+// Geometries sharing one value of the group field
+struct FeatureGroup {
+    value: Option<FieldValue>,
+    geometries: Vec<Geometry>,
+}
+
+fn vertices(geometry: &Geometry, points: &mut Vec<Point>) {
+    let count = geometry.geometry_count();
+    if count > 0 {
+        for index in 0..count {
+            vertices(&geometry.get_geometry(index), points);
+        }
+        return;
+    }
+    let mut coordinates = Vec::new();
+    geometry.get_points(&mut coordinates);
+    points.extend(coordinates.into_iter().map(|(x, y, _)| (x, y)));
+}
+
+fn polygon(ring: &[Point]) -> Result<Geometry, GdalError> {
+    let mut exterior = Geometry::empty(OGRwkbGeometryType::wkbLinearRing)?;
+    for &point in ring.iter().chain(ring.first()) {
+        exterior.add_point_2d(point);
+    }
+    let mut polygon = Geometry::empty(OGRwkbGeometryType::wkbPolygon)?;
+    polygon.add_geometry(exterior)?;
+    Ok(polygon)
+}
+
+// Concave hulls may fall apart into several polygons, so the layer holds multipolygons
+fn to_multi_polygon(geometry: Geometry) -> Result<Geometry, GdalError> {
+    if geometry_type_flatten(geometry.geometry_type()) != OGRwkbGeometryType::wkbPolygon {
+        return Ok(geometry);
+    }
+    let mut multi = Geometry::empty(OGRwkbGeometryType::wkbMultiPolygon)?;
+    multi.add_geometry(geometry)?;
+    Ok(multi)
+}
+
+// gdal has no binding for OGR_G_ConcaveHull, so the hull comes back through WKB
+fn concave_hull(geometry: &Geometry, ratio: f64, allow_holes: bool) -> Result<Geometry, GdalError> {
+    unsafe {
+        let hull =
+            gdal_sys::OGR_G_ConcaveHull(geometry.c_geometry(), ratio.clamp(0.0, 1.0), allow_holes);
+        if hull.is_null() {
+            return Err(last_error("OGR_G_ConcaveHull"));
+        }
+        let mut wkb = vec![0u8; gdal_sys::OGR_G_WkbSize(hull).max(0) as usize];
+        let result =
+            gdal_sys::OGR_G_ExportToWkb(hull, gdal_sys::OGRwkbByteOrder::wkbNDR, wkb.as_mut_ptr());
+        gdal_sys::OGR_G_DestroyGeometry(hull);
+        if result != gdal_sys::OGRErr::OGRERR_NONE {
+            return Err(last_error("OGR_G_ExportToWkb"));
+        }
+        Ok(Geometry::from_wkb(&wkb)?)
+    }
+}
+
+// Rotating calipers: the smallest rectangle has a side on an edge of the convex hull
+fn oriented_rectangle(hull: &[Point]) -> Option<Vec<Point>> {
+    let mut best: Option<(f64, Vec<Point>)> = None;
+    for (index, &a) in hull.iter().enumerate() {
+        let b = hull[(index + 1) % hull.len()];
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        if length == 0.0 {
+            continue;
+        }
+        let u = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+        let v = (-u.1, u.0);
+        let (mut min_u, mut max_u, mut min_v, mut max_v) = (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        );
+        for &(x, y) in hull {
+            let (along, across) = (x * u.0 + y * u.1, x * v.0 + y * v.1);
+            min_u = min_u.min(along);
+            max_u = max_u.max(along);
+            min_v = min_v.min(across);
+            max_v = max_v.max(across);
+        }
+        let area = (max_u - min_u) * (max_v - min_v);
+        if best.as_ref().is_none_or(|(best, _)| area < *best) {
+            let corner =
+                |along: f64, across: f64| (along * u.0 + across * v.0, along * u.1 + across * v.1);
+            let corners = vec![
+                corner(min_u, min_v),
+                corner(max_u, min_v),
+                corner(max_u, max_v),
+                corner(min_u, max_v),
+            ];
+            best = Some((area, corners));
+        }
+    }
+    best.filter(|(area, _)| *area > 0.0)
+        .map(|(_, corners)| corners)
+}
+
+fn circle_through(a: Point, b: Point) -> (Point, f64) {
+    let centre = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+    (centre, (a.0 - centre.0).hypot(a.1 - centre.1))
+}
+
+// Circumcircle, falling back to the widest pair for collinear points
+fn circle_through_three(a: Point, b: Point, c: Point) -> (Point, f64) {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    if d.abs() < f64::EPSILON {
+        return [
+            circle_through(a, b),
+            circle_through(a, c),
+            circle_through(b, c),
+        ]
+        .into_iter()
+        .max_by(|x, y| x.1.total_cmp(&y.1))
+        .unwrap();
+    }
+    let (a2, b2, c2) = (
+        a.0 * a.0 + a.1 * a.1,
+        b.0 * b.0 + b.1 * b.1,
+        c.0 * c.0 + c.1 * c.1,
+    );
+    let centre = (
+        (a2 * (b.1 - c.1) + b2 * (c.1 - a.1) + c2 * (a.1 - b.1)) / d,
+        (a2 * (c.0 - b.0) + b2 * (a.0 - c.0) + c2 * (b.0 - a.0)) / d,
+    );
+    (centre, (a.0 - centre.0).hypot(a.1 - centre.1))
+}
+
+// Welzl's incremental algorithm over the hull vertices. The points are shuffled with a
+// fixed seed, which keeps the expected linear time and repeatable output.
+fn enclosing_circle(points: &[Point]) -> Option<(Point, f64)> {
+    let mut points = points.to_vec();
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    for index in (1..points.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        points.swap(index, (state % (index as u64 + 1)) as usize);
+    }
+    let inside = |circle: (Point, f64), p: Point| {
+        (p.0 - circle.0 .0).hypot(p.1 - circle.0 .1) <= circle.1 * (1.0 + 1e-12)
+    };
+
+    let mut circle = (*points.first()?, 0.0);
+    for i in 1..points.len() {
+        if inside(circle, points[i]) {
+            continue;
+        }
+        circle = (points[i], 0.0);
+        for j in 0..i {
+            if inside(circle, points[j]) {
+                continue;
+            }
+            circle = circle_through(points[i], points[j]);
+            for k in 0..j {
+                if !inside(circle, points[k]) {
+                    circle = circle_through_three(points[i], points[j], points[k]);
+                }
+            }
+        }
+    }
+    Some(circle).filter(|(_, radius)| *radius > 0.0)
+}
+
+// Bounding polygon of a group, None when it has no area
+fn bounding_geometry(
+    geometries: &[Geometry],
+    kind: &BoundingKind,
+) -> Result<Option<Geometry>, GdalError> {
+    let mut collection = Geometry::empty(OGRwkbGeometryType::wkbGeometryCollection)?;
+    for geometry in geometries {
+        collection.add_geometry(geometry.clone())?;
+    }
+
+    let result = match kind {
+        BoundingKind::ConvexHull => Some(collection.convex_hull()?),
+        BoundingKind::ConcaveHull { ratio, allow_holes } => Some(concave_hull(
+            &collection,
+            ratio.unwrap_or(DEFAULT_HULL_RATIO),
+            *allow_holes,
+        )?),
+        BoundingKind::Envelope => {
+            let envelope = collection.envelope();
+            Some(polygon(&[
+                (envelope.MinX, envelope.MinY),
+                (envelope.MaxX, envelope.MinY),
+                (envelope.MaxX, envelope.MaxY),
+                (envelope.MinX, envelope.MaxY),
+            ])?)
+        }
+        BoundingKind::OrientedRectangle | BoundingKind::Circle => {
+            // Both only depend on the convex hull's vertices
+            let mut hull = Vec::new();
+            vertices(&collection.convex_hull()?, &mut hull);
+            if hull.len() > 1 && hull.first() == hull.last() {
+                hull.pop();
+            }
+            if matches!(kind, BoundingKind::OrientedRectangle) {
+                oriented_rectangle(&hull)
+                    .map(|corners| polygon(&corners))
+                    .transpose()?
+            } else {
+                enclosing_circle(&hull)
+                    .map(|((x, y), radius)| {
+                        let ring: Vec<Point> = (0..CIRCLE_SEGMENTS)
+                            .map(|index| {
+                                let angle = 2.0 * PI * index as f64 / CIRCLE_SEGMENTS as f64;
+                                (x + radius * angle.cos(), y + radius * angle.sin())
+                            })
+                            .collect();
+                        polygon(&ring)
+                    })
+                    .transpose()?
+            }
+        }
+    };
+    Ok(result.filter(|geometry| geometry.area() > 0.0))
+}
+
+fn bound(
+    src: &str,
+    layer: Option<&str>,
+    dst: &str,
+    kind: &BoundingKind,
+    group_by: Option<&str>,
+    progress: &Progress,
+) -> Result<BoundingReport, GdalError> {
+    if matches!(
+        kind,
+        BoundingKind::ConvexHull | BoundingKind::ConcaveHull { .. }
+    ) && !VersionInfo::has_geos()
+    {
+        return Err(GdalError::InvalidArgument(
+            "GDAL was built without GEOS, which hulls need".to_string(),
+        ));
+    }
+    let source = Dataset::open(src)?;
+    let mut input = layer_by_name(&source, layer)?;
+    let field = group_by
+        .map(|name| {
+            input.defn().field_index(name).map_err(|_| {
+                GdalError::InvalidArgument(format!(
+                    "Layer {} has no field named {}",
+                    input.name(),
+                    name
+                ))
+            })
+        })
+        .transpose()?;
+
+    // Geometries of each group value in order of first appearance, or one group of all
+    let mut groups: Vec<FeatureGroup> = Vec::new();
+    let mut group_index: HashMap<Option<String>, usize> = HashMap::new();
+    let total = input.feature_count().max(1) as f64;
+    let mut feature_count = 0u64;
+    for feature in input.features() {
+        feature_count += 1;
+        if feature_count.is_multiple_of(1000) {
+            progress.report(0.5 * feature_count as f64 / total, None);
+        }
+        let Some(geometry) = feature.geometry().filter(|geometry| !geometry.is_empty()) else {
+            continue;
+        };
+        let key = field
+            .map(|index| feature.field_as_string(index))
+            .transpose()?
+            .flatten();
+        let index = match group_index.get(&key) {
+            Some(&index) => index,
+            None => {
+                let value = field
+                    .map(|index| feature.field(index))
+                    .transpose()?
+                    .flatten();
+                groups.push(FeatureGroup {
+                    value,
+                    geometries: Vec::new(),
+                });
+                group_index.insert(key, groups.len() - 1);
+                groups.len() - 1
+            }
+        };
+        groups[index].geometries.push(geometry.clone());
+    }
+
+    // The group field, then how many features each polygon bounds and its area
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer_name = input.name();
+    let srs = input.spatial_ref();
+    let target = transaction.create_layer(LayerOptions {
+        name: &layer_name,
+        srs: srs.as_ref(),
+        ty: OGRwkbGeometryType::wkbMultiPolygon,
+        ..Default::default()
+    })?;
+    if let Some(index) = field {
+        if let Some(field) = input.defn().fields().nth(index) {
+            let defn = FieldDefn::new(&field.name(), field.field_type())?;
+            defn.set_width(field.width());
+            defn.set_precision(field.precision());
+            defn.add_to_layer(&target)?;
+        }
+    }
+    let first = field.map_or(0, |_| 1);
+    FieldDefn::new("feature_count", OGRFieldType::OFTInteger64)?.add_to_layer(&target)?;
+    FieldDefn::new("area", OGRFieldType::OFTReal)?.add_to_layer(&target)?;
+
+    progress.set_range(0.5, 1.0);
+    let mut degenerate = 0u64;
+    let group_total = groups.len().max(1) as f64;
+    for (done, group) in groups.iter().enumerate() {
+        progress.report(done as f64 / group_total, None);
+        let Some(bounds) = bounding_geometry(&group.geometries, kind)? else {
+            degenerate += 1;
+            continue;
+        };
+        let mut feature = Feature::new(target.defn())?;
+        let area = bounds.area();
+        feature.set_geometry(to_multi_polygon(bounds)?)?;
+        if let Some(value) = &group.value {
+            feature.set_field(0, value)?;
+        }
+        feature.set_field_integer64(first, group.geometries.len() as i64)?;
+        feature.set_field_double(first + 1, area)?;
+        feature.create(&target)?;
+    }
+    transaction.commit()?;
+    output.close()?;
+
+    Ok(BoundingReport {
+        path: dst.to_string(),
+        layer: layer_name,
+        feature_count,
+        group_count: groups.len() as u64,
+        degenerate,
+    })
+}
+
+// Writes one bounding polygon per value of `group_by`, or one around the whole layer:
+// convex or concave hulls, envelopes, oriented rectangles or enclosing circles. Areas are
+// in squared layer units.
+#[tauri::command]
+pub async fn bounding_geometries(
+    app: AppHandle,
+    src: String,
+    layer: Option<String>,
+    dst: String,
+    kind: BoundingKind,
+    group_by: Option<String>,
+) -> Result<BoundingReport, String> {
+    let params = json!({
+        "src": src,
+        "layer": layer,
+        "dst": dst,
+        "kind": kind,
+        "group_by": group_by,
+    });
+    run_job(app.clone(), "bounding_geometries", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let progress = Progress::new(&app, "bounding_geometries");
+        bound(
+            &src,
+            layer.as_deref(),
+            &dst,
+            &kind,
+            group_by.as_deref(),
+            &progress,
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
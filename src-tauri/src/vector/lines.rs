@@ -0,0 +1,636 @@
+use gdal::vector::{
+    geometry_type_flatten, geometry_type_has_z, Feature, FieldDefn, FieldValue, Geometry, Layer,
+    LayerAccess, LayerOptions, OGRFieldType, OGRwkbGeometryType,
+};
+use gdal::version::VersionInfo;
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::{create_output, layer_by_name};
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, GdalError};
+
+pub(crate) type Vertex = (f64, f64, f64);
+pub(crate) type Line = Vec<Vertex>;
+
+// Lines sharing one combination of group values
+struct LineGroup {
+    values: Vec<Option<FieldValue>>,
+    lines: Vec<Line>,
+    feature_count: u64,
+}
+
+// A feature of the layer being split, read before the layer is queried for neighbours
+struct SourceLine {
+    fid: Option<u64>,
+    geometry: Option<Geometry>,
+    values: Vec<Option<FieldValue>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineReport {
+    pub path: String,
+    pub layer: String,
+    pub input_count: u64,
+    pub output_count: u64,
+    // Features without a line geometry, left out of the output
+    pub skipped: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkReport {
+    pub path: String,
+    pub layer: String,
+    pub input_count: u64,
+    pub edge_count: u64,
+    // Distinct edge end points: junctions, dead ends and line ends
+    pub node_count: u64,
+    pub skipped: u64,
+}
+
+// Fails unless the layer holds (multi)linestrings
+pub(crate) fn check_line_layer(layer: &Layer) -> Result<bool, GdalError> {
+    let ty = layer.defn().geometry_type();
+    match geometry_type_flatten(ty) {
+        OGRwkbGeometryType::wkbLineString
+        | OGRwkbGeometryType::wkbMultiLineString
+        | OGRwkbGeometryType::wkbUnknown => Ok(geometry_type_has_z(ty)),
+        _ => Err(GdalError::InvalidArgument(format!(
+            "Layer {} does not contain lines",
+            layer.name()
+        ))),
+    }
+}
+
+fn require_geos() -> Result<(), GdalError> {
+    if VersionInfo::has_geos() {
+        Ok(())
+    } else {
+        Err(GdalError::InvalidArgument(
+            "GDAL was built without GEOS, which line intersections need".to_string(),
+        ))
+    }
+}
+
+// Linestring parts of a geometry with at least two vertices each
+pub(crate) fn line_parts(geometry: &Geometry, parts: &mut Vec<Line>) {
+    let count = geometry.geometry_count();
+    if count > 0 {
+        for index in 0..count {
+            line_parts(&geometry.get_geometry(index), parts);
+        }
+        return;
+    }
+    if geometry_type_flatten(geometry.geometry_type()) != OGRwkbGeometryType::wkbLineString {
+        return;
+    }
+    let mut points = Vec::new();
+    geometry.get_points(&mut points);
+    if points.len() >= 2 {
+        parts.push(points);
+    }
+}
+
+fn line_geometry(line: &[Vertex], has_z: bool) -> Result<Geometry, GdalError> {
+    let mut geometry = Geometry::empty(line_type(has_z))?;
+    for &(x, y, z) in line {
+        if has_z {
+            geometry.add_point((x, y, z));
+        } else {
+            geometry.add_point_2d((x, y));
+        }
+    }
+    Ok(geometry)
+}
+
+fn line_type(has_z: bool) -> OGRwkbGeometryType::Type {
+    if has_z {
+        OGRwkbGeometryType::wkbLineString25D
+    } else {
+        OGRwkbGeometryType::wkbLineString
+    }
+}
+
+// Exact position of a vertex in the plane; -0.0 and 0.0 are the same node
+pub(crate) fn node_key(vertex: Vertex) -> (u64, u64) {
+    ((vertex.0 + 0.0).to_bits(), (vertex.1 + 0.0).to_bits())
+}
+
+// Joins lines end to end wherever exactly two of them meet, so a chain only ends at a
+// dead end or a junction of three or more lines. Closed chains are kept as rings.
+fn line_merge(lines: &[Line]) -> Vec<Line> {
+    let mut ends: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        ends.entry(node_key(line[0])).or_default().push(index);
+        ends.entry(node_key(line[line.len() - 1]))
+            .or_default()
+            .push(index);
+    }
+    let degree = |vertex: Vertex| ends[&node_key(vertex)].len();
+
+    // Chains are walked from an end that is not a pass-through node; lines left over
+    // after that are rings
+    let open = (0..lines.len()).filter(|&index| {
+        degree(lines[index][0]) != 2 || degree(lines[index][lines[index].len() - 1]) != 2
+    });
+    let starts: Vec<usize> = open.chain(0..lines.len()).collect();
+
+    let mut used = vec![false; lines.len()];
+    let mut merged = Vec::new();
+    for start in starts {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let mut chain = lines[start].clone();
+        if degree(chain[0]) == 2 && degree(chain[chain.len() - 1]) != 2 {
+            chain.reverse();
+        }
+        loop {
+            let end = chain[chain.len() - 1];
+            let at = &ends[&node_key(end)];
+            if at.len() != 2 {
+                break;
+            }
+            let Some(&next) = at.iter().find(|&&index| !used[index]) else {
+                break;
+            };
+            used[next] = true;
+            let mut part = lines[next].clone();
+            if node_key(part[0]) != node_key(end) {
+                part.reverse();
+            }
+            chain.extend_from_slice(&part[1..]);
+        }
+        merged.push(chain);
+    }
+    merged
+}
+
+fn merge(
+    src: &str,
+    layer: Option<&str>,
+    dst: &str,
+    group_by: &[String],
+    progress: &Progress,
+) -> Result<LineReport, GdalError> {
+    let source = Dataset::open(src)?;
+    let mut input = layer_by_name(&source, layer)?;
+    let has_z = check_line_layer(&input)?;
+    let fields = group_by
+        .iter()
+        .map(|name| {
+            input.defn().field_index(name).map_err(|_| {
+                GdalError::InvalidArgument(format!(
+                    "Layer {} has no field named {}",
+                    input.name(),
+                    name
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if group_by
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case("line_count"))
+    {
+        return Err(GdalError::InvalidArgument(
+            "line_count is written by the merge and cannot be a group field".to_string(),
+        ));
+    }
+
+    // Lines of each combination of group values, in order of first appearance
+    let mut groups: Vec<LineGroup> = Vec::new();
+    let mut group_index: HashMap<Vec<Option<String>>, usize> = HashMap::new();
+    let total = input.feature_count().max(1) as f64;
+    let (mut input_count, mut skipped) = (0u64, 0u64);
+    for feature in input.features() {
+        input_count += 1;
+        if input_count.is_multiple_of(1000) {
+            progress.report(0.8 * input_count as f64 / total, None);
+        }
+        let mut parts = Vec::new();
+        if let Some(geometry) = feature.geometry() {
+            line_parts(geometry, &mut parts);
+        }
+        if parts.is_empty() {
+            skipped += 1;
+            continue;
+        }
+        let key = fields
+            .iter()
+            .map(|&index| feature.field_as_string(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let index = match group_index.get(&key) {
+            Some(&index) => index,
+            None => {
+                let values = fields
+                    .iter()
+                    .map(|&index| feature.field(index))
+                    .collect::<Result<Vec<_>, _>>()?;
+                groups.push(LineGroup {
+                    values,
+                    lines: Vec::new(),
+                    feature_count: 0,
+                });
+                group_index.insert(key, groups.len() - 1);
+                groups.len() - 1
+            }
+        };
+        groups[index].lines.extend(parts);
+        groups[index].feature_count += 1;
+    }
+
+    // The group fields, then how many input features each merged line came from
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer_name = input.name();
+    let srs = input.spatial_ref();
+    let target = transaction.create_layer(LayerOptions {
+        name: &layer_name,
+        srs: srs.as_ref(),
+        ty: line_type(has_z),
+        ..Default::default()
+    })?;
+    for &index in &fields {
+        if let Some(field) = input.defn().fields().nth(index) {
+            let defn = FieldDefn::new(&field.name(), field.field_type())?;
+            defn.set_width(field.width());
+            defn.set_precision(field.precision());
+            defn.add_to_layer(&target)?;
+        }
+    }
+    FieldDefn::new("line_count", OGRFieldType::OFTInteger64)?.add_to_layer(&target)?;
+
+    progress.set_range(0.8, 1.0);
+    let mut output_count = 0u64;
+    let group_total = groups.len().max(1) as f64;
+    for (done, group) in groups.iter().enumerate() {
+        progress.report(done as f64 / group_total, None);
+        for line in line_merge(&group.lines) {
+            let mut feature = Feature::new(target.defn())?;
+            feature.set_geometry(line_geometry(&line, has_z)?)?;
+            for (index, value) in group.values.iter().enumerate() {
+                if let Some(value) = value {
+                    feature.set_field(index, value)?;
+                }
+            }
+            feature.set_field_integer64(group.values.len(), group.feature_count as i64)?;
+            feature.create(&target)?;
+            output_count += 1;
+        }
+    }
+    transaction.commit()?;
+    output.close()?;
+
+    Ok(LineReport {
+        path: dst.to_string(),
+        layer: layer_name,
+        input_count,
+        output_count,
+        skipped,
+    })
+}
+
+// End points of the pieces of an intersection: crossing points, and where lines overlap
+// the two ends of the shared stretch
+fn cut_points(geometry: &Geometry, cuts: &mut Vec<(f64, f64)>) {
+    let count = geometry.geometry_count();
+    if count > 0 {
+        for index in 0..count {
+            cut_points(&geometry.get_geometry(index), cuts);
+        }
+        return;
+    }
+    let mut points = Vec::new();
+    geometry.get_points(&mut points);
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        cuts.push((first.0, first.1));
+        cuts.push((last.0, last.1));
+    }
+}
+
+fn planar_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+// Position of `point` along segment a-b as a fraction, when it lies within `epsilon`
+fn segment_position(point: (f64, f64), a: Vertex, b: Vertex, epsilon: f64) -> Option<f64> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return None;
+    }
+    let t = ((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length_squared;
+    if !(0.0..=1.0).contains(&t) {
+        return None;
+    }
+    let on = (a.0 + t * dx, a.1 + t * dy);
+    (planar_distance(point, on) <= epsilon).then_some(t)
+}
+
+// Cuts a line at every point lying on it; points at its ends are ignored and points on
+// a vertex cut there
+fn split_line(line: &[Vertex], cuts: &[(f64, f64)], epsilon: f64) -> Vec<Line> {
+    let at_vertex = |vertex: Vertex| {
+        cuts.iter()
+            .any(|&cut| planar_distance(cut, (vertex.0, vertex.1)) <= epsilon)
+    };
+    let mut pieces = Vec::new();
+    let mut current = vec![line[0]];
+    for index in 0..line.len() - 1 {
+        let (a, b) = (line[index], line[index + 1]);
+        let mut positions: Vec<f64> = cuts
+            .iter()
+            .filter(|&&cut| {
+                planar_distance(cut, (a.0, a.1)) > epsilon
+                    && planar_distance(cut, (b.0, b.1)) > epsilon
+            })
+            .filter_map(|&cut| segment_position(cut, a, b, epsilon))
+            .collect();
+        positions.sort_by(f64::total_cmp);
+        positions.dedup();
+        for t in positions {
+            let point = (
+                a.0 + t * (b.0 - a.0),
+                a.1 + t * (b.1 - a.1),
+                a.2 + t * (b.2 - a.2),
+            );
+            if node_key(point) == node_key(current[current.len() - 1]) {
+                continue;
+            }
+            current.push(point);
+            pieces.push(std::mem::replace(&mut current, vec![point]));
+        }
+        current.push(b);
+        if index + 2 < line.len() && at_vertex(b) {
+            pieces.push(std::mem::replace(&mut current, vec![b]));
+        }
+    }
+    pieces.push(current);
+    pieces
+}
+
+fn split(
+    src: &str,
+    layer: Option<&str>,
+    dst: &str,
+    progress: &Progress,
+) -> Result<LineReport, GdalError> {
+    require_geos()?;
+    let source = Dataset::open(src)?;
+    let mut input = layer_by_name(&source, layer)?;
+    let has_z = check_line_layer(&input)?;
+    // Intersection points computed by GEOS are rarely exactly on either line
+    let epsilon = input
+        .get_extent()
+        .map(|extent| (extent.MaxX - extent.MinX).max(extent.MaxY - extent.MinY) * 1e-9)
+        .unwrap_or(0.0);
+
+    let field_count = input.defn().fields().count();
+    let mut features = Vec::new();
+    for feature in input.features() {
+        features.push(SourceLine {
+            fid: feature.fid(),
+            geometry: feature.geometry().cloned(),
+            values: (0..field_count)
+                .map(|index| feature.field(index))
+                .collect::<Result<Vec<_>, _>>()?,
+        });
+    }
+
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer_name = input.name();
+    let srs = input.spatial_ref();
+    let target = transaction.create_layer(LayerOptions {
+        name: &layer_name,
+        srs: srs.as_ref(),
+        ty: line_type(has_z),
+        ..Default::default()
+    })?;
+    for field in input.defn().fields() {
+        let defn = FieldDefn::new(&field.name(), field.field_type())?;
+        defn.set_width(field.width());
+        defn.set_precision(field.precision());
+        defn.add_to_layer(&target)?;
+    }
+
+    let total = features.len().max(1) as f64;
+    let (mut output_count, mut skipped) = (0u64, 0u64);
+    for (done, line) in features.iter().enumerate() {
+        if done.is_multiple_of(100) {
+            progress.report(done as f64 / total, None);
+        }
+        let mut parts = Vec::new();
+        if let Some(geometry) = &line.geometry {
+            line_parts(geometry, &mut parts);
+        }
+        let Some(geometry) = line.geometry.as_ref().filter(|_| !parts.is_empty()) else {
+            skipped += 1;
+            continue;
+        };
+
+        let envelope = geometry.envelope();
+        input.set_spatial_filter_rect(envelope.MinX, envelope.MinY, envelope.MaxX, envelope.MaxY);
+        let mut cuts = Vec::new();
+        for other in input.features() {
+            if line.fid.is_some() && other.fid() == line.fid {
+                continue;
+            }
+            if let Some(crossing) = other
+                .geometry()
+                .and_then(|other| geometry.intersection(other))
+            {
+                cut_points(&crossing, &mut cuts);
+            }
+        }
+
+        for part in &parts {
+            for piece in split_line(part, &cuts, epsilon) {
+                let mut feature = Feature::new(target.defn())?;
+                feature.set_geometry(line_geometry(&piece, has_z)?)?;
+                for (index, value) in line.values.iter().enumerate() {
+                    if let Some(value) = value {
+                        feature.set_field(index, value)?;
+                    }
+                }
+                feature.create(&target)?;
+                output_count += 1;
+            }
+        }
+    }
+    input.clear_spatial_filter();
+    transaction.commit()?;
+    output.close()?;
+
+    Ok(LineReport {
+        path: dst.to_string(),
+        layer: layer_name,
+        input_count: features.len() as u64,
+        output_count,
+        skipped,
+    })
+}
+
+fn planarize(
+    src: &str,
+    layer: Option<&str>,
+    dst: &str,
+    progress: &Progress,
+) -> Result<NetworkReport, GdalError> {
+    require_geos()?;
+    let source = Dataset::open(src)?;
+    let mut input = layer_by_name(&source, layer)?;
+    let has_z = check_line_layer(&input)?;
+
+    let mut network = Geometry::empty(if has_z {
+        OGRwkbGeometryType::wkbMultiLineString25D
+    } else {
+        OGRwkbGeometryType::wkbMultiLineString
+    })?;
+    let (mut input_count, mut skipped) = (0u64, 0u64);
+    for feature in input.features() {
+        input_count += 1;
+        let mut parts = Vec::new();
+        if let Some(geometry) = feature.geometry() {
+            line_parts(geometry, &mut parts);
+        }
+        if parts.is_empty() {
+            skipped += 1;
+        }
+        for part in parts {
+            network.add_geometry(line_geometry(&part, has_z)?)?;
+        }
+    }
+    progress.report(0.2, None);
+
+    // The union of the lines with themselves nodes them at every crossing and dissolves
+    // stretches where they overlap
+    let mut edges = Vec::new();
+    if !network.is_empty() {
+        let noded = network.union(&network).ok_or_else(|| {
+            GdalError::InvalidArgument("The lines could not be noded".to_string())
+        })?;
+        line_parts(&noded, &mut edges);
+    }
+    progress.report(0.8, None);
+
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer_name = input.name();
+    let srs = input.spatial_ref();
+    let target = transaction.create_layer(LayerOptions {
+        name: &layer_name,
+        srs: srs.as_ref(),
+        ty: line_type(has_z),
+        ..Default::default()
+    })?;
+    let mut nodes = HashSet::new();
+    for edge in &edges {
+        nodes.insert(node_key(edge[0]));
+        nodes.insert(node_key(edge[edge.len() - 1]));
+        let mut feature = Feature::new(target.defn())?;
+        feature.set_geometry(line_geometry(edge, has_z)?)?;
+        feature.create(&target)?;
+    }
+    transaction.commit()?;
+    output.close()?;
+
+    Ok(NetworkReport {
+        path: dst.to_string(),
+        layer: layer_name,
+        input_count,
+        edge_count: edges.len() as u64,
+        node_count: nodes.len() as u64,
+        skipped,
+    })
+}
+
+// Dissolves lines sharing the values of `group_by` (all lines when empty) and joins the
+// ones that meet end to end into single linestrings, stopping at junctions
+#[tauri::command]
+pub async fn merge_lines(
+    app: AppHandle,
+    src: String,
+    layer: Option<String>,
+    dst: String,
+    group_by: Option<Vec<String>>,
+) -> Result<LineReport, String> {
+    let params = json!({ "src": src, "layer": layer, "dst": dst, "group_by": group_by });
+    run_job(app.clone(), "merge_lines", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let progress = Progress::new(&app, "merge_lines");
+        merge(
+            &src,
+            layer.as_deref(),
+            &dst,
+            &group_by.unwrap_or_default(),
+            &progress,
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+}
+
+// Cuts every line where another line of the layer crosses or touches it, keeping the
+// attributes of the line on each piece. Crossings between parts of one multilinestring
+// are not cut; `planarize_lines` handles those.
+#[tauri::command]
+pub async fn split_lines_at_intersections(
+    app: AppHandle,
+    src: String,
+    layer: Option<String>,
+    dst: String,
+) -> Result<LineReport, String> {
+    let params = json!({ "src": src, "layer": layer, "dst": dst });
+    run_job(
+        app.clone(),
+        "split_lines_at_intersections",
+        params,
+        move || {
+            // Ensure GDAL runtime is set up
+            setup_gdal_runtime();
+
+            if !Path::new(&src).exists() {
+                return Err(format!("File not found: {}", src));
+            }
+            let progress = Progress::new(&app, "split_lines_at_intersections");
+            split(&src, layer.as_deref(), &dst, &progress).map_err(|e| e.to_string())
+        },
+    )
+    .await
+}
+
+// Nodes a line layer into a planar network: every crossing becomes a node, overlapping
+// stretches are kept once, and each edge between two nodes is written as a feature
+// without attributes
+#[tauri::command]
+pub async fn planarize_lines(
+    app: AppHandle,
+    src: String,
+    layer: Option<String>,
+    dst: String,
+) -> Result<NetworkReport, String> {
+    let params = json!({ "src": src, "layer": layer, "dst": dst });
+    run_job(app.clone(), "planarize_lines", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let progress = Progress::new(&app, "planarize_lines");
+        planarize(&src, layer.as_deref(), &dst, &progress).map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,140 @@
+use gdal::spatial_ref::AxisMappingStrategy;
+use gdal::vector::{geometry_type_flatten, Geometry, OGRwkbGeometryType};
+use serde::{Deserialize, Serialize};
+
+use super::parse_geometries;
+use crate::crs::parse_srs;
+use crate::ffi::last_error;
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MeasureMode {
+    // Length of lines, and of polygon outlines
+    #[default]
+    Distance,
+    // Area and perimeter of polygons
+    Area,
+}
+
+// Measures on the ellipsoid of the geometry's CRS, in metres and square metres
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Measurement {
+    pub mode: MeasureMode,
+    pub distance: Option<f64>,
+    // Length of every segment in vertex order, for labelling the legs of a measured line
+    pub segments: Vec<f64>,
+    pub area: Option<f64>,
+    pub perimeter: Option<f64>,
+}
+
+fn geodesic_length(geometry: &Geometry) -> Result<f64, GdalError> {
+    let length = unsafe { gdal_sys::OGR_G_GeodesicLength(geometry.c_geometry()) };
+    if length < 0.0 {
+        return Err(last_error("OGR_G_GeodesicLength"));
+    }
+    Ok(length)
+}
+
+fn geodesic_area(geometry: &Geometry) -> Result<f64, GdalError> {
+    let area = unsafe { gdal_sys::OGR_G_GeodesicArea(geometry.c_geometry()) };
+    if area < 0.0 {
+        return Err(last_error("OGR_G_GeodesicArea"));
+    }
+    Ok(area)
+}
+
+// Two-point lines for the segments of every linestring and ring in `geometry`
+fn segments(geometry: &Geometry, lines: &mut Vec<Geometry>) -> Result<(), GdalError> {
+    let count = geometry.geometry_count();
+    if count > 0 {
+        for index in 0..count {
+            segments(&geometry.get_geometry(index), lines)?;
+        }
+        return Ok(());
+    }
+    let mut points = Vec::new();
+    geometry.get_points(&mut points);
+    for pair in points.windows(2) {
+        let mut line = Geometry::empty(OGRwkbGeometryType::wkbLineString)?;
+        line.add_point_2d((pair[0].0, pair[0].1));
+        line.add_point_2d((pair[1].0, pair[1].1));
+        lines.push(line);
+    }
+    Ok(())
+}
+
+fn has_polygons(geometry: &Geometry) -> bool {
+    match geometry_type_flatten(geometry.geometry_type()) {
+        OGRwkbGeometryType::wkbPolygon | OGRwkbGeometryType::wkbMultiPolygon => true,
+        OGRwkbGeometryType::wkbGeometryCollection => {
+            (0..geometry.geometry_count()).any(|index| has_polygons(&geometry.get_geometry(index)))
+        }
+        _ => false,
+    }
+}
+
+fn measure_geometries(
+    input: &str,
+    crs: Option<&str>,
+    mode: MeasureMode,
+) -> Result<Measurement, GdalError> {
+    let mut srs = parse_srs(crs.unwrap_or("EPSG:4326"))?;
+    // Coordinates come as x/y, i.e. lon/lat for geographic CRSs, like everywhere else
+    srs.set_axis_mapping_strategy(AxisMappingStrategy::TraditionalGisOrder);
+
+    let mut measurement = Measurement {
+        mode,
+        distance: None,
+        segments: Vec::new(),
+        area: None,
+        perimeter: None,
+    };
+    for mut geometry in parse_geometries(input)? {
+        geometry.set_spatial_ref(srs.clone());
+        match mode {
+            MeasureMode::Distance => {
+                let length = geodesic_length(&geometry)?;
+                *measurement.distance.get_or_insert(0.0) += length;
+                let mut lines = Vec::new();
+                segments(&geometry, &mut lines)?;
+                for mut line in lines {
+                    line.set_spatial_ref(srs.clone());
+                    measurement.segments.push(geodesic_length(&line)?);
+                }
+            }
+            MeasureMode::Area => {
+                if !has_polygons(&geometry) {
+                    continue;
+                }
+                *measurement.area.get_or_insert(0.0) += geodesic_area(&geometry)?;
+                *measurement.perimeter.get_or_insert(0.0) += geodesic_length(&geometry)?;
+            }
+        }
+    }
+    if matches!(mode, MeasureMode::Area) && measurement.area.is_none() {
+        return Err(GdalError::InvalidArgument(
+            "Measuring an area needs a polygon".to_string(),
+        ));
+    }
+    Ok(measurement)
+}
+
+// Geodesic distance, or area and perimeter, of a WKT or GeoJSON geometry in `crs`
+// (EPSG:4326 when unset), computed on the CRS's ellipsoid rather than in the plane of
+// its projection. Several geometries, e.g. a FeatureCollection, are summed.
+#[tauri::command]
+pub async fn measure(
+    geometry: String,
+    crs: Option<String>,
+    mode: Option<MeasureMode>,
+) -> Result<Measurement, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        measure_geometries(&geometry, crs.as_deref(), mode.unwrap_or_default())
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,402 @@
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use gdal::vector::{
+    field_type_to_name, geometry_type_flatten, geometry_type_has_z, geometry_type_set_z,
+    geometry_type_to_name, Feature, FieldDefn, Geometry, LayerAccess, LayerOptions, OGRFieldType,
+    OGRwkbGeometryType,
+};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::create_output;
+use crate::crs::{parse_srs, transformer};
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, GdalError};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MergeVectorOptions {
+    // Output layer name, `merged` when unset
+    pub layer_name: Option<String>,
+    // Field recording where each feature came from, `source_file` when unset
+    pub source_field: Option<String>,
+    // CRS of the output, the first input's when unset
+    pub target_crs: Option<String>,
+    // Match field names exactly instead of ignoring case
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergedField {
+    pub name: String,
+    pub field_type: String,
+    // Inputs disagreed on the type and the field was widened
+    pub promoted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergedVectors {
+    pub path: String,
+    pub layer: String,
+    pub feature_count: u64,
+    pub geometry_type: String,
+    pub fields: Vec<MergedField>,
+}
+
+// One output field and what it was reconciled from
+struct FieldPlan {
+    key: String,
+    name: String,
+    field_type: OGRFieldType::Type,
+    width: i32,
+    precision: i32,
+    promoted: bool,
+}
+
+// One input layer, labelled with its file name and, in multi-layer files, its layer name
+struct SourceLayer {
+    path: String,
+    index: usize,
+    label: String,
+}
+
+// Narrowest type holding values of both; anything without a numeric or temporal
+// relation falls back to text
+fn promote(a: OGRFieldType::Type, b: OGRFieldType::Type) -> OGRFieldType::Type {
+    match (a, b) {
+        _ if a == b => a,
+        (OGRFieldType::OFTInteger, OGRFieldType::OFTInteger64)
+        | (OGRFieldType::OFTInteger64, OGRFieldType::OFTInteger) => OGRFieldType::OFTInteger64,
+        (OGRFieldType::OFTInteger | OGRFieldType::OFTInteger64, OGRFieldType::OFTReal)
+        | (OGRFieldType::OFTReal, OGRFieldType::OFTInteger | OGRFieldType::OFTInteger64) => {
+            OGRFieldType::OFTReal
+        }
+        (OGRFieldType::OFTDate, OGRFieldType::OFTDateTime)
+        | (OGRFieldType::OFTDateTime, OGRFieldType::OFTDate) => OGRFieldType::OFTDateTime,
+        _ => OGRFieldType::OFTString,
+    }
+}
+
+// The multi type collecting a single geometry type
+fn multi_of(ty: OGRwkbGeometryType::Type) -> Option<OGRwkbGeometryType::Type> {
+    match ty {
+        OGRwkbGeometryType::wkbPoint | OGRwkbGeometryType::wkbMultiPoint => {
+            Some(OGRwkbGeometryType::wkbMultiPoint)
+        }
+        OGRwkbGeometryType::wkbLineString | OGRwkbGeometryType::wkbMultiLineString => {
+            Some(OGRwkbGeometryType::wkbMultiLineString)
+        }
+        OGRwkbGeometryType::wkbPolygon | OGRwkbGeometryType::wkbMultiPolygon => {
+            Some(OGRwkbGeometryType::wkbMultiPolygon)
+        }
+        _ => None,
+    }
+}
+
+// Geometry type holding both: points and multipoints become multipoints, unrelated
+// types leave the layer untyped
+fn merge_geometry_type(
+    a: OGRwkbGeometryType::Type,
+    b: OGRwkbGeometryType::Type,
+) -> OGRwkbGeometryType::Type {
+    if a == b || b == OGRwkbGeometryType::wkbNone {
+        return a;
+    }
+    if a == OGRwkbGeometryType::wkbNone {
+        return b;
+    }
+    let (flat_a, flat_b) = (geometry_type_flatten(a), geometry_type_flatten(b));
+    let merged = if flat_a == flat_b {
+        flat_a
+    } else {
+        match (multi_of(flat_a), multi_of(flat_b)) {
+            (Some(multi_a), Some(multi_b)) if multi_a == multi_b => multi_a,
+            _ => return OGRwkbGeometryType::wkbUnknown,
+        }
+    };
+    if geometry_type_has_z(a) || geometry_type_has_z(b) {
+        geometry_type_set_z(merged)
+    } else {
+        merged
+    }
+}
+
+// Wraps single geometries when the layer holds their multi type
+fn fit_geometry(
+    geometry: Geometry,
+    layer_type: OGRwkbGeometryType::Type,
+) -> Result<Geometry, GdalError> {
+    let target = geometry_type_flatten(layer_type);
+    let flat = geometry_type_flatten(geometry.geometry_type());
+    if flat != target && multi_of(flat) == Some(target) {
+        let mut multi = Geometry::empty(target)?;
+        multi.add_geometry(geometry)?;
+        return Ok(multi);
+    }
+    Ok(geometry)
+}
+
+fn field_key(name: &str, case_sensitive: bool) -> String {
+    if case_sensitive {
+        name.to_string()
+    } else {
+        name.to_lowercase()
+    }
+}
+
+// Copies one value into the output field, converting it when the field was promoted
+fn copy_field(
+    source: &Feature,
+    source_index: usize,
+    target: &mut Feature,
+    plan: &FieldPlan,
+    target_index: usize,
+) -> Result<(), GdalError> {
+    let Some(value) = source.field(source_index)? else {
+        return Ok(());
+    };
+    if !plan.promoted {
+        target.set_field(target_index, &value)?;
+        return Ok(());
+    }
+    match plan.field_type {
+        OGRFieldType::OFTInteger64 => {
+            if let Some(value) = source.field_as_integer64(source_index)? {
+                target.set_field_integer64(target_index, value)?;
+            }
+        }
+        OGRFieldType::OFTReal => {
+            if let Some(value) = source.field_as_double(source_index)? {
+                target.set_field_double(target_index, value)?;
+            }
+        }
+        OGRFieldType::OFTDateTime => {
+            if let Some(value) = source.field_as_datetime(source_index)? {
+                target.set_field_datetime(target_index, value)?;
+            }
+        }
+        _ => {
+            if let Some(value) = source.field_as_string(source_index)? {
+                target.set_field_string(target_index, &value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge(
+    inputs: &[String],
+    dst: &str,
+    options: &MergeVectorOptions,
+    progress: &Progress,
+) -> Result<MergedVectors, GdalError> {
+    if inputs.is_empty() {
+        return Err(GdalError::InvalidArgument(
+            "No input files to merge".to_string(),
+        ));
+    }
+    let layer_name = options.layer_name.as_deref().unwrap_or("merged");
+    let source_field = options.source_field.as_deref().unwrap_or("source_file");
+
+    // First pass: the union of the schemas, the geometry type and the CRS
+    let mut sources = Vec::new();
+    let mut fields: Vec<FieldPlan> = Vec::new();
+    let mut geometry_type = OGRwkbGeometryType::wkbNone;
+    let mut first_srs: Option<SpatialRef> = None;
+    let mut total_features = 0u64;
+    for path in inputs {
+        if !Path::new(path).exists() {
+            return Err(GdalError::InvalidArgument(format!(
+                "File not found: {}",
+                path
+            )));
+        }
+        let dataset = Dataset::open(path)?;
+        let file_name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+        let layer_count = dataset.layer_count();
+        for (index, layer) in dataset.layers().enumerate() {
+            let defn = layer.defn();
+            geometry_type = if sources.is_empty() {
+                defn.geometry_type()
+            } else {
+                merge_geometry_type(geometry_type, defn.geometry_type())
+            };
+            if first_srs.is_none() {
+                first_srs = layer.spatial_ref();
+            }
+            total_features += layer.feature_count();
+
+            for field in defn.fields() {
+                let name = field.name();
+                let key = field_key(&name, options.case_sensitive);
+                let (field_type, width, precision) =
+                    (field.field_type(), field.width(), field.precision());
+                match fields.iter_mut().find(|plan| plan.key == key) {
+                    Some(plan) => {
+                        let promoted = promote(plan.field_type, field_type);
+                        if promoted != plan.field_type || promoted != field_type {
+                            plan.promoted = true;
+                        }
+                        if plan.promoted {
+                            // Widths of the source types say nothing about the new one
+                            plan.width = 0;
+                            plan.precision = 0;
+                        } else if plan.width != 0 && width != 0 {
+                            plan.width = plan.width.max(width);
+                            plan.precision = plan.precision.max(precision);
+                        } else {
+                            plan.width = 0;
+                        }
+                        plan.field_type = promoted;
+                    }
+                    None => fields.push(FieldPlan {
+                        key,
+                        name,
+                        field_type,
+                        width,
+                        precision,
+                        promoted: false,
+                    }),
+                }
+            }
+
+            let label = if layer_count > 1 {
+                format!("{}:{}", file_name, layer.name())
+            } else {
+                file_name.clone()
+            };
+            sources.push(SourceLayer {
+                path: path.clone(),
+                index,
+                label,
+            });
+        }
+    }
+    let source_key = field_key(source_field, options.case_sensitive);
+    if fields.iter().any(|plan| plan.key == source_key) {
+        return Err(GdalError::InvalidArgument(format!(
+            "The inputs already have a field named {}, choose another source field",
+            source_field
+        )));
+    }
+
+    let target_srs = match &options.target_crs {
+        Some(crs) => Some(parse_srs(crs)?),
+        None => first_srs,
+    };
+
+    // Second pass: append every layer, field values moved to their reconciled position
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer = transaction.create_layer(LayerOptions {
+        name: layer_name,
+        srs: target_srs.as_ref(),
+        ty: geometry_type,
+        ..Default::default()
+    })?;
+    for plan in &fields {
+        let defn = FieldDefn::new(&plan.name, plan.field_type)?;
+        defn.set_width(plan.width);
+        defn.set_precision(plan.precision);
+        defn.add_to_layer(&layer)?;
+    }
+    FieldDefn::new(source_field, OGRFieldType::OFTString)?.add_to_layer(&layer)?;
+    let source_index = fields.len();
+
+    let mut written = 0u64;
+    for source in &sources {
+        let dataset = Dataset::open(&source.path)?;
+        let mut input = dataset.layer(source.index)?;
+        let transform: Option<CoordTransform> = match (input.spatial_ref(), &target_srs) {
+            (Some(from), Some(to)) if &from != to => Some(transformer(&from, to)?),
+            _ => None,
+        };
+        let mapping: Vec<(usize, usize)> = input
+            .defn()
+            .fields()
+            .enumerate()
+            .filter_map(|(index, field)| {
+                let key = field_key(&field.name(), options.case_sensitive);
+                fields
+                    .iter()
+                    .position(|plan| plan.key == key)
+                    .map(|target| (index, target))
+            })
+            .collect();
+
+        for feature in input.features() {
+            let mut merged = Feature::new(layer.defn())?;
+            if let Some(geometry) = feature.geometry() {
+                let geometry = match &transform {
+                    Some(transform) => geometry.transform(transform)?,
+                    None => geometry.clone(),
+                };
+                merged.set_geometry(fit_geometry(geometry, geometry_type)?)?;
+            }
+            for &(from, to) in &mapping {
+                copy_field(&feature, from, &mut merged, &fields[to], to)?;
+            }
+            merged.set_field_string(source_index, &source.label)?;
+            merged.create(&layer)?;
+
+            written += 1;
+            if written.is_multiple_of(1000) && total_features > 0 {
+                progress.report(written as f64 / total_features as f64, None);
+            }
+        }
+    }
+    transaction.commit()?;
+    output.close()?;
+
+    let mut merged_fields: Vec<MergedField> = fields
+        .iter()
+        .map(|plan| MergedField {
+            name: plan.name.clone(),
+            field_type: field_type_to_name(plan.field_type),
+            promoted: plan.promoted,
+        })
+        .collect();
+    merged_fields.push(MergedField {
+        name: source_field.to_string(),
+        field_type: field_type_to_name(OGRFieldType::OFTString),
+        promoted: false,
+    });
+
+    Ok(MergedVectors {
+        path: dst.to_string(),
+        layer: layer_name.to_string(),
+        feature_count: written,
+        geometry_type: geometry_type_to_name(geometry_type),
+        fields: merged_fields,
+    })
+}
+
+// Appends every layer of every input into one layer. Fields are matched by name
+// (ignoring case unless `case_sensitive`) and the output holds their union; a field
+// whose type differs between inputs is widened (integer to 64-bit integer or real,
+// date to datetime, otherwise text). Geometries are reprojected to the target CRS and
+// single parts are made multi when the inputs mix both. Each feature records the file
+// it came from in the source field.
+#[tauri::command]
+pub async fn merge_vectors(
+    app: AppHandle,
+    inputs: Vec<String>,
+    dst: String,
+    options: Option<MergeVectorOptions>,
+) -> Result<MergedVectors, String> {
+    let params = json!({ "inputs": inputs, "dst": dst, "options": options });
+    run_job(app.clone(), "merge_vectors", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let progress = Progress::new(&app, "merge_vectors");
+        merge(&inputs, &dst, &options.unwrap_or_default(), &progress).map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,183 @@
+pub mod bins;
+pub mod cluster;
+pub mod dedupe;
+pub mod dxf;
+pub mod features;
+pub mod filter;
+pub mod flatgeobuf;
+pub mod geojson;
+pub mod hulls;
+pub mod lines;
+pub mod measure;
+pub mod merge;
+pub mod mvt;
+pub mod osm;
+pub mod pmtiles;
+pub mod routing;
+pub mod selection;
+pub mod snap;
+pub mod split;
+pub mod stats;
+pub(crate) mod translate;
+
+use gdal::spatial_ref::CoordTransform;
+use gdal::vector::{
+    geometry_type_flatten, Feature, FieldDefn, FieldValue, Geometry, Layer, LayerAccess,
+    LayerOptions, OGRwkbGeometryType,
+};
+use gdal::{Dataset, DriverManager, DriverType};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+use crate::GdalError;
+
+// Named layer, or the first one when no name is given
+pub(crate) fn layer_by_name<'a>(
+    dataset: &'a Dataset,
+    name: Option<&str>,
+) -> Result<Layer<'a>, GdalError> {
+    match name {
+        Some(name) => Ok(dataset.layer_by_name(name)?),
+        None => Ok(dataset.layer(0)?),
+    }
+}
+
+// Adds the coordinates of a point or multipoint to `points`; false for other geometry types
+pub(crate) fn point_parts(geometry: &Geometry, points: &mut Vec<(f64, f64)>) -> bool {
+    match geometry_type_flatten(geometry.geometry_type()) {
+        OGRwkbGeometryType::wkbPoint => {
+            if !geometry.is_empty() {
+                let (x, y, _) = geometry.get_point(0);
+                points.push((x, y));
+            }
+            true
+        }
+        OGRwkbGeometryType::wkbMultiPoint => {
+            for index in 0..geometry.geometry_count() {
+                point_parts(&geometry.get_geometry(index), points);
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn field_value_json(value: FieldValue) -> Value {
+    match value {
+        FieldValue::IntegerValue(v) => json!(v),
+        FieldValue::IntegerListValue(v) => json!(v),
+        FieldValue::Integer64Value(v) => json!(v),
+        FieldValue::Integer64ListValue(v) => json!(v),
+        FieldValue::StringValue(v) => json!(v),
+        FieldValue::StringListValue(v) => json!(v),
+        // NaN and infinity have no JSON representation and become null
+        FieldValue::RealValue(v) => json!(v),
+        FieldValue::RealListValue(v) => json!(v),
+        FieldValue::DateValue(v) => json!(v.to_string()),
+        FieldValue::DateTimeValue(v) => json!(v.to_string()),
+    }
+}
+
+// GeoJSON Feature object, with the geometry optionally reprojected
+pub(crate) fn feature_to_geojson(
+    feature: &Feature,
+    transform: Option<&CoordTransform>,
+) -> Result<Value, GdalError> {
+    let geometry = match feature.geometry() {
+        Some(geometry) => {
+            let json = match transform {
+                Some(transform) => geometry.transform(transform)?.json()?,
+                None => geometry.json()?,
+            };
+            serde_json::from_str(&json).unwrap_or(Value::Null)
+        }
+        None => Value::Null,
+    };
+
+    let properties: Map<String, Value> = feature
+        .fields()
+        .map(|(name, value)| (name, value.map(field_value_json).unwrap_or(Value::Null)))
+        .collect();
+
+    Ok(json!({
+        "type": "Feature",
+        "id": feature.fid(),
+        "geometry": geometry,
+        "properties": properties,
+    }))
+}
+
+// Creates an empty vector dataset at `dst`, picking the driver from the extension
+// (GeoPackage when unknown) and replacing any existing dataset there
+pub(crate) fn create_output(dst: &str) -> Result<Dataset, GdalError> {
+    let driver = match DriverManager::get_output_driver_for_dataset_name(dst, DriverType::Vector) {
+        Some(driver) => driver,
+        None => DriverManager::get_driver_by_name("GPKG")?,
+    };
+    if Path::new(dst).exists() {
+        // Deleting through the driver also removes sidecars such as .shx and .dbf
+        driver.delete(dst)?;
+    }
+    Ok(driver.create_vector_only(dst)?)
+}
+
+// Creates a layer in `output` with the CRS, geometry type and fields of `input`
+pub(crate) fn create_layer_like<'a>(
+    output: &'a mut Dataset,
+    input: &Layer,
+    name: &str,
+) -> Result<Layer<'a>, GdalError> {
+    let srs = input.spatial_ref();
+    let layer = output.create_layer(LayerOptions {
+        name,
+        srs: srs.as_ref(),
+        ty: input.defn().geometry_type(),
+        ..Default::default()
+    })?;
+    for field in input.defn().fields() {
+        let defn = FieldDefn::new(&field.name(), field.field_type())?;
+        defn.set_width(field.width());
+        defn.set_precision(field.precision());
+        defn.add_to_layer(&layer)?;
+    }
+    Ok(layer)
+}
+
+// Parses user supplied geometry text: WKT, or a GeoJSON geometry, Feature or FeatureCollection
+pub(crate) fn parse_geometries(input: &str) -> Result<Vec<Geometry>, GdalError> {
+    let input = input.trim();
+    if !input.starts_with('{') {
+        return Ok(vec![Geometry::from_wkt(input)?]);
+    }
+
+    let json: Value = serde_json::from_str(input)
+        .map_err(|e| GdalError::InvalidArgument(format!("Invalid GeoJSON: {}", e)))?;
+
+    let geometries: Vec<&Value> = match json.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => json
+            .get("features")
+            .and_then(Value::as_array)
+            .map(|features| features.iter().filter_map(|f| f.get("geometry")).collect())
+            .unwrap_or_default(),
+        Some("Feature") => json.get("geometry").into_iter().collect(),
+        Some(_) => vec![&json],
+        None => {
+            return Err(GdalError::InvalidArgument(
+                "GeoJSON object has no type".to_string(),
+            ))
+        }
+    };
+
+    let geometries = geometries
+        .into_iter()
+        .filter(|g| !g.is_null())
+        .map(|g| Geometry::from_geojson(&g.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if geometries.is_empty() {
+        return Err(GdalError::InvalidArgument(
+            "No geometry found in input".to_string(),
+        ));
+    }
+    Ok(geometries)
+}
@@ -0,0 +1,216 @@
+use gdal::vector::LayerAccess;
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::vector_translate;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::render::tiles::MAX_ZOOM;
+use crate::setup_gdal_runtime;
+
+// Per-layer settings, written to the driver's CONF option
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MvtLayerConfig {
+    // Layer name inside the tiles, the source layer name when unset
+    pub target_name: Option<String>,
+    pub description: Option<String>,
+    // Zoom levels the layer appears at, within the overall range
+    pub min_zoom: Option<u8>,
+    pub max_zoom: Option<u8>,
+}
+
+// How geometries are reduced inside the tiles, shared with the PMTiles export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileGeometry {
+    // Size of the integer grid coordinates are quantized to, per tile side; 4096 when
+    // unset. Smaller grids give smaller tiles and blockier shapes.
+    pub extent: Option<u32>,
+    // Simplification tolerance in grid units, none when unset
+    pub simplification: Option<f64>,
+    // Tolerance used at the maximum zoom level, `simplification` when unset
+    pub simplification_max_zoom: Option<f64>,
+}
+
+impl TileGeometry {
+    // Dataset creation options for the MVT and PMTiles drivers
+    pub(crate) fn creation_args(&self) -> Result<Vec<String>, String> {
+        let mut options = Vec::new();
+        if let Some(extent) = self.extent {
+            if extent == 0 {
+                return Err("Tile extent must be positive".to_string());
+            }
+            options.push(format!("EXTENT={}", extent));
+        }
+        for (name, tolerance) in [
+            ("SIMPLIFICATION", self.simplification),
+            ("SIMPLIFICATION_MAX_ZOOM", self.simplification_max_zoom),
+        ] {
+            if let Some(tolerance) = tolerance {
+                if !(tolerance >= 0.0 && tolerance.is_finite()) {
+                    return Err("Simplification tolerance must not be negative".to_string());
+                }
+                options.push(format!("{}={}", name, tolerance));
+            }
+        }
+        Ok(options
+            .into_iter()
+            .flat_map(|option| ["-dsco".to_string(), option])
+            .collect())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MvtExport {
+    pub path: String,
+    // True for a single MBTiles file, false for a `{z}/{x}/{y}.pbf` directory
+    pub mbtiles: bool,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    // Layer names as they appear in the tiles
+    pub layers: Vec<String>,
+}
+
+fn driver_conf(layer_config: &BTreeMap<String, MvtLayerConfig>) -> Value {
+    let layers: Map<String, Value> = layer_config
+        .iter()
+        .map(|(name, config)| {
+            let mut entry = Map::new();
+            if let Some(target_name) = &config.target_name {
+                entry.insert("target_name".to_string(), json!(target_name));
+            }
+            if let Some(description) = &config.description {
+                entry.insert("description".to_string(), json!(description));
+            }
+            if let Some(min_zoom) = config.min_zoom {
+                entry.insert("minzoom".to_string(), json!(min_zoom));
+            }
+            if let Some(max_zoom) = config.max_zoom {
+                entry.insert("maxzoom".to_string(), json!(max_zoom));
+            }
+            (name.clone(), Value::Object(entry))
+        })
+        .collect();
+    Value::Object(layers)
+}
+
+// Cuts vector data into Mapbox Vector Tiles with the MVT driver, once, so large layers
+// draw smoothly in the map. Destinations ending in `.mbtiles` get a single MBTiles
+// file, anything else a directory of uncompressed `{z}/{x}/{y}.pbf` tiles the webview
+// can fetch as is. `layer_config` is keyed by source layer name; every layer is
+// exported whether or not it has an entry. `geometry` trades shape detail for tile size.
+#[tauri::command]
+pub async fn export_mvt(
+    app: AppHandle,
+    src_vector: String,
+    dst: String,
+    zoom_range: Option<(u8, u8)>,
+    layer_config: Option<BTreeMap<String, MvtLayerConfig>>,
+    geometry: Option<TileGeometry>,
+) -> Result<MvtExport, String> {
+    let params = json!({
+        "src_vector": src_vector,
+        "dst": dst,
+        "zoom_range": zoom_range,
+        "layer_config": layer_config,
+        "geometry": geometry,
+    });
+    run_job(app.clone(), "export_mvt", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src_vector).exists() {
+            return Err(format!("File not found: {}", src_vector));
+        }
+        let (min_zoom, max_zoom) = zoom_range.unwrap_or((0, 14));
+        if min_zoom > max_zoom || max_zoom > MAX_ZOOM {
+            return Err(format!(
+                "Zoom range must satisfy 0 <= min <= max <= {}",
+                MAX_ZOOM
+            ));
+        }
+
+        let source = Dataset::open(&src_vector).map_err(|e| e.to_string())?;
+        if source.layer_count() == 0 {
+            return Err("Only vector datasets can be exported to vector tiles".to_string());
+        }
+        let layer_config = layer_config.unwrap_or_default();
+        for name in layer_config.keys() {
+            if source.layer_by_name(name).is_err() {
+                return Err(format!("Layer not found: {}", name));
+            }
+        }
+
+        let mbtiles = dst.to_ascii_lowercase().ends_with(".mbtiles");
+        // The MVT writer reprojects to Web Mercator itself
+        let mut args = vec![
+            "-f".to_string(),
+            "MVT".to_string(),
+            "-dsco".to_string(),
+            format!("MINZOOM={}", min_zoom),
+            "-dsco".to_string(),
+            format!("MAXZOOM={}", max_zoom),
+        ];
+        if mbtiles {
+            args.extend(["-dsco".to_string(), "FORMAT=MBTILES".to_string()]);
+        } else {
+            // Gzipped tiles would need a Content-Encoding header to load in the webview
+            args.extend([
+                "-dsco".to_string(),
+                "FORMAT=DIRECTORY".to_string(),
+                "-dsco".to_string(),
+                "COMPRESS=NO".to_string(),
+            ]);
+        }
+        args.extend(geometry.unwrap_or_default().creation_args()?);
+        if !layer_config.is_empty() {
+            args.extend([
+                "-dsco".to_string(),
+                format!("CONF={}", driver_conf(&layer_config)),
+            ]);
+        }
+
+        // The driver refuses to write over an existing output. Directories are only
+        // removed when empty, a mistyped path must not wipe unrelated files.
+        let existing = Path::new(&dst);
+        if existing.is_dir() {
+            fs::remove_dir(existing).map_err(|_| {
+                format!("Destination directory must be empty or not exist: {}", dst)
+            })?;
+        } else if existing.exists() {
+            fs::remove_file(existing).map_err(|e| e.to_string())?;
+        }
+
+        let progress = Progress::new(&app, "export_mvt");
+        let output =
+            vector_translate(&[&source], &dst, &args, &progress).map_err(|e| e.to_string())?;
+        // Tiles are only written out when the dataset is closed
+        output.close().map_err(|e| e.to_string())?;
+
+        let layers = source
+            .layers()
+            .map(|layer| {
+                let name = layer.name();
+                layer_config
+                    .get(&name)
+                    .and_then(|config| config.target_name.clone())
+                    .unwrap_or(name)
+            })
+            .collect();
+
+        Ok(MvtExport {
+            path: dst,
+            mbtiles,
+            min_zoom,
+            max_zoom,
+            layers,
+        })
+    })
+    .await
+}
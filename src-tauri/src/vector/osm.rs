@@ -0,0 +1,164 @@
+use gdal::vector::LayerAccess;
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::translate::vector_translate;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{run_blocking, setup_gdal_runtime};
+
+// Layers the OSM driver always exposes
+pub const OSM_LAYERS: &[&str] = &[
+    "points",
+    "lines",
+    "multilinestrings",
+    "multipolygons",
+    "other_relations",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OsmLayer {
+    pub name: String,
+    // Tags promoted to columns by osmconf.ini; everything else is in `other_tags`
+    pub fields: Vec<String>,
+}
+
+// Matches features carrying `key`, or `key=value` when a value is given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsmTagFilter {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+// SQL where clause for the filters on one layer, OR-ed together. Tags without their own
+// column are matched inside the `other_tags` hstore text.
+fn where_clause(filters: &[OsmTagFilter], fields: &[String]) -> Option<String> {
+    let conditions: Vec<String> = filters
+        .iter()
+        .map(|filter| {
+            if fields.iter().any(|field| field == &filter.key) {
+                let column = format!("\"{}\"", filter.key.replace('"', "\"\""));
+                match &filter.value {
+                    Some(value) => format!("{} = {}", column, quote(value)),
+                    None => format!("{} IS NOT NULL", column),
+                }
+            } else {
+                let pattern = match &filter.value {
+                    Some(value) => format!("%\"{}\"=>\"{}\"%", filter.key, value),
+                    None => format!("%\"{}\"=>%", filter.key),
+                };
+                format!("other_tags LIKE {}", quote(&pattern))
+            }
+        })
+        .collect();
+
+    if conditions.is_empty() {
+        None
+    } else {
+        Some(conditions.join(" OR "))
+    }
+}
+
+fn osm_layers(dataset: &Dataset) -> Vec<OsmLayer> {
+    dataset
+        .layers()
+        .map(|layer| OsmLayer {
+            name: layer.name(),
+            fields: layer.defn().fields().map(|field| field.name()).collect(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn get_osm_layers(path: String) -> Result<Vec<OsmLayer>, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&path).exists() {
+            return Err(format!("File not found: {}", path));
+        }
+
+        let dataset = Dataset::open(&path).map_err(|e| e.to_string())?;
+        if dataset.driver().short_name() != "OSM" {
+            return Err(format!("{} is not an OpenStreetMap file", path));
+        }
+        Ok(osm_layers(&dataset))
+    })
+    .await
+}
+
+// Copies OSM features into a GeoPackage, one output layer per source layer
+#[tauri::command]
+pub async fn extract_osm(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    layers: Option<Vec<String>>,
+    filters: Option<Vec<OsmTagFilter>>,
+) -> Result<Vec<OsmLayer>, String> {
+    let params = json!({ "src": src, "dst": dst, "layers": layers, "filters": filters });
+    run_job(app.clone(), "extract_osm", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let layers = layers.unwrap_or_else(|| {
+            // other_relations rarely holds anything useful outside a database
+            OSM_LAYERS[..4]
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        });
+        let filters = filters.unwrap_or_default();
+
+        if Path::new(&dst).exists() {
+            std::fs::remove_file(&dst).map_err(|e| e.to_string())?;
+        }
+
+        let progress = Progress::new(&app, "extract_osm");
+        for (index, layer_name) in layers.iter().enumerate() {
+            progress.set_range(
+                index as f64 / layers.len() as f64,
+                (index + 1) as f64 / layers.len() as f64,
+            );
+
+            // The OSM driver reads the file sequentially, so each layer gets a fresh handle
+            let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+            let fields: Vec<String> = source
+                .layer_by_name(layer_name)
+                .map_err(|e| e.to_string())?
+                .defn()
+                .fields()
+                .map(|field| field.name())
+                .collect();
+
+            let mut args = vec!["-f".to_string(), "GPKG".to_string()];
+            if index > 0 {
+                args.push("-update".to_string());
+            }
+            if let Some(clause) = where_clause(&filters, &fields) {
+                args.push("-where".to_string());
+                args.push(clause);
+            }
+            args.push(layer_name.clone());
+
+            let output =
+                vector_translate(&[&source], &dst, &args, &progress).map_err(|e| e.to_string())?;
+            output.close().map_err(|e| e.to_string())?;
+        }
+
+        let output = Dataset::open(&dst).map_err(|e| e.to_string())?;
+        Ok(osm_layers(&output))
+    })
+    .await
+}
@@ -0,0 +1,146 @@
+use gdal::vector::{geometry_type_to_name, LayerAccess};
+use gdal::{Dataset, Metadata};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::mvt::TileGeometry;
+use super::translate::vector_translate;
+use crate::jobs::run_job;
+use crate::network::check_remote;
+use crate::progress::Progress;
+use crate::{run_blocking, setup_gdal_runtime};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TileLayer {
+    pub name: String,
+    pub geometry_type: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PmtilesInfo {
+    pub path: String,
+    pub min_zoom: Option<u8>,
+    pub max_zoom: Option<u8>,
+    pub layers: Vec<TileLayer>,
+}
+
+fn tile_layers(dataset: &Dataset) -> Vec<TileLayer> {
+    dataset
+        .layers()
+        .map(|layer| TileLayer {
+            name: layer.name(),
+            geometry_type: geometry_type_to_name(layer.defn().geometry_type()),
+            fields: layer.defn().fields().map(|field| field.name()).collect(),
+        })
+        .collect()
+}
+
+// Lists the vector layers of a PMTiles archive; each one can then be opened with
+// `open_dataset` and read per viewport with `read_features`
+#[tauri::command]
+pub async fn get_pmtiles_info(path: String) -> Result<PmtilesInfo, String> {
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !path.starts_with("/vsi") && !Path::new(&path).exists() {
+            return Err(format!("File not found: {}", path));
+        }
+        check_remote(&path).map_err(|e| e.to_string())?;
+
+        let dataset = Dataset::open(&path).map_err(|e| e.to_string())?;
+        if dataset.driver().short_name() != "PMTiles" {
+            return Err(format!("{} is not a PMTiles archive", path));
+        }
+
+        let zoom = |key: &str| {
+            dataset
+                .metadata_item(key, "")
+                .and_then(|value| value.parse().ok())
+        };
+        let layers = tile_layers(&dataset);
+
+        Ok(PmtilesInfo {
+            min_zoom: zoom("ZOOM_LEVEL_MIN").or_else(|| zoom("minzoom")),
+            max_zoom: zoom("ZOOM_LEVEL_MAX").or_else(|| zoom("maxzoom")),
+            path,
+            layers,
+        })
+    })
+    .await
+}
+
+// Writes vector data as a PMTiles archive of Mapbox Vector Tiles. GDAL can only write
+// vector PMTiles, raster tile sets are not supported by the driver.
+#[tauri::command]
+pub async fn export_pmtiles(
+    app: AppHandle,
+    src: String,
+    dst: String,
+    layers: Option<Vec<String>>,
+    min_zoom: Option<u8>,
+    max_zoom: Option<u8>,
+    geometry: Option<TileGeometry>,
+) -> Result<PmtilesInfo, String> {
+    let params = json!({
+        "src": src,
+        "dst": dst,
+        "layers": layers,
+        "min_zoom": min_zoom,
+        "max_zoom": max_zoom,
+        "geometry": geometry,
+    });
+    run_job(app.clone(), "export_pmtiles", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let (min_zoom, max_zoom) = (min_zoom.unwrap_or(0), max_zoom.unwrap_or(14));
+        if min_zoom > max_zoom || max_zoom > 30 {
+            return Err("Zoom range must satisfy 0 <= min <= max <= 30".to_string());
+        }
+
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        if source.layer_count() == 0 {
+            return Err("Only vector datasets can be exported to PMTiles".to_string());
+        }
+
+        // The MVT writer reprojects to Web Mercator itself
+        let mut args = vec![
+            "-f".to_string(),
+            "PMTiles".to_string(),
+            "-dsco".to_string(),
+            format!("MINZOOM={}", min_zoom),
+            "-dsco".to_string(),
+            format!("MAXZOOM={}", max_zoom),
+        ];
+        args.extend(geometry.unwrap_or_default().creation_args()?);
+        args.extend(layers.unwrap_or_default());
+
+        if Path::new(&dst).exists() {
+            std::fs::remove_file(&dst).map_err(|e| e.to_string())?;
+        }
+
+        let progress = Progress::new(&app, "export_pmtiles");
+        let output =
+            vector_translate(&[&source], &dst, &args, &progress).map_err(|e| e.to_string())?;
+        // Closing finalises the archive directory, so reopen to read it back
+        output.close().map_err(|e| e.to_string())?;
+
+        let output = Dataset::open(&dst).map_err(|e| e.to_string())?;
+        let layers = tile_layers(&output);
+
+        Ok(PmtilesInfo {
+            path: dst,
+            min_zoom: Some(min_zoom),
+            max_zoom: Some(max_zoom),
+            layers,
+        })
+    })
+    .await
+}
@@ -0,0 +1,849 @@
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use gdal::vector::{Geometry, LayerAccess, OGRwkbGeometryType};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use super::layer_by_name;
+use super::lines::{check_line_layer, line_parts, node_key};
+use crate::crs::{crs_key, parse_srs, transformer};
+use crate::ffi::{last_error, string_from_ptr};
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+type Point = (f64, f64);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouteGraphOptions {
+    // Numeric field with the cost of travelling a line in its digitized direction; the
+    // line's length in layer units when unset
+    pub cost_field: Option<String>,
+    // Cost against the digitized direction, the forward cost when unset. Negative
+    // values close a direction, the usual coding of one-way streets.
+    pub reverse_cost_field: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RouteGraphInfo {
+    pub handle: u64,
+    pub node_count: usize,
+    pub edge_count: usize,
+    // Features without a line geometry or a cost, left out of the graph
+    pub skipped: u64,
+    pub crs: Option<String>,
+}
+
+// Where a route starts or ends: the nearest point on the network to the requested one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnappedPoint {
+    pub x: f64,
+    pub y: f64,
+    // From the requested point, in layer units
+    pub distance: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServiceAreaOptions {
+    // Tightness of the hull, from 0 for the tightest to 1 for the convex hull
+    pub hull_ratio: f64,
+    // Let the hull leave holes where the network does not reach
+    pub allow_holes: bool,
+}
+
+impl Default for ServiceAreaOptions {
+    fn default() -> Self {
+        Self {
+            hull_ratio: 0.3,
+            allow_holes: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServiceArea {
+    pub budget: f64,
+    pub start: SnappedPoint,
+    // FIDs of the lines reached, at least in part
+    pub fids: Vec<u64>,
+    // Planar length of `lines` in layer units
+    pub length: f64,
+    // GeoJSON MultiLineString of everything reachable within the budget, with lines cut
+    // where the budget runs out, in the CRS of the query point
+    pub lines: Value,
+    // GeoJSON concave hull around `lines`, null when nothing is reachable
+    pub hull: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Route {
+    pub cost: f64,
+    // Planar length in layer units
+    pub length: f64,
+    // FIDs of the lines travelled, in order
+    pub fids: Vec<u64>,
+    // GeoJSON LineString, in the CRS of the query points
+    pub geometry: Value,
+    pub start: SnappedPoint,
+    pub end: SnappedPoint,
+}
+
+// One linestring between two nodes, with None as the cost of a closed direction
+pub(crate) struct Edge {
+    pub fid: u64,
+    pub from: usize,
+    pub to: usize,
+    pub forward: Option<f64>,
+    pub backward: Option<f64>,
+    pub length: f64,
+    pub points: Vec<Point>,
+}
+
+// Nodes are the end points of the lines, joined where they coincide exactly, so the
+// layer should be planarized first for lines to connect where they cross
+pub(crate) struct RouteGraph {
+    pub srs_wkt: Option<String>,
+    pub nodes: Vec<Point>,
+    pub edges: Vec<Edge>,
+    // Edges touching each node
+    adjacency: Vec<Vec<usize>>,
+}
+
+// Position on an edge, as distance along it from its first vertex
+pub(crate) struct Snapped {
+    pub edge: usize,
+    pub along: f64,
+    pub point: Point,
+    pub distance: f64,
+}
+
+// Nodes reached from a set of starting costs, and the edge each was reached through
+pub(crate) struct ShortestPaths {
+    pub cost: Vec<f64>,
+    pub previous: Vec<Option<(usize, usize)>>,
+}
+
+// Min-heap entry for Dijkstra
+struct Visit {
+    cost: f64,
+    node: usize,
+}
+
+impl PartialEq for Visit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Visit {}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+// Point `along` units from the start of a polyline, clamped to its ends
+fn point_at(points: &[Point], along: f64) -> Point {
+    let mut travelled = 0.0;
+    for pair in points.windows(2) {
+        let length = distance(pair[0], pair[1]);
+        if travelled + length >= along && length > 0.0 {
+            let t = ((along - travelled) / length).clamp(0.0, 1.0);
+            return (
+                pair[0].0 + t * (pair[1].0 - pair[0].0),
+                pair[0].1 + t * (pair[1].1 - pair[0].1),
+            );
+        }
+        travelled += length;
+    }
+    if along <= 0.0 {
+        points[0]
+    } else {
+        points[points.len() - 1]
+    }
+}
+
+// Stretch of a polyline between two distances along it, reversed when `to` < `from`
+pub(crate) fn sub_line(points: &[Point], from: f64, to: f64) -> Vec<Point> {
+    let (start, end) = (from.min(to), from.max(to));
+    let mut stretch = vec![point_at(points, start)];
+    let mut travelled = 0.0;
+    for pair in points.windows(2) {
+        travelled += distance(pair[0], pair[1]);
+        if travelled > start && travelled < end {
+            stretch.push(pair[1]);
+        }
+    }
+    stretch.push(point_at(points, end));
+    if from > to {
+        stretch.reverse();
+    }
+    stretch
+}
+
+impl Edge {
+    // Cost of travelling part of the edge, as a share of its whole cost
+    fn partial(cost: Option<f64>, share: f64) -> Option<f64> {
+        cost.map(|cost| cost * share.clamp(0.0, 1.0))
+    }
+
+    fn share(&self, along: f64) -> f64 {
+        if self.length > 0.0 {
+            along / self.length
+        } else {
+            0.0
+        }
+    }
+}
+
+impl RouteGraph {
+    pub(crate) fn build(
+        src: &str,
+        layer: Option<&str>,
+        options: &RouteGraphOptions,
+    ) -> Result<(Self, u64), GdalError> {
+        let source = Dataset::open(src)?;
+        let mut input = layer_by_name(&source, layer)?;
+        check_line_layer(&input)?;
+        let field = |name: &Option<String>| {
+            name.as_deref()
+                .map(|name| {
+                    input.defn().field_index(name).map_err(|_| {
+                        GdalError::InvalidArgument(format!(
+                            "Layer {} has no field named {}",
+                            input.name(),
+                            name
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let cost_field = field(&options.cost_field)?;
+        let reverse_field = field(&options.reverse_cost_field)?;
+        let srs_wkt = input.spatial_ref().and_then(|srs| srs.to_wkt().ok());
+
+        let mut graph = RouteGraph {
+            srs_wkt,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            adjacency: Vec::new(),
+        };
+        let mut node_index: HashMap<(u64, u64), usize> = HashMap::new();
+        let mut skipped = 0u64;
+        for (position, feature) in input.features().enumerate() {
+            let mut parts = Vec::new();
+            if let Some(geometry) = feature.geometry() {
+                line_parts(geometry, &mut parts);
+            }
+            // None costs the length; a null in a cost field leaves the feature out
+            let cost = |index: Option<usize>, default: Option<f64>| match index {
+                Some(index) => feature.field_as_double(index).map(|cost| cost.map(Some)),
+                None => Ok(Some(default)),
+            };
+            let Some(forward) = cost(cost_field, None)? else {
+                skipped += 1;
+                continue;
+            };
+            let Some(backward) = cost(reverse_field, forward)? else {
+                skipped += 1;
+                continue;
+            };
+            if parts.is_empty() {
+                skipped += 1;
+                continue;
+            }
+            let fid = feature.fid().unwrap_or(position as u64);
+
+            // A cost applies to the whole feature, shared between its parts by length
+            let parts: Vec<Vec<Point>> = parts
+                .into_iter()
+                .map(|part| part.into_iter().map(|(x, y, _)| (x, y)).collect())
+                .collect();
+            let lengths: Vec<f64> = parts
+                .iter()
+                .map(|part| part.windows(2).map(|pair| distance(pair[0], pair[1])).sum())
+                .collect();
+            let total: f64 = lengths.iter().sum();
+            for (points, length) in parts.into_iter().zip(lengths) {
+                let share = if total > 0.0 { length / total } else { 0.0 };
+                let cost = |cost: Option<f64>| match cost {
+                    Some(cost) => (cost >= 0.0).then_some(cost * share),
+                    None => Some(length),
+                };
+                let mut node = |point: Point| {
+                    *node_index
+                        .entry(node_key((point.0, point.1, 0.0)))
+                        .or_insert_with(|| {
+                            graph.nodes.push(point);
+                            graph.adjacency.push(Vec::new());
+                            graph.nodes.len() - 1
+                        })
+                };
+                let from = node(points[0]);
+                let to = node(points[points.len() - 1]);
+                let edge = graph.edges.len();
+                graph.adjacency[from].push(edge);
+                if to != from {
+                    graph.adjacency[to].push(edge);
+                }
+                graph.edges.push(Edge {
+                    fid,
+                    from,
+                    to,
+                    forward: cost(forward),
+                    backward: cost(backward),
+                    length,
+                    points,
+                });
+            }
+        }
+        Ok((graph, skipped))
+    }
+
+    // Nearest point on any edge
+    pub(crate) fn snap(&self, point: Point) -> Option<Snapped> {
+        let mut best: Option<Snapped> = None;
+        for (index, edge) in self.edges.iter().enumerate() {
+            let mut travelled = 0.0;
+            for pair in edge.points.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                let length = dx.hypot(dy);
+                let t = if length > 0.0 {
+                    (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / (length * length))
+                        .clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let on = (a.0 + t * dx, a.1 + t * dy);
+                let d = distance(point, on);
+                if best.as_ref().is_none_or(|best| d < best.distance) {
+                    best = Some(Snapped {
+                        edge: index,
+                        along: travelled + t * length,
+                        point: on,
+                        distance: d,
+                    });
+                }
+                travelled += length;
+            }
+        }
+        best
+    }
+
+    // Costs from a snapped position to the two nodes of its edge
+    pub(crate) fn departures(&self, at: &Snapped) -> Vec<(usize, f64)> {
+        let edge = &self.edges[at.edge];
+        let share = edge.share(at.along);
+        let mut departures = Vec::new();
+        if let Some(cost) = Edge::partial(edge.forward, 1.0 - share) {
+            departures.push((edge.to, cost));
+        }
+        if let Some(cost) = Edge::partial(edge.backward, share) {
+            departures.push((edge.from, cost));
+        }
+        departures
+    }
+
+    // Costs from the two nodes of an edge to a snapped position on it
+    fn arrivals(&self, at: &Snapped) -> Vec<(usize, f64)> {
+        let edge = &self.edges[at.edge];
+        let share = edge.share(at.along);
+        let mut arrivals = Vec::new();
+        if let Some(cost) = Edge::partial(edge.forward, share) {
+            arrivals.push((edge.from, cost));
+        }
+        if let Some(cost) = Edge::partial(edge.backward, 1.0 - share) {
+            arrivals.push((edge.to, cost));
+        }
+        arrivals
+    }
+
+    // Dijkstra from several starting nodes, stopping at `limit` when given
+    pub(crate) fn shortest_paths(
+        &self,
+        sources: &[(usize, f64)],
+        limit: Option<f64>,
+    ) -> ShortestPaths {
+        let mut cost = vec![f64::INFINITY; self.nodes.len()];
+        let mut previous = vec![None; self.nodes.len()];
+        let mut queue = BinaryHeap::new();
+        for &(node, start) in sources {
+            if start < cost[node] {
+                cost[node] = start;
+                queue.push(Visit { cost: start, node });
+            }
+        }
+        while let Some(Visit {
+            cost: reached,
+            node,
+        }) = queue.pop()
+        {
+            if reached > cost[node] {
+                continue;
+            }
+            for &index in &self.adjacency[node] {
+                let edge = &self.edges[index];
+                let mut moves = Vec::with_capacity(2);
+                if edge.from == node {
+                    moves.extend(edge.forward.map(|step| (edge.to, step)));
+                }
+                if edge.to == node {
+                    moves.extend(edge.backward.map(|step| (edge.from, step)));
+                }
+                for (next, step) in moves {
+                    let total = reached + step;
+                    if limit.is_some_and(|limit| total > limit) {
+                        continue;
+                    }
+                    if total < cost[next] {
+                        cost[next] = total;
+                        previous[next] = Some((index, node));
+                        queue.push(Visit {
+                            cost: total,
+                            node: next,
+                        });
+                    }
+                }
+            }
+        }
+        ShortestPaths { cost, previous }
+    }
+
+    // Stretches of each edge reachable from `start` within `budget`, as distances along
+    // the edge, overlapping stretches merged
+    fn reachable(&self, start: &Snapped, budget: f64) -> Vec<(usize, f64, f64)> {
+        let paths = self.shortest_paths(&self.departures(start), Some(budget));
+        let mut reachable = Vec::new();
+        for (index, edge) in self.edges.iter().enumerate() {
+            // How far into the edge the cost left over gets from either end
+            let reach = |cost: Option<f64>, left: f64| {
+                cost.filter(|_| left >= 0.0).map(|cost| {
+                    if cost <= left {
+                        edge.length
+                    } else {
+                        edge.length * left / cost
+                    }
+                })
+            };
+            let mut stretches = Vec::new();
+            if let Some(reached) = reach(edge.forward, budget - paths.cost[edge.from]) {
+                stretches.push((0.0, reached));
+            }
+            if let Some(reached) = reach(edge.backward, budget - paths.cost[edge.to]) {
+                stretches.push((edge.length - reached, edge.length));
+            }
+            if index == start.edge {
+                if let Some(reached) = reach(edge.forward, budget) {
+                    stretches.push((start.along, (start.along + reached).min(edge.length)));
+                }
+                if let Some(reached) = reach(edge.backward, budget) {
+                    stretches.push(((start.along - reached).max(0.0), start.along));
+                }
+            }
+
+            stretches.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut merged: Vec<(f64, f64)> = Vec::new();
+            for (from, to) in stretches {
+                match merged.last_mut() {
+                    Some(last) if from <= last.1 => last.1 = last.1.max(to),
+                    _ => merged.push((from, to)),
+                }
+            }
+            reachable.extend(
+                merged
+                    .into_iter()
+                    .filter(|(from, to)| to > from)
+                    .map(|(from, to)| (index, from, to)),
+            );
+        }
+        reachable
+    }
+
+    // Cheapest route between two snapped positions, None when the end is unreachable
+    fn route(&self, start: &Snapped, end: &Snapped) -> Option<(f64, Vec<Point>, Vec<u64>)> {
+        let mut best: Option<(f64, Vec<Point>, Vec<u64>)> = None;
+
+        // Along the shared edge without passing a node
+        if start.edge == end.edge {
+            let edge = &self.edges[start.edge];
+            let share = edge.share(end.along) - edge.share(start.along);
+            let direct = if share >= 0.0 {
+                Edge::partial(edge.forward, share)
+            } else {
+                Edge::partial(edge.backward, -share)
+            };
+            if let Some(cost) = direct {
+                best = Some((
+                    cost,
+                    sub_line(&edge.points, start.along, end.along),
+                    vec![edge.fid],
+                ));
+            }
+        }
+
+        let departures = self.departures(start);
+        let paths = self.shortest_paths(&departures, None);
+        let through_nodes = self
+            .arrivals(end)
+            .into_iter()
+            .map(|(node, cost)| (node, paths.cost[node] + cost))
+            .filter(|(_, cost)| cost.is_finite())
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let (node, cost) = match through_nodes {
+            Some((_, cost)) if best.as_ref().is_some_and(|best| best.0 <= cost) => return best,
+            Some(through_nodes) => through_nodes,
+            None => return best,
+        };
+
+        // Walk back from the node the end is reached from to the node left from the start
+        let mut edges = Vec::new();
+        let mut at = node;
+        while let Some((edge, from)) = paths.previous[at] {
+            edges.push((edge, from));
+            at = from;
+        }
+        edges.reverse();
+
+        // Which way the first and last edges are travelled; both ends of a loop edge are
+        // the same node, so the cheaper direction decides
+        let first = &self.edges[start.edge];
+        let share = first.share(start.along);
+        let leave_along = match (
+            Edge::partial(first.forward, 1.0 - share).filter(|_| at == first.to),
+            Edge::partial(first.backward, share).filter(|_| at == first.from),
+        ) {
+            (Some(forward), Some(backward)) if backward < forward => 0.0,
+            (Some(_), _) => first.length,
+            _ => 0.0,
+        };
+        let mut points = sub_line(&first.points, start.along, leave_along);
+        let mut fids = vec![first.fid];
+        for (index, from) in edges {
+            let edge = &self.edges[index];
+            if edge.from == from {
+                points.extend(edge.points.iter().copied());
+            } else {
+                points.extend(edge.points.iter().rev().copied());
+            }
+            fids.push(edge.fid);
+        }
+        let last = &self.edges[end.edge];
+        let share = last.share(end.along);
+        let enter_along = match (
+            Edge::partial(last.forward, share).filter(|_| node == last.from),
+            Edge::partial(last.backward, 1.0 - share).filter(|_| node == last.to),
+        ) {
+            (Some(forward), Some(backward)) if backward < forward => last.length,
+            (Some(_), _) => 0.0,
+            _ => last.length,
+        };
+        points.extend(sub_line(&last.points, enter_along, end.along));
+        fids.push(last.fid);
+
+        points.dedup();
+        fids.dedup();
+        Some((cost, points, fids))
+    }
+}
+
+// Concave hull around `points` as GeoJSON, transformed with `transform` when given.
+// gdal has no binding for OGR_G_ConcaveHull, so the hull is only handled as a pointer.
+fn concave_hull(
+    points: &[Point],
+    options: &ServiceAreaOptions,
+    transform: Option<&CoordTransform>,
+) -> Result<Value, GdalError> {
+    let mut multipoint = Geometry::empty(OGRwkbGeometryType::wkbMultiPoint)?;
+    for &point in points {
+        let mut vertex = Geometry::empty(OGRwkbGeometryType::wkbPoint)?;
+        vertex.add_point_2d(point);
+        multipoint.add_geometry(vertex)?;
+    }
+    unsafe {
+        let hull = gdal_sys::OGR_G_ConcaveHull(
+            multipoint.c_geometry(),
+            options.hull_ratio.clamp(0.0, 1.0),
+            options.allow_holes,
+        );
+        if hull.is_null() {
+            return Err(last_error("OGR_G_ConcaveHull"));
+        }
+        if let Some(transform) = transform {
+            if gdal_sys::OGR_G_Transform(hull, transform.to_c_hct())
+                != gdal_sys::OGRErr::OGRERR_NONE
+            {
+                gdal_sys::OGR_G_DestroyGeometry(hull);
+                return Err(last_error("OGR_G_Transform"));
+            }
+        }
+        let json = gdal_sys::OGR_G_ExportToJson(hull);
+        gdal_sys::OGR_G_DestroyGeometry(hull);
+        let text = string_from_ptr(json);
+        gdal_sys::VSIFree(json as _);
+        Ok(text
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or(Value::Null))
+    }
+}
+
+// Route graphs kept between queries, addressed by handle
+#[derive(Default)]
+pub struct RouteGraphStore {
+    next_handle: Mutex<u64>,
+    graphs: Mutex<HashMap<u64, Arc<RouteGraph>>>,
+}
+
+impl RouteGraphStore {
+    fn insert(&self, graph: RouteGraph) -> u64 {
+        let handle = {
+            let mut next_handle = self.next_handle.lock().unwrap();
+            *next_handle += 1;
+            *next_handle
+        };
+        self.graphs.lock().unwrap().insert(handle, Arc::new(graph));
+        handle
+    }
+
+    pub(crate) fn get(&self, handle: u64) -> Result<Arc<RouteGraph>, GdalError> {
+        self.graphs
+            .lock()
+            .unwrap()
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| GdalError::InvalidArgument(format!("Unknown route graph {}", handle)))
+    }
+
+    fn remove(&self, handle: u64) -> bool {
+        self.graphs.lock().unwrap().remove(&handle).is_some()
+    }
+}
+
+// Transforms between the CRS of query points and the graph's, both ways; None when no
+// CRS is given or the graph has none
+pub(crate) struct QueryTransform {
+    pub to_graph: CoordTransform,
+    pub from_graph: CoordTransform,
+}
+
+impl QueryTransform {
+    pub(crate) fn new(graph: &RouteGraph, crs: Option<&str>) -> Result<Option<Self>, GdalError> {
+        let (Some(crs), Some(wkt)) = (crs, graph.srs_wkt.as_deref()) else {
+            return Ok(None);
+        };
+        let query = parse_srs(crs)?;
+        let layer = SpatialRef::from_wkt(wkt)?;
+        Ok(Some(Self {
+            to_graph: transformer(&query, &layer)?,
+            from_graph: transformer(&layer, &query)?,
+        }))
+    }
+}
+
+pub(crate) fn transform_points(
+    transform: Option<&CoordTransform>,
+    points: &[Point],
+) -> Result<Vec<Point>, GdalError> {
+    let Some(transform) = transform else {
+        return Ok(points.to_vec());
+    };
+    let mut x: Vec<f64> = points.iter().map(|point| point.0).collect();
+    let mut y: Vec<f64> = points.iter().map(|point| point.1).collect();
+    transform.transform_coords(&mut x, &mut y, &mut [])?;
+    Ok(x.into_iter().zip(y).collect())
+}
+
+// Reads a line layer into a routing graph kept in memory for `find_route`. Lines join
+// only where their end points coincide, so run `planarize_lines` first on data where
+// lines cross without being split.
+#[tauri::command]
+pub async fn build_route_graph(
+    graphs: State<'_, RouteGraphStore>,
+    src: String,
+    layer: Option<String>,
+    options: Option<RouteGraphOptions>,
+) -> Result<RouteGraphInfo, String> {
+    let (graph, skipped) = run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        RouteGraph::build(&src, layer.as_deref(), &options.unwrap_or_default())
+            .map_err(|e| e.to_string())
+    })
+    .await?;
+
+    let crs = graph
+        .srs_wkt
+        .as_deref()
+        .and_then(|wkt| SpatialRef::from_wkt(wkt).ok())
+        .as_ref()
+        .and_then(crs_key);
+    let (node_count, edge_count) = (graph.nodes.len(), graph.edges.len());
+    Ok(RouteGraphInfo {
+        handle: graphs.insert(graph),
+        node_count,
+        edge_count,
+        skipped,
+        crs,
+    })
+}
+
+// Cheapest route between two points, each snapped to the nearest point on the network.
+// Points are in `crs` when given and in the layer's CRS otherwise; returns null when
+// the end cannot be reached from the start.
+#[tauri::command]
+pub async fn find_route(
+    graphs: State<'_, RouteGraphStore>,
+    handle: u64,
+    from: (f64, f64),
+    to: (f64, f64),
+    crs: Option<String>,
+) -> Result<Option<Route>, String> {
+    let graph = graphs.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let transform = QueryTransform::new(&graph, crs.as_deref()).map_err(|e| e.to_string())?;
+        let query = transform_points(
+            transform.as_ref().map(|transform| &transform.to_graph),
+            &[from, to],
+        )
+        .map_err(|e| e.to_string())?;
+        let (Some(start), Some(end)) = (graph.snap(query[0]), graph.snap(query[1])) else {
+            return Err("The route graph has no edges".to_string());
+        };
+        let Some((cost, points, fids)) = graph.route(&start, &end) else {
+            return Ok(None);
+        };
+
+        let length = points
+            .windows(2)
+            .map(|pair| distance(pair[0], pair[1]))
+            .sum();
+        let back = transform.as_ref().map(|transform| &transform.from_graph);
+        let coordinates = transform_points(back, &points).map_err(|e| e.to_string())?;
+        let snapped =
+            transform_points(back, &[start.point, end.point]).map_err(|e| e.to_string())?;
+        Ok(Some(Route {
+            cost,
+            length,
+            fids,
+            geometry: json!({
+                "type": "LineString",
+                "coordinates": coordinates.iter().map(|&(x, y)| [x, y]).collect::<Vec<_>>(),
+            }),
+            start: SnappedPoint {
+                x: snapped[0].0,
+                y: snapped[0].1,
+                distance: start.distance,
+            },
+            end: SnappedPoint {
+                x: snapped[1].0,
+                y: snapped[1].1,
+                distance: end.distance,
+            },
+        }))
+    })
+    .await
+}
+
+// Everything reachable from a point within a cost budget, in the units of the graph's
+// cost field (or of the layer when costs are lengths), as lines and as a concave-hull
+// polygon for accessibility maps
+#[tauri::command]
+pub async fn service_area(
+    graphs: State<'_, RouteGraphStore>,
+    handle: u64,
+    from: (f64, f64),
+    budget: f64,
+    crs: Option<String>,
+    options: Option<ServiceAreaOptions>,
+) -> Result<ServiceArea, String> {
+    let graph = graphs.get(handle).map_err(|e| e.to_string())?;
+    if !(budget >= 0.0 && budget.is_finite()) {
+        return Err("The budget must be zero or positive".to_string());
+    }
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let transform = QueryTransform::new(&graph, crs.as_deref()).map_err(|e| e.to_string())?;
+        let back = transform.as_ref().map(|transform| &transform.from_graph);
+        let query = transform_points(
+            transform.as_ref().map(|transform| &transform.to_graph),
+            &[from],
+        )
+        .map_err(|e| e.to_string())?;
+        let start = graph
+            .snap(query[0])
+            .ok_or_else(|| "The route graph has no edges".to_string())?;
+
+        let mut fids = BTreeSet::new();
+        let mut length = 0.0;
+        let mut vertices = Vec::new();
+        let mut lines = Vec::new();
+        for (index, from, to) in graph.reachable(&start, budget) {
+            let edge = &graph.edges[index];
+            let stretch = sub_line(&edge.points, from, to);
+            fids.insert(edge.fid);
+            length += to - from;
+            vertices.extend_from_slice(&stretch);
+            let coordinates = transform_points(back, &stretch).map_err(|e| e.to_string())?;
+            lines.push(
+                coordinates
+                    .into_iter()
+                    .map(|(x, y)| [x, y])
+                    .collect::<Vec<_>>(),
+            );
+        }
+        let hull = if vertices.is_empty() {
+            Value::Null
+        } else {
+            concave_hull(&vertices, &options.unwrap_or_default(), back)
+                .map_err(|e| e.to_string())?
+        };
+        let snapped = transform_points(back, &[start.point]).map_err(|e| e.to_string())?;
+
+        Ok(ServiceArea {
+            budget,
+            start: SnappedPoint {
+                x: snapped[0].0,
+                y: snapped[0].1,
+                distance: start.distance,
+            },
+            fids: fids.into_iter().collect(),
+            length,
+            lines: json!({ "type": "MultiLineString", "coordinates": lines }),
+            hull,
+        })
+    })
+    .await
+}
+
+// Frees a route graph; false when the handle was unknown
+#[tauri::command]
+pub fn close_route_graph(graphs: State<'_, RouteGraphStore>, handle: u64) -> bool {
+    graphs.remove(handle)
+}
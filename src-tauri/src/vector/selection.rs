@@ -0,0 +1,237 @@
+use gdal::vector::{Layer, LayerAccess};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::CStr;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::filter::{matching_fids, FeatureFilter, SpatialPredicate};
+use super::{layer_by_name, parse_geometries};
+use crate::crs::{parse_srs, transformer};
+use crate::datasets::DatasetRegistry;
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SelectionQuery {
+    // Feature IDs, as returned in the `id` of GeoJSON features; unknown IDs are ignored
+    Ids {
+        ids: Vec<u64>,
+    },
+    // Filter expression, e.g. `population > 10000 AND name LIKE 'A%'`
+    Expression {
+        expression: String,
+    },
+    // WKT or GeoJSON geometry, in `crs` when given and the layer's CRS otherwise
+    Spatial {
+        geometry: String,
+        crs: Option<String>,
+        #[serde(default)]
+        predicate: SpatialPredicate,
+    },
+}
+
+// How the query result is combined with the current selection
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionMode {
+    #[default]
+    Replace,
+    Add,
+    Remove,
+    Intersect,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SelectionSummary {
+    pub layer: String,
+    pub count: usize,
+}
+
+// Selected feature IDs per layer, keyed by dataset path and layer name rather than by
+// handle so commands that take a file path can work on the selection as well
+#[derive(Default)]
+pub struct SelectionStore {
+    selections: Mutex<HashMap<(String, String), BTreeSet<u64>>>,
+}
+
+impl SelectionStore {
+    pub fn get(&self, path: &str, layer: &str) -> BTreeSet<u64> {
+        self.selections
+            .lock()
+            .unwrap()
+            .get(&(path.to_string(), layer.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn set(&self, path: &str, layer: &str, fids: BTreeSet<u64>) {
+        let key = (path.to_string(), layer.to_string());
+        let mut selections = self.selections.lock().unwrap();
+        if fids.is_empty() {
+            selections.remove(&key);
+        } else {
+            selections.insert(key, fids);
+        }
+    }
+
+    // Clears one layer, or every layer of the dataset when `layer` is None
+    fn clear(&self, path: &str, layer: Option<&str>) {
+        self.selections
+            .lock()
+            .unwrap()
+            .retain(|(selected_path, selected_layer), _| {
+                selected_path != path || layer.is_some_and(|layer| layer != selected_layer)
+            });
+    }
+}
+
+// Selection a "selected only" command works on; an empty selection is an error rather
+// than silently processing nothing
+pub(crate) fn selected_fids(
+    app: &AppHandle,
+    path: &str,
+    layer: &str,
+) -> Result<BTreeSet<u64>, GdalError> {
+    let fids = app.state::<SelectionStore>().get(path, layer);
+    if fids.is_empty() {
+        return Err(GdalError::InvalidArgument(format!(
+            "No features are selected in layer '{}'",
+            layer
+        )));
+    }
+    Ok(fids)
+}
+
+// OGR SQL WHERE clause matching the selected features, for ogr2ogr's `-where`
+pub(crate) fn fid_filter(layer: &Layer, fids: &BTreeSet<u64>) -> String {
+    let column = unsafe { CStr::from_ptr(gdal_sys::OGR_L_GetFIDColumn(layer.c_layer())) }
+        .to_string_lossy()
+        .to_string();
+    // Formats without a FID column (shapefiles, GeoJSON) expose the special FID field
+    let column = if column.is_empty() {
+        "FID".to_string()
+    } else {
+        format!("\"{}\"", column.replace('"', "\"\""))
+    };
+    let ids: Vec<String> = fids.iter().map(u64::to_string).collect();
+    format!("{} IN ({})", column, ids.join(","))
+}
+
+fn query_fids(layer: &mut Layer, query: &SelectionQuery) -> Result<BTreeSet<u64>, GdalError> {
+    match query {
+        SelectionQuery::Ids { ids } => Ok(ids
+            .iter()
+            .copied()
+            .filter(|fid| layer.feature(*fid).is_some())
+            .collect()),
+        SelectionQuery::Expression { expression } => {
+            let filter = FeatureFilter::parse(expression, layer)?;
+            matching_fids(layer, &filter)
+        }
+        SelectionQuery::Spatial {
+            geometry,
+            crs,
+            predicate,
+        } => {
+            let mut geometries = parse_geometries(geometry)?;
+            if let (Some(crs), Some(layer_srs)) = (crs, layer.spatial_ref()) {
+                let transform = transformer(&parse_srs(crs)?, &layer_srs)?;
+                geometries = geometries
+                    .iter()
+                    .map(|geometry| geometry.transform(&transform))
+                    .collect::<Result<_, _>>()?;
+            }
+
+            let mut fids = BTreeSet::new();
+            for query in &geometries {
+                // The spatial filter only compares envelopes, the predicate is exact
+                layer.set_spatial_filter(query);
+                for feature in layer.features() {
+                    let (Some(fid), Some(geometry)) = (feature.fid(), feature.geometry()) else {
+                        continue;
+                    };
+                    if predicate.test(geometry, query) {
+                        fids.insert(fid);
+                    }
+                }
+            }
+            layer.clear_spatial_filter();
+            Ok(fids)
+        }
+    }
+}
+
+// Selects features of a layer the way desktop GIS does, for later commands to work on
+// through their "selected only" flags
+#[tauri::command]
+pub async fn select_features(
+    registry: State<'_, DatasetRegistry>,
+    selections: State<'_, SelectionStore>,
+    handle: u64,
+    layer: Option<String>,
+    query: SelectionQuery,
+    mode: Option<SelectionMode>,
+) -> Result<SelectionSummary, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    let (path, layer, found) = run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let mut layer =
+            layer_by_name(&open.dataset, layer.as_deref()).map_err(|e| e.to_string())?;
+        let found = query_fids(&mut layer, &query).map_err(|e| e.to_string())?;
+        Ok((open.path.clone(), layer.name(), found))
+    })
+    .await?;
+
+    let current = selections.get(&path, &layer);
+    let selected: BTreeSet<u64> = match mode.unwrap_or_default() {
+        SelectionMode::Replace => found,
+        SelectionMode::Add => current.union(&found).copied().collect(),
+        SelectionMode::Remove => current.difference(&found).copied().collect(),
+        SelectionMode::Intersect => current.intersection(&found).copied().collect(),
+    };
+    let count = selected.len();
+    selections.set(&path, &layer, selected);
+    Ok(SelectionSummary { layer, count })
+}
+
+// Selected feature IDs of a layer, in ascending order
+#[tauri::command]
+pub async fn get_selection(
+    registry: State<'_, DatasetRegistry>,
+    selections: State<'_, SelectionStore>,
+    handle: u64,
+    layer: Option<String>,
+) -> Result<Vec<u64>, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    let (path, layer) = run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let layer = layer_by_name(&open.dataset, layer.as_deref()).map_err(|e| e.to_string())?;
+        Ok((open.path.clone(), layer.name()))
+    })
+    .await?;
+
+    Ok(selections.get(&path, &layer).into_iter().collect())
+}
+
+// Clears the selection of one layer, or of every layer when no layer is given
+#[tauri::command]
+pub fn clear_selection(
+    registry: State<'_, DatasetRegistry>,
+    selections: State<'_, SelectionStore>,
+    handle: u64,
+    layer: Option<String>,
+) -> Result<(), String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+    let path = entry.lock().unwrap().path.clone();
+    selections.clear(&path, layer.as_deref());
+    Ok(())
+}
@@ -0,0 +1,339 @@
+use gdal::vector::{geometry_type_has_z, Feature, Geometry, LayerAccess};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::{create_layer_like, create_output, layer_by_name};
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, GdalError};
+
+// Moved vertices listed in the report; the counts always cover all of them
+const MAX_REPORTED: usize = 10_000;
+
+type Point = (f64, f64);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "to", rename_all = "snake_case")]
+pub enum SnapTarget {
+    // Vertices of another layer within `tolerance`, or with `edges` also the nearest
+    // point on its segments when no vertex is in reach. The layer must share the CRS
+    // of the snapped one.
+    Layer {
+        path: String,
+        layer: Option<String>,
+        tolerance: f64,
+        #[serde(default)]
+        edges: bool,
+    },
+    // Nodes of a grid of `size` layer units, through `origin` (0, 0 when unset)
+    Grid {
+        size: f64,
+        #[serde(default)]
+        origin: Option<(f64, f64)>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MovedVertex {
+    pub fid: u64,
+    pub from: Point,
+    pub to: Point,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapReport {
+    pub path: String,
+    pub layer: String,
+    pub feature_count: u64,
+    pub features_changed: u64,
+    pub vertices_moved: u64,
+    pub max_distance: f64,
+    // Features whose geometry was valid before snapping and is not afterwards, e.g.
+    // because a ring collapsed
+    pub invalidated: Vec<u64>,
+    pub moved: Vec<MovedVertex>,
+    // More vertices moved than are listed in `moved`
+    pub truncated: bool,
+}
+
+// Vertices and segments of the reference features near one input feature
+#[derive(Default)]
+struct Candidates {
+    vertices: Vec<Point>,
+    segments: Vec<(Point, Point)>,
+}
+
+impl Candidates {
+    fn add(&mut self, geometry: &Geometry) {
+        let parts = geometry.geometry_count();
+        if parts > 0 {
+            for index in 0..parts {
+                self.add(&geometry.get_geometry(index));
+            }
+            return;
+        }
+        let mut points = Vec::new();
+        geometry.get_points(&mut points);
+        let points: Vec<Point> = points.into_iter().map(|(x, y, _)| (x, y)).collect();
+        self.segments
+            .extend(points.windows(2).map(|pair| (pair[0], pair[1])));
+        self.vertices.extend(points);
+    }
+
+    // Nearest vertex within `tolerance`, else with `edges` the nearest point on a segment
+    fn snap(&self, point: Point, tolerance: f64, edges: bool) -> Option<Point> {
+        let nearest = |candidates: &mut dyn Iterator<Item = Point>| {
+            candidates
+                .map(|candidate| (distance(point, candidate), candidate))
+                .filter(|(d, _)| *d <= tolerance)
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, candidate)| candidate)
+        };
+        nearest(&mut self.vertices.iter().copied()).or_else(|| {
+            if edges {
+                nearest(
+                    &mut self
+                        .segments
+                        .iter()
+                        .map(|&(a, b)| closest_on_segment(point, a, b)),
+                )
+            } else {
+                None
+            }
+        })
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn closest_on_segment(point: Point, a: Point, b: Point) -> Point {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return a;
+    }
+    let t = (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length_squared).clamp(0.0, 1.0);
+    (a.0 + t * dx, a.1 + t * dy)
+}
+
+fn snap_to_grid(point: Point, size: f64, origin: Point) -> Point {
+    (
+        origin.0 + ((point.0 - origin.0) / size).round() * size,
+        origin.1 + ((point.1 - origin.1) / size).round() * size,
+    )
+}
+
+// Moves every vertex of `geometry` to where `snap` puts it, recording each move
+fn snap_vertices(
+    geometry: &mut Geometry,
+    snap: &dyn Fn(Point) -> Option<Point>,
+    moves: &mut Vec<(Point, Point)>,
+) {
+    let parts = geometry.geometry_count();
+    if parts > 0 {
+        for index in 0..parts {
+            snap_vertices(&mut geometry.get_geometry(index), snap, moves);
+        }
+        return;
+    }
+    let has_z = geometry_type_has_z(geometry.geometry_type());
+    for index in 0..geometry.point_count() {
+        let (x, y, z) = geometry.get_point(index as i32);
+        let Some(to) = snap((x, y)).filter(|&to| to != (x, y)) else {
+            continue;
+        };
+        // Setting a z on a 2D geometry would turn it 3D
+        if has_z {
+            geometry.set_point(index, (to.0, to.1, z));
+        } else {
+            geometry.set_point_2d(index, to);
+        }
+        moves.push(((x, y), to));
+    }
+}
+
+fn snap(
+    src: &str,
+    layer: Option<&str>,
+    dst: &str,
+    target: &SnapTarget,
+    progress: &Progress,
+) -> Result<SnapReport, GdalError> {
+    let source = Dataset::open(src)?;
+    let mut input = layer_by_name(&source, layer)?;
+
+    // The reference layer is queried around each feature through a spatial filter
+    let reference_dataset = match target {
+        SnapTarget::Layer {
+            path, tolerance, ..
+        } => {
+            if !(*tolerance > 0.0 && tolerance.is_finite()) {
+                return Err(GdalError::InvalidArgument(
+                    "Snapping tolerance must be positive".to_string(),
+                ));
+            }
+            if !Path::new(path).exists() {
+                return Err(GdalError::InvalidArgument(format!(
+                    "File not found: {}",
+                    path
+                )));
+            }
+            Some(Dataset::open(path)?)
+        }
+        SnapTarget::Grid { size, .. } => {
+            if !(*size > 0.0 && size.is_finite()) {
+                return Err(GdalError::InvalidArgument(
+                    "Grid size must be positive".to_string(),
+                ));
+            }
+            None
+        }
+    };
+    let mut reference = match (&reference_dataset, target) {
+        (Some(dataset), SnapTarget::Layer { layer, .. }) => {
+            let reference = layer_by_name(dataset, layer.as_deref())?;
+            if let (Some(a), Some(b)) = (input.spatial_ref(), reference.spatial_ref()) {
+                if a != b {
+                    return Err(GdalError::InvalidArgument(
+                        "The reference layer has a different CRS, reproject it first".to_string(),
+                    ));
+                }
+            }
+            Some(reference)
+        }
+        _ => None,
+    };
+    // Snapping a layer to itself must not pull a feature onto its own vertices
+    let same_layer = match (&reference, target) {
+        (Some(reference), SnapTarget::Layer { path, .. }) => {
+            Path::new(path).canonicalize().ok() == Path::new(src).canonicalize().ok()
+                && reference.name() == input.name()
+        }
+        _ => false,
+    };
+
+    let mut output = create_output(dst)?;
+    let mut transaction = output.start_transaction()?;
+    let layer_name = input.name();
+    let target_layer = create_layer_like(&mut transaction, &input, &layer_name)?;
+    let field_count = input.defn().fields().count();
+
+    let mut report = SnapReport {
+        path: dst.to_string(),
+        layer: layer_name,
+        feature_count: 0,
+        features_changed: 0,
+        vertices_moved: 0,
+        max_distance: 0.0,
+        invalidated: Vec::new(),
+        moved: Vec::new(),
+        truncated: false,
+    };
+    let total = input.feature_count().max(1) as f64;
+    for feature in input.features() {
+        report.feature_count += 1;
+        if report.feature_count.is_multiple_of(100) {
+            progress.report(report.feature_count as f64 / total, None);
+        }
+        let fid = feature.fid().unwrap_or(report.feature_count - 1);
+
+        let mut copy = Feature::new(target_layer.defn())?;
+        if let Some(geometry) = feature.geometry() {
+            let mut geometry = geometry.clone();
+            let was_valid = geometry.is_valid();
+            let mut moves = Vec::new();
+            match target {
+                SnapTarget::Layer {
+                    tolerance, edges, ..
+                } => {
+                    let mut candidates = Candidates::default();
+                    if let Some(reference) = reference.as_mut() {
+                        let envelope = geometry.envelope();
+                        reference.set_spatial_filter_rect(
+                            envelope.MinX - tolerance,
+                            envelope.MinY - tolerance,
+                            envelope.MaxX + tolerance,
+                            envelope.MaxY + tolerance,
+                        );
+                        for other in reference.features() {
+                            if same_layer && other.fid() == Some(fid) {
+                                continue;
+                            }
+                            if let Some(other) = other.geometry() {
+                                candidates.add(other);
+                            }
+                        }
+                    }
+                    snap_vertices(
+                        &mut geometry,
+                        &|point| candidates.snap(point, *tolerance, *edges),
+                        &mut moves,
+                    );
+                }
+                SnapTarget::Grid { size, origin } => {
+                    let origin = origin.unwrap_or((0.0, 0.0));
+                    snap_vertices(
+                        &mut geometry,
+                        &|point| Some(snap_to_grid(point, *size, origin)),
+                        &mut moves,
+                    );
+                }
+            }
+
+            if !moves.is_empty() {
+                report.features_changed += 1;
+                if was_valid && !geometry.is_valid() {
+                    report.invalidated.push(fid);
+                }
+            }
+            for (from, to) in moves {
+                report.vertices_moved += 1;
+                report.max_distance = report.max_distance.max(distance(from, to));
+                if report.moved.len() < MAX_REPORTED {
+                    report.moved.push(MovedVertex { fid, from, to });
+                } else {
+                    report.truncated = true;
+                }
+            }
+            copy.set_geometry(geometry)?;
+        }
+        for index in 0..field_count {
+            if let Some(value) = feature.field(index)? {
+                copy.set_field(index, &value)?;
+            }
+        }
+        copy.create(&target_layer)?;
+    }
+    transaction.commit()?;
+    output.close()?;
+    Ok(report)
+}
+
+// Writes a copy of a layer with vertices snapped to a reference layer or a grid, so
+// shared boundaries line up exactly, and reports every vertex that moved
+#[tauri::command]
+pub async fn snap_geometries(
+    app: AppHandle,
+    src: String,
+    layer: Option<String>,
+    dst: String,
+    target: SnapTarget,
+) -> Result<SnapReport, String> {
+    let params = json!({ "src": src, "layer": layer, "dst": dst, "target": target });
+    run_job(app.clone(), "snap_geometries", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let progress = Progress::new(&app, "snap_geometries");
+        snap(&src, layer.as_deref(), &dst, &target, &progress).map_err(|e| e.to_string())
+    })
+    .await
+}
@@ -0,0 +1,223 @@
+use gdal::vector::{LayerAccess, OGRFieldType};
+use gdal::Dataset;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::layer_by_name;
+use super::translate::vector_translate;
+use crate::jobs::run_job;
+use crate::progress::Progress;
+use crate::{setup_gdal_runtime, GdalError};
+
+// More outputs than this is almost always a split on an ID-like field by mistake
+const MAX_PARTS: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SplitTarget {
+    // One layer per value in a single GeoPackage, replacing the file if it exists
+    Layers {
+        path: String,
+    },
+    // One file per value in `dir`, `.gpkg` unless another extension is given
+    Files {
+        dir: String,
+        extension: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitPart {
+    // Field value of the features in this part, null for features without one
+    pub value: Option<String>,
+    pub path: String,
+    pub layer: String,
+    pub feature_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitManifest {
+    pub field: String,
+    pub parts: Vec<SplitPart>,
+}
+
+// Output name for a value: letters, digits, `-` and `_` kept, anything else replaced,
+// with a numeric suffix when two values end up with the same name
+fn part_name(value: Option<&str>, taken: &mut BTreeSet<String>) -> String {
+    let base: String = match value {
+        Some(value) if !value.trim().is_empty() => value
+            .trim()
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect(),
+        Some(_) => "empty".to_string(),
+        None => "null".to_string(),
+    };
+    let mut name = base.clone();
+    let mut suffix = 2;
+    while !taken.insert(name.to_lowercase()) {
+        name = format!("{}_{}", base, suffix);
+        suffix += 1;
+    }
+    name
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// Where clause selecting the features of one value
+fn value_filter(field: &str, value: Option<&str>, numeric: bool) -> String {
+    let column = quote_identifier(field);
+    match value {
+        None => format!("{} IS NULL", column),
+        Some(value) if numeric => format!("{} = {}", column, value),
+        Some(value) => format!("{} = '{}'", column, value.replace('\'', "''")),
+    }
+}
+
+// Distinct values of a field and how many features carry each
+struct ValueCounts {
+    layer: String,
+    counts: BTreeMap<Option<String>, u64>,
+    // Values are compared as numbers rather than text in the where clauses
+    numeric: bool,
+}
+
+fn count_values(
+    dataset: &Dataset,
+    layer: Option<&str>,
+    field: &str,
+) -> Result<ValueCounts, GdalError> {
+    let mut source = layer_by_name(dataset, layer)?;
+    let index = source.defn().field_index(field).map_err(|_| {
+        GdalError::InvalidArgument(format!(
+            "Layer {} has no field named {}",
+            source.name(),
+            field
+        ))
+    })?;
+    let numeric = source
+        .defn()
+        .fields()
+        .nth(index)
+        .map(|field| {
+            matches!(
+                field.field_type(),
+                OGRFieldType::OFTInteger | OGRFieldType::OFTInteger64 | OGRFieldType::OFTReal
+            )
+        })
+        .unwrap_or(false);
+
+    let mut counts = BTreeMap::new();
+    for feature in source.features() {
+        let value = feature.field_as_string(index)?;
+        *counts.entry(value).or_insert(0) += 1;
+        if counts.len() > MAX_PARTS {
+            return Err(GdalError::InvalidArgument(format!(
+                "{} has more than {} distinct values",
+                field, MAX_PARTS
+            )));
+        }
+    }
+    Ok(ValueCounts {
+        layer: source.name(),
+        counts,
+        numeric,
+    })
+}
+
+// Splits a layer into one output per value of `field`, either as layers of one
+// GeoPackage or as separate files, and returns the manifest of what was written
+#[tauri::command]
+pub async fn split_by_attribute(
+    app: AppHandle,
+    src: String,
+    layer: Option<String>,
+    field: String,
+    target: SplitTarget,
+) -> Result<SplitManifest, String> {
+    let params = json!({ "src": src, "layer": layer, "field": field, "target": target });
+    run_job(app.clone(), "split_by_attribute", params, move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        if !Path::new(&src).exists() {
+            return Err(format!("File not found: {}", src));
+        }
+        let source = Dataset::open(&src).map_err(|e| e.to_string())?;
+        let values = count_values(&source, layer.as_deref(), &field).map_err(|e| e.to_string())?;
+
+        match &target {
+            SplitTarget::Layers { path } => {
+                if Path::new(path).exists() {
+                    fs::remove_file(path).map_err(|e| e.to_string())?;
+                }
+            }
+            SplitTarget::Files { dir, .. } => {
+                fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+        }
+
+        let progress = Progress::new(&app, "split_by_attribute");
+        let mut taken = BTreeSet::new();
+        let mut parts = Vec::new();
+        let total = values.counts.len() as f64;
+        for (index, (value, feature_count)) in values.counts.into_iter().enumerate() {
+            progress.set_range(index as f64 / total, (index + 1) as f64 / total);
+            let name = part_name(value.as_deref(), &mut taken);
+            let path = match &target {
+                SplitTarget::Layers { path } => path.clone(),
+                SplitTarget::Files { dir, extension } => Path::new(dir)
+                    .join(format!(
+                        "{}.{}",
+                        name,
+                        extension
+                            .as_deref()
+                            .unwrap_or("gpkg")
+                            .trim_start_matches('.')
+                    ))
+                    .to_string_lossy()
+                    .to_string(),
+            };
+
+            let mut args = match &target {
+                // Later parts are added as layers to the GeoPackage the first one created
+                SplitTarget::Layers { .. } if index > 0 => vec!["-update".to_string()],
+                SplitTarget::Layers { .. } => vec!["-f".to_string(), "GPKG".to_string()],
+                SplitTarget::Files { .. } => vec!["-overwrite".to_string()],
+            };
+            args.extend([
+                "-nln".to_string(),
+                name.clone(),
+                "-where".to_string(),
+                value_filter(&field, value.as_deref(), values.numeric),
+                values.layer.clone(),
+            ]);
+
+            let output =
+                vector_translate(&[&source], &path, &args, &progress).map_err(|e| e.to_string())?;
+            output.close().map_err(|e| e.to_string())?;
+
+            parts.push(SplitPart {
+                value,
+                path,
+                layer: name,
+                feature_count,
+            });
+        }
+
+        Ok(SplitManifest { field, parts })
+    })
+    .await
+}
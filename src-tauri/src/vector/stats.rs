@@ -0,0 +1,193 @@
+use gdal::cpl::CslStringList;
+use gdal::vector::{field_type_to_name, FieldValue, Layer, LayerAccess};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::ptr;
+use tauri::{AppHandle, State};
+
+use super::selection::selected_fids;
+use super::{field_value_json, layer_by_name};
+use crate::datasets::DatasetRegistry;
+use crate::{run_blocking, setup_gdal_runtime, GdalError};
+
+const DEFAULT_TOP_VALUES: usize = 10;
+
+// Distinct values tracked per field. Past this the distinct count is a lower bound and
+// values first seen later are not counted towards the top values.
+const MAX_DISTINCT: usize = 100_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValueCount {
+    pub value: Value,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldStatistics {
+    pub field: String,
+    pub field_type: String,
+    // Features with a value, and without one
+    pub count: u64,
+    pub null_count: u64,
+    // Only set for numeric fields
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub distinct_count: u64,
+    pub distinct_exact: bool,
+    // Most frequent values, most frequent first
+    pub top_values: Vec<ValueCount>,
+}
+
+fn numeric(value: &FieldValue) -> Option<f64> {
+    match value {
+        FieldValue::IntegerValue(v) => Some(*v as f64),
+        FieldValue::Integer64Value(v) => Some(*v as f64),
+        FieldValue::RealValue(v) if v.is_finite() => Some(*v),
+        _ => None,
+    }
+}
+
+// Tells OGR to skip the geometry and every other field, which makes the scan much
+// cheaper on wide layers. Passing None restores normal reads.
+fn ignore_other_fields(layer: &Layer, keep: Option<&str>) -> Result<(), GdalError> {
+    let mut ignored = CslStringList::new();
+    if let Some(keep) = keep {
+        ignored.add_string("OGR_GEOMETRY")?;
+        ignored.add_string("OGR_STYLE")?;
+        for field in layer.defn().fields() {
+            let name = field.name();
+            if name != keep {
+                ignored.add_string(&name)?;
+            }
+        }
+    }
+    let list = if keep.is_some() {
+        ignored.as_ptr()
+    } else {
+        ptr::null_mut()
+    };
+    unsafe { gdal_sys::OGR_L_SetIgnoredFields(layer.c_layer(), list as _) };
+    Ok(())
+}
+
+// Only features in `selection` are counted when one is given
+fn scan(
+    layer: &mut Layer,
+    field: &str,
+    top_n: usize,
+    selection: Option<&BTreeSet<u64>>,
+) -> Result<FieldStatistics, GdalError> {
+    let defn = layer.defn();
+    let index = defn.field_index(field)?;
+    let field_type = defn
+        .fields()
+        .nth(index)
+        .map(|field| field_type_to_name(field.field_type()))
+        .unwrap_or_default();
+
+    let mut count = 0;
+    let mut null_count = 0;
+    let mut min = f64::INFINITY;
+    let mut max = f64::NEG_INFINITY;
+    let mut sum = 0.0;
+    let mut numeric_count = 0;
+    let mut values: HashMap<String, (Value, u64)> = HashMap::new();
+    let mut distinct_exact = true;
+
+    layer.reset_feature_reading();
+    for feature in layer.features() {
+        if let Some(selection) = selection {
+            if !feature.fid().is_some_and(|fid| selection.contains(&fid)) {
+                continue;
+            }
+        }
+        let Some(value) = feature.field(index)? else {
+            null_count += 1;
+            continue;
+        };
+        count += 1;
+        if let Some(number) = numeric(&value) {
+            min = min.min(number);
+            max = max.max(number);
+            sum += number;
+            numeric_count += 1;
+        }
+
+        let value = field_value_json(value);
+        let key = value.to_string();
+        if let Some((_, count)) = values.get_mut(&key) {
+            *count += 1;
+        } else if values.len() < MAX_DISTINCT {
+            values.insert(key, (value, 1));
+        } else {
+            distinct_exact = false;
+        }
+    }
+
+    let distinct_count = values.len() as u64;
+    let mut top_values: Vec<ValueCount> = values
+        .into_values()
+        .map(|(value, count)| ValueCount { value, count })
+        .collect();
+    top_values.sort_by_key(|value| Reverse(value.count));
+    top_values.truncate(top_n);
+
+    let has_numbers = numeric_count > 0;
+    Ok(FieldStatistics {
+        field: field.to_string(),
+        field_type,
+        count,
+        null_count,
+        min: has_numbers.then_some(min),
+        max: has_numbers.then_some(max),
+        mean: has_numbers.then(|| sum / numeric_count as f64),
+        distinct_count,
+        distinct_exact,
+        top_values,
+    })
+}
+
+// Streams one attribute of a layer in a single pass for classification and filter UIs.
+// Memory use is bounded by MAX_DISTINCT rather than by the number of features. With
+// `selected_only` only the features selected in the layer are counted.
+#[tauri::command]
+pub async fn get_field_statistics(
+    app: AppHandle,
+    registry: State<'_, DatasetRegistry>,
+    handle: u64,
+    layer: Option<String>,
+    field: String,
+    top_n: Option<usize>,
+    selected_only: Option<bool>,
+) -> Result<FieldStatistics, String> {
+    let entry = registry.get(handle).map_err(|e| e.to_string())?;
+
+    run_blocking(move || {
+        // Ensure GDAL runtime is set up
+        setup_gdal_runtime();
+
+        let open = entry.lock().unwrap();
+        let mut layer =
+            layer_by_name(&open.dataset, layer.as_deref()).map_err(|e| e.to_string())?;
+        let selection = if selected_only.unwrap_or(false) {
+            Some(selected_fids(&app, &open.path, &layer.name()).map_err(|e| e.to_string())?)
+        } else {
+            None
+        };
+
+        ignore_other_fields(&layer, Some(&field)).map_err(|e| e.to_string())?;
+        let result = scan(
+            &mut layer,
+            &field,
+            top_n.unwrap_or(DEFAULT_TOP_VALUES),
+            selection.as_ref(),
+        );
+        // The layer stays open in the registry, so later reads must see every field again
+        let _ = ignore_other_fields(&layer, None);
+        result.map_err(|e| e.to_string())
+    })
+    .await
+}
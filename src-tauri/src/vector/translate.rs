@@ -0,0 +1,50 @@
+use gdal::Dataset;
+use std::ptr;
+
+use crate::jobs::{dataset_name, record_command_line};
+use crate::progress::{gdal_progress, Progress};
+use crate::{ffi, GdalError};
+
+// Runs GDALVectorTranslate (the library form of ogr2ogr) from `sources` into `dst`
+pub(crate) fn vector_translate(
+    sources: &[&Dataset],
+    dst: &str,
+    args: &[String],
+    progress: &Progress,
+) -> Result<Dataset, GdalError> {
+    let mut command_args = args.to_vec();
+    command_args.push(dst.to_string());
+    command_args.extend(sources.iter().map(|source| dataset_name(source)));
+    record_command_line("ogr2ogr", &command_args);
+    let argv = ffi::arg_list(args)?;
+    let c_dst = ffi::c_string(dst)?;
+    let mut handles: Vec<_> = sources.iter().map(|ds| ds.c_dataset()).collect();
+
+    unsafe {
+        let options = gdal_sys::GDALVectorTranslateOptionsNew(argv.as_ptr(), ptr::null_mut());
+        if options.is_null() {
+            return Err(ffi::last_error("GDALVectorTranslateOptionsNew"));
+        }
+        gdal_sys::GDALVectorTranslateOptionsSetProgress(
+            options,
+            Some(gdal_progress),
+            progress.as_arg(),
+        );
+
+        let mut usage_error = 0;
+        let result = gdal_sys::GDALVectorTranslate(
+            c_dst.as_ptr(),
+            ptr::null_mut(),
+            handles.len() as i32,
+            handles.as_mut_ptr(),
+            options,
+            &mut usage_error,
+        );
+        gdal_sys::GDALVectorTranslateOptionsFree(options);
+
+        if result.is_null() {
+            return Err(ffi::last_error("GDALVectorTranslate"));
+        }
+        Ok(Dataset::from_c_dataset(result))
+    }
+}
@@ -0,0 +1,352 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::jobs::{apply_overrides, replay, unix_millis};
+use crate::presets::find_preset;
+use crate::settings::SettingsStore;
+use crate::GdalError;
+
+pub const WATCH_EVENT: &str = "watch-folder";
+
+// Directories are polled rather than watched through OS notifications, which behave
+// differently per platform and on network shares
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn all_files() -> String {
+    "*".to_string()
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+// A directory whose new files are run through a preset. The preset's command must take
+// `src` and `dst`, e.g. export_cog or warp_raster.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolder {
+    pub name: String,
+    pub path: String,
+    pub preset: String,
+    // Glob matched against file names, e.g. "*.tif"
+    #[serde(default = "all_files")]
+    pub pattern: String,
+    // Defaults to a "processed" directory inside the watched one
+    #[serde(default)]
+    pub output_dir: Option<String>,
+    // Extension of outputs, defaulting to the input's
+    #[serde(default)]
+    pub output_extension: Option<String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+impl WatchFolder {
+    fn output_path(&self, input: &Path) -> PathBuf {
+        let dir = self
+            .output_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(&self.path).join("processed"));
+        let stem = input.file_stem().unwrap_or_default();
+        let extension = self
+            .output_extension
+            .as_deref()
+            .map(|extension| extension.trim_start_matches('.').to_string())
+            .or_else(|| {
+                input
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().into_owned())
+            });
+        let mut path = dir.join(stem);
+        if let Some(extension) = extension {
+            path.set_extension(extension);
+        }
+        path
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchStatus {
+    Started,
+    Succeeded,
+    Failed,
+}
+
+// One step in processing a watched file, emitted as WATCH_EVENT and kept in the log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchActivity {
+    pub folder: String,
+    pub file: String,
+    pub status: WatchStatus,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    // Unix time in milliseconds
+    pub time: u64,
+}
+
+// Running watchers by folder name, and the activity log as JSON Lines
+pub struct WatchManager {
+    path: PathBuf,
+    activity: Mutex<Vec<WatchActivity>>,
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl WatchManager {
+    pub fn load(path: PathBuf) -> Self {
+        let activity = fs::read_to_string(&path)
+            .map(|text| {
+                text.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            activity: Mutex::new(activity),
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn record(&self, entry: WatchActivity) -> Result<(), GdalError> {
+        let mut activity = self.activity.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let line =
+            serde_json::to_string(&entry).map_err(|e| GdalError::OperationFailed(e.to_string()))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        activity.push(entry);
+        Ok(())
+    }
+
+    // Most recent first
+    pub fn list(&self, limit: usize) -> Vec<WatchActivity> {
+        self.activity
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) -> Result<(), GdalError> {
+        let mut activity = self.activity.lock().unwrap();
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        activity.clear();
+        Ok(())
+    }
+
+    fn stop(&self, name: &str) {
+        if let Some(stop) = self.running.lock().unwrap().remove(name) {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Starts polling `folder`, replacing any watcher already running under its name
+    fn start(&self, app: &AppHandle, folder: WatchFolder) {
+        self.stop(&folder.name);
+        if !folder.enabled {
+            return;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        self.running
+            .lock()
+            .unwrap()
+            .insert(folder.name.clone(), stop.clone());
+        let app = app.clone();
+        thread::spawn(move || poll(app, folder, stop));
+    }
+}
+
+fn notify(app: &AppHandle, entry: WatchActivity) {
+    let _ = app.emit(WATCH_EVENT, entry.clone());
+    if let Some(manager) = app.try_state::<WatchManager>() {
+        let _ = manager.record(entry);
+    }
+}
+
+// Size and modification time, to tell when a file has stopped being written
+type FileState = (u64, Option<SystemTime>);
+
+fn scan(folder: &WatchFolder) -> HashMap<PathBuf, FileState> {
+    let pattern = glob::Pattern::new(&folder.pattern).ok();
+    let Ok(entries) = fs::read_dir(&folder.path) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_file() {
+                return None;
+            }
+            let name = entry.file_name();
+            let matches = pattern
+                .as_ref()
+                .is_some_and(|pattern| pattern.matches(&name.to_string_lossy()));
+            matches.then(|| (entry.path(), (metadata.len(), metadata.modified().ok())))
+        })
+        .collect()
+}
+
+fn poll(app: AppHandle, folder: WatchFolder, stop: Arc<AtomicBool>) {
+    // Files present when watching starts are left alone
+    let mut processed: HashMap<PathBuf, FileState> = scan(&folder);
+    let mut pending: HashMap<PathBuf, FileState> = HashMap::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(POLL_INTERVAL);
+        let current = scan(&folder);
+        processed.retain(|path, _| current.contains_key(path));
+
+        for (path, state) in current {
+            if processed.get(&path) == Some(&state) {
+                continue;
+            }
+            // Process a file only once it is unchanged between two polls
+            if pending.get(&path) != Some(&state) {
+                pending.insert(path, state);
+                continue;
+            }
+            pending.remove(&path);
+            processed.insert(path.clone(), state);
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            process(&app, &folder, &path);
+        }
+    }
+}
+
+fn process(app: &AppHandle, folder: &WatchFolder, input: &Path) {
+    let output = folder.output_path(input);
+    let entry = |status, error: Option<String>| WatchActivity {
+        folder: folder.name.clone(),
+        file: input.to_string_lossy().into_owned(),
+        status,
+        output: Some(output.to_string_lossy().into_owned()),
+        error,
+        time: unix_millis(),
+    };
+    notify(app, entry(WatchStatus::Started, None));
+
+    let result = run(app, folder, input, &output);
+    match result {
+        Ok(()) => notify(app, entry(WatchStatus::Succeeded, None)),
+        Err(e) => notify(app, entry(WatchStatus::Failed, Some(e))),
+    }
+}
+
+fn run(app: &AppHandle, folder: &WatchFolder, input: &Path, output: &Path) -> Result<(), String> {
+    let store = app
+        .try_state::<SettingsStore>()
+        .ok_or_else(|| "Settings are not available".to_string())?;
+    let preset = find_preset(&store, &folder.preset)?;
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let mut inputs = Map::new();
+    inputs.insert("src".to_string(), json!(input));
+    inputs.insert("dst".to_string(), json!(output));
+    let params = apply_overrides(Value::Object(preset.params), Some(inputs));
+    tauri::async_runtime::block_on(replay(app.clone(), &preset.command, params)).map(|_| ())
+}
+
+// Starts every enabled watch folder from the settings, at application startup
+pub(crate) fn start_all(app: &AppHandle) {
+    let (Some(store), Some(manager)) = (
+        app.try_state::<SettingsStore>(),
+        app.try_state::<WatchManager>(),
+    ) else {
+        return;
+    };
+    for folder in store.get().watch_folders {
+        manager.start(app, folder);
+    }
+}
+
+#[tauri::command]
+pub fn list_watch_folders(store: State<'_, SettingsStore>) -> Vec<WatchFolder> {
+    store.get().watch_folders
+}
+
+// Adds or replaces a watch folder by name and (re)starts it when enabled
+#[tauri::command]
+pub fn save_watch_folder(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    manager: State<'_, WatchManager>,
+    folder: WatchFolder,
+) -> Result<Vec<WatchFolder>, String> {
+    if folder.name.trim().is_empty() {
+        return Err("Watch folder name must not be empty".to_string());
+    }
+    if !Path::new(&folder.path).is_dir() {
+        return Err(format!("Directory not found: {}", folder.path));
+    }
+    // Outputs written into the watched directory would be picked up and processed again
+    if let Some(output_dir) = &folder.output_dir {
+        if fs::canonicalize(output_dir).ok() == fs::canonicalize(&folder.path).ok() {
+            return Err("Output directory must differ from the watched directory".to_string());
+        }
+    }
+    glob::Pattern::new(&folder.pattern).map_err(|e| e.to_string())?;
+    find_preset(&store, &folder.preset)?;
+
+    let settings = store
+        .update(|settings| {
+            settings
+                .watch_folders
+                .retain(|existing| existing.name != folder.name);
+            settings.watch_folders.push(folder.clone());
+        })
+        .map_err(|e| e.to_string())?;
+    manager.start(&app, folder);
+    Ok(settings.watch_folders)
+}
+
+#[tauri::command]
+pub fn delete_watch_folder(
+    store: State<'_, SettingsStore>,
+    manager: State<'_, WatchManager>,
+    name: String,
+) -> Result<Vec<WatchFolder>, String> {
+    manager.stop(&name);
+    store
+        .update(|settings| settings.watch_folders.retain(|folder| folder.name != name))
+        .map(|settings| settings.watch_folders)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_watch_activity(
+    manager: State<'_, WatchManager>,
+    limit: Option<usize>,
+) -> Vec<WatchActivity> {
+    manager.list(limit.unwrap_or(100))
+}
+
+#[tauri::command]
+pub fn clear_watch_activity(manager: State<'_, WatchManager>) -> Result<(), String> {
+    manager.clear().map_err(|e| e.to_string())
+}
@@ -14,6 +14,7 @@ interface DatasetInfo {
   projection: string;
   band_count: number;
   driver_name: string;
+  subdataset_count: number;
 }
 
 // GDAL functionality
@@ -66,6 +67,8 @@ async function analyzeDataset(filePath: string) {
 
     Bands: ${datasetInfo.band_count}
 
+    Subdatasets: ${datasetInfo.subdataset_count}
+
     Driver: ${datasetInfo.driver_name}
 
     Dimensions: ${datasetInfo.size_x} × ${datasetInfo.size_y} pixels